@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+// Generated from wasm2glulx's glk.spec. Do not edit; see that file for how
+// to regenerate this one.
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+#[link(wasm_import_module = "glk")]
+extern "C" {
+    pub fn exit() -> !;
+    pub fn tick();
+    pub fn gestalt(sel: Gestalt, val: u32) -> u32;
+    pub fn gestalt_ext(sel: Gestalt, val: u32, arr: *mut u32, arrlen: u32) -> u32;
+
+    pub fn window_iterate(win: WinId, rockptr: *mut u32) -> WinId;
+    pub fn window_get_rock(win: WinId) -> u32;
+    pub fn window_get_root() -> WinId;
+    pub fn window_open(
+        split: WinId,
+        method: WinMethod,
+        size: u32,
+        wintype: WinType,
+        rock: u32,
+    ) -> WinId;
+    pub fn window_close(win: WinId, result: *mut StreamResult);
+    pub fn window_get_size(win: WinId, widthptr: *mut u32, heightptr: *mut u32);
+    pub fn window_set_arrangement(win: WinId, method: WinMethod, size: u32, keywin: WinId);
+    pub fn window_get_arrangement(
+        win: WinId,
+        methodptr: *mut WinMethod,
+        sizeptr: *mut u32,
+        keywinptr: *mut WinId,
+    );
+    pub fn window_get_type(win: WinId) -> WinType;
+    pub fn window_get_parent(win: WinId) -> WinId;
+    pub fn window_clear(win: WinId);
+    pub fn window_move_cursor(win: WinId, xpos: u32, ypos: u32);
+    pub fn window_get_stream(win: WinId) -> StrId;
+    pub fn window_set_echo_stream(win: WinId, str: StrId);
+    pub fn window_get_echo_stream(win: WinId) -> StrId;
+    pub fn set_window(win: WinId);
+    pub fn window_get_sibling(win: WinId) -> WinId;
+
+    pub fn stream_iterate(str: StrId, rockptr: *mut u32) -> StrId;
+    pub fn stream_get_rock(str: StrId) -> u32;
+    pub fn stream_open_file(fileref: FrefId, mode: FileMode, rock: u32) -> StrId;
+    pub fn stream_open_memory(glkaddr: u32, buflen: u32, mode: FileMode, rock: u32) -> StrId;
+    pub fn stream_close(str: StrId, result: *mut StreamResult);
+    pub fn stream_set_position(str: StrId, pos: i32, seekmode: SeekMode);
+    pub fn stream_get_position(str: StrId) -> u32;
+    pub fn stream_set_current(str: StrId);
+    pub fn stream_get_current() -> StrId;
+    pub fn stream_open_resource(filenum: u32, rock: u32) -> StrId;
+
+    pub fn fileref_create_temp(usage: FileUsage, rock: u32) -> FrefId;
+    pub fn fileref_create_by_name(usage: FileUsage, name: *const c_char, rock: u32) -> FrefId;
+    pub fn fileref_create_by_prompt(usage: FileUsage, fmode: FileMode, rock: u32) -> FrefId;
+    pub fn fileref_destroy(fref: FrefId);
+    pub fn fileref_iterate(fref: FrefId, rockptr: *mut u32) -> FrefId;
+    pub fn fileref_get_rock(fref: FrefId) -> u32;
+    pub fn fileref_delete_file(fref: FrefId);
+    pub fn fileref_does_file_exist(fref: FrefId) -> u32;
+    pub fn fileref_create_from_fileref(usage: FileUsage, fref: FrefId, rock: u32) -> FrefId;
+
+    pub fn put_char(ch: u32);
+    pub fn put_char_stream(str: StrId, ch: u32);
+    pub fn put_string(s: *const c_char);
+    pub fn put_string_stream(str: StrId, s: *const c_char);
+    pub fn put_buffer(buf: *const c_char, len: u32);
+    pub fn put_buffer_stream(str: StrId, buf: *const c_char, len: u32);
+    pub fn set_style(styl: Style);
+    pub fn set_style_stream(str: StrId, styl: Style);
+
+    pub fn get_char_stream(str: StrId) -> i32;
+    pub fn get_line_stream(str: StrId, buf: *mut c_char, len: u32) -> u32;
+    pub fn get_buffer_stream(str: StrId, buf: *mut c_char, len: u32) -> u32;
+
+    pub fn char_to_lower(ch: u32) -> u32;
+    pub fn char_to_upper(ch: u32) -> u32;
+
+    pub fn stylehint_set(wintype: WinType, styl: Style, hint: StyleHint, val: i32);
+    pub fn stylehint_clear(wintype: WinType, styl: Style, hint: StyleHint);
+    pub fn style_distinguish(win: WinId, styl1: Style, styl2: Style) -> u32;
+    pub fn style_measure(win: WinId, styl: Style, hint: StyleHint, result: *mut u32) -> u32;
+
+    pub fn select(event: *mut Event);
+    pub fn select_poll(event: *mut Event);
+
+    pub fn request_line_event(win: WinId, glkaddr: u32, maxlen: u32, initlen: u32);
+    pub fn cancel_line_event(win: WinId, event: *mut Event);
+    pub fn request_char_event(win: WinId);
+    pub fn cancel_char_event(win: WinId);
+    pub fn request_mouse_event(win: WinId);
+    pub fn cancel_mouse_event(win: WinId);
+    pub fn request_timer_events(millisecs: u32);
+
+    pub fn image_get_info(image: u32, width: *mut u32, height: *mut u32) -> u32;
+    pub fn image_draw(win: WinId, image: u32, val1: i32, val2: i32) -> u32;
+    pub fn image_draw_scaled(
+        win: WinId,
+        image: u32,
+        val1: i32,
+        val2: i32,
+        width: u32,
+        height: u32,
+    ) -> u32;
+    pub fn window_flow_break(win: WinId);
+    pub fn window_erase_rect(win: WinId, left: i32, top: i32, width: u32, height: u32);
+    pub fn window_fill_rect(win: WinId, color: u32, left: i32, top: i32, width: u32, height: u32);
+    pub fn window_set_background_color(win: WinId, color: u32);
+
+    pub fn schannel_iterate(chan: SchanId, rockptr: *mut u32) -> SchanId;
+    pub fn schannel_get_rock(chan: SchanId) -> u32;
+    pub fn schannel_create(rock: u32) -> SchanId;
+    pub fn schannel_destroy(chan: SchanId);
+    pub fn schannel_create_ext(rock: u32, volume: u32) -> SchanId;
+    pub fn schannel_play_multi(
+        chans: *const SchanId,
+        chancount: u32,
+        sounds: *const u32,
+        soundcount: u32,
+        notify: u32,
+    ) -> u32;
+    pub fn schannel_play(chan: SchanId, snd: u32) -> u32;
+    pub fn schannel_play_ext(chan: SchanId, snd: u32, repeats: u32, notify: u32) -> u32;
+    pub fn schannel_stop(chan: SchanId);
+    pub fn schannel_set_volume(chan: SchanId, vol: u32);
+    pub fn sound_load_hint(snd: u32, flag: u32);
+    pub fn schannel_set_volume_ext(chan: SchanId, vol: u32, duration: u32, notify: u32);
+    pub fn schannel_pause(chan: SchanId);
+    pub fn schannel_unpause(chan: SchanId);
+
+    pub fn set_hyperlink(linkval: u32);
+    pub fn set_hyperlink_stream(str: StrId, linkval: u32);
+    pub fn request_hyperlink_event(win: WinId);
+    pub fn cancel_hyperlink_event(win: WinId);
+
+    pub fn buffer_to_lower_case_uni(buf: *mut u32, len: u32, numchars: u32) -> u32;
+    pub fn buffer_to_upper_case_uni(buf: *mut u32, len: u32, numchars: u32) -> u32;
+    pub fn buffer_to_title_case_uni(buf: *mut u32, len: u32, numchars: u32, lowerrest: u32) -> u32;
+    pub fn buffer_canon_decompose_uni(buf: *mut u32, len: u32, numchars: u32) -> u32;
+    pub fn buffer_canon_normalize_uni(buf: *mut u32, len: u32, numchars: u32) -> u32;
+    pub fn put_char_uni(ch: u32);
+    pub fn put_string_uni(s: *const u32);
+    pub fn put_buffer_uni(buf: *const u32, len: u32);
+    pub fn put_char_stream_uni(str: StrId, ch: u32);
+    pub fn put_string_stream_uni(str: StrId, s: *const u32);
+    pub fn put_buffer_stream_uni(str: StrId, buf: *const u32, len: u32);
+
+    pub fn get_char_stream_uni(str: StrId) -> i32;
+    pub fn get_buffer_stream_uni(str: StrId, buf: *mut u32, len: u32) -> u32;
+    pub fn get_line_stream_uni(str: StrId, buf: *mut u32, len: u32) -> u32;
+    pub fn stream_open_file_uni(fileref: FrefId, mode: FileMode, rock: u32) -> StrId;
+    pub fn stream_open_memory_uni(glkaddr: u32, buflen: u32, mode: FileMode, rock: u32) -> StrId;
+    pub fn stream_open_resource_uni(filenum: u32, rock: u32) -> StrId;
+
+    pub fn request_char_event_uni(win: WinId);
+    pub fn request_line_event_uni(win: WinId, glkaddr: u32, maxlen: u32, initlen: u32);
+
+    pub fn set_echo_line_event(win: WinId, val: u32);
+    pub fn set_terminators_line_event(win: WinId, keycodes: *const Keycode, count: u32);
+
+    pub fn current_time(time: *mut Timeval);
+    pub fn current_simple_time(factor: u32) -> i32;
+    pub fn time_to_date_utc(time: *const Timeval, date: *mut Date);
+    pub fn time_to_date_local(time: *const Timeval, date: *mut Date);
+    pub fn simple_time_to_date_utc(time: i32, factor: u32, date: *mut Date);
+    pub fn simple_time_to_date_local(time: i32, factor: u32, date: *mut Date);
+    pub fn date_to_time_utc(date: *const Date, time: *mut Timeval);
+    pub fn date_to_time_local(date: *const Date, time: *mut Timeval);
+    pub fn date_to_simple_time_utc(date: *const Date, factor: u32) -> i32;
+    pub fn date_to_simple_time_local(date: *const Date, factor: u32) -> i32;
+
+    #[cfg(feature = "garglk")]
+    pub fn garglk_set_zcolors(fg: u32, bg: u32);
+    #[cfg(feature = "garglk")]
+    pub fn garglk_set_zcolors_stream(str: StrId, fg: u32, bg: u32);
+    #[cfg(feature = "garglk")]
+    pub fn garglk_set_reversevideo(reverse: u32);
+    #[cfg(feature = "garglk")]
+    pub fn garglk_set_reversevideo_stream(str: StrId, reverse: u32);
+}
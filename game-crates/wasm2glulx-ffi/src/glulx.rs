@@ -1,3 +1,7 @@
+/// The value which `catch` returns when a function compiled with
+/// `--stack-guard` runs out of stack beneath it.
+pub const STACK_EXHAUSTED: u32 = 0xffff_ffff;
+
 #[cfg(target_arch = "wasm32")]
 #[link(wasm_import_module = "glulx")]
 extern "C" {
@@ -47,4 +51,170 @@ extern "C" {
     pub fn hasundo() -> u32;
     pub fn discardundo();
     pub fn protect(addr: *mut (), len: u32);
+    pub fn stack_remaining() -> u32;
+
+    pub fn accelfunc(number: u32, func: *const ());
+    pub fn accelparam(index: u32, value: u32);
+
+    pub fn catch(func: extern "C" fn(token: u32, arg: u32) -> u32, arg: u32) -> u32;
+    pub fn throw(token: u32, value: u32) -> !;
+
+    pub fn print_compressed(id: u32);
+}
+
+/// Bindings which each emit a single Glulx instruction. These are an escape
+/// hatch for functionality not yet covered by anything safer; see the "Raw
+/// instructions" section of the Wasm2Glulx manual for caveats.
+pub mod raw {
+    #[cfg(target_arch = "wasm32")]
+    #[link(wasm_import_module = "glulx")]
+    extern "C" {
+        #[link_name = "raw_nop"]
+        pub fn nop();
+        #[link_name = "raw_streamchar"]
+        pub fn streamchar(ch: u32);
+        #[link_name = "raw_streamnum"]
+        pub fn streamnum(n: i32);
+        #[link_name = "raw_streamunichar"]
+        pub fn streamunichar(ch: u32);
+        #[link_name = "raw_gestalt"]
+        pub fn gestalt(selector: u32, extra: u32) -> u32;
+        #[link_name = "raw_debugtrap"]
+        pub fn debugtrap(code: u32);
+        #[link_name = "raw_getmemsize"]
+        pub fn getmemsize() -> u32;
+        #[link_name = "raw_random"]
+        pub fn random(range: i32) -> i32;
+        #[link_name = "raw_setrandom"]
+        pub fn setrandom(seed: u32);
+        #[link_name = "raw_quit"]
+        pub fn quit();
+        #[link_name = "raw_verify"]
+        pub fn verify() -> u32;
+        #[link_name = "raw_restart"]
+        pub fn restart();
+        #[link_name = "raw_saveundo"]
+        pub fn saveundo() -> u32;
+        #[link_name = "raw_restoreundo"]
+        pub fn restoreundo() -> u32;
+        #[link_name = "raw_hasundo"]
+        pub fn hasundo() -> u32;
+        #[link_name = "raw_discardundo"]
+        pub fn discardundo();
+        #[link_name = "raw_setiosys"]
+        pub fn setiosys(mode: u32, rock: u32);
+        #[link_name = "raw_accelfunc"]
+        pub fn accelfunc(l1: u32, l2: u32);
+        #[link_name = "raw_accelparam"]
+        pub fn accelparam(l1: u32, l2: u32);
+        #[link_name = "raw_numtof"]
+        pub fn numtof(n: i32) -> f32;
+        #[link_name = "raw_ftonumz"]
+        pub fn ftonumz(x: f32) -> i32;
+        #[link_name = "raw_ftonumn"]
+        pub fn ftonumn(x: f32) -> i32;
+        #[link_name = "raw_ceil"]
+        pub fn ceil(x: f32) -> f32;
+        #[link_name = "raw_floor"]
+        pub fn floor(x: f32) -> f32;
+        #[link_name = "raw_fadd"]
+        pub fn fadd(x: f32, y: f32) -> f32;
+        #[link_name = "raw_fsub"]
+        pub fn fsub(x: f32, y: f32) -> f32;
+        #[link_name = "raw_fmul"]
+        pub fn fmul(x: f32, y: f32) -> f32;
+        #[link_name = "raw_fdiv"]
+        pub fn fdiv(x: f32, y: f32) -> f32;
+        #[link_name = "raw_sqrt"]
+        pub fn sqrt(x: f32) -> f32;
+        #[link_name = "raw_exp"]
+        pub fn exp(x: f32) -> f32;
+        #[link_name = "raw_log"]
+        pub fn log(x: f32) -> f32;
+        #[link_name = "raw_pow"]
+        pub fn pow(x: f32, y: f32) -> f32;
+        #[link_name = "raw_sin"]
+        pub fn sin(x: f32) -> f32;
+        #[link_name = "raw_cos"]
+        pub fn cos(x: f32) -> f32;
+        #[link_name = "raw_tan"]
+        pub fn tan(x: f32) -> f32;
+        #[link_name = "raw_asin"]
+        pub fn asin(x: f32) -> f32;
+        #[link_name = "raw_acos"]
+        pub fn acos(x: f32) -> f32;
+        #[link_name = "raw_atan"]
+        pub fn atan(x: f32) -> f32;
+        #[link_name = "raw_atan2"]
+        pub fn atan2(y: f32, x: f32) -> f32;
+    }
+}
+
+/// Declares the Glk area size, in bytes, which this game expects.
+///
+/// Wasm2Glulx will emit a check at startup which traps with "glk area size
+/// mismatch" if the story was compiled with a different `--glk-area-size`.
+/// Invoke this at most once, at module scope.
+#[macro_export]
+macro_rules! expect_glkarea_size {
+    ($size:expr) => {
+        #[export_name = "glulx_expected_glkarea_size"]
+        static GLULX_EXPECTED_GLKAREA_SIZE: u32 = $size;
+    };
+}
+
+/// Computes the id under which [`compressed_str!`](crate::compressed_str)
+/// registers `s`. This is the 32-bit FNV-1a hash of its bytes.
+pub const fn compressed_str_id(s: &str) -> u32 {
+    let bytes = s.as_bytes();
+    let mut hash: u32 = 0x811c9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u32;
+        hash = hash.wrapping_mul(0x01000193);
+        i += 1;
+    }
+    hash
+}
+
+/// Builds an entry of the `glulx_strings` custom section for `s`. `N` must be
+/// `s.len() + 8`.
+pub const fn compressed_str_entry<const N: usize>(s: &str) -> [u8; N] {
+    let bytes = s.as_bytes();
+    assert!(N == bytes.len() + 8);
+    let id = compressed_str_id(s).to_le_bytes();
+    let len = (bytes.len() as u32).to_le_bytes();
+
+    let mut entry = [0u8; N];
+    let mut i = 0;
+    while i < 4 {
+        entry[i] = id[i];
+        entry[i + 4] = len[i];
+        i += 1;
+    }
+    while i < bytes.len() + 4 {
+        entry[i + 4] = bytes[i - 4];
+        i += 1;
+    }
+    entry
+}
+
+/// Registers a string literal for compression, evaluating to its id.
+///
+/// The string is stored in the `glulx_strings` custom section rather than in
+/// linear memory. Wasm2Glulx compresses it into the story file, and it can be
+/// printed with [`print_compressed`].
+///
+/// ```ignore
+/// unsafe { print_compressed(compressed_str!("You are standing in an open field.")) }
+/// ```
+#[macro_export]
+macro_rules! compressed_str {
+    ($s:expr) => {{
+        const S: &str = $s;
+        #[link_section = "glulx_strings"]
+        #[used]
+        static ENTRY: [u8; S.len() + 8] = $crate::glulx::compressed_str_entry(S);
+        $crate::glulx::compressed_str_id(S)
+    }};
 }
@@ -5,6 +5,12 @@
 //! when they are compiled for the Glulx virtual machine by way of
 //! [Wasm2Glulx](https://docs.rs/wasm2glulx). This crate is part of the
 //! [Bedquilt project](https://bedquilt.io).
+//!
+//! The [`glk`] and [`glulx`] modules are raw declarations. The only safe layer
+//! is [`state`], which wraps single instructions whose return conventions are
+//! easy to misuse: a successful restore returns through the earlier save.
+//! Anything higher-level belongs in a crate built on this one.
 
 pub mod glk;
 pub mod glulx;
+pub mod state;
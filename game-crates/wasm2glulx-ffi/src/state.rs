@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Safe wrappers around Glulx's save, restore, and undo instructions.
+//!
+//! A successful restore never returns to its caller. Instead, execution
+//! resumes from the [`save_to`] or [`save_undo`] call which saved the state
+//! being restored, and that call returns a second time, now with
+//! [`SaveOutcome::Restored`]. None of these functions wait for input, and the
+//! state of any pending future lives in linear memory along with everything
+//! else that gets saved, so they can be called from async code.
+
+#[cfg(target_arch = "wasm32")]
+use crate::{glk::StrId, glulx};
+#[cfg(target_arch = "wasm32")]
+use core::convert::Infallible;
+
+/// How a call to [`save_to`] or [`save_undo`] returned.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum SaveOutcome {
+    /// The state was saved, and execution continues as usual.
+    Saved,
+    /// A later call to [`restore_from`] or [`restore_undo`] brought back the
+    /// state saved here, and execution has resumed from it.
+    Restored,
+}
+
+/// The interpreter could not save the game state.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct SaveError;
+
+/// The interpreter could not restore a game state, and the current one is
+/// left unchanged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RestoreError;
+
+impl core::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("failed to save game state")
+    }
+}
+
+impl core::fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("failed to restore game state")
+    }
+}
+
+/// Interprets the value stored by `save` or `saveundo`: 0 for success, -1
+/// after a restore, and anything else for failure.
+#[cfg(target_arch = "wasm32")]
+fn save_outcome(result: i32) -> Result<SaveOutcome, SaveError> {
+    match result {
+        0 => Ok(SaveOutcome::Saved),
+        -1 => Ok(SaveOutcome::Restored),
+        _ => Err(SaveError),
+    }
+}
+
+/// Saves the game state to `str`, which must be a stream open for writing.
+#[cfg(target_arch = "wasm32")]
+pub fn save_to(str: StrId) -> Result<SaveOutcome, SaveError> {
+    save_outcome(unsafe { glulx::save(str) })
+}
+
+/// Restores the game state from `str`, which must be a stream open for
+/// reading. This only returns if the restore fails.
+#[cfg(target_arch = "wasm32")]
+pub fn restore_from(str: StrId) -> Result<Infallible, RestoreError> {
+    unsafe { glulx::restore(str) };
+    Err(RestoreError)
+}
+
+/// Saves the game state in the interpreter's undo history.
+#[cfg(target_arch = "wasm32")]
+pub fn save_undo() -> Result<SaveOutcome, SaveError> {
+    save_outcome(unsafe { glulx::saveundo() } as i32)
+}
+
+/// Restores the most recent game state in the interpreter's undo history.
+/// This only returns if the restore fails, which it does if the history is
+/// empty.
+#[cfg(target_arch = "wasm32")]
+pub fn restore_undo() -> Result<Infallible, RestoreError> {
+    unsafe { glulx::restoreundo() };
+    Err(RestoreError)
+}
+
+/// Returns whether the interpreter's undo history holds any states.
+#[cfg(target_arch = "wasm32")]
+pub fn has_undo() -> bool {
+    // `hasundo` stores 0 when there is a state to restore.
+    unsafe { glulx::hasundo() == 0 }
+}
+
+/// Discards the most recent game state in the interpreter's undo history.
+#[cfg(target_arch = "wasm32")]
+pub fn discard_undo() {
+    unsafe { glulx::discardundo() }
+}
@@ -0,0 +1,102 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Compares generating a listing with `to_string` against streaming it with
+//! `write_listing`. Run with `cargo bench --bench listing`.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::{
+    borrow::Cow,
+    fmt,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+const FUNCTIONS: u32 = 20_000;
+const BLOB_LEN: usize = 4 << 20;
+const ITERATIONS: u32 = 5;
+
+/// Builds an assembly shaped roughly like wasm2glulx output for a large game:
+/// many small functions followed by a big data segment.
+fn big_assembly() -> Assembly<'static, u32> {
+    let mut rom_items = Vec::new();
+    for func in 0..FUNCTIONS {
+        let base = func * 3;
+        rom_items.extend([
+            label(base),
+            fnhead_local(4),
+            add(lloc(0), lloc(1), sloc(2)),
+            jz(lloc(2), base + 1),
+            callfii(imml(base / 2 * 3), lloc(2), imm(7), push()),
+            aload(imml(FUNCTIONS * 3), pop(), sloc(3)),
+            label(base + 1),
+            ret(lloc(3)),
+        ]);
+    }
+    rom_items.push(label(FUNCTIONS * 3));
+    rom_items.push(Item::Blob(
+        (0..BLOB_LEN)
+            .map(|i| u8::try_from(i % 251).unwrap())
+            .collect::<Vec<u8>>()
+            .into(),
+    ));
+
+    Assembly {
+        rom_items: Cow::Owned(rom_items),
+        ram_items: Cow::Owned(vec![]),
+        zero_items: Cow::Owned(vec![]),
+        stack_size: 0x10000,
+        start_func: LabelRef(0, 0),
+        decoding_table: None,
+    }
+}
+
+/// Adapts an [`io::Write`] into a [`fmt::Write`], counting bytes written.
+struct IoAdapter<W> {
+    inner: W,
+    len: usize,
+}
+
+impl<W: Write> fmt::Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.len += s.len();
+        self.inner.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+fn bench<F: FnMut() -> usize>(name: &str, mut f: F) {
+    let mut total = Duration::ZERO;
+    let mut len = 0;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        len = f();
+        total += start.elapsed();
+    }
+    println!(
+        "{name:<32} {:>10.2?} per iteration ({len} bytes)",
+        total / ITERATIONS
+    );
+}
+
+fn main() {
+    let assembly = big_assembly();
+
+    bench("to_string", || assembly.to_string().len());
+
+    bench("write_listing into String", || {
+        let mut out = String::new();
+        assembly.write_listing(&mut out).unwrap();
+        out.len()
+    });
+
+    bench("write_listing into io::sink", || {
+        let mut out = IoAdapter {
+            inner: io::BufWriter::new(io::sink()),
+            len: 0,
+        };
+        assembly.write_listing(&mut out).unwrap();
+        out.inner.flush().unwrap();
+        out.len
+    });
+}
@@ -4,7 +4,8 @@
 //! Main assembler implementation.
 
 use alloc::borrow::{Borrow, Cow};
-use bytes::{Buf, BufMut, Bytes, BytesMut};
+use alloc::vec::Vec;
+use bytes::{Buf, BufMut, BytesMut};
 use core::{fmt::Display, hash::Hash};
 
 #[cfg(not(feature = "std"))]
@@ -19,8 +20,18 @@ use crate::{
     resolver::{ResolvedAddr, Resolver},
 };
 
+#[cfg(feature = "std")]
+use crate::error::WriteError;
+
+/// Number of items which [`serialize_body_chunked`] serializes before passing
+/// them on.
+#[cfg(feature = "std")]
+const ITEMS_PER_CHUNK: usize = 4096;
+
 /// Length of the story file header.
 const HEADER_LENGTH: u32 = 0x24;
+/// [`HEADER_LENGTH`] as a `usize`.
+const HEADER_LENGTH_USIZE: usize = 0x24;
 /// Magic number identifying a Glulx story file.
 const MAGIC_NUMBER: u32 = 0x476C756C;
 /// The Glulx version we're implementing (3.1.3).
@@ -123,13 +134,87 @@ where
 
     /// Assembles a Glulx binary, ready to be written out as a `.ulx` file.
     pub fn assemble(&self) -> Result<BytesMut, AssemblerError<L>> {
-        assemble(
+        let (output, _) = assemble(
             self.rom_items.borrow(),
             self.ram_items.borrow(),
             self.zero_items.borrow(),
             self.stack_size,
             &self.start_func,
             &self.decoding_table,
+        )?;
+        Ok(output)
+    }
+
+    /// Like [`assemble`](Self::assemble), but also returns the address of
+    /// every label, sorted by address. This is useful for generating a symbol
+    /// map to accompany the story file.
+    #[allow(clippy::type_complexity)]
+    pub fn assemble_with_labels(&self) -> Result<(BytesMut, Vec<(L, u32)>), AssemblerError<L>> {
+        let (output, layout) = assemble(
+            self.rom_items.borrow(),
+            self.ram_items.borrow(),
+            self.zero_items.borrow(),
+            self.stack_size,
+            &self.start_func,
+            &self.decoding_table,
+        )?;
+        let mut labels: Vec<(L, u32)> = layout.labeled.into_iter().collect();
+        labels.sort_by_key(|(_, addr)| *addr);
+        Ok((output, labels))
+    }
+
+    /// Returns the length of the story file that [`assemble`](Self::assemble)
+    /// would output, without serializing anything.
+    pub fn output_len(&self) -> Result<usize, AssemblerError<L>> {
+        layout(
+            self.rom_items.borrow(),
+            self.ram_items.borrow(),
+            self.zero_items.borrow(),
+        )?
+        .file_len()
+    }
+
+    /// Assembles a Glulx binary into `buf`, returning the number of bytes
+    /// written. Fails with [`AssemblerError::BufferTooSmall`] if `buf` is
+    /// shorter than [`output_len`](Self::output_len), in which case its
+    /// contents are unspecified.
+    ///
+    /// Unlike [`assemble`](Self::assemble), this never allocates space for the
+    /// output, though label positions are still tracked in a hash table.
+    #[cfg(feature = "slice-output")]
+    pub fn assemble_into(&self, buf: &mut [u8]) -> Result<usize, AssemblerError<L>> {
+        assemble_into(
+            self.rom_items.borrow(),
+            self.ram_items.borrow(),
+            self.zero_items.borrow(),
+            self.stack_size,
+            &self.start_func,
+            &self.decoding_table,
+            buf,
+        )
+    }
+
+    /// Assembles a Glulx binary and writes it to `w`, returning the number of
+    /// bytes written.
+    ///
+    /// The header's checksum covers everything after it, so the output is
+    /// serialized twice: once to compute the checksum, and again to write it
+    /// out a piece at a time. This is slower than [`assemble`](Self::assemble),
+    /// but never holds more than a small part of the story file in memory.
+    /// If `w` fails, part of the story file may already have been written.
+    #[cfg(feature = "std")]
+    pub fn assemble_to_writer<W>(&self, w: W) -> Result<usize, WriteError<L>>
+    where
+        W: std::io::Write,
+    {
+        assemble_to_writer(
+            self.rom_items.borrow(),
+            self.ram_items.borrow(),
+            self.zero_items.borrow(),
+            self.stack_size,
+            &self.start_func,
+            &self.decoding_table,
+            w,
         )
     }
 
@@ -146,39 +231,71 @@ where
     }
 }
 
-impl<L> Display for Assembly<'_, L>
+impl<L> Assembly<'_, L>
 where
     L: Display + Clone,
 {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        writeln!(f, ".stack_size {}", self.stack_size)?;
-        write!(f, ".start_func ({}", self.start_func.0)?;
+    /// Writes a human-readable listing of the assembly to `w`.
+    ///
+    /// This produces the same text as the [`Display`] impl, but lets the
+    /// caller supply the destination, so a large listing can be streamed out
+    /// rather than collected into one giant `String` by `to_string`.
+    pub fn write_listing<W>(&self, w: &mut W) -> core::fmt::Result
+    where
+        W: core::fmt::Write + ?Sized,
+    {
+        writeln!(w, ".stack_size {}", self.stack_size)?;
+        write!(w, ".start_func ({}", self.start_func.0)?;
         if self.start_func.1 != 0 {
-            write!(f, "{:+#x}", self.start_func.1)?;
+            write!(w, "{:+#x}", self.start_func.1)?;
         }
-        writeln!(f, ")")?;
+        writeln!(w, ")")?;
         if let Some(decoding_table) = &self.decoding_table {
-            write!(f, ".initial_decoding_table ({}", decoding_table.0)?;
+            write!(w, ".initial_decoding_table ({}", decoding_table.0)?;
             if decoding_table.1 != 0 {
-                write!(f, "{:+#x}", decoding_table.1)?;
+                write!(w, "{:+#x}", decoding_table.1)?;
             }
-            writeln!(f, ")")?;
+            writeln!(w, ")")?;
         }
         for item in self.rom_items.iter() {
-            writeln!(f, "{item}")?;
+            writeln!(w, "{item}")?;
         }
-        writeln!(f, ".ram_items")?;
+        writeln!(w, ".ram_items")?;
         for item in self.ram_items.iter() {
-            writeln!(f, "{item}")?;
+            writeln!(w, "{item}")?;
         }
-        writeln!(f, ".zero_items")?;
+        writeln!(w, ".zero_items")?;
         for item in self.zero_items.iter() {
-            writeln!(f, "{item}")?;
+            writeln!(w, "{item}")?;
         }
         Ok(())
     }
 }
 
+impl<L> Display for Assembly<'_, L>
+where
+    L: Display + Clone,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.write_listing(f)
+    }
+}
+
+/// Final label positions and section boundaries computed by [`layout`].
+struct Layout<L> {
+    labeled: HashMap<L, u32>,
+    ramstart: u32,
+    extstart: u32,
+    endmem: u32,
+}
+
+impl<L> Layout<L> {
+    /// Returns the length of the story file this layout describes.
+    fn file_len(&self) -> Result<usize, AssemblerError<L>> {
+        usize::try_from(self.extstart).overflow()
+    }
+}
+
 /// Top-level function of our main assembler algorithm.
 ///
 /// The hard part of this is dealing with variable-length operands, and
@@ -206,6 +323,9 @@ where
 ///
 /// 4. Finally, serialize the output, checking assertions along the way to make
 ///    sure the lengths we got are the ones we planned to get.
+///
+/// Steps 1 through 3 are implemented by [`layout`], which is returned along
+/// with the output.
 fn assemble<L>(
     rom_items: &[Item<L>],
     ram_items: &[Item<L>],
@@ -213,7 +333,125 @@ fn assemble<L>(
     stack_size: u32,
     start_func: &LabelRef<L>,
     decoding_table: &Option<LabelRef<L>>,
-) -> Result<BytesMut, AssemblerError<L>>
+) -> Result<(BytesMut, Layout<L>), AssemblerError<L>>
+where
+    L: Clone + Eq + Hash,
+{
+    let layout = layout(rom_items, ram_items, zero_items)?;
+
+    // Step 4: serialize output.
+    let mut body = BytesMut::with_capacity(
+        layout
+            .file_len()?
+            .checked_sub(HEADER_LENGTH_USIZE)
+            .expect("extstart should be >= HEADER_LENGTH"),
+    );
+    serialize_body(rom_items, ram_items, &layout, &mut body)?;
+    let body = body.freeze();
+
+    let mut output = BytesMut::with_capacity(layout.file_len()?);
+    serialize_header(
+        &layout,
+        stack_size,
+        start_func,
+        decoding_table,
+        checksum(body.clone()),
+        &mut output,
+    )?;
+    output.put(body);
+
+    Ok((output, layout))
+}
+
+/// Like [`assemble`], but writes into a caller-provided buffer rather than
+/// allocating one, and returns the number of bytes written.
+#[cfg(feature = "slice-output")]
+fn assemble_into<L>(
+    rom_items: &[Item<L>],
+    ram_items: &[Item<L>],
+    zero_items: &[ZeroItem<L>],
+    stack_size: u32,
+    start_func: &LabelRef<L>,
+    decoding_table: &Option<LabelRef<L>>,
+    buf: &mut [u8],
+) -> Result<usize, AssemblerError<L>>
+where
+    L: Clone + Eq + Hash,
+{
+    let layout = layout(rom_items, ram_items, zero_items)?;
+    let len = layout.file_len()?;
+
+    if buf.len() < len {
+        return Err(AssemblerError::BufferTooSmall {
+            needed: len,
+            available: buf.len(),
+        });
+    }
+
+    let (mut header, body) = buf[..len].split_at_mut(HEADER_LENGTH_USIZE);
+    serialize_body(rom_items, ram_items, &layout, &mut &mut *body)?;
+    serialize_header(
+        &layout,
+        stack_size,
+        start_func,
+        decoding_table,
+        checksum(&*body),
+        &mut header,
+    )?;
+
+    Ok(len)
+}
+
+/// Like [`assemble`], but writes to `w` a chunk at a time, and returns the
+/// number of bytes written.
+#[cfg(feature = "std")]
+fn assemble_to_writer<L, W>(
+    rom_items: &[Item<L>],
+    ram_items: &[Item<L>],
+    zero_items: &[ZeroItem<L>],
+    stack_size: u32,
+    start_func: &LabelRef<L>,
+    decoding_table: &Option<LabelRef<L>>,
+    mut w: W,
+) -> Result<usize, WriteError<L>>
+where
+    L: Clone + Eq + Hash,
+    W: std::io::Write,
+{
+    let layout = layout(rom_items, ram_items, zero_items)?;
+
+    // The first pass can fail only with assembler errors, so once it has
+    // succeeded, any error in the second can only come from the writer.
+    let mut body_checksum: u32 = 0;
+    serialize_body_chunked(rom_items, ram_items, &layout, |chunk| {
+        body_checksum = body_checksum.wrapping_add(checksum(chunk));
+        Ok::<(), AssemblerError<L>>(())
+    })?;
+
+    let mut header = BytesMut::with_capacity(HEADER_LENGTH_USIZE);
+    serialize_header(
+        &layout,
+        stack_size,
+        start_func,
+        decoding_table,
+        body_checksum,
+        &mut header,
+    )?;
+    w.write_all(&header)?;
+    serialize_body_chunked(rom_items, ram_items, &layout, |chunk| {
+        w.write_all(chunk).map_err(WriteError::Io)
+    })?;
+    w.flush()?;
+
+    Ok(layout.file_len()?)
+}
+
+/// Computes final label positions (steps 1 through 3 of [`assemble`]).
+fn layout<L>(
+    rom_items: &[Item<L>],
+    ram_items: &[Item<L>],
+    zero_items: &[ZeroItem<L>],
+) -> Result<Layout<L>, AssemblerError<L>>
 where
     L: Clone + Eq + Hash,
 {
@@ -230,7 +468,7 @@ where
     initialize_zero_positions(zero_items, &mut labeled, &mut position)?;
 
     // Step 2/3: update positions until we reach a fixed point.
-    loop {
+    let extstart = loop {
         position = HEADER_LENGTH;
 
         let rom_improved = update_positions(rom_items, &mut labeled, &mut position, ramstart)?;
@@ -238,35 +476,113 @@ where
         ramstart = position;
         let ram_improved = update_positions(ram_items, &mut labeled, &mut position, ramstart)?;
         position = checked_next_multiple_of(position, 256)?;
+        let extstart = position;
         let zero_improved = update_zero_positions(zero_items, &mut labeled, &mut position)?;
 
         if !rom_improved && !ram_improved && !zero_improved {
-            break;
+            break extstart;
         }
-    }
+    };
 
-    // Step 4: serialize output.
-    let mut body = BytesMut::new();
-    serialize_items(rom_items, &labeled, ramstart, &mut body)?;
+    let endmem = checked_next_multiple_of(verify_zero_items(zero_items, &labeled, extstart)?, 256)?;
+
+    Ok(Layout {
+        labeled,
+        ramstart,
+        extstart,
+        endmem,
+    })
+}
+
+/// Serializes the ROM and RAM sections, which together make up everything
+/// after the header.
+fn serialize_body<L, B>(
+    rom_items: &[Item<L>],
+    ram_items: &[Item<L>],
+    layout: &Layout<L>,
+    buf: &mut B,
+) -> Result<(), AssemblerError<L>>
+where
+    L: Clone + Eq + Hash,
+    B: BufMut,
+{
+    let position = serialize_items(
+        rom_items,
+        &layout.labeled,
+        layout.ramstart,
+        HEADER_LENGTH,
+        &mut *buf,
+    )?;
+    assert_eq!(
+        layout.ramstart, position,
+        "ramstart should match previous calculation"
+    );
+    let position = serialize_items(
+        ram_items,
+        &layout.labeled,
+        layout.ramstart,
+        position,
+        &mut *buf,
+    )?;
     assert_eq!(
-        ramstart
-            .checked_sub(HEADER_LENGTH)
-            .expect("ramstart should be >= HEADER_LENGTH"),
-        body.len().try_into().overflow()?
+        layout.extstart, position,
+        "extstart should match previous calculation"
     );
-    serialize_items(ram_items, &labeled, ramstart, &mut body)?;
+    Ok(())
+}
 
-    let body = body.freeze();
-    let extstart = u32::try_from(body.len())
-        .overflow()?
-        .checked_add(HEADER_LENGTH)
-        .overflow()?;
+/// Like [`serialize_body`], but serializes [`ITEMS_PER_CHUNK`] items at a time
+/// and passes the result to `sink`. Every chunk but the last is truncated to a
+/// multiple of four bytes, with the rest carried over to the next, so that
+/// each one can be checksummed separately.
+#[cfg(feature = "std")]
+fn serialize_body_chunked<L, E, F>(
+    rom_items: &[Item<L>],
+    ram_items: &[Item<L>],
+    layout: &Layout<L>,
+    mut sink: F,
+) -> Result<(), E>
+where
+    L: Clone + Eq + Hash,
+    E: From<AssemblerError<L>>,
+    F: FnMut(&[u8]) -> Result<(), E>,
+{
+    let mut buf = BytesMut::new();
+    let mut position = HEADER_LENGTH;
 
-    let endmem = checked_next_multiple_of(verify_zero_items(zero_items, &labeled, extstart)?, 256)?;
+    for (items, end) in [(rom_items, layout.ramstart), (ram_items, layout.extstart)] {
+        for chunk in items.chunks(ITEMS_PER_CHUNK) {
+            position = serialize_run(chunk, &layout.labeled, layout.ramstart, position, &mut buf)?;
+            let whole_words = buf.len() & !3;
+            sink(&buf[..whole_words])?;
+            buf.advance(whole_words);
+        }
+        position = pad_to_page(position, &mut buf)?;
+        assert_eq!(
+            end, position,
+            "section end should match previous calculation"
+        );
+    }
+
+    sink(&buf)
+}
 
+/// Serializes the story file header.
+fn serialize_header<L, B>(
+    layout: &Layout<L>,
+    stack_size: u32,
+    start_func: &LabelRef<L>,
+    decoding_table: &Option<LabelRef<L>>,
+    body_checksum: u32,
+    mut buf: B,
+) -> Result<(), AssemblerError<L>>
+where
+    L: Clone + Eq + Hash,
+    B: BufMut,
+{
     let resolver = HashResolver {
-        hashmap: &labeled,
-        ramstart,
+        hashmap: &layout.labeled,
+        ramstart: layout.ramstart,
     };
 
     let resolved_decoding_table = if let Some(decoding_table) = decoding_table {
@@ -279,34 +595,25 @@ where
 
     let sum = MAGIC_NUMBER
         .wrapping_add(GLULX_VERSION)
-        .wrapping_add(ramstart)
-        .wrapping_add(extstart)
-        .wrapping_add(endmem)
+        .wrapping_add(layout.ramstart)
+        .wrapping_add(layout.extstart)
+        .wrapping_add(layout.endmem)
         .wrapping_add(stack_size)
         .wrapping_add(resolved_start_func)
         .wrapping_add(resolved_decoding_table)
-        .wrapping_add(checksum(body.clone()));
-
-    let mut output = BytesMut::with_capacity(
-        body.len()
-            .checked_add(
-                usize::try_from(HEADER_LENGTH).expect("u32 to usize conversion should succeed"),
-            )
-            .overflow()?,
-    );
+        .wrapping_add(body_checksum);
+
+    buf.put_u32(MAGIC_NUMBER);
+    buf.put_u32(GLULX_VERSION);
+    buf.put_u32(layout.ramstart);
+    buf.put_u32(layout.extstart);
+    buf.put_u32(layout.endmem);
+    buf.put_u32(stack_size);
+    buf.put_u32(resolved_start_func);
+    buf.put_u32(resolved_decoding_table);
+    buf.put_u32(sum);
 
-    output.put_u32(MAGIC_NUMBER);
-    output.put_u32(GLULX_VERSION);
-    output.put_u32(ramstart);
-    output.put_u32(extstart);
-    output.put_u32(endmem);
-    output.put_u32(stack_size);
-    output.put_u32(resolved_start_func);
-    output.put_u32(resolved_decoding_table);
-    output.put_u32(sum);
-    output.put(body);
-
-    Ok(output)
+    Ok(())
 }
 
 /// Initializes item positions for the first step of assembly.
@@ -426,21 +733,37 @@ where
 }
 
 /// Serializes items after all final label positions have been computed.
-fn serialize_items<L>(
+/// `position` is the address of the first item; returns the address following
+/// the last one, after padding to a 256-byte boundary.
+fn serialize_items<L, B>(
     items: &[Item<L>],
     labeled: &HashMap<L, u32>,
     ramstart: u32,
-    buf: &mut BytesMut,
-) -> Result<(), AssemblerError<L>>
+    position: u32,
+    buf: &mut B,
+) -> Result<u32, AssemblerError<L>>
 where
     L: Clone + Eq + Hash,
+    B: BufMut,
 {
-    for item in items {
-        let position = u32::try_from(buf.len())
-            .overflow()?
-            .checked_add(HEADER_LENGTH)
-            .overflow()?;
+    let position = serialize_run(items, labeled, ramstart, position, &mut *buf)?;
+    pad_to_page(position, buf)
+}
 
+/// Serializes `items` without any padding after them, returning the position
+/// after the last one.
+fn serialize_run<L, B>(
+    items: &[Item<L>],
+    labeled: &HashMap<L, u32>,
+    ramstart: u32,
+    mut position: u32,
+    buf: &mut B,
+) -> Result<u32, AssemblerError<L>>
+where
+    L: Clone + Eq + Hash,
+    B: BufMut,
+{
+    for item in items {
         if let Item::Label(label) = item {
             let expected_position = *labeled
                 .get(label)
@@ -456,24 +779,35 @@ where
             ramstart,
         };
 
+        let remaining = buf.remaining_mut();
         item.serialize(position, &resolver, &mut *buf)?;
+        let written = remaining - buf.remaining_mut();
+        position = position
+            .checked_add(u32::try_from(written).overflow()?)
+            .overflow()?;
     }
 
-    let position = u32::try_from(buf.len())
-        .overflow()?
-        .checked_add(HEADER_LENGTH)
-        .overflow()?;
+    Ok(position)
+}
 
+/// Pads to the next multiple of 256 bytes, returning the position after the
+/// padding.
+fn pad_to_page<L, B>(position: u32, buf: &mut B) -> Result<u32, AssemblerError<L>>
+where
+    B: BufMut,
+{
     let page_offset = position % 256;
-    let padding = usize::try_from(if page_offset == 0 {
+    let padding = if page_offset == 0 {
         0
     } else {
         256 - page_offset
-    })
-    .expect("u32 to usize conversion should succeed");
-    buf.put_bytes(0, padding);
+    };
+    buf.put_bytes(
+        0,
+        usize::try_from(padding).expect("u32 to usize conversion should succeed"),
+    );
 
-    Ok(())
+    position.checked_add(padding).overflow()
 }
 
 /// Checks assertions to ensure that all zero-items were placed as intended.
@@ -506,7 +840,7 @@ where
 }
 
 /// Header checksum calculation.
-fn checksum(mut bytes: Bytes) -> u32 {
+fn checksum<B: Buf>(mut bytes: B) -> u32 {
     let mut sum: u32 = 0;
     while bytes.has_remaining() {
         sum = sum.wrapping_add(bytes.get_u32());
@@ -7,8 +7,9 @@ use crate::cast::{CastSign, Overflow};
 use crate::error::AssemblerError;
 use crate::items::LabelRef;
 use crate::resolver::Resolver;
-use crate::strings::{MysteryString, Utf32String};
+use crate::strings::{write_quoted, MysteryString, Utf32String};
 use bytes::BufMut;
+use core::fmt::Display;
 
 /// A node in a decoding table.
 #[derive(Debug, Clone)]
@@ -187,6 +188,79 @@ impl<L> DecodeNode<L> {
     }
 }
 
+/// Writes a label reference the way a [`LoadOperand::ImmLabel`] appears in a
+/// listing, but with the given brackets.
+///
+/// [`LoadOperand::ImmLabel`]: crate::LoadOperand::ImmLabel
+fn write_labelref<L: Display>(
+    f: &mut core::fmt::Formatter<'_>,
+    LabelRef(label, offset): &LabelRef<L>,
+    open: char,
+    close: char,
+) -> core::fmt::Result {
+    write!(f, "{open}{label}")?;
+    if *offset != 0 {
+        write!(f, "{offset:+#x}")?;
+    }
+    write!(f, "{close}")
+}
+
+impl<L> Display for DecodeArg<L>
+where
+    L: Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeArg::Label(l) => write_labelref(f, l, '(', ')'),
+            DecodeArg::Literal(x) => write!(f, "{x:#x}"),
+        }
+    }
+}
+
+impl<L> Display for DecodeNode<L>
+where
+    L: Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DecodeNode::Branch(left, right) => write!(f, "{{ {left} {right} }}"),
+            DecodeNode::StringTerminator => f.write_str("end"),
+            DecodeNode::MysteryChar(c) => write!(f, "char {c:#x}"),
+            DecodeNode::MysteryString(s) => {
+                f.write_str("string ")?;
+                write_quoted(f, s)
+            }
+            DecodeNode::UnicodeChar(c) => write!(f, "unichar {:#x}", u32::from(*c)),
+            DecodeNode::Utf32String(s) => {
+                f.write_str("unistring ")?;
+                write_quoted(f, s)
+            }
+            DecodeNode::IndirectRef(r) => {
+                f.write_str("ref ")?;
+                write_labelref(f, r, '[', ']')
+            }
+            DecodeNode::DoubleIndirectRef(r) => {
+                f.write_str("dref ")?;
+                write_labelref(f, r, '[', ']')
+            }
+            DecodeNode::IndirectRefWithArgs(r, args)
+            | DecodeNode::DoubleIndirectRefWithArgs(r, args) => {
+                if let DecodeNode::IndirectRefWithArgs(_, _) = self {
+                    f.write_str("ref ")?;
+                } else {
+                    f.write_str("dref ")?;
+                }
+                write_labelref(f, r, '[', ']')?;
+                f.write_str(" <")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                f.write_str(" >")
+            }
+        }
+    }
+}
+
 impl ResolvedDecodeNode {
     pub(crate) fn count_nodes(&self) -> usize {
         match self {
@@ -210,27 +284,31 @@ impl ResolvedDecodeNode {
         }
     }
 
-    pub(crate) fn serialize<B>(&self, num: u32, mut buf: B)
+    /// Serializes the node, which is to be placed at address `position`.
+    pub(crate) fn serialize<B>(&self, position: u32, mut buf: B)
     where
         B: BufMut,
     {
-        self.serialize_inner(num, &mut buf)
+        self.serialize_inner(position, &mut buf)
     }
 
-    fn serialize_inner<B>(&self, num: u32, buf: &mut B)
+    fn serialize_inner<B>(&self, position: u32, buf: &mut B)
     where
         B: BufMut,
     {
         match self {
             ResolvedDecodeNode::Branch(left, right) => {
-                let panic_msg = "decode tables with >= 2**32 nodes should have been rejected before serialization";
-                let left_num = num.checked_add(1).expect(panic_msg);
-                let right_num = left_num
-                    .checked_add(left.count_nodes().try_into().expect(panic_msg))
+                let panic_msg =
+                    "decoding tables which overflow memory should have been rejected during layout";
+                let left_position = position.checked_add(9).expect(panic_msg);
+                let right_position = left_position
+                    .checked_add(left.len().try_into().expect(panic_msg))
                     .expect(panic_msg);
                 buf.put_u8(0);
-                left.serialize_inner(left_num, &mut *buf);
-                right.serialize_inner(right_num, &mut *buf);
+                buf.put_u32(left_position);
+                buf.put_u32(right_position);
+                left.serialize_inner(left_position, &mut *buf);
+                right.serialize_inner(right_position, &mut *buf);
             }
             ResolvedDecodeNode::StringTerminator => {
                 buf.put_u8(1);
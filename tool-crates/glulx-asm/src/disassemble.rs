@@ -0,0 +1,804 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! A best-effort disassembler which turns story files back into [`Item`]s.
+
+use alloc::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    vec,
+    vec::Vec,
+};
+use core::fmt::Display;
+
+use arrayvec::ArrayVec;
+use bytes::Bytes;
+
+use crate::{
+    cast::CastSign, error::DisassemblerError, Assembly, CallingConvention, Instr, Item, LabelRef,
+    LoadOperand, StoreOperand, ZeroItem,
+};
+
+/// Length of the story file header.
+const HEADER_LEN: u32 = 0x24;
+
+/// Magic number at the start of every story file: "Glul".
+const MAGIC: u32 = 0x476C756C;
+
+/// The label type of a disassembled [`Assembly`].
+///
+/// Each label is named for the address at which it stood in the original story
+/// file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Addr(pub u32);
+
+impl Display for Addr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "addr_{:08x}", self.0)
+    }
+}
+
+/// The parts of the story file header that the disassembler cares about.
+#[derive(Debug, Copy, Clone)]
+struct Header {
+    ramstart: u32,
+    extstart: u32,
+    endmem: u32,
+    stack_size: u32,
+    start_func: u32,
+    decoding_table: u32,
+}
+
+impl Header {
+    fn read(story: &[u8]) -> Result<Header, DisassemblerError> {
+        if story.len() < usize::try_from(HEADER_LEN).unwrap() {
+            return Err(DisassemblerError::TooShort);
+        }
+
+        let field = |n: usize| {
+            u32::from_be_bytes(
+                story[4 * n..4 * n + 4]
+                    .try_into()
+                    .expect("slice should have length 4"),
+            )
+        };
+
+        if field(0) != MAGIC {
+            return Err(DisassemblerError::BadMagic);
+        }
+
+        let header = Header {
+            ramstart: field(2),
+            extstart: field(3),
+            endmem: field(4),
+            stack_size: field(5),
+            start_func: field(6),
+            decoding_table: field(7),
+        };
+
+        let story_len = u32::try_from(story.len()).unwrap_or(u32::MAX);
+        if header.ramstart < HEADER_LEN
+            || header.ramstart > header.extstart
+            || header.extstart > header.endmem
+            || header.extstart > story_len
+        {
+            return Err(DisassemblerError::BadLayout);
+        }
+
+        Ok(header)
+    }
+}
+
+/// A decoded operand, before we've decided how to represent it.
+#[derive(Debug, Copy, Clone)]
+enum RawOperand {
+    Null,
+    Const(i32),
+    Addr(u32),
+    Stack,
+    Frame(u32),
+    Ram(u32),
+}
+
+/// Decides how addresses found in operands are turned into labels.
+trait Labeler {
+    type Label;
+
+    /// Returns a reference to the given address, which an operand dereferences.
+    fn deref(&mut self, addr: u32) -> LabelRef<Self::Label>;
+
+    /// Returns a label for the given branch target.
+    fn branch(&mut self, addr: u32) -> Self::Label;
+}
+
+/// Labeler for the discovery pass, which keeps addresses as they are and
+/// records every one it sees.
+#[derive(Debug, Default)]
+struct Recorder {
+    derefs: Vec<u32>,
+    branches: Vec<u32>,
+}
+
+impl Labeler for Recorder {
+    type Label = u32;
+
+    fn deref(&mut self, addr: u32) -> LabelRef<u32> {
+        self.derefs.push(addr);
+        LabelRef(addr, 0)
+    }
+
+    fn branch(&mut self, addr: u32) -> u32 {
+        self.branches.push(addr);
+        addr
+    }
+}
+
+/// Labeler for the output pass, which expresses each address relative to the
+/// nearest item boundary at or below it, and records which labels it used.
+#[derive(Debug)]
+struct Anchors<'a> {
+    boundaries: &'a BTreeSet<u32>,
+    labels: &'a mut BTreeSet<u32>,
+}
+
+impl Anchors<'_> {
+    fn anchor(&mut self, addr: u32) -> LabelRef<Addr> {
+        let base = self
+            .boundaries
+            .range(..=addr)
+            .next_back()
+            .copied()
+            .unwrap_or(HEADER_LEN);
+        self.labels.insert(base);
+        LabelRef(Addr(base), addr.wrapping_sub(base).cast_sign())
+    }
+}
+
+impl Labeler for Anchors<'_> {
+    type Label = Addr;
+
+    fn deref(&mut self, addr: u32) -> LabelRef<Addr> {
+        self.anchor(addr)
+    }
+
+    fn branch(&mut self, addr: u32) -> Addr {
+        self.labels.insert(addr);
+        Addr(addr)
+    }
+}
+
+/// A cursor for decoding instructions out of ROM.
+#[derive(Debug)]
+struct Decoder<'a> {
+    story: &'a [u8],
+    ramstart: u32,
+    pos: u32,
+    modes: ArrayVec<u8, 8>,
+    next_mode: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(story: &'a [u8], header: &Header, pos: u32) -> Self {
+        Decoder {
+            story,
+            ramstart: header.ramstart,
+            pos,
+            modes: ArrayVec::new(),
+            next_mode: 0,
+        }
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let len = u32::try_from(N).ok()?;
+        let end = self.pos.checked_add(len)?;
+        if end > self.ramstart {
+            return None;
+        }
+        let start = usize::try_from(self.pos).ok()?;
+        let bytes = self.story.get(start..start.checked_add(N)?)?;
+        self.pos = end;
+        bytes.try_into().ok()
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        let [b] = self.bytes()?;
+        Some(b)
+    }
+
+    fn opcode(&mut self) -> Option<u32> {
+        let first = self.byte()?;
+        Some(if first < 0x80 {
+            first.into()
+        } else if first < 0xc0 {
+            let [second] = self.bytes()?;
+            u32::from(u16::from_be_bytes([first, second]) - 0x8000)
+        } else {
+            let [b1, b2, b3] = self.bytes()?;
+            u32::from_be_bytes([first, b1, b2, b3]) - 0xc000_0000
+        })
+    }
+
+    /// Reads the addressing modes for an instruction with `n` operands.
+    fn modes(&mut self, n: usize) -> Option<()> {
+        self.modes.clear();
+        self.next_mode = 0;
+        for _ in 0..n.div_ceil(2) {
+            let b = self.byte()?;
+            self.modes.push(b & 0xf);
+            self.modes.push(b >> 4);
+        }
+        Some(())
+    }
+
+    fn raw(&mut self) -> Option<RawOperand> {
+        let mode = *self.modes.get(self.next_mode)?;
+        self.next_mode += 1;
+        Some(match mode {
+            0x0 => RawOperand::Null,
+            0x1 => RawOperand::Const(i8::from_be_bytes(self.bytes()?).into()),
+            0x2 => RawOperand::Const(i16::from_be_bytes(self.bytes()?).into()),
+            0x3 => RawOperand::Const(i32::from_be_bytes(self.bytes()?)),
+            0x5 => RawOperand::Addr(u8::from_be_bytes(self.bytes()?).into()),
+            0x6 => RawOperand::Addr(u16::from_be_bytes(self.bytes()?).into()),
+            0x7 => RawOperand::Addr(u32::from_be_bytes(self.bytes()?)),
+            0x8 => RawOperand::Stack,
+            0x9 => RawOperand::Frame(u8::from_be_bytes(self.bytes()?).into()),
+            0xa => RawOperand::Frame(u16::from_be_bytes(self.bytes()?).into()),
+            0xb => RawOperand::Frame(u32::from_be_bytes(self.bytes()?)),
+            0xd => RawOperand::Ram(u8::from_be_bytes(self.bytes()?).into()),
+            0xe => RawOperand::Ram(u16::from_be_bytes(self.bytes()?).into()),
+            0xf => RawOperand::Ram(u32::from_be_bytes(self.bytes()?)),
+            _ => return None,
+        })
+    }
+
+    fn deref<B: Labeler>(&self, raw: RawOperand, lab: &mut B) -> Option<LabelRef<B::Label>> {
+        match raw {
+            RawOperand::Addr(addr) => Some(lab.deref(addr)),
+            RawOperand::Ram(offset) => Some(lab.deref(self.ramstart.checked_add(offset)?)),
+            _ => None,
+        }
+    }
+
+    fn load<B: Labeler>(&mut self, lab: &mut B) -> Option<LoadOperand<B::Label>> {
+        let raw = self.raw()?;
+        Some(match raw {
+            RawOperand::Null => LoadOperand::Imm(0),
+            RawOperand::Const(x) => LoadOperand::Imm(x),
+            RawOperand::Stack => LoadOperand::Pop,
+            RawOperand::Frame(x) => LoadOperand::FrameAddr(x),
+            RawOperand::Addr(_) | RawOperand::Ram(_) => {
+                LoadOperand::DerefLabel(self.deref(raw, lab)?)
+            }
+        })
+    }
+
+    fn store<B: Labeler>(&mut self, lab: &mut B) -> Option<StoreOperand<B::Label>> {
+        let raw = self.raw()?;
+        Some(match raw {
+            RawOperand::Null => StoreOperand::Discard,
+            RawOperand::Const(_) => return None,
+            RawOperand::Stack => StoreOperand::Push,
+            RawOperand::Frame(x) => StoreOperand::FrameAddr(x),
+            RawOperand::Addr(_) | RawOperand::Ram(_) => {
+                StoreOperand::DerefLabel(self.deref(raw, lab)?)
+            }
+        })
+    }
+
+    /// Decodes a branch offset. Glulx only ever puts these last, so the
+    /// current position is the end of the instruction.
+    fn branch<B: Labeler>(&mut self, lab: &mut B) -> Option<LoadOperand<B::Label>> {
+        let raw = self.raw()?;
+        Some(match raw {
+            RawOperand::Null => LoadOperand::Imm(0),
+            RawOperand::Const(x @ (0 | 1)) => LoadOperand::Imm(x),
+            RawOperand::Const(x) => {
+                LoadOperand::Branch(lab.branch(self.pos.wrapping_add_signed(x).wrapping_sub(2)))
+            }
+            RawOperand::Stack => LoadOperand::Pop,
+            RawOperand::Frame(x) => LoadOperand::FrameAddr(x),
+            RawOperand::Addr(_) | RawOperand::Ram(_) => {
+                LoadOperand::DerefLabel(self.deref(raw, lab)?)
+            }
+        })
+    }
+
+    /// Decodes a function header, returning its calling convention and its
+    /// number of locals if it's in the form [`Item::FnHeader`] produces.
+    fn fn_header(&mut self) -> Option<Option<(CallingConvention, u32)>> {
+        let cc = match self.byte()? {
+            0xc0 => CallingConvention::ArgsOnStack,
+            0xc1 => CallingConvention::ArgsInLocals,
+            _ => return None,
+        };
+
+        let mut records = Vec::new();
+        loop {
+            let [size, count] = self.bytes()?;
+            if size == 0 && count == 0 {
+                break;
+            }
+            records.push((size, count));
+        }
+
+        let nlocals: u32 = records.iter().map(|&(_, count)| u32::from(count)).sum();
+        let mut canonical = vec![(4, 255); usize::try_from(nlocals / 255).ok()?];
+        if !nlocals.is_multiple_of(255) {
+            canonical.push((4, u8::try_from(nlocals % 255).ok()?));
+        }
+
+        Some((records == canonical).then_some((cc, nlocals)))
+    }
+}
+
+/// Supplies the operands of an instruction being built by [`build_instr`], in
+/// encoding order.
+pub(crate) trait OperandSource<L> {
+    /// Called before any operand is requested, with the number of operands the
+    /// instruction takes.
+    fn start(&mut self, n: usize) -> Option<()>;
+    fn load(&mut self) -> Option<LoadOperand<L>>;
+    fn store(&mut self) -> Option<StoreOperand<L>>;
+    fn branch(&mut self) -> Option<LoadOperand<L>>;
+}
+
+/// Supplies operands by decoding them from ROM.
+struct DecodeSource<'a, 'b, B> {
+    d: &'a mut Decoder<'b>,
+    lab: &'a mut B,
+}
+
+impl<B: Labeler> OperandSource<B::Label> for DecodeSource<'_, '_, B> {
+    fn start(&mut self, n: usize) -> Option<()> {
+        self.d.modes(n)
+    }
+
+    fn load(&mut self) -> Option<LoadOperand<B::Label>> {
+        self.d.load(self.lab)
+    }
+
+    fn store(&mut self) -> Option<StoreOperand<B::Label>> {
+        self.d.store(self.lab)
+    }
+
+    fn branch(&mut self) -> Option<LoadOperand<B::Label>> {
+        self.d.branch(self.lab)
+    }
+}
+
+/// Builds an instruction out of operands supplied in order.
+macro_rules! op {
+    ($s:ident, $variant:ident) => {{
+        $s.start(0)?;
+        Instr::$variant
+    }};
+    ($s:ident, $variant:ident, $($kind:ident),*) => {{
+        $s.start([$(stringify!($kind)),*].len())?;
+        Instr::$variant($($s.$kind()?),*)
+    }};
+}
+
+/// Decodes the instruction at the decoder's position, or returns `None` if
+/// there isn't a valid one there.
+fn decode_instr<B: Labeler>(d: &mut Decoder<'_>, lab: &mut B) -> Option<Instr<B::Label>> {
+    let opcode = d.opcode()?;
+    build_instr(opcode, &mut DecodeSource { d, lab })
+}
+
+/// Builds the instruction with the given opcode, taking its operands from `s`,
+/// or returns `None` if there is no such instruction or `s` can't supply its
+/// operands.
+pub(crate) fn build_instr<L, S: OperandSource<L>>(opcode: u32, s: &mut S) -> Option<Instr<L>> {
+    Some(match opcode {
+        0x00 => op!(s, Nop),
+        0x10 => op!(s, Add, load, load, store),
+        0x11 => op!(s, Sub, load, load, store),
+        0x12 => op!(s, Mul, load, load, store),
+        0x13 => op!(s, Div, load, load, store),
+        0x14 => op!(s, Mod, load, load, store),
+        0x15 => op!(s, Neg, load, store),
+        0x18 => op!(s, Bitand, load, load, store),
+        0x19 => op!(s, Bitor, load, load, store),
+        0x1A => op!(s, Bitxor, load, load, store),
+        0x1B => op!(s, Bitnot, load, store),
+        0x1C => op!(s, Shiftl, load, load, store),
+        0x1D => op!(s, Sshiftr, load, load, store),
+        0x1E => op!(s, Ushiftr, load, load, store),
+        0x20 => op!(s, Jump, branch),
+        0x22 => op!(s, Jz, load, branch),
+        0x23 => op!(s, Jnz, load, branch),
+        0x24 => op!(s, Jeq, load, load, branch),
+        0x25 => op!(s, Jne, load, load, branch),
+        0x26 => op!(s, Jlt, load, load, branch),
+        0x27 => op!(s, Jge, load, load, branch),
+        0x28 => op!(s, Jgt, load, load, branch),
+        0x29 => op!(s, Jle, load, load, branch),
+        0x2A => op!(s, Jltu, load, load, branch),
+        0x2B => op!(s, Jgeu, load, load, branch),
+        0x2C => op!(s, Jgtu, load, load, branch),
+        0x2D => op!(s, Jleu, load, load, branch),
+        0x30 => op!(s, Call, load, load, store),
+        0x31 => op!(s, Return, load),
+        0x32 => op!(s, Catch, store, branch),
+        0x33 => op!(s, Throw, load, load),
+        0x34 => op!(s, Tailcall, load, load),
+        0x40 => op!(s, Copy, load, store),
+        0x41 => op!(s, Copys, load, store),
+        0x42 => op!(s, Copyb, load, store),
+        0x44 => op!(s, Sexs, load, store),
+        0x45 => op!(s, Sexb, load, store),
+        0x48 => op!(s, Aload, load, load, store),
+        0x49 => op!(s, Aloads, load, load, store),
+        0x4A => op!(s, Aloadb, load, load, store),
+        0x4B => op!(s, Aloadbit, load, load, store),
+        0x4C => op!(s, Astore, load, load, load),
+        0x4D => op!(s, Astores, load, load, load),
+        0x4E => op!(s, Astoreb, load, load, load),
+        0x4F => op!(s, Astorebit, load, load, load),
+        0x50 => op!(s, Stkcount, store),
+        0x51 => op!(s, Stkpeek, load, store),
+        0x52 => op!(s, Stkswap),
+        0x53 => op!(s, Stkroll, load, load),
+        0x54 => op!(s, Stkcopy, load),
+        0x70 => op!(s, Streamchar, load),
+        0x71 => op!(s, Streamnum, load),
+        0x72 => op!(s, Streamstr, load),
+        0x73 => op!(s, Streamunichar, load),
+        0x100 => op!(s, Gestalt, load, load, store),
+        0x101 => op!(s, Debugtrap, load),
+        0x102 => op!(s, Getmemsize, store),
+        0x103 => op!(s, Setmemsize, load, store),
+        0x104 => op!(s, Jumpabs, load),
+        0x110 => op!(s, Random, load, store),
+        0x111 => op!(s, Setrandom, load),
+        0x120 => op!(s, Quit),
+        0x121 => op!(s, Verify, store),
+        0x122 => op!(s, Restart),
+        0x123 => op!(s, Save, load, store),
+        0x124 => op!(s, Restore, load, store),
+        0x125 => op!(s, Saveundo, store),
+        0x126 => op!(s, Restoreundo, store),
+        0x127 => op!(s, Protect, load, load),
+        0x128 => op!(s, Hasundo, store),
+        0x129 => op!(s, Discardundo),
+        0x130 => op!(s, Glk, load, load, store),
+        0x140 => op!(s, Getstringtbl, store),
+        0x141 => op!(s, Setstringtbl, load),
+        0x148 => op!(s, Getiosys, store, store),
+        0x149 => op!(s, Setiosys, load, load),
+        0x150 => op!(
+            s,
+            Linearsearch,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            store
+        ),
+        0x151 => op!(
+            s,
+            Binarysearch,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            store
+        ),
+        0x152 => op!(s, Linkedsearch, load, load, load, load, load, load, store),
+        0x160 => op!(s, Callf, load, store),
+        0x161 => op!(s, Callfi, load, load, store),
+        0x162 => op!(s, Callfii, load, load, load, store),
+        0x163 => op!(s, Callfiii, load, load, load, load, store),
+        0x170 => op!(s, Mzero, load, load),
+        0x171 => op!(s, Mcopy, load, load, load),
+        0x178 => op!(s, Malloc, load, store),
+        0x179 => op!(s, Mfree, load),
+        0x180 => op!(s, Accelfunc, load, load),
+        0x181 => op!(s, Accelparam, load, load),
+        0x190 => op!(s, Numtof, load, store),
+        0x191 => op!(s, Ftonumz, load, store),
+        0x192 => op!(s, Ftonumn, load, store),
+        0x198 => op!(s, Ceil, load, store),
+        0x199 => op!(s, Floor, load, store),
+        0x1A0 => op!(s, Fadd, load, load, store),
+        0x1A1 => op!(s, Fsub, load, load, store),
+        0x1A2 => op!(s, Fmul, load, load, store),
+        0x1A3 => op!(s, Fdiv, load, load, store),
+        0x1A4 => op!(s, Fmod, load, load, store, store),
+        0x1A8 => op!(s, Sqrt, load, store),
+        0x1A9 => op!(s, Exp, load, store),
+        0x1AA => op!(s, Log, load, store),
+        0x1AB => op!(s, Pow, load, load, store),
+        0x1B0 => op!(s, Sin, load, store),
+        0x1B1 => op!(s, Cos, load, store),
+        0x1B2 => op!(s, Tan, load, store),
+        0x1B3 => op!(s, Asin, load, store),
+        0x1B4 => op!(s, Acos, load, store),
+        0x1B5 => op!(s, Atan, load, store),
+        0x1B6 => op!(s, Atan2, load, store),
+        0x1C0 => op!(s, Jfeq, load, load, load, branch),
+        0x1C1 => op!(s, Jfne, load, load, load, branch),
+        0x1C2 => op!(s, Jflt, load, load, branch),
+        0x1C3 => op!(s, Jfle, load, load, branch),
+        0x1C4 => op!(s, Jfgt, load, load, branch),
+        0x1C5 => op!(s, Jfge, load, load, branch),
+        0x1C8 => op!(s, Jisnan, load, branch),
+        0x1C9 => op!(s, Jisinf, load, branch),
+        0x200 => op!(s, Numtod, load, store, store),
+        0x201 => op!(s, Dtonumz, load, load, store),
+        0x202 => op!(s, Dtonumn, load, load, store),
+        0x203 => op!(s, Ftod, load, store, store),
+        0x204 => op!(s, Dtof, load, load, store),
+        0x208 => op!(s, Dceil, load, load, store, store),
+        0x209 => op!(s, Dfloor, load, load, store, store),
+        0x210 => op!(s, Dadd, load, load, load, load, store, store),
+        0x211 => op!(s, Dsub, load, load, load, load, store, store),
+        0x212 => op!(s, Dmul, load, load, load, load, store, store),
+        0x213 => op!(s, Ddiv, load, load, load, load, store, store),
+        0x214 => op!(s, Dmodr, load, load, load, load, store, store),
+        0x215 => op!(s, Dmodq, load, load, load, load, store, store),
+        0x218 => op!(s, Dsqrt, load, load, store, store),
+        0x219 => op!(s, Dexp, load, load, store, store),
+        0x21A => op!(s, Dlog, load, load, store, store),
+        0x21B => op!(s, Dpow, load, load, load, load, store, store),
+        0x220 => op!(s, Dsin, load, load, store, store),
+        0x221 => op!(s, Dcos, load, load, store, store),
+        0x222 => op!(s, Dtan, load, load, store, store),
+        0x223 => op!(s, Dasin, load, load, store, store),
+        0x224 => op!(s, Dacos, load, load, store, store),
+        0x225 => op!(s, Datan, load, load, store, store),
+        0x226 => op!(s, Datan2, load, load, load, load, store, store),
+        0x230 => op!(s, Jdeq, load, load, load, load, load, load, branch),
+        0x231 => op!(s, Jdne, load, load, load, load, load, load, branch),
+        0x232 => op!(s, Jdlt, load, load, load, load, branch),
+        0x233 => op!(s, Jdle, load, load, load, load, branch),
+        0x234 => op!(s, Jdgt, load, load, load, load, branch),
+        0x235 => op!(s, Jdge, load, load, load, load, branch),
+        0x238 => op!(s, Jdisnan, load, load, branch),
+        0x239 => op!(s, Jdisinf, load, load, branch),
+        _ => return None,
+    })
+}
+
+/// Returns whether execution can continue to the instruction following this
+/// one.
+fn falls_through<L>(instr: &Instr<L>) -> bool {
+    !matches!(
+        instr,
+        Instr::Return(_)
+            | Instr::Tailcall(_, _)
+            | Instr::Jump(_)
+            | Instr::Jumpabs(_)
+            | Instr::Throw(_, _)
+            | Instr::Quit
+            | Instr::Restart
+    )
+}
+
+/// Returns the address of the function that this instruction calls, if it's a
+/// call to a constant address.
+fn call_target(instr: &Instr<u32>) -> Option<u32> {
+    match instr {
+        Instr::Call(LoadOperand::Imm(x), ..)
+        | Instr::Callf(LoadOperand::Imm(x), ..)
+        | Instr::Callfi(LoadOperand::Imm(x), ..)
+        | Instr::Callfii(LoadOperand::Imm(x), ..)
+        | Instr::Callfiii(LoadOperand::Imm(x), ..)
+        | Instr::Tailcall(LoadOperand::Imm(x), ..) => Some(u32::from_be_bytes(x.to_be_bytes())),
+        _ => None,
+    }
+}
+
+/// Returns whether any of the given non-overlapping ranges, keyed by start and
+/// valued by end, overlaps `start..end`.
+fn overlaps(ranges: &BTreeMap<u32, u32>, start: u32, end: u32) -> bool {
+    ranges
+        .range(..end)
+        .next_back()
+        .is_some_and(|(_, &range_end)| range_end > start)
+}
+
+/// A function found during the discovery pass.
+#[derive(Debug)]
+struct Function {
+    header_end: u32,
+    header: Option<(CallingConvention, u32)>,
+    /// Start and end of each instruction.
+    instrs: BTreeMap<u32, u32>,
+    derefs: Vec<u32>,
+    calls: Vec<u32>,
+}
+
+/// Decodes the function at `addr`, following every branch, and returns `None`
+/// if anything about it fails to make sense as code: an invalid opcode or
+/// operand, a branch out of ROM, or an instruction overlapping another one or
+/// any code previously claimed.
+fn explore(
+    story: &[u8],
+    header: &Header,
+    addr: u32,
+    claimed: &BTreeMap<u32, u32>,
+) -> Option<Function> {
+    if !(HEADER_LEN..header.ramstart).contains(&addr) {
+        return None;
+    }
+
+    let mut d = Decoder::new(story, header, addr);
+    let fn_header = d.fn_header()?;
+    let header_end = d.pos;
+    if overlaps(claimed, addr, header_end) {
+        return None;
+    }
+
+    let mut ranges = BTreeMap::from([(addr, header_end)]);
+    let mut function = Function {
+        header_end,
+        header: fn_header,
+        instrs: BTreeMap::new(),
+        derefs: Vec::new(),
+        calls: Vec::new(),
+    };
+    let mut pending = vec![header_end];
+
+    while let Some(start) = pending.pop() {
+        // Branching to an instruction already known to be code, even in
+        // another function, is fine.
+        if function.instrs.contains_key(&start) || claimed.contains_key(&start) {
+            continue;
+        }
+
+        let mut d = Decoder::new(story, header, start);
+        let mut recorder = Recorder::default();
+        let instr = decode_instr(&mut d, &mut recorder)?;
+        let end = d.pos;
+
+        if overlaps(&ranges, start, end) || overlaps(claimed, start, end) {
+            return None;
+        }
+        ranges.insert(start, end);
+        function.instrs.insert(start, end);
+
+        for &target in &recorder.branches {
+            if !(HEADER_LEN..header.ramstart).contains(&target) {
+                return None;
+            }
+            pending.push(target);
+        }
+        if falls_through(&instr) {
+            pending.push(end);
+        }
+        function.derefs.extend(recorder.derefs);
+        function.calls.extend(call_target(&instr));
+    }
+
+    Some(function)
+}
+
+/// Disassembles a story file.
+///
+/// Code is found by recursive descent from the start function, following
+/// branches and calls to constant addresses. Functions which are only ever
+/// reached indirectly, and anything else which can't be shown to be code, such
+/// as strings and tables, come out as [`Item::Blob`]s, split wherever an
+/// instruction refers into them. RAM likewise comes out as blobs, and the zero
+/// region as [`ZeroItem::Space`]. Every address that an instruction
+/// dereferences is expressed as an offset from the nearest label, so the
+/// result can be freely edited before reassembling it.
+///
+/// Reassembling the result without modification reproduces the original story
+/// file exactly when it was encoded the way this crate encodes things, which
+/// includes every story file this crate produced. Other compilers sometimes
+/// choose wider operand encodings or function header layouts than necessary;
+/// the result for such files is equivalent but not identical.
+pub fn disassemble(story: &[u8]) -> Result<Assembly<'static, Addr>, DisassemblerError> {
+    let header = Header::read(story)?;
+
+    let mut claimed = BTreeMap::new();
+    let mut functions = BTreeMap::new();
+    let mut tried = BTreeSet::new();
+    let mut roots = vec![header.start_func];
+    let mut derefs = BTreeSet::new();
+
+    while let Some(root) = roots.pop() {
+        if !tried.insert(root) {
+            continue;
+        }
+        let Some(function) = explore(story, &header, root, &claimed) else {
+            continue;
+        };
+        claimed.insert(root, function.header_end);
+        claimed.extend(function.instrs.iter().map(|(&start, &end)| (start, end)));
+        derefs.extend(function.derefs.iter().copied());
+        roots.extend(function.calls.iter().copied());
+        functions.insert(root, function);
+    }
+
+    // Items begin at each of these addresses, and every reference into an
+    // item is expressed relative to the start of it.
+    let mut boundaries = BTreeSet::from([HEADER_LEN, header.ramstart, header.extstart]);
+    for (&start, &end) in &claimed {
+        boundaries.insert(start);
+        boundaries.insert(end);
+    }
+    let data_refs = derefs
+        .iter()
+        .copied()
+        .chain([header.start_func, header.decoding_table]);
+    for addr in data_refs {
+        if (HEADER_LEN..header.endmem).contains(&addr) && !overlaps(&claimed, addr, addr + 1) {
+            boundaries.insert(addr);
+        }
+    }
+
+    let mut labels: BTreeSet<u32> = functions.keys().copied().collect();
+    let mut anchors = Anchors {
+        boundaries: &boundaries,
+        labels: &mut labels,
+    };
+
+    let start_func = anchors.anchor(header.start_func);
+    let decoding_table =
+        (header.decoding_table != 0).then(|| anchors.anchor(header.decoding_table));
+
+    let mut instrs = BTreeMap::new();
+    for function in functions.values() {
+        for &start in function.instrs.keys() {
+            let mut d = Decoder::new(story, &header, start);
+            let instr = decode_instr(&mut d, &mut anchors)
+                .expect("an instruction that decoded once should decode again");
+            instrs.insert(start, instr);
+        }
+    }
+
+    let mut rom_items = Vec::new();
+    let mut ram_items = Vec::new();
+    let mut zero_items = Vec::new();
+
+    let mut starts: Vec<u32> = boundaries.range(..header.endmem).copied().collect();
+    starts.push(header.endmem);
+
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(start);
+        let len = end.saturating_sub(start);
+        let label = labels.contains(&start).then_some(Addr(start));
+        let bytes = || {
+            let range = usize::try_from(start).unwrap()..usize::try_from(end).unwrap();
+            Bytes::copy_from_slice(&story[range])
+        };
+
+        if start < header.ramstart {
+            rom_items.extend(label.map(Item::Label));
+            if let Some(instr) = instrs.remove(&start) {
+                rom_items.push(Item::Instr(instr));
+            } else if let Some(Some((cc, nlocals))) = functions.get(&start).map(|f| f.header) {
+                rom_items.push(Item::FnHeader(cc, nlocals));
+            } else {
+                rom_items.push(Item::Blob(bytes()));
+            }
+        } else if start < header.extstart {
+            ram_items.extend(label.map(Item::Label));
+            ram_items.push(Item::Blob(bytes()));
+        } else {
+            zero_items.extend(label.map(ZeroItem::Label));
+            if len != 0 {
+                zero_items.push(ZeroItem::Space(len));
+            }
+        }
+    }
+
+    Ok(Assembly {
+        rom_items: Cow::Owned(rom_items),
+        ram_items: Cow::Owned(ram_items),
+        zero_items: Cow::Owned(zero_items),
+        stack_size: header.stack_size,
+        start_func,
+        decoding_table,
+    })
+}
@@ -1,8 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
 // Copyright 2024 Daniel Fox Franke.
 
-//! Definition and impls for [`AssemblerError`].
+//! Definitions and impls for [`AssemblerError`], [`DisassemblerError`], and
+//! [`ParseError`].
 
+use alloc::string::String;
 use core::fmt::{Debug, Display};
 
 #[derive(Debug, Copy, Clone)]
@@ -23,6 +25,13 @@ pub enum AssemblerError<L> {
         /// The attempted right-shift amount.
         shift: u8,
     },
+    /// A caller-provided output buffer was too small to hold the story file.
+    BufferTooSmall {
+        /// The length of the story file.
+        needed: usize,
+        /// The length of the buffer that was provided.
+        available: usize,
+    },
 }
 
 impl<L> Display for AssemblerError<L>
@@ -42,9 +51,156 @@ where
                 f,
                 "label {label} + offset {offset} is insufficiently aligned to be shifted by {shift}"
             ),
+            AssemblerError::BufferTooSmall { needed, available } => write!(
+                f,
+                "output buffer too small: need {needed} bytes but only {available} available"
+            ),
         }
     }
 }
 
 #[cfg(feature = "std")]
 impl<L> std::error::Error for AssemblerError<L> where L: Debug + Display {}
+
+/// Errors that can occur while assembling directly to a writer.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub enum WriteError<L> {
+    /// Assembly failed.
+    Assembler(AssemblerError<L>),
+    /// Writing the output failed.
+    Io(std::io::Error),
+}
+
+#[cfg(feature = "std")]
+impl<L> From<AssemblerError<L>> for WriteError<L> {
+    fn from(e: AssemblerError<L>) -> Self {
+        WriteError::Assembler(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<L> From<std::io::Error> for WriteError<L> {
+    fn from(e: std::io::Error) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<L> Display for WriteError<L>
+where
+    L: Display,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WriteError::Assembler(e) => write!(f, "{e}"),
+            WriteError::Io(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<L> std::error::Error for WriteError<L>
+where
+    L: Debug + Display,
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WriteError::Assembler(_) => None,
+            WriteError::Io(e) => Some(e),
+        }
+    }
+}
+
+/// Errors that can occur during disassembly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum DisassemblerError {
+    /// The input is too short to contain a story file header.
+    TooShort,
+    /// The input does not begin with the Glulx magic number.
+    BadMagic,
+    /// The header's memory layout is inconsistent, or describes a story file
+    /// longer than the input.
+    BadLayout,
+}
+
+impl Display for DisassemblerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DisassemblerError::TooShort => write!(f, "input is too short to be a story file"),
+            DisassemblerError::BadMagic => write!(f, "input is not a Glulx story file"),
+            DisassemblerError::BadLayout => {
+                write!(f, "story file header has an invalid memory layout")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DisassemblerError {}
+
+/// An error encountered while parsing an assembly listing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseError {
+    /// The line on which the error occurred, counting from 1.
+    pub line: usize,
+    /// What went wrong.
+    pub kind: ParseErrorKind,
+}
+
+/// The kinds of [`ParseError`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    /// A line began with a directive that doesn't exist.
+    UnknownDirective(String),
+    /// A line began with a mnemonic that doesn't exist.
+    UnknownInstruction(String),
+    /// A directive or instruction appeared in a section where it isn't
+    /// allowed, such as `.space` outside of `.zero_items`.
+    Misplaced(String),
+    /// An instruction was given the wrong number of operands.
+    OperandCount {
+        /// The number of operands the instruction takes.
+        expected: usize,
+        /// The number of operands that were given.
+        found: usize,
+    },
+    /// A token couldn't be parsed as what belongs in its position.
+    BadToken(String),
+    /// A string literal was malformed, or contained characters which its
+    /// string type can't represent.
+    BadString,
+    /// A line ended before everything it needed was given.
+    UnexpectedEnd,
+    /// A line continued after everything it needed was given.
+    TrailingInput(String),
+    /// A required directive never appeared.
+    Missing(&'static str),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParseErrorKind::UnknownDirective(d) => write!(f, "unknown directive .{d}"),
+            ParseErrorKind::UnknownInstruction(i) => write!(f, "unknown instruction {i}"),
+            ParseErrorKind::Misplaced(what) => write!(f, "{what} is not allowed in this section"),
+            ParseErrorKind::OperandCount { expected, found } => {
+                write!(f, "expected {expected} operands but found {found}")
+            }
+            ParseErrorKind::BadToken(t) => write!(f, "could not parse {t:?}"),
+            ParseErrorKind::BadString => write!(f, "invalid string literal"),
+            ParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of line"),
+            ParseErrorKind::TrailingInput(t) => write!(f, "unexpected {t:?} at end of line"),
+            ParseErrorKind::Missing(d) => write!(f, "missing .{d} directive"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
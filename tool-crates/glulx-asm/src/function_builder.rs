@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Definition and impls for [`FunctionBuilder`] and [`Local`].
+
+use alloc::vec::Vec;
+
+use crate::items::{CallingConvention, Item};
+use crate::operands::{LoadOperand, StoreOperand};
+
+/// A local variable allocated by a [`FunctionBuilder`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Local(u32);
+
+impl Local {
+    /// Returns the index of this local, counting in four-byte words from the
+    /// start of the frame.
+    pub fn index(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the local `n` words after this one, for addressing the words of
+    /// a local allocated with [`FunctionBuilder::local_words`].
+    pub fn word(self, n: u32) -> Local {
+        Local(self.0 + n)
+    }
+
+    /// Returns a load operand which reads this local.
+    pub fn load<L>(self) -> LoadOperand<L> {
+        LoadOperand::FrameAddr(4 * self.0)
+    }
+
+    /// Returns a store operand which writes this local.
+    pub fn store<L>(self) -> StoreOperand<L> {
+        StoreOperand::FrameAddr(4 * self.0)
+    }
+}
+
+/// Builds a function's items while keeping track of its locals.
+///
+/// Rather than choosing local indices by hand and counting them up for the
+/// function header, allocate each local by name with [`arg`](Self::arg),
+/// [`local`](Self::local) or [`local_words`](Self::local_words), and use the
+/// returned [`Local`]'s [`load`](Local::load) and [`store`](Local::store)
+/// methods to construct operands. [`finish`](Self::finish) emits the label and
+/// a header declaring exactly as many locals as were allocated, followed by
+/// the function body.
+///
+/// Allocating the same name twice, or calling `arg` after `local` or for a
+/// function with the `ArgsOnStack` calling convention, is a bug in the caller
+/// and panics.
+#[derive(Debug, Clone)]
+pub struct FunctionBuilder<L> {
+    label: L,
+    convention: CallingConvention,
+    names: Vec<(&'static str, Local)>,
+    nlocals: u32,
+    args_done: bool,
+    items: Vec<Item<L>>,
+}
+
+impl<L> FunctionBuilder<L> {
+    /// Starts building a function with the given label and calling convention.
+    pub fn new(label: L, convention: CallingConvention) -> Self {
+        FunctionBuilder {
+            label,
+            convention,
+            names: Vec::new(),
+            nlocals: 0,
+            args_done: false,
+            items: Vec::new(),
+        }
+    }
+
+    fn alloc(&mut self, name: &'static str, words: u32) -> Local {
+        assert!(
+            self.get(name).is_none(),
+            "local {name:?} allocated more than once"
+        );
+        let local = Local(self.nlocals);
+        self.nlocals = self
+            .nlocals
+            .checked_add(words)
+            .expect("local count should not overflow");
+        self.names.push((name, local));
+        local
+    }
+
+    /// Allocates the local which receives the next argument. Arguments are
+    /// received in order, so this must be called once for each parameter,
+    /// first to last, before any other locals are allocated.
+    pub fn arg(&mut self, name: &'static str) -> Local {
+        assert!(
+            self.convention == CallingConvention::ArgsInLocals,
+            "arg {name:?} allocated for a function which takes its arguments on the stack"
+        );
+        assert!(!self.args_done, "arg {name:?} allocated after other locals");
+        self.alloc(name, 1)
+    }
+
+    /// Allocates a one-word local.
+    pub fn local(&mut self, name: &'static str) -> Local {
+        self.local_words(name, 1)
+    }
+
+    /// Allocates `words` consecutive one-word locals, returning the first.
+    /// Use [`Local::word`] to address the rest.
+    pub fn local_words(&mut self, name: &'static str, words: u32) -> Local {
+        self.args_done = true;
+        self.alloc(name, words)
+    }
+
+    /// Returns the local previously allocated with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<Local> {
+        self.names
+            .iter()
+            .find_map(|(n, local)| (*n == name).then_some(*local))
+    }
+
+    /// Returns the number of locals allocated so far.
+    pub fn nlocals(&self) -> u32 {
+        self.nlocals
+    }
+
+    /// Appends an item to the function body.
+    pub fn push(&mut self, item: Item<L>) {
+        self.items.push(item);
+    }
+
+    /// Consumes the builder, returning the function's label, its header, and
+    /// its body.
+    pub fn finish(self) -> Vec<Item<L>> {
+        let mut items = Vec::with_capacity(self.items.len() + 2);
+        items.push(Item::Label(self.label));
+        items.push(Item::FnHeader(self.convention, self.nlocals));
+        items.extend(self.items);
+        items
+    }
+}
+
+impl<L> Extend<Item<L>> for FunctionBuilder<L> {
+    fn extend<T: IntoIterator<Item = Item<L>>>(&mut self, iter: T) {
+        self.items.extend(iter);
+    }
+}
@@ -510,15 +510,15 @@ pub enum Instr<L> {
     /// These systems are currently defined:
 
     /// * 0: The null system. All output is discarded. (When the Glulx machine
-    ///    starts up, this is the current system.)
+    ///   starts up, this is the current system.)
     ///
     /// * 1: The filtering system. The rock (L2) value should be the address of a
-    ///    Glulx function. This function will be called for every character output
-    ///    (with the character value as its sole argument). The function's return
-    ///    value is ignored.
+    ///   Glulx function. This function will be called for every character output
+    ///   (with the character value as its sole argument). The function's return
+    ///   value is ignored.
     ///
     /// * 2: The Glk system. All output will be handled through Glk function
-    ///    calls, sent to the current Glk stream.
+    ///   calls, sent to the current Glk stream.
     ///
     /// * 20: The FyreVM channel system. See section 0.2, "Glulx and Other IF
     ///   Systems".
@@ -5,10 +5,12 @@
 
 use core::fmt::Display;
 
+use crate::disassemble::{build_instr, OperandSource};
 use crate::error::AssemblerError;
 use crate::instr_def::Instr;
-use crate::operands::RawOperand;
+use crate::operands::{LoadOperand, Operand, OperandKind, RawOperand, StoreOperand};
 use crate::resolver::Resolver;
+use alloc::vec::Vec;
 use arrayvec::ArrayVec;
 use bytes::BufMut;
 
@@ -73,7 +75,176 @@ macro_rules! worst_len {
     };
 }
 
+/// Collect references to each argument into an [`Operand`] list.
+macro_rules! operands {
+    ($($x:expr),* $(,)*) => {
+        alloc::vec![$(Operand::from($x)),*]
+    };
+}
+
 impl<L> Instr<L> {
+    /// Returns the instruction's operands, in the order they are encoded.
+    pub fn operands(&self) -> Vec<Operand<'_, L>> {
+        match self {
+            Instr::Nop => operands!(),
+            Instr::Add(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Sub(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Mul(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Div(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Mod(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Neg(l1, s1) => operands!(l1, s1),
+            Instr::Bitand(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Bitor(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Bitxor(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Bitnot(l1, s1) => operands!(l1, s1),
+            Instr::Shiftl(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Ushiftr(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Sshiftr(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Jump(l1) => operands!(l1),
+            Instr::Jz(l1, l2) => operands!(l1, l2),
+            Instr::Jnz(l1, l2) => operands!(l1, l2),
+            Instr::Jeq(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jne(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jlt(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jle(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jgt(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jge(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jltu(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jleu(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jgtu(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jgeu(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jumpabs(l1) => operands!(l1),
+            Instr::Copy(l1, s1) => operands!(l1, s1),
+            Instr::Copys(l1, s1) => operands!(l1, s1),
+            Instr::Copyb(l1, s1) => operands!(l1, s1),
+            Instr::Sexs(l1, s1) => operands!(l1, s1),
+            Instr::Sexb(l1, s1) => operands!(l1, s1),
+            Instr::Astore(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Aload(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Astores(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Aloads(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Astoreb(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Aloadb(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Astorebit(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Aloadbit(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Stkcount(s1) => operands!(s1),
+            Instr::Stkpeek(l1, s1) => operands!(l1, s1),
+            Instr::Stkswap => operands!(),
+            Instr::Stkcopy(l1) => operands!(l1),
+            Instr::Stkroll(l1, l2) => operands!(l1, l2),
+            Instr::Call(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Callf(l1, s1) => operands!(l1, s1),
+            Instr::Callfi(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Callfii(l1, l2, l3, s1) => operands!(l1, l2, l3, s1),
+            Instr::Callfiii(l1, l2, l3, l4, s1) => operands!(l1, l2, l3, l4, s1),
+            Instr::Return(l1) => operands!(l1),
+            Instr::Tailcall(l1, l2) => operands!(l1, l2),
+            Instr::Catch(s1, l1) => operands!(s1, l1),
+            Instr::Throw(l1, l2) => operands!(l1, l2),
+            Instr::Getmemsize(s1) => operands!(s1),
+            Instr::Setmemsize(l1, s1) => operands!(l1, s1),
+            Instr::Malloc(l1, s1) => operands!(l1, s1),
+            Instr::Mfree(l1) => operands!(l1),
+            Instr::Quit => operands!(),
+            Instr::Restart => operands!(),
+            Instr::Save(l1, s1) => operands!(l1, s1),
+            Instr::Restore(l1, s1) => operands!(l1, s1),
+            Instr::Saveundo(s1) => operands!(s1),
+            Instr::Restoreundo(s1) => operands!(s1),
+            Instr::Hasundo(s1) => operands!(s1),
+            Instr::Discardundo => operands!(),
+            Instr::Protect(l1, l2) => operands!(l1, l2),
+            Instr::Verify(s1) => operands!(s1),
+            Instr::Getiosys(s1, s2) => operands!(s1, s2),
+            Instr::Setiosys(l1, l2) => operands!(l1, l2),
+            Instr::Streamchar(l1) => operands!(l1),
+            Instr::Streamunichar(l1) => operands!(l1),
+            Instr::Streamnum(l1) => operands!(l1),
+            Instr::Streamstr(l1) => operands!(l1),
+            Instr::Getstringtbl(s1) => operands!(s1),
+            Instr::Setstringtbl(l1) => operands!(l1),
+            Instr::Numtof(l1, s1) => operands!(l1, s1),
+            Instr::Ftonumz(l1, s1) => operands!(l1, s1),
+            Instr::Ftonumn(l1, s1) => operands!(l1, s1),
+            Instr::Fadd(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Fsub(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Fmul(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Fdiv(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Fmod(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Ceil(l1, s1) => operands!(l1, s1),
+            Instr::Floor(l1, s1) => operands!(l1, s1),
+            Instr::Sqrt(l1, s1) => operands!(l1, s1),
+            Instr::Exp(l1, s1) => operands!(l1, s1),
+            Instr::Log(l1, s1) => operands!(l1, s1),
+            Instr::Pow(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Sin(l1, s1) => operands!(l1, s1),
+            Instr::Cos(l1, s1) => operands!(l1, s1),
+            Instr::Tan(l1, s1) => operands!(l1, s1),
+            Instr::Asin(l1, s1) => operands!(l1, s1),
+            Instr::Acos(l1, s1) => operands!(l1, s1),
+            Instr::Atan(l1, s1) => operands!(l1, s1),
+            Instr::Atan2(l1, s1) => operands!(l1, s1),
+            Instr::Numtod(l1, s1, s2) => operands!(l1, s1, s2),
+            Instr::Dtonumz(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Dtonumn(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Ftod(l1, s1, s2) => operands!(l1, s1, s2),
+            Instr::Dtof(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Dadd(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Dsub(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Dmul(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Ddiv(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Dmodr(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Dmodq(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Dceil(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dfloor(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dsqrt(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dexp(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dlog(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dpow(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Dsin(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dcos(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dtan(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dasin(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Dacos(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Datan(l1, l2, s1, s2) => operands!(l1, l2, s1, s2),
+            Instr::Datan2(l1, l2, l3, l4, s1, s2) => operands!(l1, l2, l3, l4, s1, s2),
+            Instr::Jisnan(l1, l2) => operands!(l1, l2),
+            Instr::Jisinf(l1, l2) => operands!(l1, l2),
+            Instr::Jfeq(l1, l2, l3, l4) => operands!(l1, l2, l3, l4),
+            Instr::Jfne(l1, l2, l3, l4) => operands!(l1, l2, l3, l4),
+            Instr::Jflt(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jfle(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jfgt(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jfge(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jdisnan(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jdisinf(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Jdeq(l1, l2, l3, l4, l5, l6, l7) => operands!(l1, l2, l3, l4, l5, l6, l7),
+            Instr::Jdne(l1, l2, l3, l4, l5, l6, l7) => operands!(l1, l2, l3, l4, l5, l6, l7),
+            Instr::Jdlt(l1, l2, l3, l4, l5) => operands!(l1, l2, l3, l4, l5),
+            Instr::Jdle(l1, l2, l3, l4, l5) => operands!(l1, l2, l3, l4, l5),
+            Instr::Jdgt(l1, l2, l3, l4, l5) => operands!(l1, l2, l3, l4, l5),
+            Instr::Jdge(l1, l2, l3, l4, l5) => operands!(l1, l2, l3, l4, l5),
+            Instr::Random(l1, s1) => operands!(l1, s1),
+            Instr::Setrandom(l1) => operands!(l1),
+            Instr::Mzero(l1, l2) => operands!(l1, l2),
+            Instr::Mcopy(l1, l2, l3) => operands!(l1, l2, l3),
+            Instr::Linearsearch(l1, l2, l3, l4, l5, l6, l7, s1) => {
+                operands!(l1, l2, l3, l4, l5, l6, l7, s1)
+            }
+            Instr::Binarysearch(l1, l2, l3, l4, l5, l6, l7, s1) => {
+                operands!(l1, l2, l3, l4, l5, l6, l7, s1)
+            }
+            Instr::Linkedsearch(l1, l2, l3, l4, l5, l6, s1) => {
+                operands!(l1, l2, l3, l4, l5, l6, s1)
+            }
+            Instr::Accelfunc(l1, l2) => operands!(l1, l2),
+            Instr::Accelparam(l1, l2) => operands!(l1, l2),
+            Instr::Gestalt(l1, l2, s1) => operands!(l1, l2, s1),
+            Instr::Debugtrap(l1) => operands!(l1),
+            Instr::Glk(l1, l2, s1) => operands!(l1, l2, s1),
+        }
+    }
+
     /// Applies the given mapping function to all labels within the instruction.
     pub fn map<F, M>(self, mut f: F) -> Instr<M>
     where
@@ -1197,6 +1368,87 @@ where
     }
 }
 
+/// Supplies operands from a list, in order.
+struct ListSource<'a, 'b, L> {
+    operands: &'a [Operand<'b, L>],
+    next: usize,
+}
+
+impl<'a, 'b, L> ListSource<'a, 'b, L> {
+    fn next(&mut self) -> Option<&'a Operand<'b, L>> {
+        let operand = self.operands.get(self.next)?;
+        self.next += 1;
+        Some(operand)
+    }
+}
+
+impl<L: Clone> OperandSource<L> for ListSource<'_, '_, L> {
+    fn start(&mut self, n: usize) -> Option<()> {
+        (self.operands.len() == n).then_some(())
+    }
+
+    fn load(&mut self) -> Option<LoadOperand<L>> {
+        match self.next()? {
+            Operand::Load(l) => Some((*l).clone()),
+            Operand::Store(_) => None,
+        }
+    }
+
+    fn store(&mut self) -> Option<StoreOperand<L>> {
+        match self.next()? {
+            Operand::Store(s) => Some((*s).clone()),
+            Operand::Load(_) => None,
+        }
+    }
+
+    fn branch(&mut self) -> Option<LoadOperand<L>> {
+        self.load()
+    }
+}
+
+/// Records the kind of each operand requested, supplying placeholders.
+struct KindSource(Vec<OperandKind>);
+
+impl OperandSource<()> for KindSource {
+    fn start(&mut self, _n: usize) -> Option<()> {
+        Some(())
+    }
+
+    fn load(&mut self) -> Option<LoadOperand<()>> {
+        self.0.push(OperandKind::Load);
+        Some(LoadOperand::Pop)
+    }
+
+    fn store(&mut self) -> Option<StoreOperand<()>> {
+        self.0.push(OperandKind::Store);
+        Some(StoreOperand::Discard)
+    }
+
+    fn branch(&mut self) -> Option<LoadOperand<()>> {
+        self.0.push(OperandKind::Branch);
+        Some(LoadOperand::Pop)
+    }
+}
+
+impl<L: Clone> Instr<L> {
+    /// Builds the instruction with the given opcode and operands. This is the
+    /// inverse of [`opcode`](Self::opcode) and [`operands`](Self::operands):
+    /// the operands must be listed in encoding order, with branch offsets as
+    /// load operands. Returns `None` if no instruction has this opcode, or if
+    /// it takes a different number or kind of operands.
+    pub fn from_operands(opcode: u32, operands: &[Operand<'_, L>]) -> Option<Self> {
+        build_instr(opcode, &mut ListSource { operands, next: 0 })
+    }
+}
+
+/// Returns the kinds of the operands taken by the instruction with the given
+/// opcode, in encoding order, or `None` if no instruction has this opcode.
+pub fn operand_kinds(opcode: u32) -> Option<Vec<OperandKind>> {
+    let mut kinds = KindSource(Vec::new());
+    build_instr(opcode, &mut kinds)?;
+    Some(kinds.0)
+}
+
 impl RawInstr {
     /// Returns the serialized length of the instruction.
     pub(crate) fn len(&self) -> usize {
@@ -13,7 +13,7 @@ use crate::{
     error::AssemblerError,
     instr_def::Instr,
     resolver::{ResolvedAddr, Resolver},
-    strings::{MysteryString, Utf32String},
+    strings::{write_quoted, MysteryString, Utf32String},
 };
 
 /// A reference to a label plus an offset.
@@ -177,7 +177,7 @@ where
                 buf.put_u32(length);
                 buf.put_u32(count);
                 buf.put_u32(root);
-                resolved.serialize(0, &mut buf);
+                resolved.serialize(root, &mut buf);
             }
             Item::FnHeader(cc, args) => {
                 match cc {
@@ -238,6 +238,24 @@ where
     }
 }
 
+/// Writes `bytes` as lowercase hex. This is equivalent to formatting them with
+/// `{:x}`, but avoids a trip through the formatting machinery for every byte,
+/// which otherwise dominates listing time for large blobs.
+fn write_hex(f: &mut core::fmt::Formatter<'_>, bytes: &[u8]) -> core::fmt::Result {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut buf = [0u8; 256];
+    for chunk in bytes.chunks(buf.len() / 2) {
+        for (i, byte) in chunk.iter().enumerate() {
+            buf[2 * i] = DIGITS[usize::from(byte >> 4)];
+            buf[2 * i + 1] = DIGITS[usize::from(byte & 0xf)];
+        }
+        f.write_str(
+            core::str::from_utf8(&buf[..2 * chunk.len()]).expect("hex digits should be ASCII"),
+        )?;
+    }
+    Ok(())
+}
+
 impl<L> Display for Item<L>
 where
     L: Display,
@@ -246,14 +264,26 @@ where
         match self {
             Item::Label(label) => write!(f, ".label {label}")?,
             Item::Align(a) => write!(f, ".align {a}")?,
-            Item::DecodingTable(_) => write!(f, ".decoding_table")?,
+            Item::DecodingTable(table) => write!(f, ".decoding_table {table}")?,
             Item::FnHeader(CallingConvention::ArgsInLocals, args) => write!(f, ".fnlocal {args}")?,
             Item::FnHeader(CallingConvention::ArgsOnStack, args) => write!(f, ".fnstack {args}")?,
             Item::Instr(instr) => write!(f, "\t{instr}")?,
-            Item::MysteryString(s) => write!(f, ".string {:?}", s)?,
-            Item::CompressedString(c) => write!(f, ".compressed_string {c:x}")?,
-            Item::Utf32String(s) => write!(f, ".unistring {:?}", s)?,
-            Item::Blob(b) => write!(f, ".blob {b:x}")?,
+            Item::MysteryString(s) => {
+                f.write_str(".string ")?;
+                write_quoted(f, s)?;
+            }
+            Item::CompressedString(c) => {
+                f.write_str(".compressed_string ")?;
+                write_hex(f, c)?;
+            }
+            Item::Utf32String(s) => {
+                f.write_str(".unistring ")?;
+                write_quoted(f, s)?;
+            }
+            Item::Blob(b) => {
+                f.write_str(".blob ")?;
+                write_hex(f, b)?;
+            }
             Item::LabelRef(LabelRef(label, offset), shift) => {
                 write!(f, ".labelref ({label}")?;
                 if *offset != 0 {
@@ -5,19 +5,26 @@
 //! interactive fiction. It supports version 3.1.3 of the [Glulx
 //! specification](https://www.eblong.com/zarf/glulx/Glulx-Spec.html#moving-data).
 //!
-//! Currently, the functionality of this crate is limited to generating binary
-//! Glulx files from the in-memory data structures defined herein. It is
-//! designed and suitable as a library for use by translation tools that
-//! generate Glulx, but cannot be used as a standalone assembler. `Display`
-//! impls are provided for generating human-readable assembly listings, but the
-//! syntax is subject to change and there is no tool which parses what these
-//! impls emit. This crate may be extended with such functionality in the
-//! future.
+//! This crate's main purpose is generating binary Glulx files from the
+//! in-memory data structures defined herein, as a library for use by
+//! translation tools that generate Glulx. `Display` impls are provided for
+//! generating human-readable assembly listings, and [`parse_listing`] reads
+//! them back in, so hand-written listings can be assembled too. The listing
+//! syntax is subject to change.
+//!
+//! Going the other way, [`disassemble`] turns an existing story file back into
+//! an `Assembly` on a best-effort basis, decoding whatever code it can find and
+//! leaving everything else as data.
 //!
 //! This crate's main entry point is the [`Assembly`] struct and its
 //! [`assemble`](Assembly::assemble) method, which outputs a
 //! [`BytesMut`](bytes::BytesMut) (see the [`bytes`] crate) from the public
-//! fields you create the `Assembly` from.
+//! fields you create the `Assembly` from. If you'd rather supply the output
+//! buffer yourself, enable the `slice-output` feature and use
+//! `Assembly::assemble_into` together with
+//! [`output_len`](Assembly::output_len). To send a large story file to a
+//! file or socket without ever holding all of it in memory, use
+//! `Assembly::assemble_to_writer`.
 //!
 //! The bulk of what you provide to the `Assembly` is a list of [`Item`]s, each
 //! of which may be tagged with a label. The label parameter is generic; you can
@@ -30,6 +37,11 @@
 //! lets you replace every label within it with the output of a callback,
 //! possibly changing the label's type.
 //!
+//! Functions can be written as raw [`Item`]s, with locals addressed by index
+//! using [`concise::lloc`] and [`concise::sloc`], or with a [`FunctionBuilder`],
+//! which allocates locals by name and declares the right number of them in
+//! the function header.
+//!
 //! See `examples/hello.rs` for an illustration of using this crate to assemble
 //! a story file that prints "Hello, Sailor!" and exits.
 
@@ -46,18 +58,29 @@ mod assemble;
 mod cast;
 pub mod concise;
 mod decoding_table;
+mod disassemble;
 mod error;
+mod function_builder;
 mod instr_def;
 mod instr_impls;
 mod items;
+mod literal_pool;
 mod operands;
+mod parse;
 mod resolver;
 mod strings;
 
 pub use assemble::Assembly;
 pub use decoding_table::{DecodeArg, DecodeNode};
-pub use error::AssemblerError;
+pub use disassemble::{disassemble, Addr};
+#[cfg(feature = "std")]
+pub use error::WriteError;
+pub use error::{AssemblerError, DisassemblerError, ParseError, ParseErrorKind};
+pub use function_builder::{FunctionBuilder, Local};
 pub use instr_def::Instr;
+pub use instr_impls::operand_kinds;
 pub use items::{CallingConvention, Item, LabelRef, ZeroItem};
-pub use operands::{f32_to_imm, f64_to_imm, LoadOperand, StoreOperand};
+pub use literal_pool::LiteralPool;
+pub use operands::{f32_to_imm, f64_to_imm, LoadOperand, Operand, OperandKind, StoreOperand};
+pub use parse::parse_listing;
 pub use strings::{MysteryString, StringConversionError, Utf32String};
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Definition and impls for [`LiteralPool`].
+
+use alloc::vec::Vec;
+use bytes::{BufMut, Bytes, BytesMut};
+use core::num::NonZeroU32;
+
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::items::Item;
+use crate::strings::MysteryString;
+
+/// A collection of constant blobs and strings shared among any number of
+/// functions.
+///
+/// Rather than emitting a lookup table or message alongside each function that
+/// uses it, call [`intern`](Self::intern) or
+/// [`intern_string`](Self::intern_string) from each such function to get a
+/// label for it, and then emit the pool's [`items`](Self::into_items) once,
+/// after all the functions. Literals are compared by the bytes they assemble
+/// to, so interning identical contents twice returns the same label and the
+/// contents are emitted only once, even if one was interned as a blob and the
+/// other as a string.
+#[derive(Debug, Clone)]
+pub struct LiteralPool<L> {
+    /// For each distinct literal, its label and the index in `items` of the
+    /// `Align` item which precedes it.
+    labels: HashMap<Bytes, (L, usize)>,
+    items: Vec<Item<L>>,
+}
+
+impl<L> Default for LiteralPool<L> {
+    fn default() -> Self {
+        LiteralPool {
+            labels: HashMap::new(),
+            items: Vec::new(),
+        }
+    }
+}
+
+impl<L> LiteralPool<L>
+where
+    L: Clone,
+{
+    /// Creates an empty pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a label for a blob with the given contents, aligned to
+    /// `alignment`. If identical contents are already in the pool, their label
+    /// is returned and `new_label` is not called. Otherwise, `new_label` is
+    /// called to label the new blob.
+    ///
+    /// When the same contents are interned with different alignments, they
+    /// are emitted with the strictest of them.
+    pub fn intern<B, F>(&mut self, contents: B, alignment: NonZeroU32, new_label: F) -> L
+    where
+        B: Into<Bytes>,
+        F: FnOnce() -> L,
+    {
+        let contents = contents.into();
+        self.insert(contents.clone(), alignment, Item::Blob(contents), new_label)
+    }
+
+    /// Returns a label for a string with the given contents, like
+    /// [`intern`](Self::intern) does for blobs.
+    pub fn intern_string<F>(&mut self, s: MysteryString, new_label: F) -> L
+    where
+        F: FnOnce() -> L,
+    {
+        let mut key = BytesMut::with_capacity(s.len() + 2);
+        key.put_u8(0xe0);
+        key.put(s.to_bytes());
+        key.put_u8(0);
+        self.insert(
+            key.freeze(),
+            NonZeroU32::MIN,
+            Item::MysteryString(s),
+            new_label,
+        )
+    }
+
+    fn insert<F>(&mut self, key: Bytes, alignment: NonZeroU32, item: Item<L>, new_label: F) -> L
+    where
+        F: FnOnce() -> L,
+    {
+        if let Some((label, align_index)) = self.labels.get(&key) {
+            if let Item::Align(existing) = &mut self.items[*align_index] {
+                *existing = (*existing).max(alignment);
+            }
+            return label.clone();
+        }
+
+        let label = new_label();
+        self.labels.insert(key, (label.clone(), self.items.len()));
+        self.items.push(Item::Align(alignment));
+        self.items.push(Item::Label(label.clone()));
+        self.items.push(item);
+        label
+    }
+
+    /// Returns the number of distinct literals in the pool.
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    /// Returns true if the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Consumes the pool, returning the items which define its literals.
+    pub fn into_items(self) -> Vec<Item<L>> {
+        self.items
+    }
+}
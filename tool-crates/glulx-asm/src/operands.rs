@@ -67,6 +67,40 @@ pub enum StoreOperand<L> {
     DerefLabel(LabelRef<L>),
 }
 
+/// A reference to one of an instruction's operands, as returned by
+/// [`Instr::operands`](crate::Instr::operands).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Operand<'a, L> {
+    /// An operand which is read from.
+    Load(&'a LoadOperand<L>),
+    /// An operand which is written to.
+    Store(&'a StoreOperand<L>),
+}
+
+impl<'a, L> From<&'a LoadOperand<L>> for Operand<'a, L> {
+    fn from(operand: &'a LoadOperand<L>) -> Self {
+        Operand::Load(operand)
+    }
+}
+
+impl<'a, L> From<&'a StoreOperand<L>> for Operand<'a, L> {
+    fn from(operand: &'a StoreOperand<L>) -> Self {
+        Operand::Store(operand)
+    }
+}
+
+/// What an instruction does with one of its operands, as returned by
+/// [`operand_kinds`](crate::operand_kinds).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum OperandKind {
+    /// The operand is read from.
+    Load,
+    /// The operand is written to.
+    Store,
+    /// The operand is read from as a branch offset.
+    Branch,
+}
+
 /// An encoded operand ready to be serialized.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub(crate) enum RawOperand {
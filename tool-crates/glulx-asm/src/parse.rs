@@ -0,0 +1,770 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! A parser for the listing syntax which the `Display` impls emit.
+
+use alloc::{borrow::Cow, borrow::ToOwned, boxed::Box, string::String, vec::Vec};
+use core::num::NonZeroU32;
+
+use bytes::Bytes;
+
+use crate::{
+    cast::CastSign,
+    error::{ParseError, ParseErrorKind},
+    Assembly, CallingConvention, DecodeArg, DecodeNode, Instr, Item, LabelRef, LoadOperand,
+    MysteryString, StoreOperand, Utf32String, ZeroItem,
+};
+
+/// Parses an assembly listing.
+///
+/// The syntax is whatever [`Assembly`]'s `Display` impl emits, so a listing
+/// produced by this crate parses back into an equivalent `Assembly`, with each
+/// label replaced by its displayed text. One item goes on each line, and
+/// instructions may be indented or not. Blank lines are ignored, and so is
+/// anything following a `;` at the start of a token, which allows comments in
+/// hand-written listings. Label names may be any text without whitespace,
+/// brackets, or parentheses.
+///
+/// The `.stack_size` and `.start_func` directives are required. Items come
+/// first in ROM, then in RAM after a `.ram_items` line, and then in the zero
+/// region after a `.zero_items` line.
+pub fn parse_listing(listing: &str) -> Result<Assembly<'static, String>, ParseError> {
+    let mut parser = Parser {
+        section: Section::Rom,
+        rom_items: Vec::new(),
+        ram_items: Vec::new(),
+        zero_items: Vec::new(),
+        stack_size: None,
+        start_func: None,
+        decoding_table: None,
+    };
+
+    let mut line_number = 0;
+    for (i, text) in listing.lines().enumerate() {
+        line_number = i + 1;
+        parser.line(text).map_err(|kind| ParseError {
+            line: line_number,
+            kind,
+        })?;
+    }
+
+    let missing = |directive| ParseError {
+        line: line_number,
+        kind: ParseErrorKind::Missing(directive),
+    };
+
+    Ok(Assembly {
+        stack_size: parser.stack_size.ok_or_else(|| missing("stack_size"))?,
+        start_func: parser.start_func.ok_or_else(|| missing("start_func"))?,
+        decoding_table: parser.decoding_table,
+        rom_items: Cow::Owned(parser.rom_items),
+        ram_items: Cow::Owned(parser.ram_items),
+        zero_items: Cow::Owned(parser.zero_items),
+    })
+}
+
+/// The section which items are currently being added to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Section {
+    Rom,
+    Ram,
+    Zero,
+}
+
+/// Accumulates the contents of an [`Assembly`] line by line.
+#[derive(Debug)]
+struct Parser {
+    section: Section,
+    rom_items: Vec<Item<String>>,
+    ram_items: Vec<Item<String>>,
+    zero_items: Vec<ZeroItem<String>>,
+    stack_size: Option<u32>,
+    start_func: Option<LabelRef<String>>,
+    decoding_table: Option<LabelRef<String>>,
+}
+
+impl Parser {
+    fn line(&mut self, text: &str) -> Result<(), ParseErrorKind> {
+        let mut line = Line(text);
+        let Some(first) = line.token()? else {
+            return Ok(());
+        };
+        let Token::Word(first) = first else {
+            return Err(ParseErrorKind::BadToken(first.text()));
+        };
+
+        if let Some(directive) = first.strip_prefix('.') {
+            self.directive(directive, &mut line)?;
+        } else {
+            let mut operands = Operands {
+                tokens: line.words()?,
+                next: 0,
+            };
+            let instr = parse_instr(first, &mut operands)?;
+            self.item(first, Item::Instr(instr))?;
+        }
+
+        line.end()
+    }
+
+    /// Adds an item to ROM or RAM, whichever we're in.
+    fn item(&mut self, what: &str, item: Item<String>) -> Result<(), ParseErrorKind> {
+        match self.section {
+            Section::Rom => self.rom_items.push(item),
+            Section::Ram => self.ram_items.push(item),
+            Section::Zero => return Err(ParseErrorKind::Misplaced(what.to_owned())),
+        }
+        Ok(())
+    }
+
+    fn directive(&mut self, name: &str, line: &mut Line<'_>) -> Result<(), ParseErrorKind> {
+        let misplaced = || ParseErrorKind::Misplaced(alloc::format!(".{name}"));
+
+        match name {
+            "stack_size" => self.stack_size = Some(line.number()?),
+            "start_func" => self.start_func = Some(line.imm_labelref()?),
+            "initial_decoding_table" => self.decoding_table = Some(line.imm_labelref()?),
+            "ram_items" => {
+                if self.section != Section::Rom {
+                    return Err(misplaced());
+                }
+                self.section = Section::Ram;
+            }
+            "zero_items" => {
+                if self.section == Section::Zero {
+                    return Err(misplaced());
+                }
+                self.section = Section::Zero;
+            }
+            "label" => {
+                let label = line.word()?.to_owned();
+                if self.section == Section::Zero {
+                    self.zero_items.push(ZeroItem::Label(label));
+                } else {
+                    self.item(name, Item::Label(label))?;
+                }
+            }
+            "align" => {
+                let align = NonZeroU32::new(line.number()?)
+                    .ok_or_else(|| ParseErrorKind::BadToken("0".to_owned()))?;
+                if self.section == Section::Zero {
+                    self.zero_items.push(ZeroItem::Align(align));
+                } else {
+                    self.item(name, Item::Align(align))?;
+                }
+            }
+            "space" => {
+                if self.section != Section::Zero {
+                    return Err(misplaced());
+                }
+                self.zero_items.push(ZeroItem::Space(line.number()?));
+            }
+            "decoding_table" => {
+                let root = decode_node(line)?;
+                self.item(name, Item::DecodingTable(root))?;
+            }
+            "fnlocal" => {
+                let nlocals = line.number()?;
+                self.item(
+                    name,
+                    Item::FnHeader(CallingConvention::ArgsInLocals, nlocals),
+                )?;
+            }
+            "fnstack" => {
+                let nlocals = line.number()?;
+                self.item(
+                    name,
+                    Item::FnHeader(CallingConvention::ArgsOnStack, nlocals),
+                )?;
+            }
+            "string" => {
+                let s = line.mystery_string()?;
+                self.item(name, Item::MysteryString(s))?;
+            }
+            "unistring" => {
+                let s = line.utf32_string()?;
+                self.item(name, Item::Utf32String(s))?;
+            }
+            "compressed_string" => {
+                let bytes = line.hex()?;
+                self.item(name, Item::CompressedString(bytes))?;
+            }
+            "blob" => {
+                let bytes = line.hex()?;
+                self.item(name, Item::Blob(bytes))?;
+            }
+            "labelref" => {
+                let word = line.word()?;
+                let (labelref, shift) = delimited(word, '(', ')')
+                    .and_then(labelref)
+                    .ok_or_else(|| ParseErrorKind::BadToken(word.to_owned()))?;
+                self.item(name, Item::LabelRef(labelref, shift))?;
+            }
+            _ => return Err(ParseErrorKind::UnknownDirective(name.to_owned())),
+        }
+
+        Ok(())
+    }
+}
+
+/// A token within a line.
+#[derive(Debug, Clone)]
+enum Token<'a> {
+    /// A run of non-whitespace characters.
+    Word(&'a str),
+    /// A string literal, with escapes already processed.
+    Str(String),
+}
+
+impl Token<'_> {
+    /// Returns the token as it might have appeared in the input.
+    fn text(&self) -> String {
+        match self {
+            Token::Word(w) => (*w).to_owned(),
+            Token::Str(s) => alloc::format!("{s:?}"),
+        }
+    }
+}
+
+/// The unparsed remainder of a line.
+#[derive(Debug, Copy, Clone)]
+struct Line<'a>(&'a str);
+
+impl<'a> Line<'a> {
+    /// Returns the next token, or `None` at the end of the line or the start
+    /// of a comment.
+    fn token(&mut self) -> Result<Option<Token<'a>>, ParseErrorKind> {
+        let rest = self.0.trim_start();
+        if rest.is_empty() || rest.starts_with(';') {
+            self.0 = "";
+            return Ok(None);
+        }
+
+        if rest.starts_with('"') {
+            let (s, rest) = string_literal(rest).ok_or(ParseErrorKind::BadString)?;
+            self.0 = rest;
+            Ok(Some(Token::Str(s)))
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            self.0 = &rest[end..];
+            Ok(Some(Token::Word(&rest[..end])))
+        }
+    }
+
+    /// Returns the next token without consuming it.
+    fn peek(&self) -> Result<Option<Token<'a>>, ParseErrorKind> {
+        let mut copy = *self;
+        copy.token()
+    }
+
+    fn word(&mut self) -> Result<&'a str, ParseErrorKind> {
+        match self.token()? {
+            Some(Token::Word(word)) => Ok(word),
+            Some(token) => Err(ParseErrorKind::BadToken(token.text())),
+            None => Err(ParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    /// Returns all remaining tokens, which must all be words.
+    fn words(&mut self) -> Result<Vec<&'a str>, ParseErrorKind> {
+        let mut words = Vec::new();
+        while self.peek()?.is_some() {
+            words.push(self.word()?);
+        }
+        Ok(words)
+    }
+
+    fn string(&mut self) -> Result<String, ParseErrorKind> {
+        match self.token()? {
+            Some(Token::Str(s)) => Ok(s),
+            Some(token) => Err(ParseErrorKind::BadToken(token.text())),
+            None => Err(ParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn mystery_string(&mut self) -> Result<MysteryString, ParseErrorKind> {
+        MysteryString::from_chars(self.string()?.chars()).or(Err(ParseErrorKind::BadString))
+    }
+
+    fn utf32_string(&mut self) -> Result<Utf32String, ParseErrorKind> {
+        Utf32String::from_chars(self.string()?.chars()).or(Err(ParseErrorKind::BadString))
+    }
+
+    fn number<T: TryFrom<i64>>(&mut self) -> Result<T, ParseErrorKind> {
+        let word = self.word()?;
+        number(word)
+            .and_then(|n| T::try_from(n).ok())
+            .ok_or_else(|| ParseErrorKind::BadToken(word.to_owned()))
+    }
+
+    /// Parses a parenthesized label reference without a shift.
+    fn imm_labelref(&mut self) -> Result<LabelRef<String>, ParseErrorKind> {
+        let word = self.word()?;
+        match delimited(word, '(', ')').and_then(labelref) {
+            Some((labelref, 0)) => Ok(labelref),
+            _ => Err(ParseErrorKind::BadToken(word.to_owned())),
+        }
+    }
+
+    /// Parses hex-encoded bytes, which may be absent if there are none.
+    fn hex(&mut self) -> Result<Bytes, ParseErrorKind> {
+        let Some(token) = self.token()? else {
+            return Ok(Bytes::new());
+        };
+        let Token::Word(word) = token else {
+            return Err(ParseErrorKind::BadToken(token.text()));
+        };
+        hex(word).ok_or_else(|| ParseErrorKind::BadToken(word.to_owned()))
+    }
+
+    /// Checks that nothing but perhaps a comment remains.
+    fn end(&mut self) -> Result<(), ParseErrorKind> {
+        match self.token()? {
+            Some(token) => Err(ParseErrorKind::TrailingInput(token.text())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parses a string literal in the form that `{:?}` formats a `str`, returning
+/// its contents and whatever follows it.
+fn string_literal(s: &str) -> Option<(String, &str)> {
+    let mut chars = s.strip_prefix('"')?.char_indices();
+    let mut out = String::new();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &s[i + 2..])),
+            '\\' => out.push(match chars.next()?.1 {
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                '0' => '\0',
+                '\\' => '\\',
+                '"' => '"',
+                '\'' => '\'',
+                'u' => {
+                    if chars.next()?.1 != '{' {
+                        return None;
+                    }
+                    let mut code = String::new();
+                    loop {
+                        match chars.next()?.1 {
+                            '}' => break,
+                            digit => code.push(digit),
+                        }
+                    }
+                    char::from_u32(u32::from_str_radix(&code, 16).ok()?)?
+                }
+                _ => return None,
+            }),
+            c => out.push(c),
+        }
+    }
+
+    None
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal number, with an optional
+/// sign.
+fn number(s: &str) -> Option<i64> {
+    let (negative, digits) = match s.as_bytes().first()? {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let magnitude = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+/// Parses a 32-bit immediate. Since listings show negative numbers in hex
+/// as their two's complement, anything that fits in a `u32` is accepted too.
+fn imm(s: &str) -> Option<i32> {
+    let n = number(s)?;
+    i32::try_from(n)
+        .ok()
+        .or_else(|| u32::try_from(n).ok().map(CastSign::cast_sign))
+}
+
+fn hex(s: &str) -> Option<Bytes> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()
+        .map(Bytes::from)
+}
+
+/// Strips the given brackets from around `s`.
+fn delimited(s: &str, open: char, close: char) -> Option<&str> {
+    s.strip_prefix(open)?.strip_suffix(close)
+}
+
+/// Parses the inside of a label reference, which looks like `label`,
+/// `label+0x10`, or `label+0x10>>2`.
+fn labelref(s: &str) -> Option<(LabelRef<String>, u8)> {
+    let (s, shift) = match s.rsplit_once(">>") {
+        Some((rest, shift)) => (rest, u8::try_from(number(shift)?).ok()?),
+        None => (s, 0),
+    };
+    let (label, offset) = match s.rfind(['+', '-']) {
+        Some(i) if i > 0 => match imm(&s[i..]) {
+            Some(offset) => (&s[..i], offset),
+            None => (s, 0),
+        },
+        _ => (s, 0),
+    };
+    if label.is_empty() || label.contains(['(', ')', '[', ']']) {
+        return None;
+    }
+    Some((LabelRef(label.to_owned(), offset), shift))
+}
+
+fn load_operand(s: &str) -> Option<LoadOperand<String>> {
+    Some(if s == "pop" {
+        LoadOperand::Pop
+    } else if let Some(label) = s.strip_prefix('~').and_then(|s| delimited(s, '(', ')')) {
+        let (LabelRef(label, 0), 0) = labelref(label)? else {
+            return None;
+        };
+        LoadOperand::Branch(label)
+    } else if let Some(inner) = delimited(s, '(', ')') {
+        let (labelref, shift) = labelref(inner)?;
+        LoadOperand::ImmLabel(labelref, shift)
+    } else if let Some(inner) = delimited(s, '[', ']') {
+        let (labelref, 0) = labelref(inner)? else {
+            return None;
+        };
+        LoadOperand::DerefLabel(labelref)
+    } else if let Some(frame) = s.strip_prefix('$') {
+        LoadOperand::FrameAddr(frame_addr(frame)?)
+    } else {
+        LoadOperand::Imm(imm(s)?)
+    })
+}
+
+fn store_operand(s: &str) -> Option<StoreOperand<String>> {
+    Some(if s == "push" {
+        StoreOperand::Push
+    } else if s == "discard" {
+        StoreOperand::Discard
+    } else if let Some(inner) = delimited(s, '[', ']') {
+        let (labelref, 0) = labelref(inner)? else {
+            return None;
+        };
+        StoreOperand::DerefLabel(labelref)
+    } else if let Some(frame) = s.strip_prefix('$') {
+        StoreOperand::FrameAddr(frame_addr(frame)?)
+    } else {
+        return None;
+    })
+}
+
+/// Parses a frame address in the form `$slot` or `$slot.byte` (without the
+/// `$`), where slots are four bytes.
+fn frame_addr(s: &str) -> Option<u32> {
+    let (slot, byte) = s.split_once('.').unwrap_or((s, "0"));
+    let slot: u32 = slot.parse().ok()?;
+    let byte: u32 = byte.parse().ok()?;
+    if byte >= 4 {
+        return None;
+    }
+    slot.checked_mul(4)?.checked_add(byte)
+}
+
+/// Parses a node of a decoding table, recursively.
+fn decode_node(line: &mut Line<'_>) -> Result<DecodeNode<String>, ParseErrorKind> {
+    let word = line.word()?;
+    let bad = || ParseErrorKind::BadToken(word.to_owned());
+
+    Ok(match word {
+        "{" => {
+            let left = decode_node(line)?;
+            let right = decode_node(line)?;
+            let close = line.word()?;
+            if close != "}" {
+                return Err(ParseErrorKind::BadToken(close.to_owned()));
+            }
+            DecodeNode::Branch(Box::new(left), Box::new(right))
+        }
+        "end" => DecodeNode::StringTerminator,
+        "char" => DecodeNode::MysteryChar(line.number()?),
+        "string" => DecodeNode::MysteryString(line.mystery_string()?),
+        "unichar" => DecodeNode::UnicodeChar(char::from_u32(line.number()?).ok_or_else(bad)?),
+        "unistring" => DecodeNode::Utf32String(line.utf32_string()?),
+        "ref" | "dref" => {
+            let target = line.word()?;
+            let Some((labelref, 0)) = delimited(target, '[', ']').and_then(labelref) else {
+                return Err(ParseErrorKind::BadToken(target.to_owned()));
+            };
+
+            let args = if let Some(Token::Word("<")) = line.peek()? {
+                line.word()?;
+                let mut args = Vec::new();
+                loop {
+                    let arg = line.word()?;
+                    if arg == ">" {
+                        break;
+                    }
+                    args.push(
+                        decode_arg(arg).ok_or_else(|| ParseErrorKind::BadToken(arg.to_owned()))?,
+                    );
+                }
+                Some(args)
+            } else {
+                None
+            };
+
+            match (word, args) {
+                ("ref", None) => DecodeNode::IndirectRef(labelref),
+                ("ref", Some(args)) => DecodeNode::IndirectRefWithArgs(labelref, args),
+                (_, None) => DecodeNode::DoubleIndirectRef(labelref),
+                (_, Some(args)) => DecodeNode::DoubleIndirectRefWithArgs(labelref, args),
+            }
+        }
+        _ => return Err(bad()),
+    })
+}
+
+fn decode_arg(s: &str) -> Option<DecodeArg<String>> {
+    Some(match delimited(s, '(', ')') {
+        Some(inner) => {
+            let (labelref, 0) = labelref(inner)? else {
+                return None;
+            };
+            DecodeArg::Label(labelref)
+        }
+        None => DecodeArg::Literal(imm(s)?),
+    })
+}
+
+/// The operands of an instruction, waiting to be parsed.
+#[derive(Debug)]
+struct Operands<'a> {
+    tokens: Vec<&'a str>,
+    next: usize,
+}
+
+impl Operands<'_> {
+    fn expect(&self, count: usize) -> Result<(), ParseErrorKind> {
+        if self.tokens.len() == count {
+            Ok(())
+        } else {
+            Err(ParseErrorKind::OperandCount {
+                expected: count,
+                found: self.tokens.len(),
+            })
+        }
+    }
+
+    fn load(&mut self) -> Result<LoadOperand<String>, ParseErrorKind> {
+        let token = self.tokens[self.next];
+        self.next += 1;
+        load_operand(token).ok_or_else(|| ParseErrorKind::BadToken(token.to_owned()))
+    }
+
+    fn store(&mut self) -> Result<StoreOperand<String>, ParseErrorKind> {
+        let token = self.tokens[self.next];
+        self.next += 1;
+        store_operand(token).ok_or_else(|| ParseErrorKind::BadToken(token.to_owned()))
+    }
+}
+
+/// Builds an instruction, checking the number of operands and then parsing
+/// them in order.
+macro_rules! instr {
+    ($ops:ident, $variant:ident) => {{
+        $ops.expect(0)?;
+        Instr::$variant
+    }};
+    ($ops:ident, $variant:ident, $($kind:ident),*) => {{
+        $ops.expect([$(stringify!($kind)),*].len())?;
+        Instr::$variant($($ops.$kind()?),*)
+    }};
+}
+
+fn parse_instr(mnemonic: &str, ops: &mut Operands<'_>) -> Result<Instr<String>, ParseErrorKind> {
+    Ok(match mnemonic {
+        "nop" => instr!(ops, Nop),
+        "add" => instr!(ops, Add, load, load, store),
+        "sub" => instr!(ops, Sub, load, load, store),
+        "mul" => instr!(ops, Mul, load, load, store),
+        "div" => instr!(ops, Div, load, load, store),
+        "mod" => instr!(ops, Mod, load, load, store),
+        "neg" => instr!(ops, Neg, load, store),
+        "bitand" => instr!(ops, Bitand, load, load, store),
+        "bitor" => instr!(ops, Bitor, load, load, store),
+        "bitxor" => instr!(ops, Bitxor, load, load, store),
+        "bitnot" => instr!(ops, Bitnot, load, store),
+        "shiftl" => instr!(ops, Shiftl, load, load, store),
+        "ushiftr" => instr!(ops, Ushiftr, load, load, store),
+        "sshiftr" => instr!(ops, Sshiftr, load, load, store),
+        "jump" => instr!(ops, Jump, load),
+        "jz" => instr!(ops, Jz, load, load),
+        "jnz" => instr!(ops, Jnz, load, load),
+        "jeq" => instr!(ops, Jeq, load, load, load),
+        "jne" => instr!(ops, Jne, load, load, load),
+        "jlt" => instr!(ops, Jlt, load, load, load),
+        "jle" => instr!(ops, Jle, load, load, load),
+        "jgt" => instr!(ops, Jgt, load, load, load),
+        "jge" => instr!(ops, Jge, load, load, load),
+        "jltu" => instr!(ops, Jltu, load, load, load),
+        "jleu" => instr!(ops, Jleu, load, load, load),
+        "jgtu" => instr!(ops, Jgtu, load, load, load),
+        "jgeu" => instr!(ops, Jgeu, load, load, load),
+        "jumpabs" => instr!(ops, Jumpabs, load),
+        "copy" => instr!(ops, Copy, load, store),
+        "copys" => instr!(ops, Copys, load, store),
+        "copyb" => instr!(ops, Copyb, load, store),
+        "sexs" => instr!(ops, Sexs, load, store),
+        "sexb" => instr!(ops, Sexb, load, store),
+        "astore" => instr!(ops, Astore, load, load, load),
+        "aload" => instr!(ops, Aload, load, load, store),
+        "astores" => instr!(ops, Astores, load, load, load),
+        "aloads" => instr!(ops, Aloads, load, load, store),
+        "astoreb" => instr!(ops, Astoreb, load, load, load),
+        "aloadb" => instr!(ops, Aloadb, load, load, store),
+        "astorebit" => instr!(ops, Astorebit, load, load, load),
+        "aloadbit" => instr!(ops, Aloadbit, load, load, store),
+        "stkcount" => instr!(ops, Stkcount, store),
+        "stkpeek" => instr!(ops, Stkpeek, load, store),
+        "stkswap" => instr!(ops, Stkswap),
+        "stkcopy" => instr!(ops, Stkcopy, load),
+        "stkroll" => instr!(ops, Stkroll, load, load),
+        "call" => instr!(ops, Call, load, load, store),
+        "callf" => instr!(ops, Callf, load, store),
+        "callfi" => instr!(ops, Callfi, load, load, store),
+        "callfii" => instr!(ops, Callfii, load, load, load, store),
+        "callfiii" => instr!(ops, Callfiii, load, load, load, load, store),
+        "return" => instr!(ops, Return, load),
+        "tailcall" => instr!(ops, Tailcall, load, load),
+        "catch" => instr!(ops, Catch, store, load),
+        "throw" => instr!(ops, Throw, load, load),
+        "getmemsize" => instr!(ops, Getmemsize, store),
+        "setmemsize" => instr!(ops, Setmemsize, load, store),
+        "malloc" => instr!(ops, Malloc, load, store),
+        "mfree" => instr!(ops, Mfree, load),
+        "quit" => instr!(ops, Quit),
+        "restart" => instr!(ops, Restart),
+        "save" => instr!(ops, Save, load, store),
+        "restore" => instr!(ops, Restore, load, store),
+        "saveundo" => instr!(ops, Saveundo, store),
+        "restoreundo" => instr!(ops, Restoreundo, store),
+        "hasundo" => instr!(ops, Hasundo, store),
+        "discardundo" => instr!(ops, Discardundo),
+        "protect" => instr!(ops, Protect, load, load),
+        "verify" => instr!(ops, Verify, store),
+        "getiosys" => instr!(ops, Getiosys, store, store),
+        "setiosys" => instr!(ops, Setiosys, load, load),
+        "streamchar" => instr!(ops, Streamchar, load),
+        "streamunichar" => instr!(ops, Streamunichar, load),
+        "streamnum" => instr!(ops, Streamnum, load),
+        "streamstr" => instr!(ops, Streamstr, load),
+        "getstringtbl" => instr!(ops, Getstringtbl, store),
+        "setstringtbl" => instr!(ops, Setstringtbl, load),
+        "numtof" => instr!(ops, Numtof, load, store),
+        "ftonumz" => instr!(ops, Ftonumz, load, store),
+        "ftonumn" => instr!(ops, Ftonumn, load, store),
+        "fadd" => instr!(ops, Fadd, load, load, store),
+        "fsub" => instr!(ops, Fsub, load, load, store),
+        "fmul" => instr!(ops, Fmul, load, load, store),
+        "fdiv" => instr!(ops, Fdiv, load, load, store),
+        "fmod" => instr!(ops, Fmod, load, load, store, store),
+        "ceil" => instr!(ops, Ceil, load, store),
+        "floor" => instr!(ops, Floor, load, store),
+        "sqrt" => instr!(ops, Sqrt, load, store),
+        "exp" => instr!(ops, Exp, load, store),
+        "log" => instr!(ops, Log, load, store),
+        "pow" => instr!(ops, Pow, load, load, store),
+        "sin" => instr!(ops, Sin, load, store),
+        "cos" => instr!(ops, Cos, load, store),
+        "tan" => instr!(ops, Tan, load, store),
+        "asin" => instr!(ops, Asin, load, store),
+        "acos" => instr!(ops, Acos, load, store),
+        "atan" => instr!(ops, Atan, load, store),
+        "atan2" => instr!(ops, Atan2, load, store),
+        "numtod" => instr!(ops, Numtod, load, store, store),
+        "dtonumz" => instr!(ops, Dtonumz, load, load, store),
+        "dtonumn" => instr!(ops, Dtonumn, load, load, store),
+        "ftod" => instr!(ops, Ftod, load, store, store),
+        "dtof" => instr!(ops, Dtof, load, load, store),
+        "dadd" => instr!(ops, Dadd, load, load, load, load, store, store),
+        "dsub" => instr!(ops, Dsub, load, load, load, load, store, store),
+        "dmul" => instr!(ops, Dmul, load, load, load, load, store, store),
+        "ddiv" => instr!(ops, Ddiv, load, load, load, load, store, store),
+        "dmodr" => instr!(ops, Dmodr, load, load, load, load, store, store),
+        "dmodq" => instr!(ops, Dmodq, load, load, load, load, store, store),
+        "dceil" => instr!(ops, Dceil, load, load, store, store),
+        "dfloor" => instr!(ops, Dfloor, load, load, store, store),
+        "dsqrt" => instr!(ops, Dsqrt, load, load, store, store),
+        "dexp" => instr!(ops, Dexp, load, load, store, store),
+        "dlog" => instr!(ops, Dlog, load, load, store, store),
+        "dpow" => instr!(ops, Dpow, load, load, load, load, store, store),
+        "dsin" => instr!(ops, Dsin, load, load, store, store),
+        "dcos" => instr!(ops, Dcos, load, load, store, store),
+        "dtan" => instr!(ops, Dtan, load, load, store, store),
+        "dasin" => instr!(ops, Dasin, load, load, store, store),
+        "dacos" => instr!(ops, Dacos, load, load, store, store),
+        "datan" => instr!(ops, Datan, load, load, store, store),
+        "datan2" => instr!(ops, Datan2, load, load, load, load, store, store),
+        "jisnan" => instr!(ops, Jisnan, load, load),
+        "jisinf" => instr!(ops, Jisinf, load, load),
+        "jfeq" => instr!(ops, Jfeq, load, load, load, load),
+        "jfne" => instr!(ops, Jfne, load, load, load, load),
+        "jflt" => instr!(ops, Jflt, load, load, load),
+        "jfle" => instr!(ops, Jfle, load, load, load),
+        "jfgt" => instr!(ops, Jfgt, load, load, load),
+        "jfge" => instr!(ops, Jfge, load, load, load),
+        "jdisnan" => instr!(ops, Jdisnan, load, load, load),
+        "jdisinf" => instr!(ops, Jdisinf, load, load, load),
+        "jdeq" => instr!(ops, Jdeq, load, load, load, load, load, load, load),
+        "jdne" => instr!(ops, Jdne, load, load, load, load, load, load, load),
+        "jdlt" => instr!(ops, Jdlt, load, load, load, load, load),
+        "jdle" => instr!(ops, Jdle, load, load, load, load, load),
+        "jdgt" => instr!(ops, Jdgt, load, load, load, load, load),
+        "jdge" => instr!(ops, Jdge, load, load, load, load, load),
+        "random" => instr!(ops, Random, load, store),
+        "setrandom" => instr!(ops, Setrandom, load),
+        "mzero" => instr!(ops, Mzero, load, load),
+        "mcopy" => instr!(ops, Mcopy, load, load, load),
+        "linearsearch" => instr!(
+            ops,
+            Linearsearch,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            store
+        ),
+        "binarysearch" => instr!(
+            ops,
+            Binarysearch,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            load,
+            store
+        ),
+        "linkedsearch" => instr!(ops, Linkedsearch, load, load, load, load, load, load, store),
+        "accelfunc" => instr!(ops, Accelfunc, load, load),
+        "accelparam" => instr!(ops, Accelparam, load, load),
+        "gestalt" => instr!(ops, Gestalt, load, load, store),
+        "debugtrap" => instr!(ops, Debugtrap, load),
+        "glk" => instr!(ops, Glk, load, load, store),
+        _ => return Err(ParseErrorKind::UnknownInstruction(mnemonic.to_owned())),
+    })
+}
@@ -14,6 +14,33 @@ use core::{
 #[cfg(feature = "std")]
 use std::error::Error;
 
+/// Writes `s` as a double-quoted literal, escaped the way `str`'s `Debug` impl
+/// escapes it, without collecting it into a `String` first.
+pub(crate) fn write_quoted<S>(f: &mut Formatter<'_>, s: &S) -> core::fmt::Result
+where
+    S: Display + ?Sized,
+{
+    struct Escaper<'a, 'b>(&'a mut Formatter<'b>);
+
+    impl Write for Escaper<'_, '_> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            for c in s.chars() {
+                // Unlike `char`'s, `str`'s `Debug` leaves single quotes alone.
+                if c == '\'' {
+                    self.0.write_char(c)?;
+                } else {
+                    write!(self.0, "{}", c.escape_debug())?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    f.write_char('"')?;
+    write!(Escaper(f), "{s}")?;
+    f.write_char('"')
+}
+
 /// A string encoded as UTF-32.
 ///
 /// Strings of this type can be serialized into a story file (via the
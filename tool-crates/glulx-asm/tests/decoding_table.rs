@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks the byte layout of assembled decoding tables against the Glulx
+//! specification, independently of the disassembler and listing parser.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+
+const MAIN: u32 = 0;
+const TABLE: u32 = 1;
+
+fn pair(left: DecodeNode<u32>, right: DecodeNode<u32>) -> DecodeNode<u32> {
+    DecodeNode::Branch(Box::new(left), Box::new(right))
+}
+
+fn assemble_table(root: DecodeNode<u32>) -> (Vec<u8>, usize) {
+    let story = Assembly {
+        rom_items: Cow::Owned(vec![
+            label(MAIN),
+            fnhead_local(0),
+            quit(),
+            label(TABLE),
+            decoding_table(root),
+        ]),
+        ram_items: Cow::Owned(vec![]),
+        zero_items: Cow::Owned(vec![]),
+        stack_size: 0x100,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: Some(LabelRef(TABLE, 0)),
+    }
+    .assemble()
+    .unwrap()
+    .to_vec();
+
+    let table = u32::from_be_bytes(story[0x1c..0x20].try_into().unwrap());
+    (story, usize::try_from(table).unwrap())
+}
+
+fn u32_at(story: &[u8], addr: usize) -> u32 {
+    u32::from_be_bytes(story[addr..addr + 4].try_into().unwrap())
+}
+
+#[test]
+fn branch_nodes_point_at_their_children() {
+    // Laid out depth-first, left before right:
+    //
+    // r+0   branch -> r+9, r+21
+    // r+9     branch -> r+18, r+20
+    // r+18      char 'a'
+    // r+20      end
+    // r+21    branch -> r+30, r+35
+    // r+30      unichar U+20AC
+    // r+35      branch -> r+44, r+48
+    // r+44        string "xy"
+    // r+48        end
+    let root = pair(
+        pair(DecodeNode::MysteryChar(b'a'), DecodeNode::StringTerminator),
+        pair(
+            DecodeNode::UnicodeChar('\u{20ac}'),
+            pair(
+                DecodeNode::MysteryString(MysteryString::from_chars("xy".chars()).unwrap()),
+                DecodeNode::StringTerminator,
+            ),
+        ),
+    );
+    let (story, table) = assemble_table(root);
+    let r = u32::try_from(table).unwrap() + 12;
+
+    // Table header: total length including the header, node count, and the
+    // address of the root node.
+    assert_eq!(u32_at(&story, table), 12 + 49);
+    assert_eq!(u32_at(&story, table + 4), 9);
+    assert_eq!(u32_at(&story, table + 8), r);
+
+    let mut expected = Vec::new();
+    let branch = |left: u32, right: u32| {
+        let mut node = vec![0x00];
+        node.extend((r + left).to_be_bytes());
+        node.extend((r + right).to_be_bytes());
+        node
+    };
+    expected.extend(branch(9, 21));
+    expected.extend(branch(18, 20));
+    expected.extend([0x02, b'a']);
+    expected.extend([0x01]);
+    expected.extend(branch(30, 35));
+    expected.extend([0x04, 0x00, 0x00, 0x20, 0xac]);
+    expected.extend(branch(44, 48));
+    expected.extend([0x03, b'x', b'y', 0x00]);
+    expected.extend([0x01]);
+
+    let start = table + 12;
+    assert_eq!(&story[start..start + expected.len()], expected.as_slice());
+}
+
+#[test]
+fn every_branch_child_is_a_node_within_the_table() {
+    // A lopsided tree, so that right children sit at varying distances from
+    // their parents.
+    let mut root = DecodeNode::StringTerminator;
+    for ch in b"abcdefgh" {
+        root = pair(root, DecodeNode::MysteryChar(*ch));
+    }
+    let (story, table) = assemble_table(root);
+    let length = usize::try_from(u32_at(&story, table)).unwrap();
+    let end = table + length;
+
+    let mut seen = 0;
+    let mut chars = Vec::new();
+    let mut pending = vec![usize::try_from(u32_at(&story, table + 8)).unwrap()];
+    while let Some(node) = pending.pop() {
+        assert!(
+            node >= table + 12 && node < end,
+            "node {node:#x} outside table"
+        );
+        seen += 1;
+        match story[node] {
+            0x00 => {
+                let left = usize::try_from(u32_at(&story, node + 1)).unwrap();
+                let right = usize::try_from(u32_at(&story, node + 5)).unwrap();
+                assert_eq!(left, node + 9);
+                pending.push(right);
+                pending.push(left);
+            }
+            0x01 => {}
+            0x02 => chars.push(story[node + 1]),
+            other => panic!("unexpected node type {other:#x}"),
+        }
+    }
+
+    assert_eq!(seen, usize::try_from(u32_at(&story, table + 4)).unwrap());
+    assert_eq!(chars, b"abcdefgh");
+}
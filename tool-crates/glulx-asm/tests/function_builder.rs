@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that [`FunctionBuilder`] allocates locals in order, declares exactly
+//! as many as it allocated, and produces the same code as numbering the
+//! locals by hand.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+
+const MAIN: u32 = 0;
+const F: u32 = 1;
+
+#[test]
+fn locals_are_allocated_in_order() {
+    let mut f = FunctionBuilder::<u32>::new(F, CallingConvention::ArgsInLocals);
+    let x = f.arg("x");
+    let y = f.arg("y");
+    let pair = f.local_words("pair", 2);
+    let tmp = f.local("tmp");
+
+    assert_eq!(
+        [x.index(), y.index(), pair.index(), tmp.index()],
+        [0, 1, 2, 4]
+    );
+    assert_eq!(pair.word(1).index(), 3);
+    assert_eq!(f.nlocals(), 5);
+    assert_eq!(f.get("pair"), Some(pair));
+    assert_eq!(f.get("missing"), None);
+}
+
+#[test]
+fn operands_address_locals_like_lloc_and_sloc() {
+    let mut f = FunctionBuilder::<u32>::new(F, CallingConvention::ArgsOnStack);
+    f.local("a");
+    let b = f.local("b");
+    assert_eq!(b.load::<u32>(), lloc(1));
+    assert_eq!(b.store::<u32>(), sloc(1));
+}
+
+#[test]
+fn finish_emits_label_and_header() {
+    for convention in [
+        CallingConvention::ArgsInLocals,
+        CallingConvention::ArgsOnStack,
+    ] {
+        let mut f = FunctionBuilder::new(F, convention);
+        let a = f.local("a");
+        f.local_words("b", 3);
+        f.push(copy(imm(1), a.store()));
+        f.extend([ret(a.load())]);
+
+        let items = f.finish();
+        assert_eq!(items.len(), 4);
+        assert!(matches!(items[0], Item::Label(F)));
+        assert!(matches!(items[1], Item::FnHeader(c, 4) if c == convention));
+        assert!(matches!(items[2], Item::Instr(Instr::Copy(..))));
+        assert!(matches!(items[3], Item::Instr(Instr::Return(..))));
+    }
+}
+
+#[test]
+fn builder_assembles_like_hand_numbered_locals() {
+    let mut f = FunctionBuilder::new(F, CallingConvention::ArgsInLocals);
+    let x = f.arg("x");
+    let y = f.arg("y");
+    let sum = f.local("sum");
+    f.extend([add(x.load(), y.load(), sum.store()), ret(sum.load())]);
+    let built = f.finish();
+
+    let by_hand = vec![
+        label(F),
+        fnhead_local(3),
+        add(lloc(0), lloc(1), sloc(2)),
+        ret(lloc(2)),
+    ];
+
+    let story = |function: Vec<Item<u32>>| {
+        let mut rom_items = vec![
+            label(MAIN),
+            fnhead_local(0),
+            callfii(imml(F), imm(1), imm(2), discard()),
+            quit(),
+        ];
+        rom_items.extend(function);
+        Assembly {
+            rom_items: Cow::Owned(rom_items),
+            ram_items: Cow::Owned(vec![]),
+            zero_items: Cow::Owned(vec![]),
+            stack_size: 0x100,
+            start_func: LabelRef(MAIN, 0),
+            decoding_table: None,
+        }
+        .assemble()
+        .unwrap()
+    };
+    assert_eq!(story(built), story(by_hand));
+}
+
+#[test]
+#[should_panic(expected = "allocated more than once")]
+fn duplicate_names_panic() {
+    let mut f = FunctionBuilder::<u32>::new(F, CallingConvention::ArgsInLocals);
+    f.arg("x");
+    f.local("x");
+}
+
+#[test]
+#[should_panic(expected = "allocated after other locals")]
+fn args_after_locals_panic() {
+    let mut f = FunctionBuilder::<u32>::new(F, CallingConvention::ArgsInLocals);
+    f.local("tmp");
+    f.arg("x");
+}
+
+#[test]
+#[should_panic(expected = "takes its arguments on the stack")]
+fn args_on_stack_have_no_arg_locals() {
+    let mut f = FunctionBuilder::<u32>::new(F, CallingConvention::ArgsOnStack);
+    f.arg("x");
+}
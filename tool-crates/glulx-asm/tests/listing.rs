@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that listings parse back into what they were generated from.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+
+fn story() -> Assembly<'static, &'static str> {
+    let table = DecodeNode::Branch(
+        Box::new(DecodeNode::Branch(
+            Box::new(DecodeNode::StringTerminator),
+            Box::new(DecodeNode::MysteryString(
+                MysteryString::try_from("say \"hi\"\n\u{e9}").unwrap(),
+            )),
+        )),
+        Box::new(DecodeNode::Branch(
+            Box::new(DecodeNode::Branch(
+                Box::new(DecodeNode::MysteryChar(b'x')),
+                Box::new(DecodeNode::UnicodeChar('\u{263a}')),
+            )),
+            Box::new(DecodeNode::Branch(
+                Box::new(DecodeNode::IndirectRef(LabelRef("counter", 4))),
+                Box::new(DecodeNode::DoubleIndirectRefWithArgs(
+                    LabelRef("counter", 0),
+                    vec![
+                        DecodeArg::Literal(-2),
+                        DecodeArg::Label(LabelRef("main", 0)),
+                    ],
+                )),
+            )),
+        )),
+    );
+
+    Assembly {
+        rom_items: Cow::Owned(vec![
+            label("table"),
+            decoding_table(table),
+            label("greeting"),
+            mystery_string(&"Hello,\tsailor!\n"),
+            label("wide"),
+            utf32_string(&"\u{263a} \\ \u{1}"),
+            label("compressed"),
+            compressed_string(vec![0xe1, 0x12, 0x34]),
+            align(4),
+            label("main"),
+            fnhead_local(2),
+            setiosys(imm(2), imm(0)),
+            copy(imm(-1), sloc(0)),
+            copyb(derefl_off("wide", -3), sloc(1)),
+            label("loop"),
+            aload(imml_off_shift("counter", 4, 2), lloc(0), push()),
+            astore(imml("scratch"), imm(3), pop()),
+            streamstr(imml("greeting")),
+            add(lloc(1), imm(0x12345), storel_off("counter", 4)),
+            jlt(lloc(0), imm(10), "loop"),
+            jz_ret(lloc(0), true),
+            callfii(imml("main"), imm(1), lloc(1), discard()),
+            ret(imm(0)),
+            labelref("main"),
+        ]),
+        ram_items: Cow::Owned(vec![label("counter"), blob(vec![0; 8]), blob(vec![])]),
+        zero_items: Cow::Owned(vec![zspace(3), zalign(4), zlabel("scratch"), zspace(64)]),
+        stack_size: 0x400,
+        start_func: LabelRef("main", 0),
+        decoding_table: Some(LabelRef("table", 0)),
+    }
+}
+
+#[test]
+fn listing_round_trips() {
+    let original = story();
+    let listing = original.to_string();
+    let parsed = parse_listing(&listing).unwrap();
+    assert_eq!(parsed.to_string(), listing);
+    assert_eq!(parsed.assemble().unwrap(), original.assemble().unwrap());
+}
+
+#[test]
+fn strings_are_quoted_as_rust_quotes_them() {
+    for text in [
+        "it's \"quoted\"",
+        "tab\tnewline\n\\",
+        "\u{1}\u{e9}\u{263a}\u{301}",
+    ] {
+        let expected = format!("{text:?}");
+        assert_eq!(
+            utf32_string::<&str, _>(&text).to_string(),
+            format!(".unistring {expected}")
+        );
+        assert_eq!(
+            DecodeNode::<&str>::Utf32String(Utf32String::try_from(text).unwrap()).to_string(),
+            format!("unistring {expected}")
+        );
+        if let Ok(latin1) = MysteryString::try_from(text) {
+            assert_eq!(
+                Item::<&str>::MysteryString(latin1).to_string(),
+                format!(".string {expected}")
+            );
+        }
+    }
+}
+
+#[test]
+fn disassembly_round_trips() {
+    let bytes = story().assemble().unwrap();
+    let listing = disassemble(&bytes).unwrap().to_string();
+    let parsed = parse_listing(&listing).unwrap();
+    assert_eq!(parsed.assemble().unwrap(), bytes);
+}
+
+#[test]
+fn accepts_hand_written_listings() {
+    let listing = "
+; Prints nothing and quits.
+.stack_size 256
+.start_func (main)
+
+.label main
+.fnstack 0
+    jump ~(done) ; skip the nop
+    nop
+.label done
+    quit
+.ram_items
+.zero_items
+";
+    let expected = Assembly {
+        rom_items: Cow::Owned(vec![
+            label("main"),
+            fnhead_stack(0),
+            jump("done"),
+            nop(),
+            label("done"),
+            quit(),
+        ]),
+        ram_items: Cow::Owned(vec![]),
+        zero_items: Cow::Owned(vec![]),
+        stack_size: 256,
+        start_func: LabelRef("main", 0),
+        decoding_table: None,
+    };
+    assert_eq!(
+        parse_listing(listing).unwrap().assemble().unwrap(),
+        expected.assemble().unwrap()
+    );
+}
+
+#[test]
+fn reports_errors_by_line() {
+    let err = parse_listing(".stack_size 256\n.start_func (main)\n\tadd 0x1 push\n").unwrap_err();
+    assert_eq!(
+        err,
+        ParseError {
+            line: 3,
+            kind: ParseErrorKind::OperandCount {
+                expected: 3,
+                found: 2
+            },
+        }
+    );
+
+    let err = parse_listing(".stack_size 256\n.zero_items\n\tnop\n").unwrap_err();
+    assert_eq!(err.line, 3);
+    assert_eq!(err.kind, ParseErrorKind::Misplaced("nop".to_owned()));
+
+    let err = parse_listing(".stack_size 256\n").unwrap_err();
+    assert_eq!(err.kind, ParseErrorKind::Missing("start_func"));
+}
@@ -0,0 +1,116 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that [`LiteralPool`] emits each distinct literal once.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+use std::num::NonZeroU32;
+
+const ALIGN_4: NonZeroU32 = NonZeroU32::new(4).unwrap();
+
+/// Returns a closure which hands out labels counting up from `*next`.
+fn labels(next: &mut u32) -> impl FnMut() -> u32 + '_ {
+    move || {
+        *next += 1;
+        *next
+    }
+}
+
+#[test]
+fn identical_blobs_share_a_label() {
+    let mut pool = LiteralPool::new();
+    let mut next = 0;
+    let first = pool.intern(vec![1, 2, 3], NonZeroU32::MIN, labels(&mut next));
+    let second = pool.intern(vec![1, 2, 3], NonZeroU32::MIN, labels(&mut next));
+    let other = pool.intern(vec![1, 2, 4], NonZeroU32::MIN, labels(&mut next));
+
+    assert_eq!(first, second);
+    assert_ne!(first, other);
+    assert_eq!(next, 2, "a label should only be made for each new blob");
+    assert_eq!(pool.len(), 2);
+
+    let blobs = pool
+        .into_items()
+        .into_iter()
+        .filter(|item| matches!(item, Item::Blob(_)))
+        .count();
+    assert_eq!(blobs, 2);
+}
+
+#[test]
+fn strings_share_a_label_with_their_encoding() {
+    let mut pool = LiteralPool::new();
+    let mut next = 0;
+    let string = pool.intern_string(MysteryString::try_from("abc").unwrap(), labels(&mut next));
+    let again = pool.intern_string(MysteryString::try_from("abc").unwrap(), labels(&mut next));
+    let blob = pool.intern(b"\xe0abc\0".to_vec(), NonZeroU32::MIN, labels(&mut next));
+    let unterminated = pool.intern(b"abc".to_vec(), NonZeroU32::MIN, labels(&mut next));
+
+    assert_eq!(string, again);
+    assert_eq!(string, blob);
+    assert_ne!(string, unterminated);
+    assert_eq!(pool.len(), 2);
+}
+
+#[test]
+fn shared_literals_get_the_strictest_alignment() {
+    let mut pool = LiteralPool::new();
+    let mut next = 0;
+    pool.intern(vec![0; 8], NonZeroU32::MIN, labels(&mut next));
+    pool.intern(vec![0; 8], ALIGN_4, labels(&mut next));
+    pool.intern(vec![0; 8], NonZeroU32::MIN, labels(&mut next));
+
+    let items = pool.into_items();
+    assert!(matches!(items[0], Item::Align(a) if a == ALIGN_4));
+}
+
+#[test]
+fn empty_pool_has_no_items() {
+    let pool = LiteralPool::<u32>::new();
+    assert!(pool.is_empty());
+    assert!(pool.into_items().is_empty());
+}
+
+/// Two functions which each look up the same table get the same address for
+/// it, and the table appears in the story file once.
+#[test]
+fn shared_table_is_assembled_once() {
+    const MAIN: u32 = 0;
+    const F: u32 = 1;
+    const G: u32 = 2;
+    const RESULT: u32 = 3;
+    let table: Vec<u8> = (0..32).map(|i| 0xa0 ^ i).collect();
+
+    let mut pool = LiteralPool::new();
+    let mut next = 100;
+    let mut rom_items = vec![label(MAIN), fnhead_local(0), quit()];
+    for function in [F, G] {
+        let table_label = pool.intern(table.clone(), ALIGN_4, labels(&mut next));
+        rom_items.extend([
+            label(function),
+            fnhead_local(0),
+            copy(imml(table_label), storel(RESULT)),
+            ret(imm(0)),
+        ]);
+    }
+    rom_items.extend(pool.into_items());
+
+    let story = Assembly {
+        rom_items: Cow::Owned(rom_items),
+        ram_items: Cow::Owned(vec![]),
+        zero_items: Cow::Owned(vec![zalign(4), zlabel(RESULT), zspace(4)]),
+        stack_size: 0x100,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: None,
+    }
+    .assemble()
+    .unwrap();
+
+    let occurrences = story
+        .windows(table.len())
+        .filter(|window| *window == table)
+        .count();
+    assert_eq!(occurrences, 1);
+}
@@ -0,0 +1,131 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that [`Instr::operands`] lists operands in encoding order, and that
+//! [`Instr::from_operands`] and [`operand_kinds`] agree with it.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+
+fn operands_of(item: Item<u32>) -> Vec<String> {
+    let Item::Instr(instr) = item else {
+        panic!("not an instruction");
+    };
+    instr
+        .operands()
+        .into_iter()
+        .map(|operand| match operand {
+            Operand::Load(l) => format!("load {l}"),
+            Operand::Store(s) => format!("store {s}"),
+        })
+        .collect()
+}
+
+#[test]
+fn operands_are_listed_in_encoding_order() {
+    assert_eq!(operands_of(nop()), Vec::<String>::new());
+    assert_eq!(
+        operands_of(add(lloc(1), imm(2), sloc(3))),
+        [
+            format!("load {}", lloc::<u32>(1)),
+            format!("load {}", imm::<u32>(2)),
+            format!("store {}", sloc::<u32>(3)),
+        ]
+    );
+    assert_eq!(
+        operands_of(jeq(pop(), imm(0), 7)),
+        [
+            format!("load {}", pop::<u32>()),
+            format!("load {}", imm::<u32>(0)),
+            format!("load {}", LoadOperand::Branch(7u32)),
+        ]
+    );
+}
+
+#[test]
+fn catch_lists_its_store_operand_before_its_branch() {
+    let Item::Instr(instr) = catch(push(), 9u32) else {
+        panic!("not an instruction");
+    };
+    let operands = instr.operands();
+    assert!(matches!(operands[0], Operand::Store(StoreOperand::Push)));
+    assert!(matches!(operands[1], Operand::Load(LoadOperand::Branch(9))));
+}
+
+fn instr_of(item: Item<u32>) -> Instr<u32> {
+    let Item::Instr(instr) = item else {
+        panic!("not an instruction");
+    };
+    instr
+}
+
+#[test]
+fn from_operands_inverts_opcode_and_operands() {
+    for item in [
+        nop(),
+        add(lloc(1), imm(2), sloc(3)),
+        jeq(pop(), imm(0), 7),
+        catch(push(), 9),
+        streamnum(lloc(4)),
+        fadd(pop(), pop(), push()),
+        glk(imm(0x48), imm(0), discard()),
+    ] {
+        let instr = instr_of(item);
+        let rebuilt = Instr::from_operands(instr.opcode(), &instr.operands())
+            .unwrap_or_else(|| panic!("{instr} could not be rebuilt"));
+        assert_eq!(rebuilt, instr);
+    }
+}
+
+#[test]
+fn from_operands_rejects_mismatched_operands() {
+    let load = imm::<u32>(0);
+    let store = discard::<u32>();
+    let add_opcode = instr_of(add(imm(0), imm(0), discard())).opcode();
+    // Too few, too many, and a store where a load belongs.
+    assert_eq!(
+        Instr::from_operands(add_opcode, &[(&load).into(), (&store).into()]),
+        None
+    );
+    assert_eq!(
+        Instr::from_operands(
+            add_opcode,
+            &[
+                (&load).into(),
+                (&load).into(),
+                (&store).into(),
+                (&store).into()
+            ]
+        ),
+        None
+    );
+    assert_eq!(
+        Instr::from_operands(
+            add_opcode,
+            &[(&load).into(), (&store).into(), (&store).into()]
+        ),
+        None
+    );
+    // No instruction has this opcode.
+    assert_eq!(Instr::<u32>::from_operands(0x1234, &[]), None);
+}
+
+#[test]
+fn operand_kinds_follow_encoding_order() {
+    use OperandKind::*;
+    let opcode = |item: Item<u32>| instr_of(item).opcode();
+    assert_eq!(operand_kinds(opcode(nop())), Some(vec![]));
+    assert_eq!(
+        operand_kinds(opcode(add(pop(), pop(), push()))),
+        Some(vec![Load, Load, Store])
+    );
+    assert_eq!(
+        operand_kinds(opcode(jz(pop(), 0))),
+        Some(vec![Load, Branch])
+    );
+    assert_eq!(
+        operand_kinds(opcode(catch(push(), 0))),
+        Some(vec![Store, Branch])
+    );
+    assert_eq!(operand_kinds(0x1234), None);
+}
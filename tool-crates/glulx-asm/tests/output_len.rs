@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that [`Assembly::output_len`] predicts the length of the story file
+//! exactly, and that [`Assembly::assemble_into`] writes the same bytes as
+//! [`Assembly::assemble`]. The `assemble_into` tests need the `slice-output`
+//! feature.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+
+const MAIN: u32 = 0;
+const COUNTER: u32 = 1;
+const FAR: u32 = 2;
+const FIRST_STRING: u32 = 100;
+
+/// Builds a story with `n` strings in ROM and `n` blobs in RAM, and a branch
+/// over all of them, so that operand widths depend on the layout.
+fn story(n: u32) -> Assembly<'static, u32> {
+    let mut rom_items = vec![
+        label(MAIN),
+        fnhead_local(0),
+        jump(FAR),
+        copy(derefl(COUNTER), push()),
+    ];
+    for i in 0..n {
+        rom_items.push(label(FIRST_STRING + i));
+        rom_items.push(mystery_string(&"y".repeat(usize::try_from(i % 5).unwrap())));
+    }
+    rom_items.extend([label(FAR), quit()]);
+
+    let mut ram_items = vec![label(COUNTER), blob(vec![0, 0, 0, 7])];
+    for i in 0..n {
+        ram_items.push(blob(vec![u8::try_from(i % 251).unwrap(); 5]));
+    }
+
+    Assembly {
+        rom_items: Cow::Owned(rom_items),
+        ram_items: Cow::Owned(ram_items),
+        zero_items: Cow::Owned(vec![zspace(100)]),
+        stack_size: 0x400,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: None,
+    }
+}
+
+/// A story which refers to a label it never defines.
+fn broken_story() -> Assembly<'static, u32> {
+    Assembly {
+        rom_items: Cow::Owned(vec![label(MAIN), fnhead_local(0), jump(999), quit()]),
+        ram_items: Cow::Owned(vec![]),
+        zero_items: Cow::Owned(vec![]),
+        stack_size: 0x100,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: None,
+    }
+}
+
+#[test]
+fn output_len_matches_assemble() {
+    // The jump over the items needs a one-, two-, and then four-byte offset.
+    for n in [0, 10, 100, 20_000] {
+        let story = story(n);
+        assert_eq!(
+            story.output_len().unwrap(),
+            story.assemble().unwrap().len(),
+            "with {n} items"
+        );
+    }
+}
+
+#[test]
+fn output_len_reports_assembler_errors() {
+    assert!(matches!(
+        broken_story().output_len(),
+        Err(AssemblerError::UndefinedLabel(999))
+    ));
+}
+
+#[cfg(feature = "slice-output")]
+#[test]
+fn assemble_into_matches_assemble() {
+    for n in [0, 10, 20_000] {
+        let story = story(n);
+        let expected = story.assemble().unwrap();
+
+        // Extra room past the end should be left alone.
+        let mut buf = vec![0xaa; expected.len() + 16];
+        let len = story.assemble_into(&mut buf).unwrap();
+
+        assert_eq!(len, expected.len(), "with {n} items");
+        assert!(
+            buf[..len] == expected[..],
+            "story files differ with {n} items"
+        );
+        assert!(buf[len..].iter().all(|b| *b == 0xaa), "with {n} items");
+    }
+}
+
+#[cfg(feature = "slice-output")]
+#[test]
+fn assemble_into_rejects_short_buffers() {
+    let story = story(10);
+    let needed = story.output_len().unwrap();
+    let mut buf = vec![0; needed - 1];
+    assert!(matches!(
+        story.assemble_into(&mut buf),
+        Err(AssemblerError::BufferTooSmall { needed: n, available })
+            if n == needed && available == needed - 1
+    ));
+}
+
+#[cfg(feature = "slice-output")]
+#[test]
+fn assemble_into_reports_assembler_errors() {
+    let mut buf = vec![0; 0x1000];
+    assert!(matches!(
+        broken_story().assemble_into(&mut buf),
+        Err(AssemblerError::UndefinedLabel(999))
+    ));
+}
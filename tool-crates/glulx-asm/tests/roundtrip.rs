@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that disassembling a story file and reassembling the result gives
+//! back the same bytes.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+
+const MAIN: u32 = 0;
+const HELPER: u32 = 1;
+const INDIRECT: u32 = 2;
+const LOOP: u32 = 3;
+const DONE: u32 = 4;
+const GREETING: u32 = 5;
+const COUNTER: u32 = 6;
+const TABLE: u32 = 7;
+const SCRATCH: u32 = 8;
+const FAR: u32 = 9;
+
+fn story() -> Assembly<'static, u32> {
+    let mut rom_items = vec![
+        label(GREETING),
+        mystery_string(&"Hello, sailor!\n"),
+        label(MAIN),
+        fnhead_local(300),
+        setiosys(imm(2), imm(0)),
+        copy(imm(0), sloc(0)),
+        label(LOOP),
+        callfi(imml(HELPER), lloc(0), sloc(1)),
+        callfi(imml(INDIRECT), lloc(1), discard()),
+        add(lloc(0), imm(1), sloc(0)),
+        astore(imml(SCRATCH), imm(3), lloc(0)),
+        copy(derefl(COUNTER), push()),
+        aload(imml_off(TABLE, 8), pop(), sloc(299)),
+        jlt(lloc(0), imm(10), LOOP),
+        jgt(lloc(0), imm(1000), FAR),
+        jz_ret(lloc(0), false),
+        streamstr(imml(GREETING)),
+        label(DONE),
+        ret(imm(0)),
+        label(HELPER),
+        fnhead_stack(0),
+        stkcopy(imm(1)),
+        jnz(pop(), DONE),
+        copy(imm(0x12345), storel(COUNTER)),
+        ret(derefl_off(COUNTER, 4)),
+    ];
+    for i in 0..100 {
+        rom_items.push(add(imm(i), imm(0x1234), push()));
+    }
+    rom_items.extend([
+        label(FAR),
+        quit(),
+        label(INDIRECT),
+        fnhead_local(1),
+        ret(lloc(0)),
+    ]);
+
+    Assembly {
+        rom_items: Cow::Owned(rom_items),
+        ram_items: Cow::Owned(vec![
+            label(COUNTER),
+            blob(vec![0; 8]),
+            label(TABLE),
+            blob((0..64).collect::<Vec<u8>>()),
+        ]),
+        zero_items: Cow::Owned(vec![zspace(16), zlabel(SCRATCH), zspace(4096)]),
+        stack_size: 0x1000,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: None,
+    }
+}
+
+#[test]
+fn reassembles_identically() {
+    let original = story().assemble().unwrap();
+    let disassembled = disassemble(&original).unwrap();
+    let reassembled = disassembled.assemble().unwrap();
+    assert_eq!(original, reassembled);
+}
+
+#[test]
+fn finds_code_reachable_by_direct_calls() {
+    let original = story().assemble().unwrap();
+    let disassembled = disassemble(&original).unwrap();
+    let headers = disassembled
+        .rom_items
+        .iter()
+        .filter(|item| matches!(item, Item::FnHeader(_, _)))
+        .count();
+    assert_eq!(headers, 3);
+    assert!(disassembled
+        .rom_items
+        .iter()
+        .any(|item| matches!(item, Item::Instr(Instr::Quit))));
+}
+
+#[test]
+fn rejects_non_story_files() {
+    assert_eq!(
+        disassemble(b"FORM").unwrap_err(),
+        DisassemblerError::TooShort
+    );
+    assert_eq!(
+        disassemble(&[0; 0x100]).unwrap_err(),
+        DisassemblerError::BadMagic
+    );
+}
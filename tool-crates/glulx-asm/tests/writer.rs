@@ -0,0 +1,107 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that [`Assembly::assemble_to_writer`] writes the same bytes as
+//! [`Assembly::assemble`], including the header checksum which it computes a
+//! chunk at a time.
+
+use glulx_asm::concise::*;
+use glulx_asm::*;
+use std::borrow::Cow;
+
+const MAIN: u32 = 0;
+const COUNTER: u32 = 1;
+const SCRATCH: u32 = 2;
+const FIRST_STRING: u32 = 100;
+
+/// Builds a story with `n` items in each of ROM and RAM. The items have odd
+/// lengths, so that chunk boundaries fall in the middle of words.
+fn story(n: u32) -> Assembly<'static, u32> {
+    let mut rom_items = vec![
+        label(MAIN),
+        fnhead_local(1),
+        copy(derefl(COUNTER), sloc(0)),
+        astore(imml(SCRATCH), imm(0), lloc(0)),
+        quit(),
+    ];
+    for i in 0..n {
+        rom_items.push(label(FIRST_STRING + i));
+        rom_items.push(mystery_string(&"x".repeat(usize::try_from(i % 7).unwrap())));
+    }
+
+    let mut ram_items = vec![label(COUNTER), blob(vec![0, 0, 0, 42])];
+    for i in 0..n {
+        ram_items.push(blob(vec![u8::try_from(i % 251).unwrap(); 3]));
+    }
+
+    Assembly {
+        rom_items: Cow::Owned(rom_items),
+        ram_items: Cow::Owned(ram_items),
+        zero_items: Cow::Owned(vec![zlabel(SCRATCH), zspace(64)]),
+        stack_size: 0x400,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: None,
+    }
+}
+
+fn assert_writer_matches(n: u32) {
+    let story = story(n);
+    let expected = story.assemble().unwrap();
+
+    let mut written = Vec::new();
+    let len = story.assemble_to_writer(&mut written).unwrap();
+
+    assert_eq!(len, written.len());
+    assert_eq!(written.len(), expected.len());
+    assert_eq!(
+        written[0x20..0x24],
+        expected[0x20..0x24],
+        "checksums differ"
+    );
+    assert!(written == expected, "story files differ");
+}
+
+#[test]
+fn small_story_matches_assemble() {
+    assert_writer_matches(10);
+}
+
+#[test]
+fn story_spanning_many_chunks_matches_assemble() {
+    assert_writer_matches(20_000);
+}
+
+#[test]
+fn assembler_errors_are_reported_before_anything_is_written() {
+    let story = Assembly {
+        rom_items: Cow::Owned(vec![label(MAIN), fnhead_local(0), jump(999), quit()]),
+        ram_items: Cow::Owned(vec![]),
+        zero_items: Cow::Owned(vec![]),
+        stack_size: 0x100,
+        start_func: LabelRef(MAIN, 0),
+        decoding_table: None,
+    };
+
+    let mut written = Vec::new();
+    let result = story.assemble_to_writer(&mut written);
+    assert!(matches!(result, Err(WriteError::Assembler(_))));
+    assert!(written.is_empty());
+}
+
+#[test]
+fn io_errors_are_reported() {
+    struct Broken;
+
+    impl std::io::Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("broken"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let result = story(10).assemble_to_writer(Broken);
+    assert!(matches!(result, Err(WriteError::Io(_))));
+}
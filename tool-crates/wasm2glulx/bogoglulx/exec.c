@@ -7,6 +7,7 @@
 #include "opcodes.h"
 
 #include <math.h>
+#include <stdlib.h>
 
 /* A couple of macros which test a pair of glui32 words as a double */
 #define DOUBLE_PAIR_ISINF(vhi, vlo) (((vhi) == 0x7FF00000 || (vhi) == 0xFFF00000) && (vlo) == 0)
@@ -525,10 +526,17 @@ void execute_loop()
         }
         break;
 
+      case op_streamchar:
+        vals0 = inst[0].value & 0xFF;
+        printf("%08x", (unsigned int)vals0);
+        break;
       case op_streamnum:
         vals0 = inst[0].value;
         printf("%08x", (unsigned int)vals0);
         break;
+      case op_streamstr:
+        stream_string(inst[0].value);
+        break;
 
       default:
         fatal_error_i("Executed unknown opcode.", opcode);
@@ -547,6 +555,18 @@ void execute_loop()
         trap(inst[0].value);
         break;
 
+      case op_glk:
+        /* There is no Glk. The only call supported is
+           glk_stream_get_current(), which reports that no stream is open,
+           so that code which prints only when it can still runs. If
+           BOGOGLULX_STREAM is set, it reports a stream instead, so that
+           tests can see what such code prints. */
+        if (inst[0].value != 0x48 || inst[1].value != 0)
+          fatal_error_i("Called unsupported Glk function.", inst[0].value);
+        store_operand(inst[2].desttype, inst[2].value,
+          getenv("BOGOGLULX_STREAM") ? 1 : 0);
+        break;
+
       case op_jumpabs:
         pc = inst[0].value;
         break;
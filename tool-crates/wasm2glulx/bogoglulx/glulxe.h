@@ -187,6 +187,9 @@ extern glui32 linked_search(glui32 key, glui32 keysize,
   glui32 start, glui32 keyoffset, glui32 nextoffset,
   glui32 options);
 
+/* string.c */
+extern void stream_string(glui32 addr);
+
 /* osdepend.c */
 extern void *glulx_malloc(glui32 len);
 extern void *glulx_realloc(void *ptr, glui32 len);
@@ -7,7 +7,7 @@
 #include <stdio.h>
 #include <stdlib.h>
 
-#define TRAP_LEN 11
+#define TRAP_LEN 12
 static const char* trap_messages[TRAP_LEN] = {
   "unreachable",
   "integer overflow",
@@ -19,6 +19,7 @@ static const char* trap_messages[TRAP_LEN] = {
   "undefined element",
   "uninitialized element",
   "call stack exhausted",
+  "glk area size mismatch",
   "unknown trap code",
 };
 
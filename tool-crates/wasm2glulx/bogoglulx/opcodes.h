@@ -62,7 +62,9 @@
 #define op_stkroll      (0x53)
 #define op_stkcopy      (0x54)
 
+#define op_streamchar   (0x70)
 #define op_streamnum    (0x71)
+#define op_streamstr    (0x72)
 
 #define op_gestalt      (0x100)
 #define op_debugtrap    (0x101)
@@ -75,6 +77,8 @@
 
 #define op_setiosys     (0x149)
 
+#define op_glk          (0x130)
+
 #define op_linearsearch (0x150)
 #define op_binarysearch (0x151)
 #define op_linkedsearch (0x152)
@@ -159,7 +159,9 @@ const operandlist_t *lookup_operandlist(glui32 opcode)
   case op_stkcopy:
     return &list_L;
 
+  case op_streamchar:
   case op_streamnum:
+  case op_streamstr:
     return &list_L;
   case op_setiosys:
     return &list_LL;
@@ -171,6 +173,7 @@ const operandlist_t *lookup_operandlist(glui32 opcode)
     return &list_none;
 
   case op_gestalt:
+  case op_glk:
     return &list_LLS;
 
   case op_debugtrap: 
@@ -0,0 +1,95 @@
+/* string.c: Glulxe code for printing strings
+    Designed by Andrew Plotkin <erkyrath@eblong.com>
+    http://eblong.com/zarf/glulx/index.html
+*/
+
+#include "glulxe.h"
+
+/* Unlike Glulxe, bogoglulx has no IO system. Every character is printed
+   to stdout as eight hexadecimal digits, just as streamnum prints a
+   number, so that tests can read printed text back as a sequence of
+   words. Only the three string types are supported; decoding-table
+   nodes which call functions or refer to other objects are fatal. */
+
+static void print_char(glui32 ch)
+{
+  printf("%08x", (unsigned int)ch);
+}
+
+static void stream_compressed(glui32 addr)
+{
+  glui32 table = Mem4(0x1C);
+  glui32 root, node;
+  int bitnum = 0;
+  unsigned char byte;
+
+  if (!table)
+    fatal_error("Attempted to print a compressed string with no table set.");
+
+  root = Mem4(table+8);
+  node = root;
+  byte = Mem1(addr);
+
+  while (1) {
+    unsigned char nodetype = Mem1(node);
+    glui32 ch;
+
+    switch (nodetype) {
+    case 0x00: /* branch */
+      if (byte & (1 << bitnum))
+        node = Mem4(node+5);
+      else
+        node = Mem4(node+1);
+      bitnum++;
+      if (bitnum == 8) {
+        bitnum = 0;
+        addr++;
+        byte = Mem1(addr);
+      }
+      continue;
+    case 0x01: /* terminator */
+      return;
+    case 0x02: /* single character */
+      print_char(Mem1(node+1));
+      break;
+    case 0x03: /* C string */
+      for (ch = node+1; Mem1(ch); ch++)
+        print_char(Mem1(ch));
+      break;
+    case 0x04: /* single Unicode character */
+      print_char(Mem4(node+1));
+      break;
+    case 0x05: /* Unicode string */
+      for (ch = node+1; Mem4(ch); ch += 4)
+        print_char(Mem4(ch));
+      break;
+    default:
+      fatal_error_i("Unsupported string-decoding node type.", nodetype);
+    }
+    node = root;
+  }
+}
+
+/* stream_string():
+   Print the string object at addr.
+*/
+void stream_string(glui32 addr)
+{
+  glui32 type = Mem1(addr);
+
+  switch (type) {
+  case 0xE0:
+    for (addr++; Mem1(addr); addr++)
+      print_char(Mem1(addr));
+    break;
+  case 0xE1:
+    stream_compressed(addr+1);
+    break;
+  case 0xE2:
+    for (addr += 4; Mem4(addr); addr += 4)
+      print_char(Mem4(addr));
+    break;
+  default:
+    fatal_error_i("Attempted to print a non-string.", type);
+  }
+}
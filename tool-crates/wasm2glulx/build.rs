@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
 // Copyright 2024 Daniel Fox Franke.
 #![allow(unused_imports)]
-use std::{ffi::OsString, path::PathBuf};
+use std::{ffi::OsString, fmt::Write as _, path::PathBuf};
 
 #[allow(dead_code)]
 static BOGOGLULX_SOURCES: &[&str] = &[
@@ -15,10 +15,314 @@ static BOGOGLULX_SOURCES: &[&str] = &[
     "operand.c",
     "osdepend.c",
     "search.c",
+    "string.c",
     "vm.c",
 ];
 
+/// How a Glk function parameter is passed to Glk. This mirrors `GlkParam` in
+/// `src/glk.rs`; array lengths are given as parameter indices.
+#[derive(Debug, Copy, Clone)]
+enum Marshal {
+    Scalar,
+    ScalarPtr(u32),
+    ByteArrayPtr(usize),
+    WordArrayPtr(usize),
+    Lat1Ptr,
+    UnicodePtr,
+    OwnedByteArrayPtr(usize),
+    OwnedWordArrayPtr(usize),
+}
+
+/// A parameter as written in the spec, before its annotation is resolved.
+struct RawParam {
+    name: String,
+    ty: String,
+    annotation: Option<(String, Option<String>)>,
+}
+
+#[derive(Debug)]
+struct SpecParam {
+    name: String,
+    ty: String,
+    marshal: Marshal,
+}
+
+#[derive(Debug)]
+struct SpecFunction {
+    selector: u16,
+    name: String,
+    params: Vec<SpecParam>,
+    ret: Option<String>,
+    /// Whether a blank line precedes this function in the spec.
+    new_group: bool,
+    /// The Cargo feature of wasm2glulx-ffi which the binding is gated behind,
+    /// if any.
+    feature: Option<String>,
+}
+
+/// Parses `glk.spec`. Malformed lines cause a panic naming the line, since
+/// there is no reasonable way to build without them.
+fn parse_glk_spec(spec: &str) -> Vec<SpecFunction> {
+    let mut functions: Vec<SpecFunction> = Vec::new();
+    let mut new_group = false;
+
+    for (lineno, line) in spec.lines().enumerate() {
+        let fail = |msg: &str| -> ! { panic!("glk.spec:{}: {msg}", lineno + 1) };
+        let line = line.trim();
+        if line.is_empty() {
+            new_group = !functions.is_empty();
+            continue;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        let (selector, sig) = line
+            .split_once(char::is_whitespace)
+            .unwrap_or_else(|| fail("expected a selector followed by a signature"));
+        let selector = selector
+            .strip_prefix("0x")
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+            .unwrap_or_else(|| fail("selector should be a hexadecimal u16 such as 0x0080"));
+
+        let (name, rest) = sig
+            .split_once('(')
+            .unwrap_or_else(|| fail("expected a parameter list"));
+        let (params, ret) = rest
+            .rsplit_once(')')
+            .unwrap_or_else(|| fail("unterminated parameter list"));
+        let (ret, feature) = match ret.split_once('[') {
+            Some((ret, gate)) => {
+                let feature = gate
+                    .strip_suffix(']')
+                    .and_then(|gate| gate.strip_prefix("feature "))
+                    .unwrap_or_else(|| fail("expected `[feature NAME]` after the signature"));
+                (ret, Some(feature.trim().to_owned()))
+            }
+            None => (ret, None),
+        };
+        let ret = match ret.trim() {
+            "" => None,
+            ret => Some(
+                ret.strip_prefix("->")
+                    .unwrap_or_else(|| fail("expected `->` before return type"))
+                    .trim()
+                    .to_owned(),
+            ),
+        };
+
+        let mut parsed: Vec<RawParam> = Vec::new();
+        for param in params.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (decl, annotation) = match param.split_once('[') {
+                Some((decl, annotation)) => {
+                    let annotation = annotation
+                        .strip_suffix(']')
+                        .unwrap_or_else(|| fail("unterminated annotation"));
+                    let mut words = annotation.split_whitespace();
+                    let kind = words
+                        .next()
+                        .unwrap_or_else(|| fail("empty annotation"))
+                        .to_owned();
+                    let arg = words.next().map(str::to_owned);
+                    if words.next().is_some() {
+                        fail("too many words in annotation");
+                    }
+                    (decl, Some((kind, arg)))
+                }
+                None => (param, None),
+            };
+            let (pname, ty) = decl
+                .split_once(':')
+                .unwrap_or_else(|| fail("expected `name: type` in parameter list"));
+            parsed.push(RawParam {
+                name: pname.trim().to_owned(),
+                ty: ty.trim().to_owned(),
+                annotation,
+            });
+        }
+
+        let index_of = |len: &str| -> usize {
+            match parsed.iter().position(|param| param.name == len) {
+                Some(i) if !parsed[i].ty.starts_with('*') => i,
+                Some(_) => fail(&format!("length parameter `{len}` is a pointer")),
+                None => fail(&format!("no parameter named `{len}`")),
+            }
+        };
+
+        let mut params = Vec::new();
+        for RawParam {
+            name: pname,
+            ty,
+            annotation,
+        } in &parsed
+        {
+            let is_ptr = ty.starts_with('*');
+            let marshal = match annotation {
+                None if is_ptr => fail(&format!("pointer parameter `{pname}` needs an annotation")),
+                None => Marshal::Scalar,
+                Some((kind, arg)) => {
+                    let wants_ptr = !kind.starts_with("glk_");
+                    if wants_ptr != is_ptr {
+                        fail(&format!(
+                            "parameter `{pname}` has the wrong type for a `{kind}` annotation"
+                        ));
+                    }
+                    match (kind.as_str(), arg.as_deref()) {
+                        ("scalar", Some(n)) => Marshal::ScalarPtr(
+                            n.parse()
+                                .unwrap_or_else(|_| fail("expected a word count after `scalar`")),
+                        ),
+                        ("bytes", Some(len)) => Marshal::ByteArrayPtr(index_of(len)),
+                        ("words", Some(len)) => Marshal::WordArrayPtr(index_of(len)),
+                        ("lat1", None) => Marshal::Lat1Ptr,
+                        ("unicode", None) => Marshal::UnicodePtr,
+                        ("glk_bytes", Some(len)) => Marshal::OwnedByteArrayPtr(index_of(len)),
+                        ("glk_words", Some(len)) => Marshal::OwnedWordArrayPtr(index_of(len)),
+                        _ => fail(&format!("unrecognized annotation `{kind}`")),
+                    }
+                }
+            };
+            params.push(SpecParam {
+                name: pname.clone(),
+                ty: ty.clone(),
+                marshal,
+            });
+        }
+
+        let name = name.trim().to_owned();
+        if functions.iter().any(|f| f.selector == selector) {
+            fail(&format!("duplicate selector {selector:#06x}"));
+        }
+        if functions.iter().any(|f| f.name == name) {
+            fail(&format!("duplicate function `{name}`"));
+        }
+        functions.push(SpecFunction {
+            selector,
+            name,
+            params,
+            ret,
+            new_group,
+            feature,
+        });
+        new_group = false;
+    }
+
+    functions
+}
+
+/// Renders the table of `GlkFunction`s which `src/glk.rs` includes.
+fn render_glk_table(functions: &[SpecFunction]) -> String {
+    let mut out = String::new();
+    writeln!(out, "// Generated by build.rs from glk.spec. Do not edit.").unwrap();
+    writeln!(out, "static GLK_FUNCTIONS: &[GlkFunction] = &[").unwrap();
+    for function in functions {
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|param| match param.marshal {
+                Marshal::Scalar => "GlkParam::Scalar".to_owned(),
+                Marshal::ScalarPtr(n) => format!("GlkParam::ScalarPtr({n})"),
+                Marshal::ByteArrayPtr(i) => format!("GlkParam::ByteArrayPtr({i})"),
+                Marshal::WordArrayPtr(i) => format!("GlkParam::WordArrayPtr({i})"),
+                Marshal::Lat1Ptr => "GlkParam::Lat1Ptr".to_owned(),
+                Marshal::UnicodePtr => "GlkParam::UnicodePtr".to_owned(),
+                Marshal::OwnedByteArrayPtr(i) => format!("GlkParam::OwnedByteArrayPtr({i})"),
+                Marshal::OwnedWordArrayPtr(i) => format!("GlkParam::OwnedWordArrayPtr({i})"),
+            })
+            .collect();
+        writeln!(out, "    GlkFunction {{").unwrap();
+        writeln!(out, "        name: {:?},", function.name).unwrap();
+        writeln!(out, "        selector: {:#06x},", function.selector).unwrap();
+        writeln!(out, "        params: &[{}],", params.join(", ")).unwrap();
+        writeln!(
+            out,
+            "        has_return: {},",
+            function.ret.as_deref().is_some_and(|ret| ret != "!")
+        )
+        .unwrap();
+        writeln!(out, "    }},").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+    out
+}
+
+/// Renders the extern block which wasm2glulx-ffi's `src/glk_functions.rs`
+/// should consist of, formatted as rustfmt would.
+fn render_glk_ffi(functions: &[SpecFunction]) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception"
+    )
+    .unwrap();
+    writeln!(out, "// Copyright 2024 Daniel Fox Franke.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "// Generated from wasm2glulx's glk.spec. Do not edit; see that file for how"
+    )
+    .unwrap();
+    writeln!(out, "// to regenerate this one.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "#[cfg(all(target_arch = \"wasm32\", target_os = \"unknown\"))]"
+    )
+    .unwrap();
+    writeln!(out, "#[link(wasm_import_module = \"glk\")]").unwrap();
+    writeln!(out, "extern \"C\" {{").unwrap();
+    for function in functions {
+        if function.new_group {
+            writeln!(out).unwrap();
+        }
+        if let Some(feature) = &function.feature {
+            writeln!(out, "    #[cfg(feature = {feature:?})]").unwrap();
+        }
+        let params: Vec<String> = function
+            .params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.ty))
+            .collect();
+        let ret = function
+            .ret
+            .as_ref()
+            .map_or(String::new(), |ret| format!(" -> {ret}"));
+        let one_line = format!("    pub fn {}({}){ret};", function.name, params.join(", "));
+        if one_line.len() <= 100 {
+            writeln!(out, "{one_line}").unwrap();
+        } else {
+            writeln!(out, "    pub fn {}(", function.name).unwrap();
+            for param in &params {
+                writeln!(out, "        {param},").unwrap();
+            }
+            writeln!(out, "    ){ret};").unwrap();
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Generates the Glk function table and FFI bindings from `glk.spec` into
+/// `OUT_DIR`.
+fn gen_glk_bindings() {
+    println!("cargo:rerun-if-changed=glk.spec");
+    let spec = std::fs::read_to_string("glk.spec").expect("glk.spec should be readable");
+    let functions = parse_glk_spec(&spec);
+
+    let out_dir =
+        PathBuf::from(std::env::var("OUT_DIR").expect("OUT_DIR should be set during builds"));
+    std::fs::write(
+        out_dir.join("glk_functions.rs"),
+        render_glk_table(&functions),
+    )
+    .expect("generated Glk function table should be writable");
+    std::fs::write(out_dir.join("glk_ffi.rs"), render_glk_ffi(&functions))
+        .expect("generated Glk FFI bindings should be writable");
+}
+
 fn main() {
+    gen_glk_bindings();
+
     #[cfg(feature = "spectest")]
     {
         let platform_bogoglulx_sources: Vec<PathBuf> = BOGOGLULX_SOURCES
@@ -0,0 +1,176 @@
+# SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+# Copyright 2024 Daniel Fox Franke.
+#
+# The Glk functions which Wasm2Glulx can bind to. The build script generates
+# both Wasm2Glulx's argument marshalling code and the extern declarations in
+# wasm2glulx-ffi from this file, so adding a function here is all it takes to
+# support it. After changing this file, run
+#
+#     WASM2GLULX_BLESS=1 cargo test -p wasm2glulx --test glk_spec
+#
+# to update wasm2glulx-ffi.
+#
+# Each line gives a function's selector followed by its signature as it appears
+# in wasm2glulx-ffi, minus the `glk_` prefix. A return type of `!` means the
+# function never returns. Every parameter which is a pointer into memory or an
+# offset into the Glk area must be followed by one of these annotations, saying
+# how to pass it to Glk:
+#
+#   [scalar N]        a pointer to a structure of N words
+#   [bytes LEN]       a pointer to a byte array, whose length is parameter LEN
+#   [words LEN]       a pointer to a word array, whose length is parameter LEN
+#   [lat1]            a pointer to a null-terminated Latin-1 string
+#   [unicode]         a pointer to a null-terminated array of code points
+#   [glk_bytes LEN]   an offset of a byte array in the Glk area
+#   [glk_words LEN]   an offset of a word array in the Glk area
+#
+# A signature may be followed by `[feature NAME]`, in which case the binding in
+# wasm2glulx-ffi is only declared when its Cargo feature NAME is enabled.
+# Wasm2Glulx itself accepts every function listed here regardless.
+#
+# Blank lines separate groups of related functions in the generated bindings.
+
+0x0001 exit() -> !
+0x0003 tick()
+0x0004 gestalt(sel: Gestalt, val: u32) -> u32
+0x0005 gestalt_ext(sel: Gestalt, val: u32, arr: *mut u32 [words arrlen], arrlen: u32) -> u32
+
+0x0020 window_iterate(win: WinId, rockptr: *mut u32 [scalar 1]) -> WinId
+0x0021 window_get_rock(win: WinId) -> u32
+0x0022 window_get_root() -> WinId
+0x0023 window_open(split: WinId, method: WinMethod, size: u32, wintype: WinType, rock: u32) -> WinId
+0x0024 window_close(win: WinId, result: *mut StreamResult [scalar 2])
+0x0025 window_get_size(win: WinId, widthptr: *mut u32 [scalar 1], heightptr: *mut u32 [scalar 1])
+0x0026 window_set_arrangement(win: WinId, method: WinMethod, size: u32, keywin: WinId)
+0x0027 window_get_arrangement(win: WinId, methodptr: *mut WinMethod [scalar 1], sizeptr: *mut u32 [scalar 1], keywinptr: *mut WinId [scalar 1])
+0x0028 window_get_type(win: WinId) -> WinType
+0x0029 window_get_parent(win: WinId) -> WinId
+0x002a window_clear(win: WinId)
+0x002b window_move_cursor(win: WinId, xpos: u32, ypos: u32)
+0x002c window_get_stream(win: WinId) -> StrId
+0x002d window_set_echo_stream(win: WinId, str: StrId)
+0x002e window_get_echo_stream(win: WinId) -> StrId
+0x002f set_window(win: WinId)
+0x0030 window_get_sibling(win: WinId) -> WinId
+
+0x0040 stream_iterate(str: StrId, rockptr: *mut u32 [scalar 1]) -> StrId
+0x0041 stream_get_rock(str: StrId) -> u32
+0x0042 stream_open_file(fileref: FrefId, mode: FileMode, rock: u32) -> StrId
+0x0043 stream_open_memory(glkaddr: u32 [glk_bytes buflen], buflen: u32, mode: FileMode, rock: u32) -> StrId
+0x0044 stream_close(str: StrId, result: *mut StreamResult [scalar 2])
+0x0045 stream_set_position(str: StrId, pos: i32, seekmode: SeekMode)
+0x0046 stream_get_position(str: StrId) -> u32
+0x0047 stream_set_current(str: StrId)
+0x0048 stream_get_current() -> StrId
+0x0049 stream_open_resource(filenum: u32, rock: u32) -> StrId
+
+0x0060 fileref_create_temp(usage: FileUsage, rock: u32) -> FrefId
+0x0061 fileref_create_by_name(usage: FileUsage, name: *const c_char [lat1], rock: u32) -> FrefId
+0x0062 fileref_create_by_prompt(usage: FileUsage, fmode: FileMode, rock: u32) -> FrefId
+0x0063 fileref_destroy(fref: FrefId)
+0x0064 fileref_iterate(fref: FrefId, rockptr: *mut u32 [scalar 1]) -> FrefId
+0x0065 fileref_get_rock(fref: FrefId) -> u32
+0x0066 fileref_delete_file(fref: FrefId)
+0x0067 fileref_does_file_exist(fref: FrefId) -> u32
+0x0068 fileref_create_from_fileref(usage: FileUsage, fref: FrefId, rock: u32) -> FrefId
+
+0x0080 put_char(ch: u32)
+0x0081 put_char_stream(str: StrId, ch: u32)
+0x0082 put_string(s: *const c_char [lat1])
+0x0083 put_string_stream(str: StrId, s: *const c_char [lat1])
+0x0084 put_buffer(buf: *const c_char [bytes len], len: u32)
+0x0085 put_buffer_stream(str: StrId, buf: *const c_char [bytes len], len: u32)
+0x0086 set_style(styl: Style)
+0x0087 set_style_stream(str: StrId, styl: Style)
+
+0x0090 get_char_stream(str: StrId) -> i32
+0x0091 get_line_stream(str: StrId, buf: *mut c_char [bytes len], len: u32) -> u32
+0x0092 get_buffer_stream(str: StrId, buf: *mut c_char [bytes len], len: u32) -> u32
+
+0x00a0 char_to_lower(ch: u32) -> u32
+0x00a1 char_to_upper(ch: u32) -> u32
+
+0x00b0 stylehint_set(wintype: WinType, styl: Style, hint: StyleHint, val: i32)
+0x00b1 stylehint_clear(wintype: WinType, styl: Style, hint: StyleHint)
+0x00b2 style_distinguish(win: WinId, styl1: Style, styl2: Style) -> u32
+0x00b3 style_measure(win: WinId, styl: Style, hint: StyleHint, result: *mut u32 [scalar 1]) -> u32
+
+0x00c0 select(event: *mut Event [scalar 4])
+0x00c1 select_poll(event: *mut Event [scalar 4])
+
+0x00d0 request_line_event(win: WinId, glkaddr: u32 [glk_bytes maxlen], maxlen: u32, initlen: u32)
+0x00d1 cancel_line_event(win: WinId, event: *mut Event [scalar 4])
+0x00d2 request_char_event(win: WinId)
+0x00d3 cancel_char_event(win: WinId)
+0x00d4 request_mouse_event(win: WinId)
+0x00d5 cancel_mouse_event(win: WinId)
+0x00d6 request_timer_events(millisecs: u32)
+
+0x00e0 image_get_info(image: u32, width: *mut u32 [scalar 1], height: *mut u32 [scalar 1]) -> u32
+0x00e1 image_draw(win: WinId, image: u32, val1: i32, val2: i32) -> u32
+0x00e2 image_draw_scaled(win: WinId, image: u32, val1: i32, val2: i32, width: u32, height: u32) -> u32
+0x00e8 window_flow_break(win: WinId)
+0x00e9 window_erase_rect(win: WinId, left: i32, top: i32, width: u32, height: u32)
+0x00ea window_fill_rect(win: WinId, color: u32, left: i32, top: i32, width: u32, height: u32)
+0x00eb window_set_background_color(win: WinId, color: u32)
+
+0x00f0 schannel_iterate(chan: SchanId, rockptr: *mut u32 [scalar 1]) -> SchanId
+0x00f1 schannel_get_rock(chan: SchanId) -> u32
+0x00f2 schannel_create(rock: u32) -> SchanId
+0x00f3 schannel_destroy(chan: SchanId)
+0x00f4 schannel_create_ext(rock: u32, volume: u32) -> SchanId
+0x00f7 schannel_play_multi(chans: *const SchanId [words chancount], chancount: u32, sounds: *const u32 [words soundcount], soundcount: u32, notify: u32) -> u32
+0x00f8 schannel_play(chan: SchanId, snd: u32) -> u32
+0x00f9 schannel_play_ext(chan: SchanId, snd: u32, repeats: u32, notify: u32) -> u32
+0x00fa schannel_stop(chan: SchanId)
+0x00fb schannel_set_volume(chan: SchanId, vol: u32)
+0x00fc sound_load_hint(snd: u32, flag: u32)
+0x00fd schannel_set_volume_ext(chan: SchanId, vol: u32, duration: u32, notify: u32)
+0x00fe schannel_pause(chan: SchanId)
+0x00ff schannel_unpause(chan: SchanId)
+
+0x0100 set_hyperlink(linkval: u32)
+0x0101 set_hyperlink_stream(str: StrId, linkval: u32)
+0x0102 request_hyperlink_event(win: WinId)
+0x0103 cancel_hyperlink_event(win: WinId)
+
+0x0120 buffer_to_lower_case_uni(buf: *mut u32 [words len], len: u32, numchars: u32) -> u32
+0x0121 buffer_to_upper_case_uni(buf: *mut u32 [words len], len: u32, numchars: u32) -> u32
+0x0122 buffer_to_title_case_uni(buf: *mut u32 [words len], len: u32, numchars: u32, lowerrest: u32) -> u32
+0x0123 buffer_canon_decompose_uni(buf: *mut u32 [words len], len: u32, numchars: u32) -> u32
+0x0124 buffer_canon_normalize_uni(buf: *mut u32 [words len], len: u32, numchars: u32) -> u32
+0x0128 put_char_uni(ch: u32)
+0x0129 put_string_uni(s: *const u32 [unicode])
+0x012a put_buffer_uni(buf: *const u32 [words len], len: u32)
+0x012b put_char_stream_uni(str: StrId, ch: u32)
+0x012c put_string_stream_uni(str: StrId, s: *const u32 [unicode])
+0x012d put_buffer_stream_uni(str: StrId, buf: *const u32 [words len], len: u32)
+
+0x0130 get_char_stream_uni(str: StrId) -> i32
+0x0131 get_buffer_stream_uni(str: StrId, buf: *mut u32 [words len], len: u32) -> u32
+0x0132 get_line_stream_uni(str: StrId, buf: *mut u32 [words len], len: u32) -> u32
+0x0138 stream_open_file_uni(fileref: FrefId, mode: FileMode, rock: u32) -> StrId
+0x0139 stream_open_memory_uni(glkaddr: u32 [glk_words buflen], buflen: u32, mode: FileMode, rock: u32) -> StrId
+0x013a stream_open_resource_uni(filenum: u32, rock: u32) -> StrId
+
+0x0140 request_char_event_uni(win: WinId)
+0x0141 request_line_event_uni(win: WinId, glkaddr: u32 [glk_words maxlen], maxlen: u32, initlen: u32)
+
+0x0150 set_echo_line_event(win: WinId, val: u32)
+0x0151 set_terminators_line_event(win: WinId, keycodes: *const Keycode [words count], count: u32)
+
+0x0160 current_time(time: *mut Timeval [scalar 3])
+0x0161 current_simple_time(factor: u32) -> i32
+0x0168 time_to_date_utc(time: *const Timeval [scalar 3], date: *mut Date [scalar 8])
+0x0169 time_to_date_local(time: *const Timeval [scalar 3], date: *mut Date [scalar 8])
+0x016a simple_time_to_date_utc(time: i32, factor: u32, date: *mut Date [scalar 8])
+0x016b simple_time_to_date_local(time: i32, factor: u32, date: *mut Date [scalar 8])
+0x016c date_to_time_utc(date: *const Date [scalar 8], time: *mut Timeval [scalar 3])
+0x016d date_to_time_local(date: *const Date [scalar 8], time: *mut Timeval [scalar 3])
+0x016e date_to_simple_time_utc(date: *const Date [scalar 8], factor: u32) -> i32
+0x016f date_to_simple_time_local(date: *const Date [scalar 8], factor: u32) -> i32
+
+0x1100 garglk_set_zcolors(fg: u32, bg: u32) [feature garglk]
+0x1101 garglk_set_zcolors_stream(str: StrId, fg: u32, bg: u32) [feature garglk]
+0x1102 garglk_set_reversevideo(reverse: u32) [feature garglk]
+0x1103 garglk_set_reversevideo_stream(str: StrId, reverse: u32) [feature garglk]
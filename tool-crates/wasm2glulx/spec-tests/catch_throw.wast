@@ -0,0 +1,39 @@
+;; The `catch` and `throw` intrinsics: a function called through `catch`
+;; either returns normally or is abandoned by a `throw` from any depth.
+
+(module
+  (import "glulx" "catch" (func $catch (param i32 i32) (result i32)))
+  (import "glulx" "throw" (func $throw (param i32 i32)))
+  (type $body (func (param i32 i32) (result i32)))
+  (table 3 funcref)
+  (elem (i32.const 0) $double $nested $wrong)
+
+  ;; Returns twice its argument, unless the argument is negative, in which
+  ;; case it throws 100 from a few calls further down.
+  (func $double (param $token i32) (param $n i32) (result i32)
+    (if (i32.lt_s (local.get $n) (i32.const 0))
+      (then (call $deep (local.get $token) (i32.const 3))))
+    (i32.mul (local.get $n) (i32.const 2)))
+  (func $deep (param $token i32) (param $depth i32)
+    (if (i32.eqz (local.get $depth))
+      (then (call $throw (local.get $token) (i32.const 100)))
+      (else (call $deep (local.get $token) (i32.sub (local.get $depth) (i32.const 1))))))
+
+  ;; Catches a throw from an inner catch's body, then throws its own.
+  (func $nested (param $token i32) (param $n i32) (result i32)
+    (drop (call $catch (i32.const 0) (local.get $n)))
+    (call $throw (local.get $token) (i32.const 7))
+    (i32.const 0))
+  (func $wrong (param i32) (result i32) (local.get 0))
+
+  ;; The test harness drops everything that the invoked function doesn't
+  ;; reference, and nothing else here mentions the table, so touch it.
+  (func (export "run") (param $func i32) (param $arg i32) (result i32)
+    (drop (table.size 0))
+    (call $catch (local.get $func) (local.get $arg))))
+
+(assert_return (invoke "run" (i32.const 0) (i32.const 21)) (i32.const 42))
+(assert_return (invoke "run" (i32.const 0) (i32.const -1)) (i32.const 100))
+(assert_return (invoke "run" (i32.const 1) (i32.const -1)) (i32.const 7))
+(assert_trap (invoke "run" (i32.const 2) (i32.const 0)) "indirect call type mismatch")
+(assert_trap (invoke "run" (i32.const 3) (i32.const 0)) "undefined element")
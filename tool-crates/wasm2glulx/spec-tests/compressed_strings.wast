@@ -0,0 +1,27 @@
+;; print_compressed prints strings from the glulx_strings custom section,
+;; Huffman-coded against a decoding table that Wasm2Glulx generates. bogoglulx
+;; prints each character as a word, so the characters read back as extra i32
+;; results ahead of the -1 that each function returns.
+
+(module
+  (import "glulx" "print_compressed" (func $print_compressed (param i32)))
+  (@custom "glulx_strings"
+    "\01\00\00\00\02\00\00\00Hi" "\02\00\00\00\0b\00\00\00abracadabra" "\03\00\00\00\08\00\00\00n\c3\a9 \e2\82\ac5" "\04\00\00\00\00\00\00\00" "\01\00\00\00\02\00\00\00Hi")
+
+  (func (export "print") (param i32) (result i32)
+    (call $print_compressed (local.get 0))
+    (i32.const -1))
+  (func (export "print_twice") (param i32 i32) (result i32)
+    (call $print_compressed (local.get 0))
+    (call $print_compressed (local.get 1))
+    (i32.const -1))
+)
+
+(assert_return (invoke "print" (i32.const 1)) (i32.const 0x48) (i32.const 0x69) (i32.const -1))
+(assert_return (invoke "print" (i32.const 2)) (i32.const 0x61) (i32.const 0x62) (i32.const 0x72) (i32.const 0x61) (i32.const 0x63) (i32.const 0x61) (i32.const 0x64) (i32.const 0x61) (i32.const 0x62) (i32.const 0x72) (i32.const 0x61) (i32.const -1))
+;; Characters outside Latin-1 use Unicode nodes.
+(assert_return (invoke "print" (i32.const 3)) (i32.const 0x6e) (i32.const 0xe9) (i32.const 0x20) (i32.const 0x20ac) (i32.const 0x35) (i32.const -1))
+(assert_return (invoke "print" (i32.const 4)) (i32.const -1))
+;; Unknown ids print nothing.
+(assert_return (invoke "print" (i32.const 5)) (i32.const -1))
+(assert_return (invoke "print_twice" (i32.const 2) (i32.const 1)) (i32.const 0x61) (i32.const 0x62) (i32.const 0x72) (i32.const 0x61) (i32.const 0x63) (i32.const 0x61) (i32.const 0x64) (i32.const 0x61) (i32.const 0x62) (i32.const 0x72) (i32.const 0x61) (i32.const 0x48) (i32.const 0x69) (i32.const -1))
@@ -0,0 +1,39 @@
+;; Extended constant expressions in global initializers and segment offsets
+;; are evaluated at compile time.
+
+(module
+  (global $base i32 (i32.const 10))
+  (global $sum i32 (i32.add (global.get $base) (i32.const 5)))
+  (global $chain i32 (i32.mul (global.get $sum) (i32.sub (i32.const 1) (i32.const 3))))
+  (global $wrap i32 (i32.mul (i32.const 0x10000) (i32.const 0x10000)))
+  (global $wide i64 (i64.mul (i64.const 3) (i64.sub (i64.const 10) (i64.const 4))))
+  (global $big i64 (i64.add (i64.const 0x7fffffffffffffff) (i64.const 2)))
+  (global $copy (mut i32) (global.get $sum))
+
+  (memory 1)
+  (data (i32.add (global.get $sum) (i32.const 1)) "\2a")
+
+  (table 4 funcref)
+  (elem (i32.sub (i32.const 3) (i32.const 1)) func $seven)
+  (func $seven (result i32) (i32.const 7))
+
+  (func (export "sum") (result i32) (global.get $sum))
+  (func (export "chain") (result i32) (global.get $chain))
+  (func (export "wrap") (result i32) (global.get $wrap))
+  (func (export "wide") (result i64) (global.get $wide))
+  (func (export "big") (result i64) (global.get $big))
+  (func (export "copy") (result i32) (global.get $copy))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0)))
+  (func (export "call") (param i32) (result i32)
+    (call_indirect (result i32) (local.get 0))))
+
+(assert_return (invoke "sum") (i32.const 15))
+(assert_return (invoke "chain") (i32.const -30))
+(assert_return (invoke "wrap") (i32.const 0))
+(assert_return (invoke "wide") (i64.const 18))
+(assert_return (invoke "big") (i64.const -0x7fffffffffffffff))
+(assert_return (invoke "copy") (i32.const 15))
+(assert_return (invoke "load8_u" (i32.const 16)) (i32.const 0x2a))
+(assert_return (invoke "call" (i32.const 2)) (i32.const 7))
+(assert_trap (invoke "call" (i32.const 1)) "uninitialized element")
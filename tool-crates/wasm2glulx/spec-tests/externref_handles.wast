@@ -0,0 +1,40 @@
+;; Externref values made with the `externref_*` intrinsics can be kept in
+;; tables, tested for null, and turned back into the values they wrap. Freed
+;; handles are reused.
+
+(module
+  (import "glulx" "externref_new" (func $new (param i32) (result externref)))
+  (import "glulx" "externref_get" (func $get (param externref) (result i32)))
+  (import "glulx" "externref_drop" (func $drop (param externref)))
+  (table $t 4 externref)
+
+  (func (export "store") (param $i i32) (param $v i32)
+    (table.set $t (local.get $i) (call $new (local.get $v))))
+  (func (export "load") (param $i i32) (result i32)
+    (call $get (table.get $t (local.get $i))))
+  (func (export "is_null") (param $i i32) (result i32)
+    (ref.is_null (table.get $t (local.get $i))))
+  (func (export "release") (param $i i32)
+    (call $drop (table.get $t (local.get $i)))
+    (table.set $t (local.get $i) (ref.null extern)))
+  (func (export "same") (param $i i32) (param $j i32) (result i32)
+    (i32.eq
+      (call $get (table.get $t (local.get $i)))
+      (call $get (table.get $t (local.get $j))))))
+
+(assert_return (invoke "is_null" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "load" (i32.const 0)) (i32.const 0))
+
+(invoke "store" (i32.const 0) (i32.const 0))
+(invoke "store" (i32.const 1) (i32.const 42))
+(assert_return (invoke "is_null" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load" (i32.const 0)) (i32.const 0))
+(assert_return (invoke "load" (i32.const 1)) (i32.const 42))
+
+(invoke "release" (i32.const 0))
+(assert_return (invoke "is_null" (i32.const 0)) (i32.const 1))
+(invoke "store" (i32.const 2) (i32.const 7))
+(invoke "store" (i32.const 3) (i32.const 42))
+(assert_return (invoke "load" (i32.const 1)) (i32.const 42))
+(assert_return (invoke "load" (i32.const 2)) (i32.const 7))
+(assert_return (invoke "same" (i32.const 1) (i32.const 3)) (i32.const 1))
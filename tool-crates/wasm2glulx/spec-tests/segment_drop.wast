@@ -0,0 +1,73 @@
+;; Dropped data and element segments behave as if empty: initializing from
+;; them traps unless both the count and the source offset are zero, and
+;; nothing is copied.
+
+(module
+  (memory 1)
+  (data $passive "\aa\bb\cc\dd")
+  (data $active (i32.const 16) "\11\22")
+
+  (func (export "init") (param $dst i32) (param $src i32) (param $n i32)
+    (memory.init $passive (local.get $dst) (local.get $src) (local.get $n)))
+  (func (export "init_active") (param $dst i32) (param $src i32) (param $n i32)
+    (memory.init $active (local.get $dst) (local.get $src) (local.get $n)))
+  (func (export "drop")
+    (data.drop $passive))
+  (func (export "load8_u") (param i32) (result i32)
+    (i32.load8_u (local.get 0))))
+
+(invoke "init" (i32.const 0) (i32.const 0) (i32.const 4))
+(assert_return (invoke "load8_u" (i32.const 3)) (i32.const 0xdd))
+(invoke "drop")
+(invoke "drop")
+(assert_return (invoke "init" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init" (i32.const 8) (i32.const 0) (i32.const 1)) "out of bounds memory access")
+(assert_trap (invoke "init" (i32.const 8) (i32.const 1) (i32.const 0)) "out of bounds memory access")
+(assert_return (invoke "load8_u" (i32.const 8)) (i32.const 0))
+
+;; Active segments are dropped once they have been copied into memory.
+(assert_return (invoke "load8_u" (i32.const 17)) (i32.const 0x22))
+(assert_return (invoke "init_active" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init_active" (i32.const 0) (i32.const 0) (i32.const 1)) "out of bounds memory access")
+
+(module
+  (table 4 funcref)
+  (func $one (result i32) (i32.const 1))
+  (func $two (result i32) (i32.const 2))
+  (elem $passive func $one $two)
+  (elem $active (i32.const 2) func $two)
+  (elem $declared declare func $one)
+
+  (func (export "init") (param $dst i32) (param $src i32) (param $n i32)
+    (table.init $passive (local.get $dst) (local.get $src) (local.get $n)))
+  (func (export "init_active") (param $dst i32) (param $src i32) (param $n i32)
+    (table.init $active (local.get $dst) (local.get $src) (local.get $n)))
+  (func (export "init_declared") (param $dst i32) (param $src i32) (param $n i32)
+    (table.init $declared (local.get $dst) (local.get $src) (local.get $n)))
+  (func (export "drop")
+    (elem.drop $passive))
+  (func (export "drop_declared")
+    (elem.drop $declared))
+  (func (export "is_null") (param i32) (result i32)
+    (ref.is_null (table.get (local.get 0))))
+  (func (export "call") (param i32) (result i32)
+    (call_indirect (result i32) (local.get 0))))
+
+(invoke "init" (i32.const 0) (i32.const 0) (i32.const 2))
+(assert_return (invoke "call" (i32.const 1)) (i32.const 2))
+(invoke "drop")
+(invoke "drop")
+(assert_return (invoke "init" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init" (i32.const 3) (i32.const 0) (i32.const 1)) "out of bounds table access")
+(assert_trap (invoke "init" (i32.const 3) (i32.const 1) (i32.const 0)) "out of bounds table access")
+(assert_return (invoke "is_null" (i32.const 3)) (i32.const 1))
+
+;; Active segments are dropped once they have been copied into the table.
+(assert_return (invoke "call" (i32.const 2)) (i32.const 2))
+(assert_return (invoke "init_active" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init_active" (i32.const 0) (i32.const 0) (i32.const 1)) "out of bounds table access")
+
+;; Declarative segments are dropped at instantiation.
+(assert_return (invoke "init_declared" (i32.const 0) (i32.const 0) (i32.const 0)))
+(assert_trap (invoke "init_declared" (i32.const 0) (i32.const 0) (i32.const 1)) "out of bounds table access")
+(invoke "drop_declared")
@@ -0,0 +1,142 @@
+;; Exercises the scalarized v128 lowering end to end: every operation here is
+;; routed through a runtime routine in rt.rs rather than rejected.
+
+(module
+  (memory 1)
+
+  (func (export "load") (param i32) (result v128)
+    (v128.load (local.get 0)))
+  (func (export "store") (param i32 v128)
+    (v128.store offset=16 (local.get 0) (local.get 1)))
+  (func (export "store_load") (param v128) (result v128)
+    (v128.store offset=3 (i32.const 29) (local.get 0))
+    (v128.load offset=2 (i32.const 30)))
+
+  (func (export "i8x16.splat") (param i32) (result v128)
+    (i8x16.splat (local.get 0)))
+  (func (export "i16x8.splat") (param i32) (result v128)
+    (i16x8.splat (local.get 0)))
+  (func (export "i32x4.splat") (param i32) (result v128)
+    (i32x4.splat (local.get 0)))
+  (func (export "f32x4.splat") (param f32) (result v128)
+    (f32x4.splat (local.get 0)))
+
+  (func (export "i8x16.extract_lane_s") (param v128) (result i32)
+    (i8x16.extract_lane_s 15 (local.get 0)))
+  (func (export "i8x16.extract_lane_u") (param v128) (result i32)
+    (i8x16.extract_lane_u 15 (local.get 0)))
+  (func (export "i16x8.extract_lane_s") (param v128) (result i32)
+    (i16x8.extract_lane_s 1 (local.get 0)))
+  (func (export "i32x4.extract_lane") (param v128) (result i32)
+    (i32x4.extract_lane 2 (local.get 0)))
+  (func (export "i8x16.replace_lane") (param v128 i32) (result v128)
+    (i8x16.replace_lane 4 (local.get 0) (local.get 1)))
+  (func (export "i16x8.replace_lane") (param v128 i32) (result v128)
+    (i16x8.replace_lane 7 (local.get 0) (local.get 1)))
+  (func (export "i32x4.replace_lane") (param v128 i32) (result v128)
+    (i32x4.replace_lane 0 (local.get 0) (local.get 1)))
+
+  (func (export "i8x16.add") (param v128 v128) (result v128)
+    (i8x16.add (local.get 0) (local.get 1)))
+  (func (export "i8x16.sub") (param v128 v128) (result v128)
+    (i8x16.sub (local.get 0) (local.get 1)))
+  (func (export "i8x16.neg") (param v128) (result v128)
+    (i8x16.neg (local.get 0)))
+  (func (export "i16x8.add") (param v128 v128) (result v128)
+    (i16x8.add (local.get 0) (local.get 1)))
+  (func (export "i16x8.mul") (param v128 v128) (result v128)
+    (i16x8.mul (local.get 0) (local.get 1)))
+  (func (export "i32x4.sub") (param v128 v128) (result v128)
+    (i32x4.sub (local.get 0) (local.get 1)))
+  (func (export "i32x4.mul") (param v128 v128) (result v128)
+    (i32x4.mul (local.get 0) (local.get 1)))
+
+  (func (export "v128.not") (param v128) (result v128)
+    (v128.not (local.get 0)))
+  (func (export "v128.andnot") (param v128 v128) (result v128)
+    (v128.andnot (local.get 0) (local.get 1)))
+  (func (export "v128.bitselect") (param v128 v128 v128) (result v128)
+    (v128.bitselect (local.get 0) (local.get 1) (local.get 2)))
+
+  (func (export "i8x16.swizzle") (param v128 v128) (result v128)
+    (i8x16.swizzle (local.get 0) (local.get 1)))
+  (func (export "i8x16.shuffle") (param v128 v128) (result v128)
+    (i8x16.shuffle 0 16 1 17 2 18 3 19 31 30 29 28 15 14 13 12
+      (local.get 0) (local.get 1)))
+)
+
+(invoke "store" (i32.const 0) (v128.const i8x16 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16))
+(assert_return (invoke "load" (i32.const 16))
+  (v128.const i8x16 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16))
+(assert_return (invoke "load" (i32.const 17))
+  (v128.const i8x16 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 0))
+(assert_return (invoke "store_load" (v128.const i32x4 0x01234567 0x89abcdef 0xdeadbeef 0x7fffffff))
+  (v128.const i32x4 0x01234567 0x89abcdef 0xdeadbeef 0x7fffffff))
+(assert_trap (invoke "load" (i32.const 65521)) "out of bounds memory access")
+(assert_trap (invoke "store" (i32.const 65505) (v128.const i32x4 0 0 0 0)) "out of bounds memory access")
+
+(assert_return (invoke "i8x16.splat" (i32.const 0x1ff)) (v128.const i8x16 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1 -1))
+(assert_return (invoke "i16x8.splat" (i32.const 0x18000)) (v128.const i16x8 -32768 -32768 -32768 -32768 -32768 -32768 -32768 -32768))
+(assert_return (invoke "i32x4.splat" (i32.const 7)) (v128.const i32x4 7 7 7 7))
+(assert_return (invoke "f32x4.splat" (f32.const -1.5)) (v128.const f32x4 -1.5 -1.5 -1.5 -1.5))
+
+(assert_return (invoke "i8x16.extract_lane_s" (v128.const i8x16 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0x80)) (i32.const -128))
+(assert_return (invoke "i8x16.extract_lane_u" (v128.const i8x16 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0x80)) (i32.const 128))
+(assert_return (invoke "i16x8.extract_lane_s" (v128.const i16x8 0 -2 0 0 0 0 0 0)) (i32.const -2))
+(assert_return (invoke "i32x4.extract_lane" (v128.const i32x4 1 2 3 4)) (i32.const 3))
+(assert_return (invoke "i8x16.replace_lane" (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15) (i32.const 0x1ff))
+  (v128.const i8x16 0 1 2 3 -1 5 6 7 8 9 10 11 12 13 14 15))
+(assert_return (invoke "i16x8.replace_lane" (v128.const i16x8 0 1 2 3 4 5 6 7) (i32.const 0x12345))
+  (v128.const i16x8 0 1 2 3 4 5 6 0x2345))
+(assert_return (invoke "i32x4.replace_lane" (v128.const i32x4 1 2 3 4) (i32.const -1))
+  (v128.const i32x4 -1 2 3 4))
+
+;; Lanes wrap independently; no carry crosses a lane boundary.
+(assert_return (invoke "i8x16.add"
+    (v128.const i8x16 0xff 0x7f 1 2 3 4 5 6 7 8 9 10 11 12 13 14)
+    (v128.const i8x16 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1 1))
+  (v128.const i8x16 0 0x80 2 3 4 5 6 7 8 9 10 11 12 13 14 15))
+(assert_return (invoke "i8x16.sub"
+    (v128.const i8x16 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0x80)
+    (v128.const i8x16 1 0 0 0 0 0 0 0 0 0 0 0 0 0 0 1))
+  (v128.const i8x16 0xff 0 0 0 0 0 0 0 0 0 0 0 0 0 0 0x7f))
+(assert_return (invoke "i8x16.neg" (v128.const i8x16 1 -1 0 0x80 2 -2 0 0 0 0 0 0 0 0 0 127))
+  (v128.const i8x16 -1 1 0 0x80 -2 2 0 0 0 0 0 0 0 0 0 -127))
+(assert_return (invoke "i16x8.add"
+    (v128.const i16x8 0xffff 0x7fff 0 0 0 0 0 1)
+    (v128.const i16x8 1 1 0 0 0 0 0 1))
+  (v128.const i16x8 0 0x8000 0 0 0 0 0 2))
+(assert_return (invoke "i16x8.mul"
+    (v128.const i16x8 0x100 -3 7 0 0 0 0 0x4000)
+    (v128.const i16x8 0x100 5 -7 0 0 0 0 4))
+  (v128.const i16x8 0 -15 -49 0 0 0 0 0))
+(assert_return (invoke "i32x4.sub"
+    (v128.const i32x4 0 0x80000000 10 0)
+    (v128.const i32x4 1 1 3 0))
+  (v128.const i32x4 -1 0x7fffffff 7 0))
+(assert_return (invoke "i32x4.mul"
+    (v128.const i32x4 0x10000 -3 0x7fffffff 0)
+    (v128.const i32x4 0x10000 3 2 0))
+  (v128.const i32x4 0 -9 -2 0))
+
+(assert_return (invoke "v128.not" (v128.const i32x4 0 -1 0x0f0f0f0f 0x12345678))
+  (v128.const i32x4 -1 0 0xf0f0f0f0 0xedcba987))
+(assert_return (invoke "v128.andnot"
+    (v128.const i32x4 -1 -1 0xff00ff00 0)
+    (v128.const i32x4 0 -1 0x0ff00ff0 -1))
+  (v128.const i32x4 -1 0 0xf000f000 0))
+(assert_return (invoke "v128.bitselect"
+    (v128.const i32x4 0xaaaaaaaa 0xaaaaaaaa 0xaaaaaaaa 0xaaaaaaaa)
+    (v128.const i32x4 0x55555555 0x55555555 0x55555555 0x55555555)
+    (v128.const i32x4 -1 0 0xffff0000 0x0000ffff))
+  (v128.const i32x4 0xaaaaaaaa 0x55555555 0xaaaa5555 0x5555aaaa))
+
+;; Out-of-range swizzle indices select zero.
+(assert_return (invoke "i8x16.swizzle"
+    (v128.const i8x16 100 101 102 103 104 105 106 107 108 109 110 111 112 113 114 115)
+    (v128.const i8x16 15 0 16 255 1 14 2 13 3 12 4 11 5 10 6 128))
+  (v128.const i8x16 115 100 0 0 101 114 102 113 103 112 104 111 105 110 106 0))
+(assert_return (invoke "i8x16.shuffle"
+    (v128.const i8x16 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15)
+    (v128.const i8x16 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31))
+  (v128.const i8x16 0 16 1 17 2 18 3 19 31 30 29 28 15 14 13 12))
@@ -0,0 +1,18 @@
+;; The `stack_remaining` intrinsic reports how much of the Glulx stack is
+;; still free, which shrinks as calls nest.
+
+(module
+  (import "glulx" "stack_remaining" (func $remaining (result i32)))
+
+  (func $at_depth (param $n i32) (result i32)
+    (if (result i32) (i32.eqz (local.get $n))
+      (then (call $remaining))
+      (else (call $at_depth (i32.sub (local.get $n) (i32.const 1))))))
+
+  (func (export "positive") (result i32)
+    (i32.gt_u (call $remaining) (i32.const 0)))
+  (func (export "shrinks") (result i32)
+    (i32.lt_u (call $at_depth (i32.const 10)) (call $at_depth (i32.const 0)))))
+
+(assert_return (invoke "positive") (i32.const 1))
+(assert_return (invoke "shrinks") (i32.const 1))
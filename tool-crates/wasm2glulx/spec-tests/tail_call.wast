@@ -0,0 +1,60 @@
+;; Tail calls reuse the caller's frame, so mutual recursion far deeper than
+;; the stack could hold runs in constant space.
+
+(module
+  (type $i64_i64 (func (param i64) (result i64)))
+  (table funcref (elem $even_indirect $odd_indirect $pair))
+
+  (func $even (export "even") (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (i32.const 1))
+      (else (return_call $odd (i32.sub (local.get 0) (i32.const 1))))))
+  (func $odd (export "odd") (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (i32.const 0))
+      (else (return_call $even (i32.sub (local.get 0) (i32.const 1))))))
+
+  (func $even_indirect (export "even_indirect") (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (i32.const 1))
+      (else
+        (return_call_indirect (param i32) (result i32)
+          (i32.sub (local.get 0) (i32.const 1)) (i32.const 1)))))
+  (func $odd_indirect (param i32) (result i32)
+    (if (result i32) (i32.eqz (local.get 0))
+      (then (i32.const 0))
+      (else
+        (return_call_indirect (param i32) (result i32)
+          (i32.sub (local.get 0) (i32.const 1)) (i32.const 0)))))
+
+  ;; Results wider than one word come back through the hi-return area.
+  (func $sum (export "sum") (param $n i64) (param $acc i64) (result i64)
+    (if (result i64) (i64.eqz (local.get $n))
+      (then (local.get $acc))
+      (else
+        (return_call $sum
+          (i64.sub (local.get $n) (i64.const 1))
+          (i64.add (local.get $acc) (local.get $n))))))
+
+  (func $pair (param i32 i64) (result i64 i32)
+    (local.get 1) (local.get 0))
+  (func (export "pair_indirect") (param i32 i64) (result i64 i32)
+    (return_call_indirect (param i32 i64) (result i64 i32)
+      (local.get 0) (local.get 1) (i32.const 2)))
+
+  (func (export "call_indirect_at") (param i32) (result i32)
+    (return_call_indirect (param i32) (result i32) (i32.const 0) (local.get 0)))
+  (func (export "mismatch") (result i64)
+    (return_call_indirect (type $i64_i64) (i64.const 0) (i32.const 0))))
+
+(assert_return (invoke "even" (i32.const 0)) (i32.const 1))
+(assert_return (invoke "odd" (i32.const 7)) (i32.const 1))
+(assert_return (invoke "even" (i32.const 1000000)) (i32.const 1))
+(assert_return (invoke "odd" (i32.const 1000000)) (i32.const 0))
+(assert_return (invoke "even_indirect" (i32.const 1000001)) (i32.const 0))
+(assert_return (invoke "sum" (i64.const 1000000) (i64.const 0)) (i64.const 500000500000))
+(assert_return (invoke "pair_indirect" (i32.const 7) (i64.const 0x100000002))
+  (i64.const 0x100000002) (i32.const 7))
+(assert_return (invoke "call_indirect_at" (i32.const 0)) (i32.const 1))
+(assert_trap (invoke "call_indirect_at" (i32.const 3)) "undefined element")
+(assert_trap (invoke "mismatch") "indirect call type mismatch")
@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Definition of [`Artifacts`] and generation of symbol and export maps.
+
+use std::{collections::HashMap, fmt::Write};
+
+use bytes::BytesMut;
+use walrus::ExportItem;
+
+use crate::common::{Context, Emit, Label};
+
+/// The outputs of a compilation, in the order they were requested.
+#[derive(Debug, Clone, Default)]
+pub struct Artifacts(Vec<(Emit, BytesMut)>);
+
+impl Artifacts {
+    pub(crate) fn push(&mut self, emit: Emit, bytes: BytesMut) {
+        self.0.push((emit, bytes));
+    }
+
+    /// Returns the output of the given kind, if it was requested.
+    pub fn get(&self, emit: Emit) -> Option<&BytesMut> {
+        self.0
+            .iter()
+            .find_map(|(e, bytes)| (*e == emit).then_some(bytes))
+    }
+
+    /// Returns the number of outputs.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns true if no outputs were requested.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterates over the outputs in the order they were requested.
+    pub fn iter(&self) -> impl Iterator<Item = (Emit, &BytesMut)> {
+        self.0.iter().map(|(e, bytes)| (*e, bytes))
+    }
+
+    /// Consumes `self`, returning the outputs in the order they were
+    /// requested.
+    pub fn into_vec(self) -> Vec<(Emit, BytesMut)> {
+        self.0
+    }
+}
+
+/// Renders a map from function addresses to function names, given the address
+/// of every label in the assembly, sorted by address.
+pub fn render_map(ctx: &Context, labels: &[(Label, u32)]) -> BytesMut {
+    let mut names: HashMap<Label, String> = HashMap::new();
+    names.insert(ctx.layout.entrypoint(), "<entrypoint>".to_owned());
+    for function in ctx.module.functions() {
+        names.insert(
+            ctx.layout.func(function.id()).addr,
+            function
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("<function {}>", function.id().index())),
+        );
+    }
+
+    let mut map = BytesMut::new();
+    for (label, addr) in labels {
+        if let Some(name) = names.get(label) {
+            writeln!(map, "{addr:08x} {name}").expect("writing to a BytesMut should not fail");
+        }
+    }
+    map
+}
+
+/// Renders a map from the addresses of exported functions to their export
+/// names, given the address of every label in the assembly, sorted by address.
+///
+/// A function exported under several names gets one line for each.
+pub fn render_exports(ctx: &Context, labels: &[(Label, u32)]) -> BytesMut {
+    let mut names: HashMap<Label, Vec<&str>> = HashMap::new();
+    for export in ctx.module.exports.iter() {
+        if let ExportItem::Function(id) = export.item {
+            names
+                .entry(ctx.layout.func(id).addr)
+                .or_default()
+                .push(&export.name);
+        }
+    }
+
+    let mut map = BytesMut::new();
+    for (label, addr) in labels {
+        for name in names.get(label).into_iter().flatten() {
+            writeln!(map, "{addr:08x} {name}").expect("writing to a BytesMut should not fail");
+        }
+    }
+    map
+}
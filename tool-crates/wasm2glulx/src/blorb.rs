@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Packaging of story files into Blorb archives.
+//!
+//! A Blorb file is an IFF `FORM` of type `IFRS`. Its first chunk is a resource
+//! index (`RIdx`) giving the file offset of every executable, picture and sound
+//! chunk, and the remaining chunks follow in the same order. The story file is
+//! always executable resource 0. Everything else comes from a TOML manifest:
+//!
+//! ```toml
+//! cover = "cover.png"        # Picture 1, and the frontispiece
+//! ifiction = "game.iFiction" # Treaty of Babel metadata
+//!
+//! [[picture]]
+//! number = 2
+//! file = "map.jpg"
+//!
+//! [[sound]]
+//! number = 3
+//! file = "theme.ogg"
+//! ```
+//!
+//! Relative paths are resolved against the manifest's directory. The chunk
+//! type of each picture and sound is determined from the file's contents.
+//! Reading a manifest requires the `blorb-manifest` feature.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context as _};
+use bytes::{BufMut, BytesMut};
+#[cfg(feature = "blorb-manifest")]
+use serde::Deserialize;
+
+use crate::CompilationError;
+
+/// The picture number given to the cover art.
+const COVER_PICTURE: u32 = 1;
+
+#[derive(Debug, Default)]
+#[cfg_attr(
+    feature = "blorb-manifest",
+    derive(Deserialize),
+    serde(deny_unknown_fields)
+)]
+struct Manifest {
+    cover: Option<PathBuf>,
+    ifiction: Option<PathBuf>,
+    #[cfg_attr(feature = "blorb-manifest", serde(default))]
+    picture: Vec<Resource>,
+    #[cfg_attr(feature = "blorb-manifest", serde(default))]
+    sound: Vec<Resource>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(
+    feature = "blorb-manifest",
+    derive(Deserialize),
+    serde(deny_unknown_fields)
+)]
+struct Resource {
+    number: u32,
+    file: PathBuf,
+}
+
+/// A chunk to be written to the archive, with its resource index usage if it
+/// is indexed.
+struct Chunk {
+    usage: Option<(&'static [u8; 4], u32)>,
+    ty: [u8; 4],
+    data: Vec<u8>,
+}
+
+impl Chunk {
+    /// Returns the length of the chunk as written, including its header and
+    /// any padding.
+    fn size(&self) -> usize {
+        if self.is_form() {
+            self.data.len() + self.data.len() % 2
+        } else {
+            8 + self.data.len() + self.data.len() % 2
+        }
+    }
+
+    /// AIFF files are already IFF `FORM` chunks, and are stored as-is rather
+    /// than wrapped in a second header.
+    fn is_form(&self) -> bool {
+        &self.ty == b"FORM"
+    }
+}
+
+fn picture_type(path: &Path, data: &[u8]) -> anyhow::Result<[u8; 4]> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok(*b"PNG ")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Ok(*b"JPEG")
+    } else {
+        bail!("{} is neither a PNG nor a JPEG image", path.display())
+    }
+}
+
+fn sound_type(path: &Path, data: &[u8]) -> anyhow::Result<[u8; 4]> {
+    if data.starts_with(b"OggS") {
+        Ok(*b"OGGV")
+    } else if data.starts_with(b"FORM") && data.get(8..12) == Some(b"AIFF") {
+        Ok(*b"FORM")
+    } else if data.get(1080..1084).is_some_and(|sig| sig == b"M.K.") {
+        Ok(*b"MOD ")
+    } else {
+        bail!(
+            "{} is not an Ogg Vorbis, AIFF, or MOD sound",
+            path.display()
+        )
+    }
+}
+
+fn read(dir: &Path, file: &Path) -> anyhow::Result<(PathBuf, Vec<u8>)> {
+    let path = dir.join(file);
+    let data = std::fs::read(&path).with_context(|| format!("Reading {}", path.display()))?;
+    Ok((path, data))
+}
+
+#[cfg(feature = "blorb-manifest")]
+fn load_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    let text =
+        std::fs::read_to_string(path).with_context(|| format!("Reading {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Parsing {}", path.display()))
+}
+
+#[cfg(not(feature = "blorb-manifest"))]
+fn load_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    bail!(
+        "Can't read {}: wasm2glulx was built without the blorb-manifest feature",
+        path.display()
+    )
+}
+
+fn chunks(story: &[u8], manifest_path: Option<&Path>) -> anyhow::Result<Vec<Chunk>> {
+    let mut chunks = vec![Chunk {
+        usage: Some((b"Exec", 0)),
+        ty: *b"GLUL",
+        data: story.to_owned(),
+    }];
+
+    let Some(manifest_path) = manifest_path else {
+        return Ok(chunks);
+    };
+    let manifest = load_manifest(manifest_path)?;
+    let dir = manifest_path.parent().unwrap_or(Path::new(""));
+
+    let mut pictures = Vec::new();
+    if let Some(cover) = &manifest.cover {
+        pictures.push((COVER_PICTURE, cover));
+    }
+    pictures.extend(manifest.picture.iter().map(|r| (r.number, &r.file)));
+    pictures.sort_by_key(|(number, _)| *number);
+    for pair in pictures.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            bail!(
+                "Picture {} is given more than once{}",
+                pair[0].0,
+                if pair[0].0 == COVER_PICTURE && manifest.cover.is_some() {
+                    " (the cover is always picture 1)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+    for (number, file) in pictures {
+        let (path, data) = read(dir, file)?;
+        chunks.push(Chunk {
+            usage: Some((b"Pict", number)),
+            ty: picture_type(&path, &data)?,
+            data,
+        });
+    }
+
+    let mut sounds: Vec<_> = manifest.sound.iter().map(|r| (r.number, &r.file)).collect();
+    sounds.sort_by_key(|(number, _)| *number);
+    for pair in sounds.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            bail!("Sound {} is given more than once", pair[0].0);
+        }
+    }
+    for (number, file) in sounds {
+        let (path, data) = read(dir, file)?;
+        chunks.push(Chunk {
+            usage: Some((b"Snd ", number)),
+            ty: sound_type(&path, &data)?,
+            data,
+        });
+    }
+
+    if manifest.cover.is_some() {
+        chunks.push(Chunk {
+            usage: None,
+            ty: *b"Fspc",
+            data: COVER_PICTURE.to_be_bytes().to_vec(),
+        });
+    }
+
+    if let Some(ifiction) = &manifest.ifiction {
+        let (_, data) = read(dir, ifiction)?;
+        chunks.push(Chunk {
+            usage: None,
+            ty: *b"IFmd",
+            data,
+        });
+    }
+
+    Ok(chunks)
+}
+
+fn assemble(chunks: &[Chunk]) -> anyhow::Result<BytesMut> {
+    let overflow = || anyhow!("Blorb file exceeds 4GiB");
+
+    let nindexed = chunks.iter().filter(|c| c.usage.is_some()).count();
+    let index_len = 4 + 12 * nindexed;
+
+    // FORM header, IFRS type, and RIdx chunk header precede the index.
+    let mut offset = 12 + 8 + index_len;
+    let mut index = Vec::with_capacity(nindexed);
+    for chunk in chunks {
+        if let Some((usage, number)) = chunk.usage {
+            index.push((
+                usage,
+                number,
+                u32::try_from(offset).map_err(|_| overflow())?,
+            ));
+        }
+        offset = offset.checked_add(chunk.size()).ok_or_else(overflow)?;
+    }
+    let form_len = u32::try_from(offset - 8).map_err(|_| overflow())?;
+
+    let mut out = BytesMut::with_capacity(offset);
+    out.put_slice(b"FORM");
+    out.put_u32(form_len);
+    out.put_slice(b"IFRS");
+
+    out.put_slice(b"RIdx");
+    out.put_u32(index_len as u32);
+    out.put_u32(nindexed as u32);
+    for (usage, number, start) in index {
+        out.put_slice(usage);
+        out.put_u32(number);
+        out.put_u32(start);
+    }
+
+    for chunk in chunks {
+        if !chunk.is_form() {
+            out.put_slice(&chunk.ty);
+            out.put_u32(chunk.data.len() as u32);
+        }
+        out.put_slice(&chunk.data);
+        if chunk.data.len() % 2 != 0 {
+            out.put_u8(0);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Packages `story` into a Blorb file along with the resources listed in the
+/// manifest at `manifest_path`, or on its own if there is no manifest.
+pub fn package(
+    story: &[u8],
+    manifest_path: Option<&Path>,
+) -> Result<BytesMut, Vec<CompilationError>> {
+    chunks(story, manifest_path)
+        .and_then(|chunks| assemble(&chunks))
+        .map_err(|e| vec![CompilationError::BlorbError(e)])
+}
@@ -6,6 +6,7 @@ mod classify;
 mod control;
 mod loadstore;
 mod memory;
+mod simd;
 mod table;
 mod toplevel;
 
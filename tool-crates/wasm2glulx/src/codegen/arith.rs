@@ -1,11 +1,10 @@
 // SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
 // Copyright 2024 Daniel Fox Franke.
 
-use super::classify::{ClassifiedInstr, Other};
 use super::loadstore::{copy_if_sensible, gen_copies, Credits, Debts};
 use super::toplevel::Frame;
 use crate::common::*;
-use glulx_asm::{concise::*, LoadOperand, StoreOperand};
+use glulx_asm::{concise::*, Item, LoadOperand, StoreOperand};
 use walrus::ir;
 
 pub fn gen_unop(
@@ -21,7 +20,7 @@ pub fn gen_unop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items.push(callfi(imml(ctx.rt.i32_eqz), x, out));
+            gen_i32_eqz(ctx, x, out);
             debts.gen(ctx);
         }
         ir::UnaryOp::I32Clz => {
@@ -504,18 +503,141 @@ pub fn gen_unop(
             debts.gen(ctx);
         }
         _ => {
+            super::simd::gen_unop(ctx, frame, unop, credits, debts);
+        }
+    }
+}
+
+/// Returns the overflow-checking runtime helper for `op`, if it has one.
+fn checked_binop_helper(ctx: &Context, op: ir::BinaryOp) -> Option<Label> {
+    match op {
+        ir::BinaryOp::I32Add => Some(ctx.rt.i32_add_checked),
+        ir::BinaryOp::I32Sub => Some(ctx.rt.i32_sub_checked),
+        ir::BinaryOp::I32Mul => Some(ctx.rt.i32_mul_checked),
+        ir::BinaryOp::I64Add => Some(ctx.rt.i64_add_checked),
+        ir::BinaryOp::I64Sub => Some(ctx.rt.i64_sub_checked),
+        ir::BinaryOp::I64Mul => Some(ctx.rt.i64_mul_checked),
+        _ => None,
+    }
+}
+
+/// Generates a call to `checked`, which performs `op` but traps on signed
+/// overflow, reporting the function whose name is at `name`.
+fn gen_checked_binop(
+    ctx: &mut Context,
+    op: ir::BinaryOp,
+    checked: Label,
+    name: Label,
+    mut credits: Credits,
+    mut debts: Debts,
+) {
+    match op {
+        ir::BinaryOp::I32Add | ir::BinaryOp::I32Sub | ir::BinaryOp::I32Mul => {
+            let y = credits.pop();
+            let x = credits.pop();
+            let out = debts.pop();
+
+            credits.gen(ctx);
+            ctx.rom_items
+                .push(callfiii(imml(checked), y, x, imml(name), out));
+            debts.gen(ctx);
+        }
+        _ => {
+            let (out_lo, out_hi) = debts.pop_lo_hi();
             credits.gen(ctx);
-            let mnemonic = Other::Unop(unop.clone()).mnemonic();
-            ctx.errors
-                .push(crate::CompilationError::UnsupportedInstruction {
-                    function: frame.function_name.map(|s| s.to_owned()),
-                    instr: mnemonic,
-                });
+            ctx.rom_items.push(copy(imml(name), push()));
+            ctx.rom_items.push(call(imml(checked), imm(5), out_lo));
+            copy_if_sensible(ctx, derefl(ctx.layout.hi_return().addr), out_hi);
             debts.gen(ctx);
         }
     }
 }
 
+/// A Glulx conditional branch, taking two operands and a branch target.
+type Branch2 = fn(LoadOperand<Label>, LoadOperand<Label>, Label) -> Item<Label>;
+
+/// A Glulx shift instruction.
+type Shift = fn(LoadOperand<Label>, LoadOperand<Label>, StoreOperand<Label>) -> Item<Label>;
+
+/// Stores 1 to `out` if the branch which `test` constructs is taken, and 0
+/// otherwise.
+fn gen_inline_test(
+    ctx: &mut Context,
+    test: impl FnOnce(Label) -> Item<Label>,
+    out: StoreOperand<Label>,
+) {
+    let is_true = ctx.gen.gen("test_true");
+    let done = ctx.gen.gen("test_done");
+    push_all!(
+        ctx.rom_items,
+        test(is_true),
+        copy(imm(0), out),
+        jump(done),
+        label(is_true),
+        copy(imm(1), out),
+        label(done)
+    );
+}
+
+/// Stores 1 to `out` if `x` is zero, and 0 otherwise. Under
+/// [`OptimizeFor::Speed`] the test is inlined; otherwise it calls the runtime.
+pub fn gen_i32_eqz(ctx: &mut Context, x: LoadOperand<Label>, out: StoreOperand<Label>) {
+    if ctx.options.optimize_for == OptimizeFor::Speed {
+        gen_inline_test(ctx, |is_true| jz(x, is_true), out);
+    } else {
+        ctx.rom_items.push(callfi(imml(ctx.rt.i32_eqz), x, out));
+    }
+}
+
+/// Generates an `i32` comparison of `x` against `y`, either as a call to
+/// `routine` or, under [`OptimizeFor::Speed`], inline using `test`. Since `y`
+/// is on top of the stack, `test` is given its operands as `(y, x)` and so
+/// must be the mirror image of the comparison: `jgt` for `lt_s`, and so on.
+fn gen_i32_compare(
+    ctx: &mut Context,
+    routine: Label,
+    test: Branch2,
+    y: LoadOperand<Label>,
+    x: LoadOperand<Label>,
+    out: StoreOperand<Label>,
+) {
+    if ctx.options.optimize_for == OptimizeFor::Speed {
+        gen_inline_test(ctx, |is_true| test(y, x, is_true), out);
+    } else {
+        ctx.rom_items.push(callfii(imml(routine), y, x, out));
+    }
+}
+
+/// Generates an `i32` shift of `x` by `y`, either as a call to `routine` or,
+/// under [`OptimizeFor::Speed`], inline using `shift` after masking the count
+/// to five bits as WASM requires.
+fn gen_i32_shift(
+    ctx: &mut Context,
+    routine: Label,
+    shift: Shift,
+    y: LoadOperand<Label>,
+    x: LoadOperand<Label>,
+    out: StoreOperand<Label>,
+) {
+    if ctx.options.optimize_for != OptimizeFor::Speed {
+        ctx.rom_items.push(callfii(imml(routine), y, x, out));
+    } else if x == LoadOperand::Pop {
+        // The masked count lands on top of `x`, so swap them back.
+        push_all!(
+            ctx.rom_items,
+            bitand(y, imm(0x1f), push()),
+            stkswap(),
+            shift(pop(), pop(), out)
+        );
+    } else {
+        push_all!(
+            ctx.rom_items,
+            bitand(y, imm(0x1f), push()),
+            shift(x, pop(), out)
+        );
+    }
+}
+
 pub fn gen_binop(
     ctx: &mut Context,
     frame: &Frame,
@@ -523,6 +645,13 @@ pub fn gen_binop(
     mut credits: Credits,
     mut debts: Debts,
 ) {
+    if let Some(name) = frame.overflow_name {
+        if let Some(checked) = checked_binop_helper(ctx, binop.op) {
+            gen_checked_binop(ctx, binop.op, checked, name, credits, debts);
+            return;
+        }
+    }
+
     match binop.op {
         ir::BinaryOp::I32Eq => {
             let y = credits.pop();
@@ -530,7 +659,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items.push(callfii(imml(ctx.rt.i32_eq), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_eq, jeq, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32Ne => {
@@ -539,7 +668,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items.push(callfii(imml(ctx.rt.i32_ne), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_ne, jne, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32LtS => {
@@ -548,8 +677,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_lt_s), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_lt_s, jgt, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32LtU => {
@@ -558,8 +686,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_lt_u), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_lt_u, jgtu, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32GtS => {
@@ -568,8 +695,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_gt_s), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_gt_s, jlt, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32GtU => {
@@ -578,8 +704,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_gt_u), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_gt_u, jltu, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32LeS => {
@@ -588,8 +713,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_le_s), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_le_s, jge, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32LeU => {
@@ -598,8 +722,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_le_u), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_le_u, jgeu, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32GeS => {
@@ -608,8 +731,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_ge_s), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_ge_s, jle, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32GeU => {
@@ -618,8 +740,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_ge_u), y, x, out));
+            gen_i32_compare(ctx, ctx.rt.i32_ge_u, jleu, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32Add => {
@@ -717,7 +838,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items.push(callfii(imml(ctx.rt.i32_shl), y, x, out));
+            gen_i32_shift(ctx, ctx.rt.i32_shl, shiftl, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32ShrS => {
@@ -726,8 +847,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_shr_s), y, x, out));
+            gen_i32_shift(ctx, ctx.rt.i32_shr_s, sshiftr, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32ShrU => {
@@ -736,8 +856,7 @@ pub fn gen_binop(
             let out = debts.pop();
 
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.i32_shr_u), y, x, out));
+            gen_i32_shift(ctx, ctx.rt.i32_shr_u, ushiftr, y, x, out);
             debts.gen(ctx);
         }
         ir::BinaryOp::I32Rotl => {
@@ -1161,14 +1280,7 @@ pub fn gen_binop(
             debts.gen(ctx);
         }
         _ => {
-            credits.gen(ctx);
-            let mnemonic = Other::Binop(binop.clone()).mnemonic();
-            ctx.errors
-                .push(crate::CompilationError::UnsupportedInstruction {
-                    function: frame.function_name.map(|s| s.to_owned()),
-                    instr: mnemonic,
-                });
-            debts.gen(ctx);
+            super::simd::gen_binop(ctx, frame, binop, credits, debts);
         }
     }
 }
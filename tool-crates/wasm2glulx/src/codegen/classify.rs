@@ -1,7 +1,7 @@
 // SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
 // Copyright 2024 Daniel Fox Franke.
 
-use walrus::ir::{self, ExtendedLoad};
+use walrus::ir::{self, ExtendedLoad, InstrLocId};
 use walrus::{LocalFunction, Module, RefType, ValType};
 
 pub trait ClassifiedInstr {
@@ -1647,10 +1647,13 @@ impl Test {
     }
 }
 
-pub fn classify(seq: &ir::InstrSeq) -> Vec<InstrClass> {
+/// Classifies the instructions of `seq`, pairing each class with the location
+/// of the first instruction it was made from.
+pub fn classify(seq: &ir::InstrSeq) -> Vec<(InstrClass, InstrLocId)> {
     let mut out = Vec::with_capacity(seq.len());
+    let mut locs = Vec::with_capacity(seq.len());
     let mut seqiter = seq.iter().peekable();
-    while let Some((instr, _)) = seqiter.next() {
+    while let Some((instr, loc)) = seqiter.next() {
         match instr {
             ir::Instr::Block(block) => out.push(InstrClass::Block(Block::Block(block.clone()))),
             ir::Instr::Loop(l) => out.push(InstrClass::Loop(Loop::Loop(l.clone()))),
@@ -1859,12 +1862,15 @@ pub fn classify(seq: &ir::InstrSeq) -> Vec<InstrClass> {
                 )));
             }
         }
+        locs.resize(out.len(), *loc);
     }
 
-    out
+    out.into_iter().zip(locs).collect()
 }
 
-pub fn subsequences(seq: &ir::InstrSeq) -> Vec<InstrSubseq> {
+/// Divides `seq` into subsequences, pairing each with the location of the
+/// first instruction it was made from.
+pub fn subsequences(seq: &ir::InstrSeq) -> Vec<(InstrSubseq, InstrLocId)> {
     #[derive(Debug)]
     enum State {
         Start,
@@ -1873,6 +1879,9 @@ pub fn subsequences(seq: &ir::InstrSeq) -> Vec<InstrSubseq> {
     }
 
     let mut subseqs = Vec::new();
+    let mut locs = Vec::new();
+    let mut start_loc = None;
+    let mut loc = InstrLocId::default();
     let mut loads = Vec::new();
     let mut block = None;
     let mut looop = None;
@@ -1882,8 +1891,11 @@ pub fn subsequences(seq: &ir::InstrSeq) -> Vec<InstrSubseq> {
     let mut ret = None;
     let mut state = State::Start;
 
+    // A subsequence starts at its first instruction, or at the current one if
+    // that is all it contains.
     macro_rules! subseq_done {
         () => {
+            let start = start_loc.take().unwrap_or(loc);
             if let Some(terminal) = terminal.take() {
                 subseqs.push(InstrSubseq::Terminal {
                     terminal,
@@ -1915,10 +1927,12 @@ pub fn subsequences(seq: &ir::InstrSeq) -> Vec<InstrSubseq> {
                     ret: ret.take(),
                 })
             }
+            locs.resize(subseqs.len(), start);
         };
     }
 
-    for class in classify(seq) {
+    for (class, class_loc) in classify(seq) {
+        loc = class_loc;
         match state {
             State::Start => match class {
                 InstrClass::Load(load) => {
@@ -2031,6 +2045,9 @@ pub fn subsequences(seq: &ir::InstrSeq) -> Vec<InstrSubseq> {
                 }
             },
         }
+        if !matches!(state, State::Start) && start_loc.is_none() {
+            start_loc = Some(loc);
+        }
     }
 
     match state {
@@ -2040,5 +2057,5 @@ pub fn subsequences(seq: &ir::InstrSeq) -> Vec<InstrSubseq> {
         }
     }
 
-    subseqs
+    subseqs.into_iter().zip(locs).collect()
 }
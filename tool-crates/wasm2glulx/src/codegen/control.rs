@@ -7,9 +7,9 @@ use super::{
     toplevel::{Frame, JumpTarget},
 };
 
-use crate::common::{Context, Label, WordCount};
+use crate::common::{Conformance, Context, Label, WordCount};
 use glulx_asm::{concise::*, LoadOperand};
-use walrus::{ir, ValType};
+use walrus::{ir, TableId, TypeId, ValType};
 
 pub fn gen_test(ctx: &mut Context, test: Test, label: Label, mut credits: Credits) {
     match test {
@@ -172,9 +172,18 @@ pub fn gen_test(ctx: &mut Context, test: Test, label: Label, mut credits: Credit
     }
 }
 
+/// Records the current function as the location for trap messages. This is
+/// needed on entry and after every call, since the callee overwrites it.
+pub fn gen_set_trap_location(ctx: &mut Context, frame: &Frame) {
+    if let Some(location) = frame.trap_location {
+        ctx.rom_items
+            .push(copy(imml(location), storel(ctx.layout.trap().location)));
+    }
+}
+
 pub fn gen_call(
     ctx: &mut Context,
-    _frame: &mut Frame,
+    frame: &mut Frame,
     call_instr: &ir::Call,
     mut credits: Credits,
     mut debts: Debts,
@@ -227,37 +236,24 @@ pub fn gen_call(
                 .push(call(addr, uimm(param_words), return_operand));
         }
     }
+    gen_set_trap_location(ctx, frame);
 
     let return_credits = Credits::from_returns(ctx, ty.results());
     gen_copies(ctx, return_credits, debts);
 }
 
-pub fn gen_call_indirect(
+/// Looks up entry `table_index` of `table` and checks that it is a function
+/// of type `ty`, trapping if not. Returns an operand for the function's
+/// address, which remains valid until the next call.
+fn gen_indirect_callee(
     ctx: &mut Context,
-    _frame: &mut Frame,
-    call_indirect: &ir::CallIndirect,
-    mut credits: Credits,
-    mut debts: Debts,
-) {
-    let ty = ctx.module.types.get(call_indirect.ty);
-    let typenum = ctx.layout.ty(call_indirect.ty).typenum;
-    let table_addr = ctx.layout.table(call_indirect.table).addr;
-    let table_count = ctx.layout.table(call_indirect.table).cur_count;
-    let param_words = ty.params().word_count();
-    let result_words: u32 = ty.results().word_count();
-
-    let return_operand = if result_words > 0 {
-        if result_words == 1 && debts.len() == 1 {
-            debts.pop()
-        } else {
-            push()
-        }
-    } else {
-        discard()
-    };
-
-    let table_index = credits.pop();
-    credits.gen(ctx);
+    ty: TypeId,
+    table: TableId,
+    table_index: LoadOperand<Label>,
+) -> LoadOperand<Label> {
+    let typenum = ctx.layout.ty(ty).typenum;
+    let table_addr = ctx.layout.table(table).addr;
+    let table_count = ctx.layout.table(table).cur_count;
 
     // Steal hi_return as a scratch register
     let fnptr = ctx.layout.hi_return().addr;
@@ -280,19 +276,92 @@ pub fn gen_call_indirect(
         .push(aload(imml(table_addr), table_index, storel(fnptr)));
     ctx.rom_items
         .push(jz(derefl(fnptr), ctx.rt.trap_uninitialized_element));
-    ctx.rom_items.push(aload(derefl(fnptr), imm(-1), push()));
-    ctx.rom_items.push(jne(
-        pop(),
-        uimm(typenum),
-        ctx.rt.trap_indirect_call_type_mismatch,
-    ));
+    if ctx.options.conformance != Conformance::Fast {
+        ctx.rom_items.push(aload(derefl(fnptr), imm(-1), push()));
+        ctx.rom_items.push(jne(
+            pop(),
+            uimm(typenum),
+            ctx.rt.trap_indirect_call_type_mismatch,
+        ));
+    }
+    derefl(fnptr)
+}
+
+pub fn gen_call_indirect(
+    ctx: &mut Context,
+    frame: &mut Frame,
+    call_indirect: &ir::CallIndirect,
+    mut credits: Credits,
+    mut debts: Debts,
+) {
+    let ty = ctx.module.types.get(call_indirect.ty);
+    let param_words = ty.params().word_count();
+    let result_words: u32 = ty.results().word_count();
+
+    let return_operand = if result_words > 0 {
+        if result_words == 1 && debts.len() == 1 {
+            debts.pop()
+        } else {
+            push()
+        }
+    } else {
+        discard()
+    };
+
+    let table_index = credits.pop();
+    credits.gen(ctx);
+
+    let callee = gen_indirect_callee(ctx, call_indirect.ty, call_indirect.table, table_index);
     ctx.rom_items
-        .push(call(derefl(fnptr), uimm(param_words), return_operand));
+        .push(call(callee, uimm(param_words), return_operand));
+    gen_set_trap_location(ctx, frame);
 
     let return_credits = Credits::from_returns(ctx, ty.results());
     gen_copies(ctx, return_credits, debts);
 }
 
+/// Generates a `return_call`. The callee's results must match the caller's, so
+/// Glulx's `tailcall` hands them, along with anything the callee leaves in
+/// the hi-return area, straight back to our caller.
+pub fn gen_return_call(
+    ctx: &mut Context,
+    _frame: &mut Frame,
+    return_call: &ir::ReturnCall,
+    mut credits: Credits,
+) {
+    let function = ctx.module.funcs.get(return_call.func);
+    let param_words: u32 = ctx.module.types.get(function.ty()).params().word_count();
+    let addr = imml(ctx.layout.func(return_call.func).addr);
+
+    credits.gen(ctx);
+    ctx.rom_items.push(tailcall(addr, uimm(param_words)));
+}
+
+pub fn gen_return_call_indirect(
+    ctx: &mut Context,
+    _frame: &mut Frame,
+    return_call_indirect: &ir::ReturnCallIndirect,
+    mut credits: Credits,
+) {
+    let param_words: u32 = ctx
+        .module
+        .types
+        .get(return_call_indirect.ty)
+        .params()
+        .word_count();
+
+    let table_index = credits.pop();
+    credits.gen(ctx);
+
+    let callee = gen_indirect_callee(
+        ctx,
+        return_call_indirect.ty,
+        return_call_indirect.table,
+        table_index,
+    );
+    ctx.rom_items.push(tailcall(callee, uimm(param_words)));
+}
+
 fn gen_br_inner(ctx: &mut Context, frame: &Frame, target: &JumpTarget, height: usize) {
     if target.base + target.arity != height {
         assert!(height > target.base + target.arity);
@@ -385,7 +454,7 @@ pub fn gen_br_table(
         len
     } else {
         ctx.errors.push(crate::CompilationError::Overflow(
-            crate::OverflowLocation::Table,
+            crate::OverflowLocation::BrTable(frame.function_name.map(|s| s.to_owned())),
         ));
         return;
     };
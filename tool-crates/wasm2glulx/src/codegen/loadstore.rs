@@ -147,6 +147,15 @@ impl Credits {
         self.loads.pop().unwrap_or(LoadOperand::Pop)
     }
 
+    /// Returns the operand that the `depth+1`th call to `pop` would return,
+    /// or `None` if it would come from the stack.
+    pub fn peek(&self, depth: usize) -> Option<&LoadOperand<Label>> {
+        self.loads
+            .len()
+            .checked_sub(depth + 1)
+            .map(|i| &self.loads[i])
+    }
+
     pub fn pop_hi_lo(&mut self) -> (LoadOperand<Label>, LoadOperand<Label>) {
         let hi = self.pop();
         let lo = self.pop();
@@ -563,5 +572,6 @@ pub fn gen_local_tee(
             ctx.rom_items.push(stkpeek(imm(3), sloc(localnum + 3)));
         }
     }
+    frame.checked_addrs.remove(&(4 * localnum));
     debts.gen(ctx);
 }
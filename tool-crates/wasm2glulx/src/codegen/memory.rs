@@ -4,16 +4,26 @@
 use glulx_asm::{concise::*, LoadOperand, StoreOperand};
 use walrus::{
     ir::{self, ExtendedLoad},
-    ValType,
+    MemoryId, ValType,
 };
 
-use crate::common::Context;
+use crate::common::{Conformance, Context, Label};
+use crate::rt::MemoryLabels;
 
 use super::{
     loadstore::{gen_copies, Credits, Debts},
     toplevel::Frame,
 };
 
+/// Returns the labels of the helpers which access memory `id`.
+fn memory_labels(ctx: &Context, id: MemoryId) -> MemoryLabels {
+    if ctx.layout.is_secondary_memory(id) {
+        ctx.rt.secondary_memory
+    } else {
+        ctx.rt.primary_memory()
+    }
+}
+
 pub fn gen_memory_init(
     ctx: &mut Context,
     _frame: &mut Frame,
@@ -23,72 +33,85 @@ pub fn gen_memory_init(
 ) {
     credits.gen(ctx);
     let data_layout = ctx.layout.data(init_instr.data);
+    let helper = memory_labels(ctx, init_instr.memory).memory_init;
     ctx.rom_items.push(copy(imml(data_layout.addr), push()));
     ctx.rom_items
         .push(copy(derefl(data_layout.cur_size), push()));
-    ctx.rom_items
-        .push(call(imml(ctx.rt.memory_init), imm(5), discard()));
+    ctx.rom_items.push(call(imml(helper), imm(5), discard()));
     debts.gen(ctx);
 }
 
 pub fn gen_memory_grow(
     ctx: &mut Context,
     _frame: &mut Frame,
-    _grow_instr: &ir::MemoryGrow,
+    grow_instr: &ir::MemoryGrow,
     mut credits: Credits,
     mut debts: Debts,
 ) {
     let arg = credits.pop();
     let out = debts.pop();
+    let helper = memory_labels(ctx, grow_instr.memory).memory_grow;
     credits.gen(ctx);
-    ctx.rom_items
-        .push(callfi(imml(ctx.rt.memory_grow), arg, out));
+    ctx.rom_items.push(callfi(imml(helper), arg, out));
     debts.gen(ctx);
 }
 
 pub fn gen_memory_copy(
     ctx: &mut Context,
-    _frame: &mut Frame,
-    _copy_instr: &ir::MemoryCopy,
+    frame: &mut Frame,
+    copy_instr: &ir::MemoryCopy,
     mut credits: Credits,
     mut debts: Debts,
 ) {
+    if copy_instr.src != copy_instr.dst {
+        credits.gen(ctx);
+        ctx.errors
+            .push(crate::CompilationError::UnsupportedInstruction {
+                function: frame.function_name.map(|s| s.to_owned()),
+                instr: "memory.copy between different memories",
+            });
+        debts.gen(ctx);
+        return;
+    }
+
     let n = credits.pop();
     let s = credits.pop();
     let d = credits.pop();
+    let helper = memory_labels(ctx, copy_instr.dst).memory_copy;
     credits.gen(ctx);
     ctx.rom_items
-        .push(callfiii(imml(ctx.rt.memory_copy), n, s, d, discard()));
+        .push(callfiii(imml(helper), n, s, d, discard()));
     debts.gen(ctx);
 }
 
 pub fn gen_memory_fill(
     ctx: &mut Context,
     _frame: &mut Frame,
-    _fill_instr: &ir::MemoryFill,
+    fill_instr: &ir::MemoryFill,
     mut credits: Credits,
     mut debts: Debts,
 ) {
     let n = credits.pop();
     let val = credits.pop();
     let d = credits.pop();
+    let helper = memory_labels(ctx, fill_instr.memory).memory_fill;
     credits.gen(ctx);
     ctx.rom_items
-        .push(callfiii(imml(ctx.rt.memory_fill), n, val, d, discard()));
+        .push(callfiii(imml(helper), n, val, d, discard()));
     debts.gen(ctx);
 }
 
 pub fn gen_memory_size(
     ctx: &mut Context,
     _frame: &mut Frame,
-    _size_instr: &ir::MemorySize,
+    size_instr: &ir::MemorySize,
     mut credits: Credits,
     mut debts: Debts,
 ) {
     let out = debts.pop();
+    let cur_size = ctx.layout.memory_by_id(size_instr.memory).cur_size;
     credits.gen(ctx);
-    ctx.rom_items
-        .push(ushiftr(derefl(ctx.layout.memory().cur_size), imm(16), out));
+    ctx.rom_items.push(ushiftr(derefl(cur_size), imm(16), out));
     debts.gen(ctx);
 }
 
@@ -106,6 +129,308 @@ pub fn gen_data_drop(
     gen_copies(ctx, credits, debts);
 }
 
+/// Returns the effective address of an access of `size` bytes at `addr +
+/// offset` if `addr` is a constant and the whole access lies within the
+/// memory's minimum size. Memory never shrinks, so such an access can never
+/// trap and doesn't need a bounds check.
+fn static_addr(ctx: &Context, addr: &LoadOperand<Label>, offset: u32, size: u32) -> Option<u32> {
+    let LoadOperand::Imm(addr) = addr else {
+        return None;
+    };
+    let ea = (*addr as u32).checked_add(offset)?;
+    if ea.checked_add(size)? <= ctx.layout.memory().min_size {
+        Some(ea)
+    } else {
+        None
+    }
+}
+
+/// Returns true if an access of `size` bytes at `addr + offset` can skip its
+/// bounds check: either because `addr` is a local which an earlier access in
+/// the current basic block already bounds-checked at least as far as `offset +
+/// size`, or because the conformance level is [`Conformance::Fast`] and `addr`
+/// is not on the stack. Memory never shrinks, so an earlier check still holds.
+/// The unchecked paths may read `addr` more than once, so it must never be
+/// `Pop`.
+fn is_checked(
+    ctx: &Context,
+    frame: &Frame,
+    addr: &LoadOperand<Label>,
+    offset: u32,
+    size: u32,
+) -> bool {
+    if matches!(addr, LoadOperand::Pop) {
+        return false;
+    }
+    if ctx.options.conformance == Conformance::Fast {
+        return offset.checked_add(size).is_some();
+    }
+    let LoadOperand::FrameAddr(local) = addr else {
+        return false;
+    };
+    match (frame.checked_addrs.get(local), offset.checked_add(size)) {
+        (Some(checked), Some(end)) => end <= *checked,
+        _ => false,
+    }
+}
+
+/// Records that an access of `size` bytes at `addr + offset` is about to be
+/// bounds-checked, so that later accesses through the same local in this basic
+/// block can skip the check.
+fn note_checked(
+    ctx: &Context,
+    frame: &mut Frame,
+    addr: &LoadOperand<Label>,
+    offset: u32,
+    size: u32,
+) {
+    if !ctx.options.elides_bounds_checks() {
+        return;
+    }
+    let LoadOperand::FrameAddr(local) = addr else {
+        return;
+    };
+    if let Some(end) = offset.checked_add(size) {
+        let checked = frame.checked_addrs.entry(*local).or_insert(0);
+        *checked = (*checked).max(end);
+    }
+}
+
+/// Returns the base and index operands with which an array instruction
+/// accessing `size`-byte elements reaches `addr + offset` in memory.
+///
+/// Array instructions read `base + size * index`. The index is a label operand
+/// for the memory's 4-aligned base address, shifted right to count elements.
+/// When `offset` is a multiple of `size` it folds into that operand, so the
+/// access takes a single instruction. Otherwise `addr + offset` is computed
+/// onto the stack first.
+fn gen_array_operands(
+    ctx: &mut Context,
+    addr: LoadOperand<Label>,
+    offset: u32,
+    size: u32,
+) -> (LoadOperand<Label>, LoadOperand<Label>) {
+    let mem = ctx.layout.memory().addr;
+    let shift = match size {
+        4 => 2,
+        2 => 1,
+        _ => 0,
+    };
+
+    if offset.is_multiple_of(size) {
+        (addr, imml_uoff_shift(mem, offset, shift))
+    } else {
+        ctx.rom_items.push(add(addr, uimm(offset), push()));
+        (pop(), imml_off_shift(mem, 0, shift))
+    }
+}
+
+/// Like `gen_memload`, but for an access already known to be in bounds.
+/// `addr` must not be `Pop`, since it may be read more than once.
+fn gen_unchecked_memload(
+    ctx: &mut Context,
+    size: u32,
+    offset: u32,
+    addr: LoadOperand<Label>,
+    out: StoreOperand<Label>,
+) {
+    match size {
+        8 => {
+            let (base, index) = gen_array_operands(ctx, addr, offset + 4, 4);
+            ctx.rom_items.push(aload(base, index, push()));
+            ctx.rom_items.push(callfi(
+                imml(ctx.rt.swap),
+                pop(),
+                storel(ctx.layout.hi_return().addr),
+            ));
+            let (base, index) = gen_array_operands(ctx, addr, offset, 4);
+            ctx.rom_items.push(aload(base, index, push()));
+            ctx.rom_items.push(callfi(imml(ctx.rt.swap), pop(), out));
+        }
+        4 => {
+            let (base, index) = gen_array_operands(ctx, addr, offset, 4);
+            ctx.rom_items.push(aload(base, index, push()));
+            ctx.rom_items.push(callfi(imml(ctx.rt.swap), pop(), out));
+        }
+        2 => {
+            let (base, index) = gen_array_operands(ctx, addr, offset, 2);
+            ctx.rom_items.push(aloads(base, index, push()));
+            ctx.rom_items.push(callfi(imml(ctx.rt.swaps), pop(), out));
+        }
+        _ => {
+            let (base, index) = gen_array_operands(ctx, addr, offset, 1);
+            ctx.rom_items.push(aloadb(base, index, out));
+        }
+    }
+}
+
+/// Like `gen_memstore`, but for an access already known to be in bounds.
+/// `val` is consumed before `addr + offset` is computed, so it may be `Pop`.
+fn gen_unchecked_memstore(
+    ctx: &mut Context,
+    size: u32,
+    offset: u32,
+    addr: LoadOperand<Label>,
+    val: LoadOperand<Label>,
+) {
+    match size {
+        4 => {
+            ctx.rom_items.push(callfi(imml(ctx.rt.swap), val, push()));
+            let (base, index) = gen_array_operands(ctx, addr, offset, 4);
+            ctx.rom_items.push(astore(base, index, pop()));
+        }
+        2 => {
+            ctx.rom_items.push(callfi(imml(ctx.rt.swaps), val, push()));
+            let (base, index) = gen_array_operands(ctx, addr, offset, 2);
+            ctx.rom_items.push(astores(base, index, pop()));
+        }
+        _ => {
+            ctx.rom_items.push(copy(val, push()));
+            let (base, index) = gen_array_operands(ctx, addr, offset, 1);
+            ctx.rom_items.push(astoreb(base, index, pop()));
+        }
+    }
+}
+
+/// Loads `size` bytes from `memory` and stores the zero-extended result in
+/// `out`. For 8-byte loads, `out` receives the low word and the high word is
+/// left in the hi-return area.
+fn gen_memload(
+    ctx: &mut Context,
+    frame: &mut Frame,
+    memory: MemoryId,
+    size: u32,
+    offset: u32,
+    addr: LoadOperand<Label>,
+    out: StoreOperand<Label>,
+) {
+    // Accesses to a secondary memory always go through its helpers. The fast
+    // paths below, and the bookkeeping behind them, only know about the
+    // primary memory.
+    if ctx.layout.is_secondary_memory(memory) {
+        let labels = ctx.rt.secondary_memory;
+        let helper = match size {
+            8 => labels.memload64,
+            4 => labels.memload32,
+            2 => labels.memload16,
+            _ => labels.memload8,
+        };
+        ctx.rom_items
+            .push(callfii(imml(helper), uimm(offset), addr, out));
+        return;
+    }
+
+    let mem = ctx.layout.memory().addr;
+
+    // Glulx's array instructions have no alignment requirement, so the fast
+    // path needs only the bounds check to be provably unnecessary. The
+    // memory's base address is 4-aligned, which `imml_off_shift` relies on.
+    match (static_addr(ctx, &addr, offset, size), size) {
+        (Some(ea), 8) => {
+            ctx.rom_items
+                .push(aload(uimm(ea + 4), imml_off_shift(mem, 0, 2), push()));
+            ctx.rom_items.push(callfi(
+                imml(ctx.rt.swap),
+                pop(),
+                storel(ctx.layout.hi_return().addr),
+            ));
+            ctx.rom_items
+                .push(aload(uimm(ea), imml_off_shift(mem, 0, 2), push()));
+            ctx.rom_items.push(callfi(imml(ctx.rt.swap), pop(), out));
+        }
+        (Some(ea), 4) => {
+            ctx.rom_items
+                .push(aload(uimm(ea), imml_off_shift(mem, 0, 2), push()));
+            ctx.rom_items.push(callfi(imml(ctx.rt.swap), pop(), out));
+        }
+        (Some(ea), 2) => {
+            ctx.rom_items
+                .push(aloads(uimm(ea), imml_off_shift(mem, 0, 1), push()));
+            ctx.rom_items.push(callfi(imml(ctx.rt.swaps), pop(), out));
+        }
+        (Some(ea), _) => {
+            ctx.rom_items.push(aloadb(uimm(ea), imml(mem), out));
+        }
+        (None, _) if is_checked(ctx, frame, &addr, offset, size) => {
+            gen_unchecked_memload(ctx, size, offset, addr, out);
+        }
+        (None, 8) => {
+            note_checked(ctx, frame, &addr, offset, size);
+            ctx.rom_items
+                .push(callfii(imml(ctx.rt.memload64), uimm(offset), addr, out));
+        }
+        (None, 4) => {
+            note_checked(ctx, frame, &addr, offset, size);
+            ctx.rom_items
+                .push(callfii(imml(ctx.rt.memload32), uimm(offset), addr, out));
+        }
+        (None, 2) => {
+            note_checked(ctx, frame, &addr, offset, size);
+            ctx.rom_items
+                .push(callfii(imml(ctx.rt.memload16), uimm(offset), addr, out));
+        }
+        (None, _) => {
+            note_checked(ctx, frame, &addr, offset, size);
+            ctx.rom_items
+                .push(callfii(imml(ctx.rt.memload8), uimm(offset), addr, out));
+        }
+    }
+}
+
+/// Stores the low `size` bytes of `val`, which must not be 8, to `memory`.
+fn gen_memstore(
+    ctx: &mut Context,
+    frame: &mut Frame,
+    memory: MemoryId,
+    size: u32,
+    offset: u32,
+    addr: LoadOperand<Label>,
+    val: LoadOperand<Label>,
+) {
+    if ctx.layout.is_secondary_memory(memory) {
+        let labels = ctx.rt.secondary_memory;
+        let helper = match size {
+            4 => labels.memstore32,
+            2 => labels.memstore16,
+            _ => labels.memstore8,
+        };
+        ctx.rom_items
+            .push(callfiii(imml(helper), uimm(offset), val, addr, discard()));
+        return;
+    }
+
+    let mem = ctx.layout.memory().addr;
+
+    match (static_addr(ctx, &addr, offset, size), size) {
+        (Some(ea), 4) => {
+            ctx.rom_items.push(callfi(imml(ctx.rt.swap), val, push()));
+            ctx.rom_items
+                .push(astore(uimm(ea), imml_off_shift(mem, 0, 2), pop()));
+        }
+        (Some(ea), 2) => {
+            ctx.rom_items.push(callfi(imml(ctx.rt.swaps), val, push()));
+            ctx.rom_items
+                .push(astores(uimm(ea), imml_off_shift(mem, 0, 1), pop()));
+        }
+        (Some(ea), _) => {
+            ctx.rom_items.push(astoreb(uimm(ea), imml(mem), val));
+        }
+        (None, _) if is_checked(ctx, frame, &addr, offset, size) => {
+            gen_unchecked_memstore(ctx, size, offset, addr, val);
+        }
+        (None, _) => {
+            note_checked(ctx, frame, &addr, offset, size);
+            let helper = match size {
+                4 => ctx.rt.memstore32,
+                2 => ctx.rt.memstore16,
+                _ => ctx.rt.memstore8,
+            };
+            ctx.rom_items
+                .push(callfiii(imml(helper), uimm(offset), val, addr, discard()));
+        }
+    }
+}
+
 pub fn gen_load(
     ctx: &mut Context,
     frame: &mut Frame,
@@ -120,111 +445,67 @@ pub fn gen_load(
             let addr = credits.pop();
             let out = debts.pop();
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.memload32), uimm(offset), addr, out));
+            gen_memload(ctx, frame, load_instr.memory, 4, offset, addr, out);
             debts.gen(ctx);
         }
         ir::LoadKind::F64 | ir::LoadKind::I64 { atomic: _ } => {
             let addr = credits.pop();
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.memload64), uimm(offset), addr, push()));
+            gen_memload(ctx, frame, load_instr.memory, 8, offset, addr, push());
             gen_copies(ctx, Credits::from_returns(ctx, &[ValType::I64]), debts);
         }
         ir::LoadKind::V128 => {
-            credits.gen(ctx);
-            ctx.errors
-                .push(crate::CompilationError::UnsupportedInstruction {
-                    function: frame.function_name.map(|s| s.to_owned()),
-                    instr: "v128.load",
-                });
-            debts.gen(ctx);
-        }
-        ir::LoadKind::I32_8 { kind } => {
             let addr = credits.pop();
-            let out = debts.pop();
+            let helper = memory_labels(ctx, load_instr.memory).memload128;
             credits.gen(ctx);
-            match kind {
-                ExtendedLoad::SignExtend => {
-                    ctx.rom_items
-                        .push(callfii(imml(ctx.rt.memload8), uimm(offset), addr, push()));
-                    ctx.rom_items.push(sexb(pop(), out));
-                }
-                ExtendedLoad::ZeroExtend | ExtendedLoad::ZeroExtendAtomic => {
-                    ctx.rom_items
-                        .push(callfii(imml(ctx.rt.memload8), uimm(offset), addr, out));
-                }
-            }
-            debts.gen(ctx);
+            ctx.rom_items
+                .push(callfii(imml(helper), uimm(offset), addr, push()));
+            gen_copies(ctx, Credits::from_returns(ctx, &[ValType::V128]), debts);
         }
-        ir::LoadKind::I32_16 { kind } => {
+        ir::LoadKind::I32_8 { kind } | ir::LoadKind::I32_16 { kind } => {
+            let size = if matches!(load_instr.kind, ir::LoadKind::I32_8 { .. }) {
+                1
+            } else {
+                2
+            };
             let addr = credits.pop();
             let out = debts.pop();
             credits.gen(ctx);
             match kind {
                 ExtendedLoad::SignExtend => {
-                    ctx.rom_items
-                        .push(callfii(imml(ctx.rt.memload16), uimm(offset), addr, push()));
-                    ctx.rom_items.push(sexs(pop(), out));
-                }
-                ExtendedLoad::ZeroExtend | ExtendedLoad::ZeroExtendAtomic => {
-                    ctx.rom_items
-                        .push(callfii(imml(ctx.rt.memload16), uimm(offset), addr, out));
-                }
-            }
-            debts.gen(ctx);
-        }
-        ir::LoadKind::I64_8 { kind } => {
-            let addr = credits.pop();
-            let out_hi = debts.pop();
-            credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.memload8), uimm(offset), addr, push()));
-
-            match kind {
-                ExtendedLoad::SignExtend => {
-                    ctx.rom_items.push(sexb(pop(), push()));
-                    ctx.rom_items.push(stkpeek(imm(0), push()));
-                    ctx.rom_items.push(sshiftr(pop(), imm(31), out_hi));
-                }
-                ExtendedLoad::ZeroExtend | ExtendedLoad::ZeroExtendAtomic => {
-                    if !matches!(out_hi, StoreOperand::Discard) {
-                        ctx.rom_items.push(copy(imm(0), out_hi));
+                    gen_memload(ctx, frame, load_instr.memory, size, offset, addr, push());
+                    if size == 1 {
+                        ctx.rom_items.push(sexb(pop(), out));
+                    } else {
+                        ctx.rom_items.push(sexs(pop(), out));
                     }
                 }
-            }
-            debts.gen(ctx);
-        }
-        ir::LoadKind::I64_16 { kind } => {
-            let addr = credits.pop();
-            let out_hi = debts.pop();
-            credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.memload16), uimm(offset), addr, push()));
-
-            match kind {
-                ExtendedLoad::SignExtend => {
-                    ctx.rom_items.push(sexs(pop(), push()));
-                    ctx.rom_items.push(stkpeek(imm(0), push()));
-                    ctx.rom_items.push(sshiftr(pop(), imm(31), out_hi));
-                }
                 ExtendedLoad::ZeroExtend | ExtendedLoad::ZeroExtendAtomic => {
-                    if !matches!(out_hi, StoreOperand::Discard) {
-                        ctx.rom_items.push(copy(imm(0), out_hi));
-                    }
+                    gen_memload(ctx, frame, load_instr.memory, size, offset, addr, out);
                 }
             }
             debts.gen(ctx);
         }
-        ir::LoadKind::I64_32 { kind } => {
+        ir::LoadKind::I64_8 { kind }
+        | ir::LoadKind::I64_16 { kind }
+        | ir::LoadKind::I64_32 { kind } => {
+            let size = match load_instr.kind {
+                ir::LoadKind::I64_8 { .. } => 1,
+                ir::LoadKind::I64_16 { .. } => 2,
+                _ => 4,
+            };
             let addr = credits.pop();
             let out_hi = debts.pop();
             credits.gen(ctx);
-            ctx.rom_items
-                .push(callfii(imml(ctx.rt.memload32), uimm(offset), addr, push()));
+            gen_memload(ctx, frame, load_instr.memory, size, offset, addr, push());
 
             match kind {
                 ExtendedLoad::SignExtend => {
+                    match size {
+                        1 => ctx.rom_items.push(sexb(pop(), push())),
+                        2 => ctx.rom_items.push(sexs(pop(), push())),
+                        _ => {}
+                    }
                     ctx.rom_items.push(stkpeek(imm(0), push()));
                     ctx.rom_items.push(sshiftr(pop(), imm(31), out_hi));
                 }
@@ -252,58 +533,87 @@ pub fn gen_store(
             let val = credits.pop();
             let addr = credits.pop();
             credits.gen(ctx);
-            ctx.rom_items.push(callfiii(
-                imml(ctx.rt.memstore32),
-                uimm(offset),
-                val,
-                addr,
-                discard(),
-            ));
+            gen_memstore(ctx, frame, store_instr.memory, 4, offset, addr, val);
             debts.gen(ctx);
         }
         ir::StoreKind::F64 | ir::StoreKind::I64 { atomic: _ } => {
-            credits.gen(ctx);
-            ctx.rom_items.push(copy(uimm(offset), push()));
-            ctx.rom_items
-                .push(call(imml(ctx.rt.memstore64), imm(4), discard()));
+            let mem = ctx.layout.memory().addr;
+            let ea = credits
+                .peek(2)
+                .and_then(|addr| static_addr(ctx, addr, offset, 8));
+            if ctx.layout.is_secondary_memory(store_instr.memory) {
+                credits.gen(ctx);
+                ctx.rom_items.push(copy(uimm(offset), push()));
+                ctx.rom_items.push(call(
+                    imml(ctx.rt.secondary_memory.memstore64),
+                    imm(4),
+                    discard(),
+                ));
+            } else if let Some(ea) = ea {
+                let (val_hi, val_lo) = credits.pop_hi_lo();
+                let _addr = credits.pop();
+                credits.gen(ctx);
+                ctx.rom_items
+                    .push(callfi(imml(ctx.rt.swap), val_hi, push()));
+                ctx.rom_items
+                    .push(astore(uimm(ea + 4), imml_off_shift(mem, 0, 2), pop()));
+                ctx.rom_items
+                    .push(callfi(imml(ctx.rt.swap), val_lo, push()));
+                ctx.rom_items
+                    .push(astore(uimm(ea), imml_off_shift(mem, 0, 2), pop()));
+            } else if credits
+                .peek(2)
+                .is_some_and(|addr| is_checked(ctx, frame, addr, offset, 8))
+            {
+                let (val_hi, val_lo) = credits.pop_hi_lo();
+                let addr = credits.pop();
+                credits.gen(ctx);
+                ctx.rom_items
+                    .push(callfi(imml(ctx.rt.swap), val_hi, push()));
+                let (base, index) = gen_array_operands(ctx, addr, offset + 4, 4);
+                ctx.rom_items.push(astore(base, index, pop()));
+                ctx.rom_items
+                    .push(callfi(imml(ctx.rt.swap), val_lo, push()));
+                let (base, index) = gen_array_operands(ctx, addr, offset, 4);
+                ctx.rom_items.push(astore(base, index, pop()));
+            } else {
+                if let Some(addr) = credits.peek(2).copied() {
+                    note_checked(ctx, frame, &addr, offset, 8);
+                }
+                credits.gen(ctx);
+                ctx.rom_items.push(copy(uimm(offset), push()));
+                ctx.rom_items
+                    .push(call(imml(ctx.rt.memstore64), imm(4), discard()));
+            }
             debts.gen(ctx);
         }
         ir::StoreKind::V128 => {
+            let helper = memory_labels(ctx, store_instr.memory).memstore128;
             credits.gen(ctx);
-            ctx.errors
-                .push(crate::CompilationError::UnsupportedInstruction {
-                    function: frame.function_name.map(|s| s.to_owned()),
-                    instr: "v128.store",
-                });
-            debts.gen(ctx);
-        }
-        ir::StoreKind::I32_8 { atomic: _ } => {
-            let val = credits.pop();
-            let addr = credits.pop();
-            credits.gen(ctx);
-            ctx.rom_items.push(callfiii(
-                imml(ctx.rt.memstore8),
-                uimm(offset),
-                val,
-                addr,
-                discard(),
-            ));
+            ctx.rom_items.push(copy(uimm(offset), push()));
+            ctx.rom_items.push(call(imml(helper), imm(6), discard()));
             debts.gen(ctx);
         }
-        ir::StoreKind::I32_16 { atomic: _ } => {
+        ir::StoreKind::I32_8 { atomic: _ } | ir::StoreKind::I32_16 { atomic: _ } => {
+            let size = if matches!(store_instr.kind, ir::StoreKind::I32_8 { .. }) {
+                1
+            } else {
+                2
+            };
             let val = credits.pop();
             let addr = credits.pop();
             credits.gen(ctx);
-            ctx.rom_items.push(callfiii(
-                imml(ctx.rt.memstore16),
-                uimm(offset),
-                val,
-                addr,
-                discard(),
-            ));
+            gen_memstore(ctx, frame, store_instr.memory, size, offset, addr, val);
             debts.gen(ctx);
         }
-        ir::StoreKind::I64_8 { atomic: _ } => {
+        ir::StoreKind::I64_8 { atomic: _ }
+        | ir::StoreKind::I64_16 { atomic: _ }
+        | ir::StoreKind::I64_32 { atomic: _ } => {
+            let size = match store_instr.kind {
+                ir::StoreKind::I64_8 { .. } => 1,
+                ir::StoreKind::I64_16 { .. } => 2,
+                _ => 4,
+            };
             let val_hi = credits.pop();
             let val_lo = credits.pop();
             let addr = credits.pop();
@@ -312,49 +622,7 @@ pub fn gen_store(
             if matches!(val_hi, LoadOperand::Pop) {
                 ctx.rom_items.push(copy(pop(), discard()));
             }
-            ctx.rom_items.push(callfiii(
-                imml(ctx.rt.memstore8),
-                uimm(offset),
-                val_lo,
-                addr,
-                discard(),
-            ));
-            debts.gen(ctx);
-        }
-        ir::StoreKind::I64_16 { atomic: _ } => {
-            let val_hi = credits.pop();
-            let val_lo = credits.pop();
-            let addr = credits.pop();
-            credits.gen(ctx);
-
-            if matches!(val_hi, LoadOperand::Pop) {
-                ctx.rom_items.push(copy(pop(), discard()));
-            }
-            ctx.rom_items.push(callfiii(
-                imml(ctx.rt.memstore16),
-                uimm(offset),
-                val_lo,
-                addr,
-                discard(),
-            ));
-            debts.gen(ctx);
-        }
-        ir::StoreKind::I64_32 { atomic: _ } => {
-            let val_hi = credits.pop();
-            let val_lo = credits.pop();
-            let addr = credits.pop();
-            credits.gen(ctx);
-
-            if matches!(val_hi, LoadOperand::Pop) {
-                ctx.rom_items.push(copy(pop(), discard()));
-            }
-            ctx.rom_items.push(callfiii(
-                imml(ctx.rt.memstore32),
-                uimm(offset),
-                val_lo,
-                addr,
-                discard(),
-            ));
+            gen_memstore(ctx, frame, store_instr.memory, size, offset, addr, val_lo);
             debts.gen(ctx);
         }
     }
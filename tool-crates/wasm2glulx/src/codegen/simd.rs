@@ -0,0 +1,243 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Scalarized lowering of SIMD instructions.
+//!
+//! A v128 is four words, pushed least significant first. Almost every
+//! instruction here calls a runtime routine which takes its v128 operands in
+//! that order and, like a function returning a v128, returns word 0 of its
+//! result and leaves words 3, 2, and 1 in the hi-return area.
+
+use super::classify::{ClassifiedInstr, Other};
+use super::loadstore::{gen_copies, Credits, Debts};
+use super::toplevel::Frame;
+use crate::common::{Context, Label};
+use glulx_asm::{concise::*, LoadOperand};
+use walrus::{ir, ValType};
+
+/// Calls `routine` with the operands in `credits` followed by `extra`, which
+/// together make up `nargs` words, and stores the v128 it returns to `debts`.
+fn gen_v128_call(
+    ctx: &mut Context,
+    routine: Label,
+    nargs: u32,
+    extra: &[LoadOperand<Label>],
+    mut credits: Credits,
+    debts: Debts,
+) {
+    credits.gen(ctx);
+    for arg in extra {
+        ctx.rom_items.push(copy(*arg, push()));
+    }
+    ctx.rom_items.push(call(imml(routine), uimm(nargs), push()));
+    gen_copies(ctx, Credits::from_returns(ctx, &[ValType::V128]), debts);
+}
+
+/// Generates a splat of the operand. If `replicate` is `Some((mask, factor))`,
+/// the lane is the operand's bits selected by `mask`, and multiplying it by
+/// `factor` copies it into every lane of a word.
+fn gen_splat(ctx: &mut Context, replicate: Option<(u32, u32)>, mut credits: Credits, debts: Debts) {
+    let x = credits.pop();
+    credits.gen(ctx);
+    let x = match replicate {
+        Some((mask, factor)) => {
+            ctx.rom_items.push(bitand(x, uimm(mask), push()));
+            ctx.rom_items.push(mul(pop(), uimm(factor), push()));
+            pop()
+        }
+        None => x,
+    };
+    ctx.rom_items
+        .push(callfi(imml(ctx.rt.v128_splat), x, push()));
+    gen_copies(ctx, Credits::from_returns(ctx, &[ValType::V128]), debts);
+}
+
+fn gen_extract_lane(
+    ctx: &mut Context,
+    lane: u8,
+    lane_bits: u32,
+    signed: bool,
+    mut credits: Credits,
+    mut debts: Debts,
+) {
+    let out = debts.pop();
+    let bit = u32::from(lane) * lane_bits;
+    let word = bit / 32;
+    let shift = bit % 32;
+
+    credits.gen(ctx);
+    ctx.rom_items.push(copy(uimm(word), push()));
+    if lane_bits == 32 {
+        ctx.rom_items
+            .push(call(imml(ctx.rt.v128_lane_word), imm(5), out));
+    } else {
+        ctx.rom_items
+            .push(call(imml(ctx.rt.v128_lane_word), imm(5), push()));
+        if shift != 0 {
+            ctx.rom_items.push(ushiftr(pop(), uimm(shift), push()));
+        }
+        ctx.rom_items.push(match (lane_bits, signed) {
+            (8, true) => sexb(pop(), out),
+            (8, false) => bitand(pop(), imm(0xff), out),
+            (_, true) => sexs(pop(), out),
+            (_, false) => bitand(pop(), imm(0xffff), out),
+        });
+    }
+    debts.gen(ctx);
+}
+
+fn gen_replace_lane(ctx: &mut Context, lane: u8, lane_bits: u32, credits: Credits, debts: Debts) {
+    let bit = u32::from(lane) * lane_bits;
+    let word = bit / 32;
+    let shift = bit % 32;
+    let mask = (u32::MAX >> (32 - lane_bits)) << shift;
+
+    gen_v128_call(
+        ctx,
+        ctx.rt.v128_replace_lane,
+        8,
+        &[uimm(mask), uimm(shift), uimm(word)],
+        credits,
+        debts,
+    );
+}
+
+pub fn gen_unop(
+    ctx: &mut Context,
+    frame: &Frame,
+    unop: &ir::Unop,
+    mut credits: Credits,
+    mut debts: Debts,
+) {
+    match unop.op {
+        ir::UnaryOp::V128Not => {
+            gen_v128_call(ctx, ctx.rt.v128_not, 4, &[], credits, debts);
+        }
+        ir::UnaryOp::I8x16Neg => {
+            gen_v128_call(ctx, ctx.rt.i8x16_neg, 4, &[], credits, debts);
+        }
+        ir::UnaryOp::I16x8Neg => {
+            gen_v128_call(ctx, ctx.rt.i16x8_neg, 4, &[], credits, debts);
+        }
+        ir::UnaryOp::I32x4Neg => {
+            gen_v128_call(ctx, ctx.rt.i32x4_neg, 4, &[], credits, debts);
+        }
+        ir::UnaryOp::I8x16Splat => {
+            gen_splat(ctx, Some((0xff, 0x01010101)), credits, debts);
+        }
+        ir::UnaryOp::I16x8Splat => {
+            gen_splat(ctx, Some((0xffff, 0x00010001)), credits, debts);
+        }
+        ir::UnaryOp::I32x4Splat | ir::UnaryOp::F32x4Splat => {
+            gen_splat(ctx, None, credits, debts);
+        }
+        ir::UnaryOp::I8x16ExtractLaneS { idx } => {
+            gen_extract_lane(ctx, idx, 8, true, credits, debts);
+        }
+        ir::UnaryOp::I8x16ExtractLaneU { idx } => {
+            gen_extract_lane(ctx, idx, 8, false, credits, debts);
+        }
+        ir::UnaryOp::I16x8ExtractLaneS { idx } => {
+            gen_extract_lane(ctx, idx, 16, true, credits, debts);
+        }
+        ir::UnaryOp::I16x8ExtractLaneU { idx } => {
+            gen_extract_lane(ctx, idx, 16, false, credits, debts);
+        }
+        ir::UnaryOp::I32x4ExtractLane { idx } | ir::UnaryOp::F32x4ExtractLane { idx } => {
+            gen_extract_lane(ctx, idx, 32, false, credits, debts);
+        }
+        _ => {
+            credits.gen(ctx);
+            let mnemonic = Other::Unop(unop.clone()).mnemonic();
+            ctx.errors
+                .push(crate::CompilationError::UnsupportedInstruction {
+                    function: frame.function_name.map(|s| s.to_owned()),
+                    instr: mnemonic,
+                });
+            debts.gen(ctx);
+        }
+    }
+}
+
+pub fn gen_binop(
+    ctx: &mut Context,
+    frame: &Frame,
+    binop: &ir::Binop,
+    mut credits: Credits,
+    mut debts: Debts,
+) {
+    let routine = match binop.op {
+        ir::BinaryOp::V128And => ctx.rt.v128_and,
+        ir::BinaryOp::V128Or => ctx.rt.v128_or,
+        ir::BinaryOp::V128Xor => ctx.rt.v128_xor,
+        ir::BinaryOp::V128AndNot => ctx.rt.v128_andnot,
+        ir::BinaryOp::I8x16Add => ctx.rt.i8x16_add,
+        ir::BinaryOp::I8x16Sub => ctx.rt.i8x16_sub,
+        ir::BinaryOp::I16x8Add => ctx.rt.i16x8_add,
+        ir::BinaryOp::I16x8Sub => ctx.rt.i16x8_sub,
+        ir::BinaryOp::I16x8Mul => ctx.rt.i16x8_mul,
+        ir::BinaryOp::I32x4Add => ctx.rt.i32x4_add,
+        ir::BinaryOp::I32x4Sub => ctx.rt.i32x4_sub,
+        ir::BinaryOp::I32x4Mul => ctx.rt.i32x4_mul,
+        ir::BinaryOp::I8x16ReplaceLane { idx } => {
+            gen_replace_lane(ctx, idx, 8, credits, debts);
+            return;
+        }
+        ir::BinaryOp::I16x8ReplaceLane { idx } => {
+            gen_replace_lane(ctx, idx, 16, credits, debts);
+            return;
+        }
+        ir::BinaryOp::I32x4ReplaceLane { idx } | ir::BinaryOp::F32x4ReplaceLane { idx } => {
+            gen_replace_lane(ctx, idx, 32, credits, debts);
+            return;
+        }
+        _ => {
+            credits.gen(ctx);
+            let mnemonic = Other::Binop(binop.clone()).mnemonic();
+            ctx.errors
+                .push(crate::CompilationError::UnsupportedInstruction {
+                    function: frame.function_name.map(|s| s.to_owned()),
+                    instr: mnemonic,
+                });
+            debts.gen(ctx);
+            return;
+        }
+    };
+    gen_v128_call(ctx, routine, 8, &[], credits, debts);
+}
+
+pub fn gen_bitselect(
+    ctx: &mut Context,
+    _frame: &mut Frame,
+    _bitselect: &ir::V128Bitselect,
+    credits: Credits,
+    debts: Debts,
+) {
+    gen_v128_call(ctx, ctx.rt.v128_bitselect, 12, &[], credits, debts);
+}
+
+pub fn gen_swizzle(
+    ctx: &mut Context,
+    _frame: &mut Frame,
+    _swizzle: &ir::I8x16Swizzle,
+    credits: Credits,
+    debts: Debts,
+) {
+    gen_v128_call(ctx, ctx.rt.i8x16_swizzle, 8, &[], credits, debts);
+}
+
+pub fn gen_shuffle(
+    ctx: &mut Context,
+    _frame: &mut Frame,
+    shuffle: &ir::I8x16Shuffle,
+    credits: Credits,
+    debts: Debts,
+) {
+    // The lane indices are passed to the routine as a third v128 operand.
+    let indices: Vec<_> = shuffle
+        .indices
+        .chunks_exact(4)
+        .map(|word| uimm(u32::from_le_bytes(word.try_into().unwrap())))
+        .collect();
+    gen_v128_call(ctx, ctx.rt.i8x16_shuffle, 12, &indices, credits, debts);
+}
@@ -2,11 +2,12 @@
 // Copyright 2024 Daniel Fox Franke.
 
 use glulx_asm::concise::*;
+use glulx_asm::{LiteralPool, MysteryString};
 use std::collections::HashMap;
 use walrus::ir::{self, InstrSeq, InstrSeqId};
 use walrus::{LocalFunction, LocalId, ValType};
 
-use crate::common::{Context, Label, WordCount};
+use crate::common::{call_frame_len, Context, Emit, Label, WordCount};
 use crate::{CompilationError, OverflowLocation};
 
 use super::classify::{
@@ -20,6 +21,17 @@ pub struct Frame<'a> {
     pub locals: &'a HashMap<LocalId, u32>,
     pub jump_targets: &'a mut HashMap<InstrSeqId, JumpTarget>,
     pub jump_tables: &'a mut HashMap<Label, Vec<Label>>,
+    /// For each local (by frame offset) holding a memory address, how far
+    /// past that address has been bounds-checked since the start of the
+    /// current basic block. Only populated with `--elide-bounds-checks`.
+    pub checked_addrs: HashMap<u32, u32>,
+    /// Label of this function's name as a string, for reporting overflows.
+    /// Only present with `--trap-on-overflow`.
+    pub overflow_name: Option<Label>,
+    /// Label of a string describing this function for trap messages, which
+    /// must be stored to the trap location on entry and after every call.
+    /// Only present with `--trap-messages`.
+    pub trap_location: Option<Label>,
 }
 pub struct JumpTarget {
     pub base: usize,
@@ -80,6 +92,7 @@ pub fn gen_function(
     function: &LocalFunction,
     my_label: Label,
     function_name: Option<&str>,
+    stack_guard: bool,
 ) {
     let mut locals = HashMap::new();
     let mut wasm_labels = HashMap::new();
@@ -109,16 +122,56 @@ pub fn gen_function(
         return;
     }
 
+    let frame_len = call_frame_len(ctr);
+    if frame_len > u64::from(ctx.options.stack_size) {
+        ctx.errors
+            .push(CompilationError::Overflow(OverflowLocation::Frame {
+                function: function_name.map(|s| s.to_owned()),
+                bytes: frame_len,
+                stack_size: ctx.options.stack_size,
+            }));
+        return;
+    }
+
+    // Without an original range, the trap location is just the function's
+    // name, and the pool lets it share a string with the overflow name.
+    let mut strings = LiteralPool::new();
+    let name = function_name.unwrap_or("<anonymous function>");
+    let overflow_name = ctx.options.traps_on_overflow().then(|| {
+        strings.intern_string(MysteryString::from_chars_lossy(name.chars()), || {
+            ctx.gen.gen("overflow_function_name")
+        })
+    });
+    let trap_location = ctx.options.trap_messages.then(|| {
+        let location = match &function.original_range {
+            Some(range) => format!("{name} (wasm offset {:#x})", range.start),
+            None => name.to_owned(),
+        };
+        strings.intern_string(MysteryString::from_chars_lossy(location.chars()), || {
+            ctx.gen.gen("trap_location_string")
+        })
+    });
+
     let mut frame = Frame {
         function,
         function_name,
         locals: &locals,
         jump_targets: &mut wasm_labels,
         jump_tables: &mut jump_tables,
+        checked_addrs: HashMap::new(),
+        overflow_name,
+        trap_location,
     };
 
     ctx.rom_items.push(label(my_label));
     ctx.rom_items.push(fnhead_local(ctr));
+    super::control::gen_set_trap_location(ctx, &frame);
+    if let Some(margin) = ctx.options.stack_guard.filter(|_| stack_guard) {
+        ctx.rom_items
+            .push(callf(imml(ctx.rt.stack_remaining), push()));
+        ctx.rom_items
+            .push(jltu(pop(), uimm(margin), ctx.rt.stack_guard_failed));
+    }
 
     let mut branch_to_entry_searcher = BranchToEntrySearcher {
         found: false,
@@ -156,6 +209,8 @@ pub fn gen_function(
             ctx.rom_items.push(labelref(l));
         }
     }
+
+    ctx.rom_items.extend(strings.into_items());
 }
 
 fn make_credits(
@@ -210,7 +265,12 @@ fn gen_instrseq(
         return;
     }
 
-    for (i, subseq) in subseqs.into_iter().enumerate() {
+    for (i, (subseq, loc)) in subseqs.into_iter().enumerate() {
+        if ctx.options.emit.contains(&Emit::Debug) && !loc.is_default() {
+            let point = ctx.gen.gen("sequence_point");
+            ctx.rom_items.push(label(point));
+            ctx.sequence_points.push((point, loc));
+        }
         match subseq {
             InstrSubseq::Copy { loads, stores, ret } => {
                 let credits = make_credits(ctx, frame, &mut initial_credits, &loads, i == 0);
@@ -227,6 +287,7 @@ fn gen_instrseq(
                     i == n_subseqs - 1,
                 );
                 gen_copies(ctx, credits, debts);
+                forget_checked_addrs(frame, &stores);
                 for store in &stores {
                     store.update_stack(ctx.module, frame.function, stack);
                 }
@@ -263,6 +324,7 @@ fn gen_instrseq(
                 );
 
                 gen_loop(ctx, frame, looop, cloned_stack, debts);
+                forget_checked_addrs(frame, &stores);
                 for store in &stores {
                     store.update_stack(ctx.module, frame.function, stack);
                 }
@@ -295,6 +357,7 @@ fn gen_instrseq(
                 );
 
                 gen_other(ctx, frame, other, pre_height, stack, credits, debts);
+                forget_checked_addrs(frame, &stores);
                 for store in &stores {
                     store.update_stack(ctx.module, frame.function, stack);
                 }
@@ -317,6 +380,19 @@ fn gen_instrseq(
     }
 }
 
+/// Forgets bounds checks on any locals overwritten by `stores`.
+fn forget_checked_addrs(frame: &mut Frame, stores: &[Store]) {
+    for store in stores {
+        if let Store::LocalSet(ir::LocalSet { local }) = store {
+            let glulx_local = *frame
+                .locals
+                .get(local)
+                .expect("All locals should have been added to the frame's map.");
+            frame.checked_addrs.remove(&(4 * glulx_local));
+        }
+    }
+}
+
 fn gen_block(
     ctx: &mut Context,
     frame: &mut Frame,
@@ -383,6 +459,7 @@ fn gen_block(
             );
             ctx.rom_items.push(jump(target));
             ctx.rom_items.push(label(test_target));
+            frame.checked_addrs.clear();
             let consequent = frame.function.block(*cid);
             gen_instrseq(
                 ctx,
@@ -396,6 +473,7 @@ fn gen_block(
     }
 
     ctx.rom_items.push(label(target));
+    frame.checked_addrs.clear();
 }
 
 fn gen_loop(
@@ -422,6 +500,7 @@ fn gen_loop(
         },
     );
     ctx.rom_items.push(label(target));
+    frame.checked_addrs.clear();
     gen_instrseq(ctx, frame, seq, &mut stack, Credits::empty(), debts);
 }
 
@@ -479,7 +558,7 @@ fn gen_other(
             let r = credits.pop();
             let out = debts.pop();
             credits.gen(ctx);
-            ctx.rom_items.push(callfi(imml(ctx.rt.i32_eqz), r, out));
+            super::arith::gen_i32_eqz(ctx, r, out);
             debts.gen(ctx);
         }
         Other::Select(test, select) => {
@@ -509,6 +588,15 @@ fn gen_other(
         Other::Unop(unop) => {
             super::arith::gen_unop(ctx, frame, unop, credits, debts);
         }
+        Other::V128BitSelect(bitselect) => {
+            super::simd::gen_bitselect(ctx, frame, bitselect, credits, debts);
+        }
+        Other::I8x16Swizzle(swizzle) => {
+            super::simd::gen_swizzle(ctx, frame, swizzle, credits, debts);
+        }
+        Other::I8x16Shuffle(shuffle) => {
+            super::simd::gen_shuffle(ctx, frame, shuffle, credits, debts);
+        }
         _ => {
             credits.gen(ctx);
             ctx.errors.push(CompilationError::UnsupportedInstruction {
@@ -525,7 +613,7 @@ fn gen_terminal(
     frame: &mut Frame,
     terminal: Terminal,
     pre_height: usize,
-    mut credits: Credits,
+    credits: Credits,
 ) {
     match &terminal {
         Terminal::Br(br) => {
@@ -537,12 +625,11 @@ fn gen_terminal(
         Terminal::Unreachable(unreachable) => {
             super::control::gen_unreachable(ctx, frame, unreachable, credits);
         }
-        _ => {
-            credits.gen(ctx);
-            ctx.errors.push(CompilationError::UnsupportedInstruction {
-                function: frame.function_name.map(|s| s.to_owned()),
-                instr: terminal.mnemonic(),
-            });
+        Terminal::ReturnCall(return_call) => {
+            super::control::gen_return_call(ctx, frame, return_call, credits);
+        }
+        Terminal::ReturnCallIndirect(return_call_indirect) => {
+            super::control::gen_return_call_indirect(ctx, frame, return_call_indirect, credits);
         }
     }
 }
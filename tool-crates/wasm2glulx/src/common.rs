@@ -7,11 +7,12 @@ use glulx_asm::{Item, ZeroItem};
 use std::{
     fmt::{Debug, Display},
     hash::Hash,
+    num::NonZeroUsize,
     path::PathBuf,
 };
-use walrus::{GlobalId, GlobalKind, Module, ValType};
+use walrus::{ir::InstrLocId, GlobalId, GlobalKind, Module, ValType};
 
-use crate::{layout::Layout, rt::RuntimeLabels, CompilationError};
+use crate::{hooks::HookContext, layout::Layout, rt::RuntimeLabels, CompilationError};
 
 macro_rules! push_all {
     ($v:expr, $($item:expr),* $(,)*) => {
@@ -23,12 +24,28 @@ macro_rules! push_all {
 #[derive(Debug)]
 pub struct LabelGenerator(pub usize);
 
+/// An opaque assembler label.
 #[derive(Debug, Copy, Clone)]
 pub struct Label {
     desc: &'static str,
     num: usize,
 }
 
+impl Label {
+    /// If this label was generated at or after number `from`, returns it
+    /// renumbered as if generation had started at `to` instead.
+    pub fn rebase(self, from: usize, to: usize) -> Label {
+        if self.num >= from {
+            Label {
+                desc: self.desc,
+                num: self.num - from + to,
+            }
+        } else {
+            self
+        }
+    }
+}
+
 impl PartialEq for Label {
     fn eq(&self, other: &Self) -> bool {
         self.num == other.num
@@ -68,6 +85,22 @@ pub struct Context<'a> {
     pub ram_items: &'a mut Vec<Item<Label>>,
     pub zero_items: &'a mut Vec<ZeroItem<Label>>,
     pub errors: &'a mut Vec<CompilationError>,
+    /// Labels placed before the code generated for an instruction, with the
+    /// instruction's location in the input module. Only collected when
+    /// emitting debug information.
+    pub sequence_points: &'a mut Vec<(Label, InstrLocId)>,
+}
+
+impl Context<'_> {
+    pub fn hook_context(&mut self) -> HookContext<'_> {
+        HookContext::new(
+            self.module,
+            self.gen,
+            self.rom_items,
+            self.ram_items,
+            self.zero_items,
+        )
+    }
 }
 
 /// The default value for `--glk-area-size`.
@@ -76,14 +109,97 @@ pub const DEFAULT_GLK_AREA_SIZE: u32 = 4096;
 pub const DEFAULT_STACK_SIZE: u32 = 1048576;
 /// The default value for `--table-growth-limit`.
 pub const DEFAULT_TABLE_GROWTH_LIMIT: u32 = 1024;
+/// The value which a failed `--stack-guard` check throws to the innermost
+/// active `catch` intrinsic.
+pub const STACK_EXHAUSTED: u32 = 0xffff_ffff;
+
+/// A kind of output that compilation can produce.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Emit {
+    /// A Glulx story file.
+    Binary,
+    /// A human-readable assembly listing.
+    Asm,
+    /// A map from the address of each function to its name.
+    Map,
+    /// A map from the address of each exported function to its export name.
+    Exports,
+    /// An Inform-style debug information file describing each function,
+    /// including where it was declared and which source lines its code came
+    /// from if the module has DWARF information.
+    Debug,
+    /// A Blorb archive containing the story file and any resources listed in
+    /// the [Blorb manifest](CompilationOptions::set_blorb_manifest).
+    Blorb,
+}
+
+impl Emit {
+    /// Returns the file extension conventionally used for this kind of output.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Emit::Binary => "ulx",
+            Emit::Asm => "glulxasm",
+            Emit::Map => "map",
+            Emit::Exports => "exports",
+            Emit::Debug => "dbg",
+            Emit::Blorb => "gblorb",
+        }
+    }
+}
+
+/// How closely generated code follows WebAssembly's trapping semantics.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Conformance {
+    /// Emit every check, ignoring options which trade checks for speed or
+    /// which trap where WebAssembly would not, such as
+    /// [`set_elide_bounds_checks`](CompilationOptions::set_elide_bounds_checks)
+    /// and [`set_trap_on_overflow`](CompilationOptions::set_trap_on_overflow).
+    Spec,
+    /// Emit every check, except those that the other options explicitly turn
+    /// off.
+    #[default]
+    Standard,
+    /// Skip bounds checks on memory accesses whose address is a local or a
+    /// constant, and skip signature checks on indirect calls. A program which
+    /// would have trapped may instead halt with an interpreter error, or go on
+    /// running with corrupted state.
+    Fast,
+}
+
+/// Whether to favor smaller or faster code where the two conflict.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum OptimizeFor {
+    /// Implement operations that take more than an instruction or two by
+    /// calling a shared runtime routine.
+    #[default]
+    Size,
+    /// Inline short runtime routines, such as those for integer comparisons
+    /// and shifts, at each site that uses them, saving the cost of a call.
+    Speed,
+}
 
 /// Options that control compilation.
 #[derive(Debug, Clone)]
 pub struct CompilationOptions {
     pub(crate) glk_area_size: u32,
     pub(crate) stack_size: u32,
+    pub(crate) stack_guard: Option<u32>,
     pub(crate) table_growth_limit: u32,
-    pub(crate) text: bool,
+    pub(crate) emit: Vec<Emit>,
+    pub(crate) early_start: bool,
+    pub(crate) elide_bounds_checks: bool,
+    pub(crate) eliminate_dead_code: bool,
+    pub(crate) strict: bool,
+    pub(crate) deny_warnings: bool,
+    pub(crate) inline_thread_spawn: bool,
+    pub(crate) lower_secondary_memory: bool,
+    pub(crate) trap_on_overflow: bool,
+    pub(crate) trap_messages: bool,
+    pub(crate) export_filter: Vec<String>,
+    pub(crate) conformance: Conformance,
+    pub(crate) optimize_for: OptimizeFor,
+    pub(crate) jobs: Option<NonZeroUsize>,
+    pub(crate) blorb_manifest: Option<PathBuf>,
     pub(crate) input: Option<PathBuf>,
     pub(crate) output: Option<PathBuf>,
 }
@@ -100,8 +216,23 @@ impl CompilationOptions {
         CompilationOptions {
             glk_area_size: DEFAULT_GLK_AREA_SIZE,
             stack_size: DEFAULT_STACK_SIZE,
+            stack_guard: None,
             table_growth_limit: DEFAULT_TABLE_GROWTH_LIMIT,
-            text: false,
+            emit: vec![Emit::Binary],
+            early_start: false,
+            elide_bounds_checks: false,
+            eliminate_dead_code: false,
+            strict: false,
+            deny_warnings: false,
+            inline_thread_spawn: false,
+            lower_secondary_memory: false,
+            trap_on_overflow: false,
+            trap_messages: false,
+            export_filter: Vec::new(),
+            conformance: Conformance::Standard,
+            optimize_for: OptimizeFor::Size,
+            jobs: None,
+            blorb_manifest: None,
             input: None,
             output: None,
         }
@@ -117,14 +248,158 @@ impl CompilationOptions {
         self.stack_size = size;
     }
 
+    /// When `Some(margin)`, make every function which can recurse check on
+    /// entry that at least `margin` bytes of stack remain, rather than leaving
+    /// the interpreter to fail in its own way when the stack overflows. A
+    /// failed check throws [`STACK_EXHAUSTED`] to the innermost active `catch`
+    /// intrinsic, or traps with "call stack exhausted" if there is none.
+    pub fn set_stack_guard(&mut self, margin: Option<u32>) {
+        self.stack_guard = margin;
+    }
+
     /// Set the table growth limit.
     pub fn set_table_growth_limit(&mut self, limit: u32) {
         self.table_growth_limit = limit;
     }
 
     /// When true, generate human-readable output instead of a story file.
+    /// This is shorthand for calling [`set_emit`](Self::set_emit) with either
+    /// `[Emit::Asm]` or `[Emit::Binary]`.
     pub fn set_text(&mut self, text: bool) {
-        self.text = text;
+        self.emit = vec![if text { Emit::Asm } else { Emit::Binary }];
+    }
+
+    /// Set which outputs to generate. Duplicates are ignored. If more than one
+    /// kind of output is requested, the output path is treated as a stem, to
+    /// which each output's [extension](Emit::extension) is appended.
+    pub fn set_emit(&mut self, emit: &[Emit]) {
+        self.emit.clear();
+        for e in emit {
+            if !self.emit.contains(e) {
+                self.emit.push(*e);
+            }
+        }
+    }
+
+    /// When true, also generate a debug information file. This adds or removes
+    /// [`Emit::Debug`] from the outputs set by [`set_emit`](Self::set_emit).
+    pub fn set_debug_info(&mut self, debug_info: bool) {
+        self.emit.retain(|e| *e != Emit::Debug);
+        if debug_info {
+            self.emit.push(Emit::Debug);
+        }
+    }
+
+    /// When true, call the module's start function before Glk initialization
+    /// rather than after it. This only matters if the module also exports a
+    /// distinct `glulx_main`.
+    pub fn set_early_start(&mut self, early_start: bool) {
+        self.early_start = early_start;
+    }
+
+    /// When true, skip the bounds check on a memory access whose address comes
+    /// from a local that an earlier access in the same basic block already
+    /// checked at least as far.
+    pub fn set_elide_bounds_checks(&mut self, elide: bool) {
+        self.elide_bounds_checks = elide;
+    }
+
+    /// When true, skip code generation for functions and data which cannot be
+    /// reached from the module's exports or start function. See
+    /// [`eliminate_dead_code`](crate::eliminate_dead_code).
+    pub fn set_eliminate_dead_code(&mut self, eliminate: bool) {
+        self.eliminate_dead_code = eliminate;
+    }
+
+    /// When true, check each generated function against limits of the Glulx
+    /// machine, such as whether its call frame fits on the stack and whether
+    /// its branches and local accesses are in range, and report violations as
+    /// errors.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// When true, [`compile`](crate::compile) fails without writing anything
+    /// if [`check_warnings`](crate::check_warnings) finds anything, reporting
+    /// each warning as a
+    /// [`CompilationError::DeniedWarning`](crate::CompilationError::DeniedWarning).
+    pub fn set_deny_warnings(&mut self, deny: bool) {
+        self.deny_warnings = deny;
+    }
+
+    /// When true, lower `wasi/thread-spawn` imports into a call that runs the
+    /// new thread to completion before returning, rather than rejecting them.
+    pub fn set_inline_thread_spawn(&mut self, inline: bool) {
+        self.inline_thread_spawn = inline;
+    }
+
+    /// When true, accept modules which define a second memory, provided that it
+    /// declares a maximum size, and place it in a region of RAM of its own
+    /// which is reserved at that maximum size. Otherwise, modules with more
+    /// than one memory are rejected.
+    pub fn set_lower_secondary_memory(&mut self, lower: bool) {
+        self.lower_secondary_memory = lower;
+    }
+
+    /// When true, trap on signed overflow in `i32` and `i64` addition,
+    /// subtraction, and multiplication, reporting the function in which it
+    /// occurred. This is a debugging aid and is off by default, since
+    /// WebAssembly arithmetic is defined to wrap.
+    pub fn set_trap_on_overflow(&mut self, trap: bool) {
+        self.trap_on_overflow = trap;
+    }
+
+    /// When true, print a message to the current Glk stream when a trap
+    /// occurs, naming the kind of trap and the function it occurred in, before
+    /// quitting. Otherwise, a trap only executes `debugtrap`, which many
+    /// interpreters ignore, and quits silently.
+    pub fn set_trap_messages(&mut self, trap_messages: bool) {
+        self.trap_messages = trap_messages;
+    }
+
+    /// Set how closely generated code follows WebAssembly's trapping
+    /// semantics. The default is [`Conformance::Standard`].
+    pub fn set_conformance(&mut self, conformance: Conformance) {
+        self.conformance = conformance;
+    }
+
+    /// Set whether to favor smaller or faster code. The default is
+    /// [`OptimizeFor::Size`].
+    pub fn set_optimize_for(&mut self, optimize_for: OptimizeFor) {
+        self.optimize_for = optimize_for;
+    }
+
+    /// Set how many threads to generate functions on. The default, `None`,
+    /// uses one per available core. The output is the same either way.
+    pub fn set_jobs(&mut self, jobs: Option<NonZeroUsize>) {
+        self.jobs = jobs;
+    }
+
+    /// Returns true if repeated bounds checks should be elided, taking the
+    /// conformance level into account.
+    pub(crate) fn elides_bounds_checks(&self) -> bool {
+        self.elide_bounds_checks && self.conformance != Conformance::Spec
+    }
+
+    /// Returns true if signed overflow should trap, taking the conformance
+    /// level into account.
+    pub(crate) fn traps_on_overflow(&self) -> bool {
+        self.trap_on_overflow && self.conformance != Conformance::Spec
+    }
+
+    /// Set the regular expressions naming which function exports to keep. If
+    /// any are given, [`filter_exports`](crate::filter_exports) removes every
+    /// other function export, along with whatever becomes unreachable.
+    pub fn set_export_filter(&mut self, patterns: Vec<String>) {
+        self.export_filter = patterns;
+    }
+
+    /// Set the path of a TOML manifest listing the resources to package
+    /// alongside the story file when emitting [`Emit::Blorb`]. Without one, the
+    /// Blorb file contains only the story file. Reading a manifest requires the
+    /// `blorb-manifest` feature, which is enabled by default.
+    pub fn set_blorb_manifest(&mut self, manifest: Option<PathBuf>) {
+        self.blorb_manifest = manifest;
     }
 
     /// Set the input path.
@@ -138,6 +413,18 @@ impl CompilationOptions {
     }
 }
 
+/// Size of the call stub which a caller pushes before a callee's frame.
+const CALL_STUB_LEN: u64 = 16;
+
+/// Returns the number of stack bytes consumed by calling a function with
+/// `locals` four-byte locals: the call stub, the frame header, the locals
+/// format, and the locals themselves.
+pub fn call_frame_len(locals: u32) -> u64 {
+    let locals = u64::from(locals);
+    let format_len = (2 * locals.div_ceil(255) + 2).next_multiple_of(4);
+    CALL_STUB_LEN + 8 + format_len + 4 * locals
+}
+
 pub fn reject_global_constexpr(ctx: &mut Context, id: GlobalId) {
     match &ctx.module.globals.get(id).kind {
         GlobalKind::Import(id) => ctx.errors.push(CompilationError::UnrecognizedImport(
@@ -218,6 +505,7 @@ pub enum TrapCode {
     UndefinedElement,
     UninitializedElement,
     CallStackExhausted,
+    GlkAreaSizeMismatch,
 }
 
 impl TrapCode {
@@ -232,6 +520,7 @@ impl TrapCode {
         TrapCode::UndefinedElement,
         TrapCode::UninitializedElement,
         TrapCode::CallStackExhausted,
+        TrapCode::GlkAreaSizeMismatch,
     ];
 
     pub fn as_str(self) -> &'static str {
@@ -246,6 +535,7 @@ impl TrapCode {
             TrapCode::UndefinedElement => "undefined element",
             TrapCode::UninitializedElement => "uninitialized element",
             TrapCode::CallStackExhausted => "call stack exhausted",
+            TrapCode::GlkAreaSizeMismatch => "glk area size mismatch",
         }
     }
 }
@@ -263,6 +553,7 @@ impl From<TrapCode> for u32 {
             TrapCode::UndefinedElement => 7,
             TrapCode::UninitializedElement => 8,
             TrapCode::CallStackExhausted => 9,
+            TrapCode::GlkAreaSizeMismatch => 10,
         }
     }
 }
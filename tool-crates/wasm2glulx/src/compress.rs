@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Huffman-compressed strings.
+//!
+//! Strings to be compressed are placed by the program in a custom section
+//! named [`SECTION_NAME`], so that they never occupy linear memory. Each entry
+//! in the section is a little-endian `u32` id, a little-endian `u32` byte
+//! length, and that many bytes of UTF-8. The linker concatenates the entries
+//! from every object file, so the same string may appear more than once.
+//!
+//! Every string is compressed with a single Huffman code built from the
+//! combined character frequencies of all of them, and the `print_compressed`
+//! intrinsic prints a string by id.
+
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
+};
+
+use anyhow::anyhow;
+use bytes::{BufMut, BytesMut};
+use glulx_asm::{concise::*, DecodeNode, LabelRef};
+use walrus::{IdsToIndices, Module};
+
+use crate::{
+    common::{Context, Label},
+    CompilationError,
+};
+
+/// Name of the custom section containing strings to compress.
+pub const SECTION_NAME: &str = "glulx_strings";
+
+/// Collects the strings in every [`SECTION_NAME`] section of `module`, sorted
+/// by id. Repeated entries are merged; an id which is given for two different
+/// strings is an error.
+pub fn collect(module: &Module) -> Result<Vec<(u32, String)>, Vec<CompilationError>> {
+    let ids_to_indices = IdsToIndices::default();
+    let mut strings: BTreeMap<u32, String> = BTreeMap::new();
+
+    for (_, section) in module.customs.iter() {
+        if section.name() != SECTION_NAME {
+            continue;
+        }
+        let data = section.data(&ids_to_indices);
+        let mut rest: &[u8] = &data;
+
+        while !rest.is_empty() {
+            let (Some(id), Some(len)) = (read_u32(&mut rest), read_u32(&mut rest)) else {
+                return Err(vec![CompilationError::ValidationError(anyhow!(
+                    "Truncated entry header in {SECTION_NAME} section"
+                ))]);
+            };
+            let len = usize::try_from(len).unwrap_or(usize::MAX);
+            if len > rest.len() {
+                return Err(vec![CompilationError::ValidationError(anyhow!(
+                    "Entry {id:#x} in {SECTION_NAME} section runs past the end of the section"
+                ))]);
+            }
+            let (bytes, tail) = rest.split_at(len);
+            rest = tail;
+
+            let s = std::str::from_utf8(bytes).map_err(|e| {
+                vec![CompilationError::ValidationError(anyhow!(
+                    "Entry {id:#x} in {SECTION_NAME} section is not valid UTF-8: {e}"
+                ))]
+            })?;
+
+            match strings.get(&id) {
+                Some(existing) if existing != s => {
+                    return Err(vec![CompilationError::ValidationError(anyhow!(
+                        "Entry {id:#x} in {SECTION_NAME} section is given for both {existing:?} and {s:?}"
+                    ))]);
+                }
+                Some(_) => {}
+                None => {
+                    strings.insert(id, s.to_owned());
+                }
+            }
+        }
+    }
+
+    Ok(strings.into_iter().collect())
+}
+
+fn read_u32(rest: &mut &[u8]) -> Option<u32> {
+    let (word, tail) = rest.split_first_chunk::<4>()?;
+    *rest = tail;
+    Some(u32::from_le_bytes(*word))
+}
+
+/// A symbol in the Huffman code: a character, or `None` for the terminator.
+type Symbol = Option<char>;
+
+enum Tree {
+    Leaf(Symbol),
+    Branch(Box<Tree>, Box<Tree>),
+}
+
+/// Builds a Huffman tree over the characters of `strings`, plus one terminator
+/// per string. The root is always a branch, as the decoding table requires.
+fn build_tree(strings: &[(u32, String)]) -> Tree {
+    let mut freqs: BTreeMap<Symbol, u64> = BTreeMap::new();
+    for (_, s) in strings {
+        for ch in s.chars() {
+            *freqs.entry(Some(ch)).or_default() += 1;
+        }
+        *freqs.entry(None).or_default() += 1;
+    }
+
+    // Ties are broken by insertion order so that output is deterministic.
+    let mut nodes: Vec<Option<Tree>> = Vec::new();
+    let mut heap = BinaryHeap::new();
+    for (symbol, freq) in freqs {
+        heap.push(Reverse((freq, nodes.len())));
+        nodes.push(Some(Tree::Leaf(symbol)));
+    }
+
+    while heap.len() > 1 {
+        let Reverse((freq_a, a)) = heap.pop().unwrap();
+        let Reverse((freq_b, b)) = heap.pop().unwrap();
+        let left = nodes[a].take().unwrap();
+        let right = nodes[b].take().unwrap();
+        heap.push(Reverse((freq_a + freq_b, nodes.len())));
+        nodes.push(Some(Tree::Branch(Box::new(left), Box::new(right))));
+    }
+
+    let Reverse((_, root)) = heap.pop().expect("every string has a terminator");
+    match nodes[root].take().unwrap() {
+        Tree::Leaf(symbol) => {
+            Tree::Branch(Box::new(Tree::Leaf(symbol)), Box::new(Tree::Leaf(symbol)))
+        }
+        branch => branch,
+    }
+}
+
+/// Records the code for each leaf of `tree`, as a sequence of branch
+/// directions, and converts `tree` into a decoding table node.
+fn assign_codes(
+    tree: Tree,
+    prefix: &mut Vec<bool>,
+    codes: &mut HashMap<Symbol, Vec<bool>>,
+) -> DecodeNode<Label> {
+    match tree {
+        Tree::Leaf(symbol) => {
+            codes.entry(symbol).or_insert_with(|| prefix.clone());
+            match symbol {
+                None => DecodeNode::StringTerminator,
+                Some(ch) => match u8::try_from(ch) {
+                    Ok(byte) => DecodeNode::MysteryChar(byte),
+                    Err(_) => DecodeNode::UnicodeChar(ch),
+                },
+            }
+        }
+        Tree::Branch(left, right) => {
+            prefix.push(false);
+            let left = assign_codes(*left, prefix, codes);
+            prefix.pop();
+            prefix.push(true);
+            let right = assign_codes(*right, prefix, codes);
+            prefix.pop();
+            DecodeNode::Branch(Box::new(left), Box::new(right))
+        }
+    }
+}
+
+/// Encodes `s` followed by a terminator. Bits are packed starting from the
+/// least significant bit of each byte.
+fn encode(s: &str, codes: &HashMap<Symbol, Vec<bool>>) -> BytesMut {
+    let mut out = BytesMut::new();
+    let mut byte = 0u8;
+    let mut nbits = 0;
+
+    for symbol in s.chars().map(Some).chain([None]) {
+        for &bit in &codes[&symbol] {
+            if bit {
+                byte |= 1 << nbits;
+            }
+            nbits += 1;
+            if nbits == 8 {
+                out.put_u8(byte);
+                byte = 0;
+                nbits = 0;
+            }
+        }
+    }
+    if nbits > 0 {
+        out.put_u8(byte);
+    }
+    out
+}
+
+/// Generates the decoding table, the compressed strings, and the table which
+/// maps ids to strings.
+///
+/// The id table consists of eight-byte entries, each a big-endian id followed
+/// by the address of its string, sorted by id so that it can be searched with
+/// `binarysearch`.
+pub fn gen_compressed_strings(ctx: &mut Context) {
+    let layout = ctx.layout.strings();
+    if layout.entries.is_empty() {
+        return;
+    }
+
+    let mut codes = HashMap::new();
+    let root = assign_codes(build_tree(&layout.entries), &mut Vec::new(), &mut codes);
+
+    push_all!(
+        ctx.rom_items,
+        align(4),
+        label(layout.decoding_table),
+        decoding_table(root)
+    );
+
+    let mut string_labels = Vec::with_capacity(layout.entries.len());
+    for (_, s) in &layout.entries {
+        let string_label = ctx.gen.gen("compressed_string");
+        push_all!(
+            ctx.rom_items,
+            label(string_label),
+            compressed_string(encode(s, &codes))
+        );
+        string_labels.push(string_label);
+    }
+
+    push_all!(ctx.rom_items, align(4), label(layout.id_table));
+    for ((id, _), string_label) in layout.entries.iter().zip(string_labels) {
+        push_all!(
+            ctx.rom_items,
+            blob(id.to_be_bytes().to_vec()),
+            labelref(string_label)
+        );
+    }
+}
+
+/// Returns the decoding table to install at startup, if there is one.
+pub fn initial_decoding_table(ctx: &Context) -> Option<LabelRef<Label>> {
+    let layout = ctx.layout.strings();
+    (!layout.entries.is_empty()).then_some(LabelRef(layout.decoding_table, 0))
+}
+
+pub fn gen_print_compressed(ctx: &mut Context, my_label: Label) {
+    let id = 0;
+
+    let layout = ctx.layout.strings();
+    if layout.entries.is_empty() {
+        ctx.errors.push(CompilationError::ValidationError(anyhow!(
+            "Module imports glulx/print_compressed but has no {SECTION_NAME} section"
+        )));
+        return;
+    }
+    let count = u32::try_from(layout.entries.len()).unwrap_or(u32::MAX);
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(1),
+        binarysearch(
+            lloc(id),
+            imm(4),
+            imml(layout.id_table),
+            imm(8),
+            uimm(count),
+            imm(0),
+            imm(0),
+            sloc(id)
+        ),
+        jz_ret(lloc(id), false),
+        aload(lloc(id), imm(1), push()),
+        streamstr(pop()),
+        ret(imm(0)),
+    );
+}
@@ -0,0 +1,455 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Folding of extended constant expressions.
+//!
+//! Walrus only understands constant expressions which consist of a single
+//! instruction, and refuses to parse modules containing anything longer. So
+//! before handing a module to Walrus, we evaluate every longer expression we
+//! can and splice its value back into the binary as a single `i32.const` or
+//! `i64.const`.
+
+use std::borrow::Cow;
+
+const HEADER_LEN: usize = 8;
+
+const IMPORT_SECTION: u8 = 2;
+const GLOBAL_SECTION: u8 = 6;
+const ELEMENT_SECTION: u8 = 9;
+const DATA_SECTION: u8 = 11;
+
+const IMPORT_KIND_GLOBAL: u8 = 3;
+
+const OP_END: u8 = 0x0b;
+const OP_GLOBAL_GET: u8 = 0x23;
+const OP_I32_CONST: u8 = 0x41;
+const OP_I64_CONST: u8 = 0x42;
+const OP_F32_CONST: u8 = 0x43;
+const OP_F64_CONST: u8 = 0x44;
+const OP_I32_ADD: u8 = 0x6a;
+const OP_I32_SUB: u8 = 0x6b;
+const OP_I32_MUL: u8 = 0x6c;
+const OP_I64_ADD: u8 = 0x7c;
+const OP_I64_SUB: u8 = 0x7d;
+const OP_I64_MUL: u8 = 0x7e;
+const OP_REF_NULL: u8 = 0xd0;
+const OP_REF_FUNC: u8 = 0xd2;
+const OP_SIMD_PREFIX: u8 = 0xfd;
+const SIMD_V128_CONST: u32 = 12;
+
+#[derive(Debug, Copy, Clone)]
+enum Const {
+    I32(i32),
+    I64(i64),
+}
+
+/// Evaluates the extended constant expressions in a module's global
+/// initializers and data and element segment offsets, returning a module in
+/// which each of them is replaced by a single constant.
+///
+/// Expressions may combine `i32` and `i64` constants with `add`, `sub`, and
+/// `mul`, and may read any earlier immutable global whose own initializer is
+/// a constant, so this also resolves plain `global.get` initializers of
+/// globals defined in the module. Expressions which read an imported global
+/// are left alone. If nothing needs folding, or if the module is malformed,
+/// the input is returned unchanged so that parsing it reports the problem.
+///
+/// [`compile`](crate::compile) calls this before parsing its input; callers
+/// of the `compile_module_*` functions which parse modules themselves should
+/// call it on the module's bytes first.
+pub fn fold_constant_exprs(wasm: &[u8]) -> Cow<'_, [u8]> {
+    match fold_module(wasm) {
+        Some(folded) => Cow::Owned(folded),
+        None => Cow::Borrowed(wasm),
+    }
+}
+
+/// Returns the folded module, or `None` if it is malformed or nothing in it
+/// was folded.
+fn fold_module(wasm: &[u8]) -> Option<Vec<u8>> {
+    let (header, mut rest) = wasm.split_at_checked(HEADER_LEN)?;
+    let mut out = header.to_vec();
+    let mut globals: Vec<Option<Const>> = Vec::new();
+    let mut changed = false;
+
+    while !rest.is_empty() {
+        let id = read_byte(&mut rest)?;
+        let len = usize::try_from(read_u32(&mut rest)?).ok()?;
+        let contents = read_bytes(&mut rest, len)?;
+
+        let folded = match id {
+            IMPORT_SECTION => {
+                scan_imports(contents, &mut globals)?;
+                None
+            }
+            GLOBAL_SECTION => fold_globals(contents, &mut globals)?,
+            ELEMENT_SECTION => fold_elements(contents, &globals)?,
+            DATA_SECTION => fold_datas(contents, &globals)?,
+            _ => None,
+        };
+
+        out.push(id);
+        match folded {
+            Some(folded) => {
+                changed = true;
+                write_u32(&mut out, u32::try_from(folded.len()).ok()?);
+                out.extend_from_slice(&folded);
+            }
+            None => {
+                write_u32(&mut out, u32::try_from(len).ok()?);
+                out.extend_from_slice(contents);
+            }
+        }
+    }
+
+    changed.then_some(out)
+}
+
+/// Adds an unknown value to `globals` for each imported global.
+fn scan_imports(mut rest: &[u8], globals: &mut Vec<Option<Const>>) -> Option<()> {
+    let count = read_u32(&mut rest)?;
+    for _ in 0..count {
+        skip_name(&mut rest)?;
+        skip_name(&mut rest)?;
+        match read_byte(&mut rest)? {
+            0x00 => {
+                read_u32(&mut rest)?;
+            }
+            0x01 => {
+                skip_ref_type(&mut rest)?;
+                skip_limits(&mut rest)?;
+            }
+            0x02 => {
+                skip_limits(&mut rest)?;
+            }
+            IMPORT_KIND_GLOBAL => {
+                skip_val_type(&mut rest)?;
+                read_byte(&mut rest)?;
+                globals.push(None);
+            }
+            0x04 => {
+                read_byte(&mut rest)?;
+                read_u32(&mut rest)?;
+            }
+            _ => return None,
+        }
+    }
+    Some(())
+}
+
+/// Folds each global's initializer and records its value in `globals`.
+fn fold_globals(mut rest: &[u8], globals: &mut Vec<Option<Const>>) -> Option<Option<Vec<u8>>> {
+    let mut out = Vec::new();
+    let mut changed = false;
+
+    let count = read_u32(&mut rest)?;
+    write_u32(&mut out, count);
+    for _ in 0..count {
+        let start = rest;
+        skip_val_type(&mut rest)?;
+        let mutable = read_byte(&mut rest)? != 0;
+        out.extend_from_slice(consumed(start, rest));
+
+        let (value, folded) = fold_expr(&mut rest, globals, &mut out)?;
+        changed |= folded;
+        // Only immutable globals may appear in a constant expression, so
+        // there is no point remembering the value of a mutable one.
+        globals.push(if mutable { None } else { value });
+    }
+
+    Some(changed.then_some(out))
+}
+
+/// Folds the offset of each active element segment.
+fn fold_elements(mut rest: &[u8], globals: &[Option<Const>]) -> Option<Option<Vec<u8>>> {
+    let mut out = Vec::new();
+    let mut changed = false;
+
+    let count = read_u32(&mut rest)?;
+    write_u32(&mut out, count);
+    for _ in 0..count {
+        let flags = read_u32(&mut rest)?;
+        write_u32(&mut out, flags);
+        if flags > 7 {
+            return None;
+        }
+        let active = flags & 1 == 0;
+        let explicit_table = flags & 2 != 0;
+        let uses_exprs = flags & 4 != 0;
+
+        if active && explicit_table {
+            write_u32(&mut out, read_u32(&mut rest)?);
+        }
+        if active {
+            let (_, folded) = fold_expr(&mut rest, globals, &mut out)?;
+            changed |= folded;
+        }
+
+        let start = rest;
+        if !active || explicit_table {
+            if uses_exprs {
+                skip_ref_type(&mut rest)?;
+            } else {
+                read_byte(&mut rest)?;
+            }
+        }
+        let items = read_u32(&mut rest)?;
+        for _ in 0..items {
+            if uses_exprs {
+                skip_expr(&mut rest)?;
+            } else {
+                read_u32(&mut rest)?;
+            }
+        }
+        out.extend_from_slice(consumed(start, rest));
+    }
+
+    Some(changed.then_some(out))
+}
+
+/// Folds the offset of each active data segment.
+fn fold_datas(mut rest: &[u8], globals: &[Option<Const>]) -> Option<Option<Vec<u8>>> {
+    let mut out = Vec::new();
+    let mut changed = false;
+
+    let count = read_u32(&mut rest)?;
+    write_u32(&mut out, count);
+    for _ in 0..count {
+        let flags = read_u32(&mut rest)?;
+        write_u32(&mut out, flags);
+        match flags {
+            0 | 1 => {}
+            2 => write_u32(&mut out, read_u32(&mut rest)?),
+            _ => return None,
+        }
+        if flags != 1 {
+            let (_, folded) = fold_expr(&mut rest, globals, &mut out)?;
+            changed |= folded;
+        }
+
+        let start = rest;
+        let len = usize::try_from(read_u32(&mut rest)?).ok()?;
+        read_bytes(&mut rest, len)?;
+        out.extend_from_slice(consumed(start, rest));
+    }
+
+    Some(changed.then_some(out))
+}
+
+/// Reads a constant expression and writes it to `out`, replacing it with a
+/// single constant if it is longer than that and its value is known. Returns
+/// the expression's value if it is known, and whether it was replaced.
+fn fold_expr(
+    rest: &mut &[u8],
+    globals: &[Option<Const>],
+    out: &mut Vec<u8>,
+) -> Option<(Option<Const>, bool)> {
+    let start = *rest;
+    let mut stack: Vec<Option<Const>> = Vec::new();
+    let mut simple = true;
+
+    loop {
+        let op = read_byte(rest)?;
+        let value = match op {
+            OP_END => break,
+            OP_I32_CONST => Some(Const::I32(read_i32(rest)?)),
+            OP_I64_CONST => Some(Const::I64(read_i64(rest)?)),
+            OP_F32_CONST => {
+                read_bytes(rest, 4)?;
+                None
+            }
+            OP_F64_CONST => {
+                read_bytes(rest, 8)?;
+                None
+            }
+            OP_SIMD_PREFIX => {
+                if read_u32(rest)? != SIMD_V128_CONST {
+                    return None;
+                }
+                read_bytes(rest, 16)?;
+                None
+            }
+            OP_GLOBAL_GET => {
+                simple = false;
+                let index = usize::try_from(read_u32(rest)?).ok()?;
+                globals.get(index).copied().flatten()
+            }
+            OP_REF_NULL => {
+                read_i64(rest)?;
+                None
+            }
+            OP_REF_FUNC => {
+                read_u32(rest)?;
+                None
+            }
+            OP_I32_ADD | OP_I32_SUB | OP_I32_MUL | OP_I64_ADD | OP_I64_SUB | OP_I64_MUL => {
+                simple = false;
+                let y = stack.pop()?;
+                let x = stack.pop()?;
+                match (op, x, y) {
+                    (OP_I32_ADD, Some(Const::I32(x)), Some(Const::I32(y))) => {
+                        Some(Const::I32(x.wrapping_add(y)))
+                    }
+                    (OP_I32_SUB, Some(Const::I32(x)), Some(Const::I32(y))) => {
+                        Some(Const::I32(x.wrapping_sub(y)))
+                    }
+                    (OP_I32_MUL, Some(Const::I32(x)), Some(Const::I32(y))) => {
+                        Some(Const::I32(x.wrapping_mul(y)))
+                    }
+                    (OP_I64_ADD, Some(Const::I64(x)), Some(Const::I64(y))) => {
+                        Some(Const::I64(x.wrapping_add(y)))
+                    }
+                    (OP_I64_SUB, Some(Const::I64(x)), Some(Const::I64(y))) => {
+                        Some(Const::I64(x.wrapping_sub(y)))
+                    }
+                    (OP_I64_MUL, Some(Const::I64(x)), Some(Const::I64(y))) => {
+                        Some(Const::I64(x.wrapping_mul(y)))
+                    }
+                    _ => None,
+                }
+            }
+            _ => return None,
+        };
+        stack.push(value);
+    }
+
+    let value = match stack[..] {
+        [value] => value,
+        _ => return None,
+    };
+
+    match value {
+        Some(Const::I32(x)) if !simple => {
+            out.push(OP_I32_CONST);
+            write_i64(out, x.into());
+        }
+        Some(Const::I64(x)) if !simple => {
+            out.push(OP_I64_CONST);
+            write_i64(out, x);
+        }
+        _ => {
+            out.extend_from_slice(consumed(start, rest));
+            return Some((value, false));
+        }
+    }
+    out.push(OP_END);
+    Some((value, true))
+}
+
+fn skip_expr(rest: &mut &[u8]) -> Option<()> {
+    fold_expr(rest, &[], &mut Vec::new()).map(|_| ())
+}
+
+fn skip_name(rest: &mut &[u8]) -> Option<()> {
+    let len = usize::try_from(read_u32(rest)?).ok()?;
+    read_bytes(rest, len).map(|_| ())
+}
+
+fn skip_val_type(rest: &mut &[u8]) -> Option<()> {
+    match read_byte(rest)? {
+        // (ref ht) and (ref null ht), which are followed by a heap type
+        0x63 | 0x64 => read_i64(rest).map(|_| ()),
+        _ => Some(()),
+    }
+}
+
+fn skip_ref_type(rest: &mut &[u8]) -> Option<()> {
+    skip_val_type(rest)
+}
+
+fn skip_limits(rest: &mut &[u8]) -> Option<()> {
+    let flags = read_byte(rest)?;
+    read_u64(rest)?;
+    if flags & 1 != 0 {
+        read_u64(rest)?;
+    }
+    // Custom page size
+    if flags & 8 != 0 {
+        read_u32(rest)?;
+    }
+    Some(())
+}
+
+/// Returns the part of `start` which has been read to arrive at `rest`.
+fn consumed<'a>(start: &'a [u8], rest: &[u8]) -> &'a [u8] {
+    &start[..start.len() - rest.len()]
+}
+
+fn read_byte(rest: &mut &[u8]) -> Option<u8> {
+    let (byte, tail) = rest.split_first()?;
+    *rest = tail;
+    Some(*byte)
+}
+
+fn read_bytes<'a>(rest: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    let (bytes, tail) = rest.split_at_checked(len)?;
+    *rest = tail;
+    Some(bytes)
+}
+
+fn read_u64(rest: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = read_byte(rest)?;
+        if shift >= 64 {
+            return None;
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+}
+
+fn read_u32(rest: &mut &[u8]) -> Option<u32> {
+    u32::try_from(read_u64(rest)?).ok()
+}
+
+fn read_i64(rest: &mut &[u8]) -> Option<i64> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let byte = read_byte(rest)?;
+        if shift >= 64 {
+            return None;
+        }
+        value |= i64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                value |= -1 << shift;
+            }
+            return Some(value);
+        }
+    }
+}
+
+fn read_i32(rest: &mut &[u8]) -> Option<i32> {
+    i32::try_from(read_i64(rest)?).ok()
+}
+
+fn write_u32(out: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_i64(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
@@ -8,7 +8,7 @@ use walrus::{ir::Value, ConstExpr, ElementKind, GlobalKind};
 
 use crate::{
     common::{reject_global_constexpr, Context, Label, TrapCode},
-    CompilationError, OverflowLocation,
+    CompilationError,
 };
 
 pub fn gen_tables(ctx: &mut Context) {
@@ -22,13 +22,10 @@ pub fn gen_tables(ctx: &mut Context) {
 
         let table_layout = ctx.layout.table(table.id());
 
-        let size = if let Some(size) = table_layout.max_count.checked_mul(4) {
-            size
-        } else {
-            ctx.errors
-                .push(CompilationError::Overflow(OverflowLocation::Table));
-            continue;
-        };
+        let size = table_layout
+            .max_count
+            .checked_mul(4)
+            .expect("Layout should have rejected tables too large to address");
 
         ctx.zero_items.push(zlabel(table_layout.addr));
         ctx.zero_items.push(zspace(size));
@@ -116,13 +113,18 @@ pub fn gen_globals(ctx: &mut Context) {
 
 pub fn gen_elems(ctx: &mut Context) {
     for elem in ctx.module.elements.iter() {
+        let layout = ctx.layout.element(elem.id());
+        ctx.rom_items.push(label(layout.addr));
+
+        // Declarative segments are dropped when the module is instantiated, so
+        // their contents are never needed, but `elem.drop` and `table.init`
+        // may still refer to them.
         if matches!(elem.kind, ElementKind::Declared) {
+            ctx.ram_items.push(label(layout.cur_count));
+            ctx.ram_items.push(blob(Vec::from(0u32.to_be_bytes())));
             continue;
         }
 
-        let layout = ctx.layout.element(elem.id());
-        ctx.rom_items.push(label(layout.addr));
-
         match &elem.items {
             walrus::ElementItems::Functions(v) => {
                 for id in v {
@@ -157,6 +159,7 @@ pub fn gen_elems(ctx: &mut Context) {
 pub fn gen_datas(ctx: &mut Context) {
     for data in ctx.module.data.iter() {
         let layout = ctx.layout.data(data.id());
+        ctx.rom_items.push(align(4));
         ctx.rom_items.push(label(layout.addr));
         ctx.rom_items.push(blob(data.value.clone()));
         ctx.ram_items.push(label(layout.cur_size));
@@ -180,6 +183,12 @@ pub fn gen_trap(ctx: &mut Context) {
         ctx.rom_items.push(label(*l));
         ctx.rom_items.push(mystery_string(&code.as_str()));
     }
+
+    if ctx.options.trap_messages {
+        ctx.zero_items.push(zalign(4));
+        ctx.zero_items.push(zlabel(ctx.layout.trap().location));
+        ctx.zero_items.push(zspace(4));
+    }
 }
 
 pub fn gen_hi_return(ctx: &mut Context) {
@@ -193,7 +202,34 @@ pub fn gen_glk_area(ctx: &mut Context) {
     ctx.zero_items.push(zspace(ctx.layout.glk_area().size));
 }
 
+pub fn gen_externref_handles(ctx: &mut Context) {
+    let Some(handles) = ctx.layout.externref() else {
+        return;
+    };
+
+    ctx.zero_items.push(zalign(4));
+    ctx.zero_items.push(zlabel(handles.free));
+    ctx.zero_items.push(zspace(4));
+    ctx.zero_items.push(zlabel(handles.used));
+    ctx.zero_items.push(zspace(4));
+    ctx.zero_items.push(zlabel(handles.addr));
+    ctx.zero_items.push(zspace(handles.capacity * 4));
+}
+
 pub fn gen_memory(ctx: &mut Context) {
+    // The secondary memory comes first, since the primary memory has to be at
+    // the very end of RAM in order to grow.
+    if let Some(mem) = ctx.layout.secondary_memory() {
+        let mut bytes = BytesMut::with_capacity(4);
+        bytes.put_u32(mem.min_size);
+
+        ctx.ram_items.push(label(mem.cur_size));
+        ctx.ram_items.push(blob(bytes));
+        ctx.zero_items.push(zalign(4));
+        ctx.zero_items.push(zlabel(mem.addr));
+        ctx.zero_items.push(zspace(mem.max_size));
+    }
+
     let mut bytes = BytesMut::with_capacity(4);
     let mem = ctx.layout.memory();
     bytes.put_u32(mem.min_size);
@@ -217,5 +253,6 @@ pub fn gen_data(ctx: &mut Context) {
     gen_datas(ctx);
     gen_hi_return(ctx);
     gen_glk_area(ctx);
+    gen_externref_handles(ctx);
     gen_memory(ctx);
 }
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Dead-code elimination.
+
+use walrus::{ElementId, ElementKind, Module};
+
+use crate::CompilationOptions;
+
+/// If [`CompilationOptions::set_eliminate_dead_code`] is enabled, removes
+/// every function, global, table, data and element segment, and type which
+/// cannot be reached from the module's exports or start function, so that no
+/// code is generated for them.
+///
+/// Active data segments are always kept, since they initialize memory which
+/// might be reached indirectly. Active element segments are kept, along with
+/// the functions they refer to, only if reachable code uses their table.
+/// Declarative element segments are dropped: they only permit `ref.func` to
+/// name a function, which wasm2glulx doesn't check, and a function which
+/// reachable code does take a reference to is kept through that reference.
+/// Unreachable imports are removed too, so an unused import that wasm2glulx
+/// does not recognize is no longer an error.
+///
+/// [`compile`](crate::compile) calls this before compiling; callers of the
+/// `compile_module_*` functions which want dead code eliminated must call it
+/// themselves.
+pub fn eliminate_dead_code(options: &CompilationOptions, module: &mut Module) {
+    if !options.eliminate_dead_code {
+        return;
+    }
+
+    // Walrus treats declarative segments as roots, which would keep every
+    // function they name alive.
+    let declared: Vec<ElementId> = module
+        .elements
+        .iter()
+        .filter(|elem| matches!(elem.kind, ElementKind::Declared))
+        .map(|elem| elem.id())
+        .collect();
+    for id in declared {
+        module.elements.delete(id);
+    }
+
+    walrus::passes::gc::run(module);
+}
@@ -0,0 +1,343 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Generation of debug information files.
+//!
+//! The output follows the XML format of the `gameinfo.dbg` files which Inform
+//! generates, as read by glulxe's debugger. Only routines and source files are
+//! described: each function becomes a `<routine>` with its Glulx address and
+//! size, and, if the input module has DWARF information for it, the file and
+//! line where it was declared. Within each routine, a `<sequence-point>` maps
+//! the address of the code generated for a WASM instruction to the source line
+//! which DWARF's line table gives for that instruction. All addresses are
+//! absolute.
+
+use std::{collections::HashMap, fmt::Write};
+
+use bytes::BytesMut;
+use gimli::{AttributeValue, EndianSlice, LittleEndian};
+use walrus::Module;
+
+use crate::common::{Context, Label};
+
+/// A line in a source file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct SourceLocation {
+    file_index: usize,
+    line: u64,
+}
+
+/// Source information recovered from the input module's DWARF sections.
+#[derive(Default)]
+struct SourceInfo {
+    files: Vec<String>,
+    file_indices: HashMap<String, usize>,
+    /// Where each function was declared.
+    functions: HashMap<String, SourceLocation>,
+    /// Rows of the line table, sorted by code section offset. A row with no
+    /// location ends a sequence, and covers offsets up to the next row.
+    lines: Vec<(u64, Option<SourceLocation>)>,
+}
+
+impl SourceInfo {
+    fn file_index(&mut self, path: String) -> usize {
+        *self.file_indices.entry(path).or_insert_with_key(|path| {
+            self.files.push(path.clone());
+            self.files.len() - 1
+        })
+    }
+
+    /// Returns the source line of the instruction at `offset` in the code
+    /// section.
+    fn line_at(&self, offset: u64) -> Option<SourceLocation> {
+        let row = self
+            .lines
+            .partition_point(|(address, _)| *address <= offset);
+        self.lines.get(row.checked_sub(1)?)?.1
+    }
+}
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// Collects the declaration site of every subprogram in `module`'s DWARF,
+/// keyed by linkage name if it has one, or by plain name otherwise, and the
+/// rows of every line table. This is best-effort: malformed DWARF just yields
+/// less information.
+fn source_info(module: &Module) -> SourceInfo {
+    let mut info = SourceInfo::default();
+    let _ = collect_source_info(module, &mut info);
+    info.lines.sort_by_key(|(address, _)| *address);
+    info
+}
+
+fn collect_source_info(module: &Module, info: &mut SourceInfo) -> gimli::Result<()> {
+    let dwarf = module
+        .debug
+        .dwarf
+        .borrow(|section| EndianSlice::new(section, LittleEndian));
+
+    let mut headers = dwarf.units();
+    while let Some(header) = headers.next()? {
+        let unit = dwarf.unit(header)?;
+        collect_lines(&dwarf, &unit, info)?;
+
+        let mut entries = unit.entries();
+        while let Some((_, entry)) = entries.next_dfs()? {
+            if entry.tag() != gimli::DW_TAG_subprogram {
+                continue;
+            }
+            let Some(name) = entry
+                .attr_value(gimli::DW_AT_linkage_name)?
+                .or(entry.attr_value(gimli::DW_AT_name)?)
+            else {
+                continue;
+            };
+            let (Some(AttributeValue::FileIndex(file)), Some(line)) = (
+                entry.attr_value(gimli::DW_AT_decl_file)?,
+                entry
+                    .attr_value(gimli::DW_AT_decl_line)?
+                    .and_then(|line| line.udata_value()),
+            ) else {
+                continue;
+            };
+            let Some(path) = file_path(&dwarf, &unit, file)? else {
+                continue;
+            };
+
+            let name = dwarf
+                .attr_string(&unit, name)?
+                .to_string_lossy()
+                .into_owned();
+            let file_index = info.file_index(path);
+            info.functions
+                .entry(name)
+                .or_insert(SourceLocation { file_index, line });
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the rows of `unit`'s line table to `info.lines`.
+fn collect_lines(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    info: &mut SourceInfo,
+) -> gimli::Result<()> {
+    let Some(program) = unit.line_program.clone() else {
+        return Ok(());
+    };
+    let mut paths: HashMap<u64, Option<usize>> = HashMap::new();
+    let mut rows = program.rows();
+    while let Some((_, row)) = rows.next_row()? {
+        if row.end_sequence() {
+            info.lines.push((row.address(), None));
+            continue;
+        }
+        let Some(line) = row.line() else {
+            continue;
+        };
+        let file_index = match paths.get(&row.file_index()) {
+            Some(index) => *index,
+            None => {
+                let index =
+                    file_path(dwarf, unit, row.file_index())?.map(|path| info.file_index(path));
+                paths.insert(row.file_index(), index);
+                index
+            }
+        };
+        if let Some(file_index) = file_index {
+            info.lines.push((
+                row.address(),
+                Some(SourceLocation {
+                    file_index,
+                    line: line.get(),
+                }),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the path of the `index`th file in `unit`'s line program, joined to
+/// its directory if it is relative.
+fn file_path(
+    dwarf: &gimli::Dwarf<Reader<'_>>,
+    unit: &gimli::Unit<Reader<'_>>,
+    index: u64,
+) -> gimli::Result<Option<String>> {
+    let Some(program) = &unit.line_program else {
+        return Ok(None);
+    };
+    let header = program.header();
+    let Some(file) = header.file(index) else {
+        return Ok(None);
+    };
+
+    let mut path = dwarf
+        .attr_string(unit, file.path_name())?
+        .to_string_lossy()
+        .into_owned();
+    if !path.starts_with('/') {
+        if let Some(dir) = file.directory(header) {
+            let dir = dwarf.attr_string(unit, dir)?.to_string_lossy().into_owned();
+            if !dir.is_empty() {
+                path = format!("{}/{path}", dir.trim_end_matches('/'));
+            }
+        }
+    }
+    Ok(Some(path))
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Returns the offset of the code section in the input module, which DWARF
+/// addresses are relative to, but instruction locations are not.
+fn code_section_offset(module: &Module) -> Option<u32> {
+    module.funcs.iter_local().find_map(|(_, function)| {
+        let (offset, loc) = function.instruction_mapping.first()?;
+        loc.data().checked_sub(u32::try_from(*offset).ok()?)
+    })
+}
+
+/// Renders a debug information file for `story`, given the address of every
+/// label in its assembly, sorted by address.
+pub fn render_debug_info(ctx: &Context, labels: &[(Label, u32)], story: &[u8]) -> BytesMut {
+    let info = source_info(ctx.module);
+    let addrs: HashMap<Label, u32> = labels.iter().copied().collect();
+
+    // Each sequence point's address, with the source line of the instruction
+    // it was placed before, sorted by address.
+    let mut points: Vec<(u32, SourceLocation)> = Vec::new();
+    if let Some(code_offset) = code_section_offset(ctx.module) {
+        for (label, loc) in ctx.sequence_points.iter() {
+            let (Some(addr), Some(offset)) =
+                (addrs.get(label), loc.data().checked_sub(code_offset))
+            else {
+                continue;
+            };
+            if let Some(location) = info.line_at(offset.into()) {
+                points.push((*addr, location));
+            }
+        }
+    }
+    points.sort_by_key(|(addr, _)| *addr);
+
+    let mut routines: Vec<(u32, u32, String)> = ctx
+        .module
+        .functions()
+        .filter_map(|function| {
+            let fn_layout = ctx.layout.func(function.id());
+            let start = *addrs.get(&fn_layout.addr)?;
+            let end = addrs.get(&fn_layout.end).copied().unwrap_or(start);
+            let name = function
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("<function {}>", function.id().index()));
+            Some((start, end, name))
+        })
+        .collect();
+    routines.sort_by_key(|(start, _, _)| *start);
+
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(
+        out,
+        r#"<inform-story-file version="1.0" content-creator="wasm2glulx" content-creator-version="{}">"#,
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "<story-file-prefix>{}</story-file-prefix>",
+        base64(&story[..story.len().min(64)])
+    )
+    .unwrap();
+
+    for (index, path) in info.files.iter().enumerate() {
+        writeln!(
+            out,
+            r#"<source index="{index}"><given-path>{}</given-path></source>"#,
+            xml_escape(path)
+        )
+        .unwrap();
+    }
+
+    for (start, end, name) in &routines {
+        writeln!(out, "<routine>").unwrap();
+        writeln!(out, "<identifier>{}</identifier>", xml_escape(name)).unwrap();
+        writeln!(out, "<address>{start}</address>").unwrap();
+        writeln!(
+            out,
+            "<byte-count>{}</byte-count>",
+            end.saturating_sub(*start)
+        )
+        .unwrap();
+        if let Some(location) = info.functions.get(name) {
+            writeln!(out, "{}", source_code_location(location)).unwrap();
+        }
+
+        // Consecutive points on the same line add nothing.
+        let first = points.partition_point(|(addr, _)| addr < start);
+        let last = points.partition_point(|(addr, _)| addr < end);
+        let mut previous = None;
+        for (addr, location) in &points[first..last] {
+            if previous == Some(location) {
+                continue;
+            }
+            previous = Some(location);
+            writeln!(
+                out,
+                "<sequence-point><address>{addr}</address>{}</sequence-point>",
+                source_code_location(location)
+            )
+            .unwrap();
+        }
+
+        writeln!(out, "</routine>").unwrap();
+    }
+
+    writeln!(out, "</inform-story-file>").unwrap();
+    BytesMut::from(out.as_bytes())
+}
+
+fn source_code_location(location: &SourceLocation) -> String {
+    format!(
+        "<source-code-location><file-index>{}</file-index><line>{}</line></source-code-location>",
+        location.file_index, location.line
+    )
+}
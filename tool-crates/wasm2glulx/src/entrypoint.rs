@@ -2,7 +2,7 @@
 // Copyright 2024 Daniel Fox Franke.
 
 use glulx_asm::concise::*;
-use walrus::{ir::Value, ConstExpr, DataKind, ElementKind};
+use walrus::{ir::Value, ConstExpr, DataKind, ElementKind, ExportItem, ValType};
 
 use crate::{
     common::{reject_global_constexpr, Context},
@@ -48,11 +48,16 @@ pub fn gen_entrypoint(ctx: &mut Context) {
 
     for data in ctx.module.data.iter() {
         if let DataKind::Active {
-            memory: _,
+            memory,
             offset: offset_expr,
         } = &data.kind
         {
             let data_layout = ctx.layout.data(data.id());
+            let memory_init = if ctx.layout.is_secondary_memory(*memory) {
+                ctx.rt.secondary_memory.memory_init
+            } else {
+                ctx.rt.memory_init
+            };
             let mem_offset = match offset_expr {
                 ConstExpr::Value(Value::I32(offset)) => *offset,
                 ConstExpr::Global(id) => {
@@ -69,12 +74,103 @@ pub fn gen_entrypoint(ctx: &mut Context) {
                 copy(derefl(data_layout.cur_size), push()),
                 copy(imml(data_layout.addr), push()),
                 copy(derefl(data_layout.cur_size), push()),
-                call(imml(ctx.rt.memory_init), imm(5), discard()),
+                call(imml(memory_init), imm(5), discard()),
                 copy(imm(0), storel(data_layout.cur_size)),
             );
         }
     }
 
+    gen_glk_area_check(ctx);
+
+    // If the module has both a start function and a distinct `glulx_main`,
+    // the start function runs after Glk initialization unless it's been
+    // asked to run early. Otherwise, whichever one exists is the entrypoint
+    // and runs last.
+    let glulx_main = ctx.module.exports.get_func("glulx_main").ok();
+    let (start, main) = match (ctx.module.start, glulx_main) {
+        (Some(start), Some(glulx_main)) if start != glulx_main => (Some(start), glulx_main),
+        (Some(start), _) => (None, start),
+        (None, Some(glulx_main)) => (None, glulx_main),
+        (None, None) => {
+            ctx.errors.push(CompilationError::NoEntrypoint);
+            return;
+        }
+    };
+
+    if let Some(start) = start {
+        if ctx.options.early_start {
+            let start_addr = ctx.layout.func(start).addr;
+            ctx.rom_items.push(callf(imml(start_addr), discard()));
+        }
+    }
+
+    gen_glk_init(ctx);
+
+    if let Some(start) = start {
+        if !ctx.options.early_start {
+            let start_addr = ctx.layout.func(start).addr;
+            ctx.rom_items.push(callf(imml(start_addr), discard()));
+        }
+    }
+
+    if Some(main) == glulx_main {
+        let glulx_main_ty = ctx.module.types.get(ctx.module.funcs.get(main).ty());
+        if !glulx_main_ty.params().is_empty() || !glulx_main_ty.results().is_empty() {
+            ctx.errors.push(CompilationError::IncorrectlyTypedExport {
+                export: ctx.module.exports.get_exported_func(main).unwrap().clone(),
+                expected: (Vec::new(), Vec::new()),
+                actual: (
+                    glulx_main_ty.params().to_owned(),
+                    glulx_main_ty.results().to_owned(),
+                ),
+            });
+        }
+    }
+
+    let main_addr = ctx.layout.func(main).addr;
+    ctx.rom_items.push(callf(imml(main_addr), discard()));
+    ctx.rom_items.push(ret(imm(0)));
+}
+
+/// Traps if the module exports `glulx_expected_glkarea_size` and the `u32` it
+/// points to differs from the configured Glk area size.
+fn gen_glk_area_check(ctx: &mut Context) {
+    let Some(global) = ctx
+        .module
+        .exports
+        .iter()
+        .find_map(|export| match export.item {
+            ExportItem::Global(id) if export.name == "glulx_expected_glkarea_size" => Some(id),
+            _ => None,
+        })
+    else {
+        return;
+    };
+
+    if ctx.module.globals.get(global).ty != ValType::I32 {
+        ctx.errors
+            .push(CompilationError::ValidationError(anyhow::anyhow!(
+                "Exported global glulx_expected_glkarea_size must have type i32"
+            )));
+        return;
+    }
+
+    // The global holds the address of the expected size, which is what a Rust
+    // `static` exported from a cdylib compiles to.
+    let global_addr = ctx.layout.global(global).addr;
+    push_all!(
+        ctx.rom_items,
+        callfii(imml(ctx.rt.memload32), imm(0), derefl(global_addr), push()),
+        jne(
+            pop(),
+            uimm(ctx.layout.glk_area().size),
+            ctx.rt.trap_glk_area_size_mismatch
+        ),
+    );
+}
+
+/// Registers `glulx_interrupt_handler` with Glk, if the module exports one.
+fn gen_glk_init(ctx: &mut Context) {
     if let Ok(interrupt_handler) = ctx.module.exports.get_func("glulx_interrupt_handler") {
         let ty = ctx
             .module
@@ -104,66 +200,4 @@ pub fn gen_entrypoint(ctx: &mut Context) {
             ),
         );
     }
-
-    match (
-        ctx.module.start,
-        ctx.module.exports.get_func("glulx_main").ok(),
-    ) {
-        (Some(start), Some(glulx_main)) if start != glulx_main => {
-            let glulx_main_ty = ctx.module.types.get(ctx.module.funcs.get(glulx_main).ty());
-            if !glulx_main_ty.params().is_empty() || !glulx_main_ty.results().is_empty() {
-                ctx.errors.push(CompilationError::IncorrectlyTypedExport {
-                    export: ctx
-                        .module
-                        .exports
-                        .get_exported_func(glulx_main)
-                        .unwrap()
-                        .clone(),
-                    expected: (Vec::new(), Vec::new()),
-                    actual: (
-                        glulx_main_ty.params().to_owned(),
-                        glulx_main_ty.results().to_owned(),
-                    ),
-                });
-            }
-
-            let start_addr = ctx.layout.func(start).addr;
-            let glulx_main_addr = ctx.layout.func(glulx_main).addr;
-            push_all!(
-                ctx.rom_items,
-                call(imml(start_addr), imm(0), discard()),
-                callf(imml(glulx_main_addr), discard()),
-                ret(imm(0)),
-            );
-        }
-        (Some(start), _) => {
-            let start_addr = ctx.layout.func(start).addr;
-            ctx.rom_items.push(callf(imml(start_addr), discard()));
-            ctx.rom_items.push(ret(imm(0)));
-        }
-        (None, Some(glulx_main)) => {
-            let glulx_main_ty = ctx.module.types.get(ctx.module.funcs.get(glulx_main).ty());
-            if !glulx_main_ty.params().is_empty() || !glulx_main_ty.results().is_empty() {
-                ctx.errors.push(CompilationError::IncorrectlyTypedExport {
-                    export: ctx
-                        .module
-                        .exports
-                        .get_exported_func(glulx_main)
-                        .unwrap()
-                        .clone(),
-                    expected: (Vec::new(), Vec::new()),
-                    actual: (
-                        glulx_main_ty.params().to_owned(),
-                        glulx_main_ty.results().to_owned(),
-                    ),
-                });
-            }
-            let glulx_main_addr = ctx.layout.func(glulx_main).addr;
-            ctx.rom_items.push(callf(imml(glulx_main_addr), discard()));
-            ctx.rom_items.push(ret(imm(0)));
-        }
-        (None, None) => {
-            ctx.errors.push(CompilationError::NoEntrypoint);
-        }
-    }
 }
@@ -2,8 +2,11 @@
 // Copyright 2024 Daniel Fox Franke.
 
 use std::fmt::Display;
+use std::path::PathBuf;
 use walrus::{Export, Import, ValType};
 
+use crate::DEFAULT_GLK_AREA_SIZE;
+
 /// Error indicating why a compilation was unsuccessful.
 #[derive(Debug)]
 pub enum CompilationError {
@@ -11,6 +14,14 @@ pub enum CompilationError {
     ValidationError(anyhow::Error),
     /// The module imports an unrecognized object
     UnrecognizedImport(Import),
+    /// The module imports a function which spawns threads
+    ThreadSpawn {
+        /// The thread-spawning import
+        import: Import,
+        /// The names of the functions which might call it, directly or
+        /// indirectly
+        callers: Vec<String>,
+    },
     /// The module declares an incorrect type for an imported function
     IncorrectlyTypedImport {
         /// The erroneous import
@@ -35,6 +46,11 @@ pub enum CompilationError {
     Overflow(OverflowLocation),
     /// The module uses multiple memories
     UnsupportedMultipleMemories,
+    /// A second memory was to be lowered, but it doesn't declare a maximum
+    /// size
+    UnboundedSecondaryMemory,
+    /// The module defines a memory with 64-bit addresses
+    UnsupportedMemory64,
     /// The module contains an unsupported instruction
     UnsupportedInstruction {
         /// The name of the function containing the unsupported instruction
@@ -42,14 +58,55 @@ pub enum CompilationError {
         /// The instruction's mnemonic
         instr: &'static str,
     },
+    /// Strict mode found generated code that exceeds a limit of the Glulx
+    /// machine
+    GlulxLimitExceeded {
+        /// The name of the function containing the offending code
+        function: Option<String>,
+        /// Description of the limit that was exceeded
+        reason: String,
+    },
     /// The was an I/O error reading the input
     InputError(std::io::Error),
     /// There was an I/O error writing the output
     OutputError(std::io::Error),
+    /// A Blorb manifest or one of the resources it names could not be
+    /// packaged
+    BlorbError(anyhow::Error),
+    /// A warning was found and warnings were to be treated as errors
+    DeniedWarning(CompilationWarning),
     /// Other, unclassified error
     OtherError(anyhow::Error),
 }
 
+/// Something which doesn't prevent compilation but is probably a mistake.
+#[derive(Debug, Clone)]
+pub enum CompilationWarning {
+    /// An exported function can't be reached from the entrypoint, so it will
+    /// never run
+    UnreachableExport {
+        /// The export's name
+        name: String,
+    },
+    /// A table reserves a great deal of space to grow into, because neither
+    /// the table's declared maximum nor the table growth limit is small
+    TableGrowth {
+        /// The table's index within the module
+        index: usize,
+        /// How many entries the table can grow by
+        entries: u32,
+    },
+    /// None of the functions imported from a module are used
+    UnusedImportNamespace {
+        /// The name of the module imported from
+        module: String,
+        /// The names of the functions imported from it
+        names: Vec<String>,
+    },
+    /// Assembly text is to be written to a path named like a story file
+    TextToBinaryPath(PathBuf),
+}
+
 /// The location of what caused an overflow error.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum OverflowLocation {
@@ -63,14 +120,51 @@ pub enum OverflowLocation {
     Locals(Option<String>),
     /// Too large a stack in named function
     Stack(Option<String>),
+    /// A call to named function needs more stack than `--stack-size` provides
+    Frame {
+        /// The function's name
+        function: Option<String>,
+        /// How many bytes of stack each call needs, before the function pushes
+        /// anything
+        bytes: u64,
+        /// The size of the stack
+        stack_size: u32,
+    },
     /// Table too large
-    Table,
+    Table {
+        /// The table's index within the module
+        index: usize,
+        /// How many entries the table would hold
+        entries: u64,
+    },
+    /// Too many branch targets in a `br_table` in named function
+    BrTable(Option<String>),
     /// Element segment too large
-    Element,
+    Element {
+        /// The segment's index within the module
+        index: usize,
+        /// How many entries the segment contains
+        entries: usize,
+    },
     /// Data segment too large
-    Data,
-    /// Initial memory size too large
-    Memory,
+    Data {
+        /// The segment's index within the module
+        index: usize,
+        /// How many bytes the segment contains
+        bytes: usize,
+    },
+    /// Memory size too large
+    Memory {
+        /// The size of the memory, in 64KiB pages
+        pages: u64,
+    },
+    /// Memory, tables, segments and the Glk area too large taken together
+    Total {
+        /// How many bytes they need
+        bytes: u64,
+        /// How many of those bytes are the Glk area
+        glk_area_size: u32,
+    },
     /// Final assembled output too large
     FinalAssembly,
 }
@@ -164,16 +258,90 @@ impl Display for CompilationError {
                     OverflowLocation::Stack(Some(name)) => {
                         write!(f, "The stack used by the function `{}` ", name)?
                     }
-                    OverflowLocation::Table => write!(f, "A table declaration ")?,
-                    OverflowLocation::Element => write!(f, "An element segment ")?,
-                    OverflowLocation::Data => write!(f, "A data segment ")?,
-                    OverflowLocation::Memory => write!(f, "The program memory ")?,
+                    OverflowLocation::Frame {
+                        function: None,
+                        bytes,
+                        ..
+                    } => write!(f, "The call frame of an unnamed function ({} bytes) ", bytes)?,
+                    OverflowLocation::Frame {
+                        function: Some(name),
+                        bytes,
+                        ..
+                    } => write!(
+                        f,
+                        "The call frame of the function `{}` ({} bytes) ",
+                        name, bytes
+                    )?,
+                    OverflowLocation::Table { index, entries } => {
+                        write!(f, "Table {} ({} entries) ", index, entries)?
+                    }
+                    OverflowLocation::BrTable(None) => {
+                        write!(f, "A br_table instruction in an unnamed function ")?
+                    }
+                    OverflowLocation::BrTable(Some(name)) => write!(
+                        f,
+                        "A br_table instruction in the function `{}` ",
+                        name
+                    )?,
+                    OverflowLocation::Element { index, entries } => {
+                        write!(f, "Element segment {} ({} entries) ", index, entries)?
+                    }
+                    OverflowLocation::Data { index, bytes } => {
+                        write!(f, "Data segment {} ({} bytes) ", index, bytes)?
+                    }
+                    OverflowLocation::Memory { pages } => {
+                        write!(f, "The program memory ({} pages) ", pages)?
+                    }
+                    OverflowLocation::Total { bytes, .. } => write!(
+                        f,
+                        "The total size of the module's memory, tables, and segments, plus the Glk area ({} bytes), ",
+                        bytes
+                    )?,
                     OverflowLocation::FinalAssembly => write!(f, "The assembled output ")?,
                 }
-                write!(f, "overflows Glulx's 4GiB address space")?;
+                match loc {
+                    OverflowLocation::Frame {
+                        bytes, stack_size, ..
+                    } => write!(
+                        f,
+                        "overflows the {}-byte stack. Try a --stack-size of at least {} bytes.",
+                        stack_size, bytes
+                    )?,
+                    _ => write!(f, "overflows Glulx's 4GiB address space")?,
+                }
+                // Only suggest shrinking the Glk area if it has been enlarged
+                // past the default and shrinking it back would be enough.
+                if let OverflowLocation::Total {
+                    bytes,
+                    glk_area_size,
+                } = loc
+                {
+                    let rest = bytes - u64::from(*glk_area_size);
+                    let room = u64::from(u32::MAX).saturating_sub(rest);
+                    if *glk_area_size > DEFAULT_GLK_AREA_SIZE
+                        && room >= u64::from(DEFAULT_GLK_AREA_SIZE)
+                    {
+                        write!(f, ". Try a --glk-area-size of at most {} bytes.", room)?;
+                    }
+                }
             }
             CompilationError::UnsupportedMultipleMemories => {
-                write!(f, "Modules that define multiple memories are not supported")?;
+                write!(
+                    f,
+                    "Modules that define more than one memory are not supported, except that --lower-secondary-memory allows a second one which declares a maximum size"
+                )?;
+            }
+            CompilationError::UnboundedSecondaryMemory => {
+                write!(
+                    f,
+                    "The module's second memory must declare a maximum size in order to be placed in a separate region of RAM"
+                )?;
+            }
+            CompilationError::UnsupportedMemory64 => {
+                write!(
+                    f,
+                    "Memories with 64-bit addresses are not supported, since Glulx is a 32-bit machine"
+                )?;
             }
             CompilationError::UnsupportedInstruction { function, instr } => {
                 if let Some(function) = function {
@@ -190,12 +358,39 @@ impl Display for CompilationError {
                     )?
                 }
             }
+            CompilationError::ThreadSpawn { import, callers } => {
+                write!(
+                    f,
+                    "The module imports {}/{}, but Glulx cannot run threads",
+                    import.module, import.name
+                )?;
+                if !callers.is_empty() {
+                    write!(f, " (called from {})", callers.join(", "))?;
+                }
+                write!(
+                    f,
+                    ". Pass --inline-thread-spawn to run spawned threads to completion when they are spawned."
+                )?;
+            }
+            CompilationError::GlulxLimitExceeded { function, reason } => {
+                if let Some(function) = function {
+                    write!(f, "In function {}: {}", function, reason)?
+                } else {
+                    write!(f, "In an unnamed function: {}", reason)?
+                }
+            }
             CompilationError::InputError(e) => {
                 write!(f, "While reading input: {}", e)?;
             }
             CompilationError::OutputError(e) => {
                 write!(f, "While writing output: {}", e)?;
             }
+            CompilationError::BlorbError(e) => {
+                write!(f, "While packaging Blorb file: {:#}", e)?;
+            }
+            CompilationError::DeniedWarning(w) => {
+                write!(f, "{} (denied by --deny-warnings)", w)?;
+            }
             CompilationError::OtherError(e) => {
                 write!(f, "{}", e)?;
             }
@@ -205,3 +400,33 @@ impl Display for CompilationError {
 }
 
 impl std::error::Error for CompilationError {}
+
+impl Display for CompilationWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompilationWarning::UnreachableExport { name } => write!(
+                f,
+                "The exported function `{}` is never called from the entrypoint, so it will never run",
+                name
+            ),
+            CompilationWarning::TableGrowth { index, entries } => write!(
+                f,
+                "Table {} reserves {} bytes of RAM so that it can grow by {} entries. Pass a smaller --table-growth-limit if it doesn't need to.",
+                index,
+                u64::from(*entries) * 4,
+                entries
+            ),
+            CompilationWarning::UnusedImportNamespace { module, names } => write!(
+                f,
+                "None of the functions imported from {} are used: {}",
+                module,
+                names.join(", ")
+            ),
+            CompilationWarning::TextToBinaryPath(path) => write!(
+                f,
+                "Writing assembly text to {}, which is named like a story file",
+                path.display()
+            ),
+        }
+    }
+}
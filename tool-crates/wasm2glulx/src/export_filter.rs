@@ -0,0 +1,66 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Removal of unwanted function exports.
+
+use anyhow::anyhow;
+use regex::RegexSet;
+use walrus::{ExportItem, Module};
+
+use crate::{CompilationError, CompilationOptions};
+
+/// Exports which wasm2glulx itself looks for, and which are therefore never
+/// removed.
+pub(crate) const RESERVED_EXPORTS: &[&str] =
+    &["glulx_main", "glulx_interrupt_handler", "wasi_thread_start"];
+
+/// Removes every function export whose name matches none of the patterns set
+/// with [`CompilationOptions::set_export_filter`], and then removes any
+/// functions, globals, tables, data and element segments, and types which are
+/// no longer reachable. Does nothing if no patterns are set.
+///
+/// Each pattern is a regular expression which must match the whole export
+/// name, so a plain name matches only itself. Exports which wasm2glulx uses,
+/// such as `glulx_main`, are always kept, as are exports other than
+/// functions.
+///
+/// [`compile`](crate::compile) calls this before compiling; callers of the
+/// `compile_module_*` functions which want filtering must call it
+/// themselves.
+pub fn filter_exports(
+    options: &CompilationOptions,
+    module: &mut Module,
+) -> Result<(), Vec<CompilationError>> {
+    if options.export_filter.is_empty() {
+        return Ok(());
+    }
+
+    let patterns = RegexSet::new(
+        options
+            .export_filter
+            .iter()
+            .map(|pattern| format!("^(?:{pattern})$")),
+    )
+    .map_err(|e| {
+        vec![CompilationError::OtherError(anyhow!(
+            "Invalid export filter: {e}"
+        ))]
+    })?;
+
+    let unwanted: Vec<_> = module
+        .exports
+        .iter()
+        .filter(|export| {
+            matches!(export.item, ExportItem::Function(_))
+                && !RESERVED_EXPORTS.contains(&export.name.as_str())
+                && !patterns.is_match(&export.name)
+        })
+        .map(|export| export.id())
+        .collect();
+
+    for id in unwanted {
+        module.exports.delete(id);
+    }
+    walrus::passes::gc::run(module);
+    Ok(())
+}
@@ -0,0 +1,184 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Support status of WebAssembly feature proposals.
+
+use std::fmt::{Display, Write};
+
+use serde::Serialize;
+
+/// How Wasm2Glulx treats modules which use a feature proposal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureStatus {
+    /// The feature is fully implemented.
+    Supported,
+    /// The feature is accepted, but translated into something weaker than the
+    /// proposal specifies. The feature's note says how.
+    Lowered,
+    /// Some of the feature's instructions are implemented and the rest are
+    /// rejected. The feature's note says which.
+    Partial,
+    /// Modules which use the feature fail to compile.
+    Rejected,
+}
+
+impl FeatureStatus {
+    /// Returns the status as a lowercase string.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FeatureStatus::Supported => "supported",
+            FeatureStatus::Lowered => "lowered",
+            FeatureStatus::Partial => "partial",
+            FeatureStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl Display for FeatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A WebAssembly feature proposal and its support status.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct WasmFeature {
+    /// The proposal's name.
+    pub name: &'static str,
+    /// The corresponding rustc target feature for `wasm32` targets, if there
+    /// is one.
+    pub rustc_feature: Option<&'static str>,
+    /// How Wasm2Glulx treats the feature.
+    pub status: FeatureStatus,
+    /// Further detail on the status, if any.
+    pub note: Option<&'static str>,
+}
+
+/// Every feature proposal that Wasm2Glulx knows about.
+pub const WASM_FEATURES: &[WasmFeature] = &[
+    WasmFeature {
+        name: "mutable-globals",
+        rustc_feature: Some("mutable-globals"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "sign-extension-ops",
+        rustc_feature: Some("sign-ext"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "nontrapping-float-to-int",
+        rustc_feature: Some("nontrapping-fptoint"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "multi-value",
+        rustc_feature: Some("multivalue"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "bulk-memory",
+        rustc_feature: Some("bulk-memory"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "reference-types",
+        rustc_feature: Some("reference-types"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "branch-hinting",
+        rustc_feature: None,
+        status: FeatureStatus::Lowered,
+        note: Some("hints are accepted and ignored"),
+    },
+    WasmFeature {
+        name: "threads",
+        rustc_feature: Some("atomics"),
+        status: FeatureStatus::Partial,
+        note: Some(
+            "atomic loads and stores are accepted as ordinary ones, but read-modify-write, wait, and notify instructions are rejected",
+        ),
+    },
+    WasmFeature {
+        name: "simd",
+        rustc_feature: Some("simd128"),
+        status: FeatureStatus::Partial,
+        note: Some(
+            "full-width loads and stores, bitwise operations, i8x16/i16x8/i32x4 addition, subtraction, negation, and multiplication, splats, lane accesses, swizzles, and shuffles are lowered to scalar code, but other instructions are rejected",
+        ),
+    },
+    WasmFeature {
+        name: "relaxed-simd",
+        rustc_feature: Some("relaxed-simd"),
+        status: FeatureStatus::Rejected,
+        note: None,
+    },
+    WasmFeature {
+        name: "tail-call",
+        rustc_feature: Some("tail-call"),
+        status: FeatureStatus::Supported,
+        note: None,
+    },
+    WasmFeature {
+        name: "extended-const",
+        rustc_feature: Some("extended-const"),
+        status: FeatureStatus::Supported,
+        note: Some("expressions are evaluated at compile time"),
+    },
+    WasmFeature {
+        name: "exception-handling",
+        rustc_feature: Some("exception-handling"),
+        status: FeatureStatus::Rejected,
+        note: None,
+    },
+    WasmFeature {
+        name: "multi-memory",
+        rustc_feature: Some("multimemory"),
+        status: FeatureStatus::Rejected,
+        note: Some("--lower-secondary-memory accepts a second memory which declares a maximum size"),
+    },
+    WasmFeature {
+        name: "memory64",
+        rustc_feature: None,
+        status: FeatureStatus::Rejected,
+        note: Some("Glulx is a 32-bit machine"),
+    },
+    WasmFeature {
+        name: "gc",
+        rustc_feature: None,
+        status: FeatureStatus::Rejected,
+        note: None,
+    },
+];
+
+/// Renders [`WASM_FEATURES`] as a human-readable table.
+pub fn features_list() -> String {
+    let mut out = String::new();
+    for feature in WASM_FEATURES {
+        write!(out, "{:<26} {:<10}", feature.name, feature.status).unwrap();
+        if let Some(rustc_feature) = feature.rustc_feature {
+            write!(out, " (+{rustc_feature})").unwrap();
+        }
+        if let Some(note) = feature.note {
+            write!(out, " -- {note}").unwrap();
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Renders [`WASM_FEATURES`] as a JSON array of objects.
+pub fn features_json() -> String {
+    let mut out = serde_json::to_string_pretty(WASM_FEATURES)
+        .expect("feature table should serialize to JSON");
+    out.push('\n');
+    out
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Extension points for third-party passes.
+
+use glulx_asm::{Item, ZeroItem};
+use walrus::{Function, Module};
+
+use crate::common::{Label, LabelGenerator};
+
+/// Mutable view of the compiler's output, passed to each [`Hooks`] callback.
+#[derive(Debug)]
+pub struct HookContext<'a> {
+    /// The module being compiled.
+    pub module: &'a Module,
+    /// Items which will be placed in ROM.
+    pub rom_items: &'a mut Vec<Item<Label>>,
+    /// Items which will be placed in RAM.
+    pub ram_items: &'a mut Vec<Item<Label>>,
+    /// Items which will be placed in zero-initialized RAM.
+    pub zero_items: &'a mut Vec<ZeroItem<Label>>,
+    gen: &'a mut LabelGenerator,
+}
+
+impl<'a> HookContext<'a> {
+    pub(crate) fn new(
+        module: &'a Module,
+        gen: &'a mut LabelGenerator,
+        rom_items: &'a mut Vec<Item<Label>>,
+        ram_items: &'a mut Vec<Item<Label>>,
+        zero_items: &'a mut Vec<ZeroItem<Label>>,
+    ) -> Self {
+        HookContext {
+            module,
+            rom_items,
+            ram_items,
+            zero_items,
+            gen,
+        }
+    }
+
+    /// Generate a fresh label, distinct from every other label in the
+    /// compilation. `desc` is used only for display.
+    pub fn gen_label(&mut self, desc: &'static str) -> Label {
+        self.gen.gen(desc)
+    }
+}
+
+/// Callbacks invoked at fixed points during compilation.
+///
+/// Every method has a default no-op implementation, so implementors need only
+/// override the ones they care about. `()` implements this trait with no
+/// overrides.
+///
+/// Items pushed or modified by a hook are assembled along with everything
+/// else, so a hook which breaks the compiler's invariants (for instance by
+/// separating a function's type number from its header) will produce a broken
+/// story file.
+pub trait Hooks {
+    /// Called once layout has been computed, before any items are generated.
+    fn after_layout(&mut self, _ctx: &mut HookContext<'_>) {}
+
+    /// Called before any items for `function` are added to `ctx.rom_items`.
+    ///
+    /// Functions are generated in parallel before any of these callbacks run,
+    /// and their items are then added one function at a time, in module
+    /// order. Labels generated by a hook still come before those of the
+    /// functions which follow it.
+    fn before_function(&mut self, _ctx: &mut HookContext<'_>, _function: &Function) {}
+
+    /// Called after all items for `function` have been added. Those items
+    /// occupy `ctx.rom_items[start..]`, beginning with the function's type
+    /// number.
+    fn after_function(&mut self, _ctx: &mut HookContext<'_>, _function: &Function, _start: usize) {}
+
+    /// Called after all items have been generated, immediately before
+    /// assembly.
+    fn before_assembly(&mut self, _ctx: &mut HookContext<'_>) {}
+}
+
+impl Hooks for () {}
@@ -2,9 +2,9 @@
 // Copyright 2024 Daniel Fox Franke.
 
 use glulx_asm::concise::*;
-use walrus::{ImportedFunction, ValType};
+use walrus::{ImportedFunction, RefType, TableId, ValType};
 
-use crate::common::{Context, Label};
+use crate::common::{Conformance, Context, Label};
 
 fn check_intrinsic_type(ctx: &mut Context, imported_func: &ImportedFunction) -> bool {
     let import = ctx.module.imports.get(imported_func.import);
@@ -28,13 +28,19 @@ fn check_intrinsic_type(ctx: &mut Context, imported_func: &ImportedFunction) ->
 
     let (expected_params, expected_results): (&[ValType], &[ValType]) = match name.as_str() {
         "restart" | "discardundo" => (&[], &[]),
-        "glkarea_size" => (&[], &[ValType::I32]),
+        "glkarea_size" | "saveundo" | "restoreundo" | "hasundo" | "stack_remaining" => {
+            (&[], &[ValType::I32])
+        }
         "random" | "glkarea_get_byte" | "glkarea_get_word" | "save" | "restore" => {
             (&[ValType::I32], &[ValType::I32])
         }
-        "setrandom" | "glkarea_put_byte" | "glkarea_put_word" | "saveundo" | "restoreundo"
-        | "hasundo" => (&[ValType::I32], &[]),
-        "protect" => (&[ValType::I32, ValType::I32], &[]),
+        "setrandom" | "glkarea_put_byte" | "glkarea_put_word" => (&[ValType::I32], &[]),
+        "protect" | "accelfunc" | "accelparam" | "throw" => (&[ValType::I32, ValType::I32], &[]),
+        "catch" => (&[ValType::I32, ValType::I32], &[ValType::I32]),
+        "print_compressed" => (&[ValType::I32], &[]),
+        "externref_new" => (&[ValType::I32], &[ValType::Ref(RefType::Externref)]),
+        "externref_get" => (&[ValType::Ref(RefType::Externref)], &[ValType::I32]),
+        "externref_drop" => (&[ValType::Ref(RefType::Externref)], &[]),
         "gesalt" => (&[ValType::I32, ValType::I32], &[ValType::I32]),
         "glkarea_get_bytes" | "glkarea_put_bytes" | "glkarea_get_words" | "glkarea_put_words" => {
             (&[ValType::I32, ValType::I32, ValType::I32], &[])
@@ -292,6 +298,80 @@ fn gen_glkarea_size(ctx: &mut Context, my_label: Label) {
     )
 }
 
+fn gen_externref_new(ctx: &mut Context, my_label: Label) {
+    let value = 0;
+    let handle = 1;
+
+    let handles = *ctx
+        .layout
+        .externref()
+        .expect("Modules importing externref intrinsics should have a handle table");
+    let fresh = ctx.gen.gen("externref_new_fresh");
+    let found = ctx.gen.gen("externref_new_found");
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(2),
+        jz(derefl(handles.free), fresh),
+        copy(derefl(handles.free), sloc(handle)),
+        sub(lloc(handle), imm(1), push()),
+        aload(imml(handles.addr), pop(), storel(handles.free)),
+        jump(found),
+        label(fresh),
+        jgeu(
+            derefl(handles.used),
+            uimm(handles.capacity),
+            ctx.rt.trap_out_of_bounds_table_access
+        ),
+        add(derefl(handles.used), imm(1), storel(handles.used)),
+        copy(derefl(handles.used), sloc(handle)),
+        label(found),
+        sub(lloc(handle), imm(1), push()),
+        astore(imml(handles.addr), pop(), lloc(value)),
+        ret(lloc(handle)),
+    );
+}
+
+fn gen_externref_get(ctx: &mut Context, my_label: Label) {
+    let handle = 0;
+
+    let handles = *ctx
+        .layout
+        .externref()
+        .expect("Modules importing externref intrinsics should have a handle table");
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(1),
+        jz_ret(lloc(handle), false),
+        sub(lloc(handle), imm(1), push()),
+        aload(imml(handles.addr), pop(), push()),
+        ret(pop()),
+    );
+}
+
+fn gen_externref_drop(ctx: &mut Context, my_label: Label) {
+    let handle = 0;
+
+    let handles = *ctx
+        .layout
+        .externref()
+        .expect("Modules importing externref intrinsics should have a handle table");
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(1),
+        jz_ret(lloc(handle), false),
+        sub(lloc(handle), imm(1), push()),
+        astore(imml(handles.addr), pop(), derefl(handles.free)),
+        copy(lloc(handle), storel(handles.free)),
+        ret(imm(0)),
+    );
+}
+
 pub fn gen_random(ctx: &mut Context, my_label: Label) {
     let arg = 0;
 
@@ -316,6 +396,15 @@ pub fn gen_setrandom(ctx: &mut Context, my_label: Label) {
     )
 }
 
+pub fn gen_stack_remaining(ctx: &mut Context, my_label: Label) {
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(0),
+        tailcall(imml(ctx.rt.stack_remaining), imm(0))
+    )
+}
+
 pub fn gen_fmodf(ctx: &mut Context, my_label: Label) {
     let x = 1;
     let y = 0;
@@ -737,7 +826,7 @@ pub fn gen_restore(ctx: &mut Context, my_label: Label) {
         ctx.rom_items,
         label(my_label),
         fnhead_local(1),
-        save(lloc(stream), push()),
+        restore(lloc(stream), push()),
         ret(pop()),
     );
 }
@@ -803,6 +892,158 @@ pub fn gen_protect(ctx: &mut Context, my_label: Label) {
     );
 }
 
+/// Returns the module's only function table, which intrinsics that take a
+/// function pointer index into, or records an error naming `intrinsic` if
+/// there isn't exactly one.
+fn main_function_table(ctx: &mut Context, intrinsic: &str) -> Option<TableId> {
+    match ctx.module.tables.main_function_table() {
+        Ok(Some(table)) => Some(table),
+        Ok(None) => {
+            ctx.errors
+                .push(crate::CompilationError::ValidationError(anyhow::anyhow!(
+                    "Module imports glulx/{intrinsic} but has no function table"
+                )));
+            None
+        }
+        Err(_) => {
+            ctx.errors
+                .push(crate::CompilationError::ValidationError(anyhow::anyhow!(
+                    "Module imports glulx/{intrinsic} but has more than one function table"
+                )));
+            None
+        }
+    }
+}
+
+pub fn gen_accelfunc(ctx: &mut Context, my_label: Label) {
+    let number = 1;
+    let func = 0;
+
+    let Some(table) = main_function_table(ctx, "accelfunc") else {
+        return;
+    };
+    let table_addr = ctx.layout.table(table).addr;
+    let table_count = ctx.layout.table(table).cur_count;
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(2),
+        jgeu(
+            lloc(func),
+            derefl(table_count),
+            ctx.rt.trap_undefined_element
+        ),
+        aload(imml(table_addr), lloc(func), sloc(func)),
+        jz(lloc(func), ctx.rt.trap_uninitialized_element),
+        accelfunc(lloc(number), lloc(func)),
+        ret(imm(0)),
+    );
+}
+
+pub fn gen_catch(ctx: &mut Context, my_label: Label) {
+    let func = 1;
+    let arg = 0;
+    let token = 2;
+    let saved_token = 3;
+
+    let Some(table) = main_function_table(ctx, "catch") else {
+        return;
+    };
+    let table_addr = ctx.layout.table(table).addr;
+    let table_count = ctx.layout.table(table).cur_count;
+    let body = ctx.gen.gen("catch_body");
+
+    // If the module has no function of type (i32, i32) -> i32 then no table
+    // entry can pass the type check, so any typenum that isn't in use will do.
+    let typenum = ctx
+        .module
+        .types
+        .find(&[ValType::I32, ValType::I32], &[ValType::I32])
+        .map_or(0, |ty| ctx.layout.ty(ty).typenum);
+
+    // Under --stack-guard, the runtime keeps the innermost active catch
+    // token so that a failed guard has somewhere to throw to.
+    let track_token = ctx.options.stack_guard.is_some();
+
+    push_all!(ctx.rom_items, label(my_label), fnhead_local(4));
+    if track_token {
+        ctx.rom_items
+            .push(copy(derefl(ctx.rt.catch_token), sloc(saved_token)));
+    }
+    ctx.rom_items.push(catch(sloc(token), body));
+    // Reached only by a throw, which leaves its value in the token local.
+    if track_token {
+        ctx.rom_items
+            .push(copy(lloc(saved_token), storel(ctx.rt.catch_token)));
+    }
+    push_all!(
+        ctx.rom_items,
+        ret(lloc(token)),
+        label(body),
+        jgeu(
+            lloc(func),
+            derefl(table_count),
+            ctx.rt.trap_undefined_element
+        ),
+        aload(imml(table_addr), lloc(func), sloc(func)),
+        jz(lloc(func), ctx.rt.trap_uninitialized_element),
+    );
+
+    if ctx.options.conformance != Conformance::Fast {
+        push_all!(
+            ctx.rom_items,
+            aload(lloc(func), imm(-1), push()),
+            jne(
+                pop(),
+                uimm(typenum),
+                ctx.rt.trap_indirect_call_type_mismatch
+            ),
+        );
+    }
+
+    if track_token {
+        ctx.rom_items
+            .push(copy(lloc(token), storel(ctx.rt.catch_token)));
+    }
+    push_all!(
+        ctx.rom_items,
+        copy(lloc(token), push()),
+        copy(lloc(arg), push()),
+        call(lloc(func), uimm(2), push()),
+    );
+    if track_token {
+        ctx.rom_items
+            .push(copy(lloc(saved_token), storel(ctx.rt.catch_token)));
+    }
+    ctx.rom_items.push(ret(pop()));
+}
+
+pub fn gen_throw(ctx: &mut Context, my_label: Label) {
+    let token = 1;
+    let value = 0;
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(2),
+        throw(lloc(value), lloc(token)),
+    );
+}
+
+pub fn gen_accelparam(ctx: &mut Context, my_label: Label) {
+    let index = 1;
+    let value = 0;
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(2),
+        accelparam(lloc(index), lloc(value)),
+        ret(imm(0)),
+    );
+}
+
 pub fn gen_gestalt(ctx: &mut Context, my_label: Label) {
     let number = 1;
     let extra = 0;
@@ -820,7 +1061,9 @@ pub fn gen_intrinsic(ctx: &mut Context, imported_func: &ImportedFunction, my_lab
     let import = ctx.module.imports.get(imported_func.import);
     let name = &import.name;
 
-    if check_intrinsic_type(ctx, imported_func) {
+    if name.starts_with("raw_") {
+        crate::raw::gen_raw(ctx, imported_func, my_label);
+    } else if check_intrinsic_type(ctx, imported_func) {
         match name.as_str() {
             #[cfg(feature = "spectest")]
             "spectest_result" => gen_spectest_result(ctx, imported_func, my_label),
@@ -835,6 +1078,7 @@ pub fn gen_intrinsic(ctx: &mut Context, imported_func: &ImportedFunction, my_lab
             "glkarea_size" => gen_glkarea_size(ctx, my_label),
             "random" => gen_random(ctx, my_label),
             "setrandom" => gen_setrandom(ctx, my_label),
+            "stack_remaining" => gen_stack_remaining(ctx, my_label),
             "fmodf" => gen_fmodf(ctx, my_label),
             "floorf" => gen_floorf(ctx, my_label),
             "ceilf" => gen_ceilf(ctx, my_label),
@@ -870,6 +1114,14 @@ pub fn gen_intrinsic(ctx: &mut Context, imported_func: &ImportedFunction, my_lab
             "discardundo" => gen_discardundo(ctx, my_label),
             "protect" => gen_protect(ctx, my_label),
             "gestalt" => gen_gestalt(ctx, my_label),
+            "accelfunc" => gen_accelfunc(ctx, my_label),
+            "accelparam" => gen_accelparam(ctx, my_label),
+            "catch" => gen_catch(ctx, my_label),
+            "throw" => gen_throw(ctx, my_label),
+            "print_compressed" => crate::compress::gen_print_compressed(ctx, my_label),
+            "externref_new" => gen_externref_new(ctx, my_label),
+            "externref_get" => gen_externref_get(ctx, my_label),
+            "externref_drop" => gen_externref_drop(ctx, my_label),
             _ => unreachable!(
                 "Unrecognized intrinsic function should have returned false from type check"
             ),
@@ -2,8 +2,10 @@
 // Copyright 2024 Daniel Fox Franke.
 
 use crate::{common::*, CompilationError, CompilationOptions, OverflowLocation};
-use std::collections::HashMap;
-use walrus::{DataId, ElementId, ElementItems, FunctionId, GlobalId, Module, TableId, TypeId};
+use std::collections::{HashMap, HashSet};
+use walrus::{
+    DataId, ElementId, ElementItems, FunctionId, GlobalId, MemoryId, Module, TableId, TypeId,
+};
 
 #[derive(Debug, Copy, Clone)]
 pub struct TypeLayout {
@@ -13,6 +15,10 @@ pub struct TypeLayout {
 #[derive(Debug, Copy, Clone)]
 pub struct FnLayout {
     pub addr: Label,
+    /// Placed after the function's code when emitting debug information.
+    pub end: Label,
+    /// Whether the function should check for stack exhaustion on entry.
+    pub stack_guard: bool,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -57,6 +63,20 @@ pub struct GlkLayout {
     pub size: u32,
 }
 
+/// The table behind the `externref_*` intrinsics. A non-null `externref` is a
+/// 1-based index into it.
+#[derive(Debug, Copy, Clone)]
+pub struct ExternrefLayout {
+    /// Array of `capacity` words. A live handle's entry holds its value, and a
+    /// free handle's entry holds the next free handle, or 0.
+    pub addr: Label,
+    pub capacity: u32,
+    /// RAM word holding the most recently freed handle, or 0.
+    pub free: Label,
+    /// RAM word holding the number of handles which have ever been allocated.
+    pub used: Label,
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct HiReturnLayout {
     pub addr: Label,
@@ -66,6 +86,16 @@ pub struct HiReturnLayout {
 #[derive(Debug, Copy, Clone)]
 pub struct TrapLayout {
     pub string_table: Label,
+    /// RAM word holding the address of a string describing the function which
+    /// is currently executing, or 0. Only maintained with `--trap-messages`.
+    pub location: Label,
+}
+
+#[derive(Debug, Clone)]
+pub struct StringsLayout {
+    pub decoding_table: Label,
+    pub id_table: Label,
+    pub entries: Vec<(u32, String)>,
 }
 
 #[derive(Debug, Clone)]
@@ -77,14 +107,20 @@ pub struct Layout {
     elems: HashMap<ElementId, ElemLayout>,
     datas: HashMap<DataId, DataLayout>,
     mem: MemLayout,
+    secondary_mem: Option<(MemoryId, MemLayout)>,
     glk_area: GlkLayout,
+    externref: Option<ExternrefLayout>,
     hi_return: HiReturnLayout,
     entrypoint: Label,
     trap: TrapLayout,
+    strings: StringsLayout,
 }
 
 const MIN_HI_RETURN_WORDS: usize = 4;
 
+/// Number of live `externref` handles a program may hold at once.
+const EXTERNREF_HANDLES: u32 = 1024;
+
 impl Layout {
     pub fn new(
         options: &CompilationOptions,
@@ -112,19 +148,44 @@ where {
             types.insert(t.id(), TypeLayout { typenum });
         }
 
+        let recursive = if options.stack_guard.is_some() {
+            crate::recursion::recursive_functions(module)
+        } else {
+            HashSet::new()
+        };
+
         for f in module.funcs.iter() {
             let addr = gen.gen("function");
-            funcs.insert(f.id(), FnLayout { addr });
+            let end = gen.gen("function_end");
+            let stack_guard = recursive.contains(&f.id());
+            funcs.insert(
+                f.id(),
+                FnLayout {
+                    addr,
+                    end,
+                    stack_guard,
+                },
+            );
         }
 
-        for t in module.tables.iter() {
+        for (index, t) in module.tables.iter().enumerate() {
             let min_count = u32::try_from(t.initial).unwrap_or_else(|_| {
-                errors.push(CompilationError::Overflow(OverflowLocation::Table));
+                errors.push(CompilationError::Overflow(OverflowLocation::Table {
+                    index,
+                    entries: t.initial,
+                }));
                 0
             });
-            let max_count = u32::try_from(t.maximum.unwrap_or(u64::MAX))
+            let mut max_count = u32::try_from(t.maximum.unwrap_or(u64::MAX))
                 .unwrap_or(u32::MAX)
                 .min(min_count.saturating_add(options.table_growth_limit));
+            if max_count.checked_mul(4).is_none() {
+                errors.push(CompilationError::Overflow(OverflowLocation::Table {
+                    index,
+                    entries: max_count.into(),
+                }));
+                max_count = 0;
+            }
             let addr = gen.gen("table_addr");
             let cur_count = gen.gen("table_cur_count");
             tables.insert(
@@ -144,7 +205,7 @@ where {
             globals.insert(g.id(), GlobalLayout { addr, words });
         }
 
-        for e in module.elements.iter() {
+        for (index, e) in module.elements.iter().enumerate() {
             let addr = gen.gen("element");
             let cur_count = gen.gen("element_count");
             let count_usize = match &e.items {
@@ -152,7 +213,10 @@ where {
                 ElementItems::Expressions(_, v) => v.len(),
             };
             let initial_count = u32::try_from(count_usize).unwrap_or_else(|_| {
-                errors.push(CompilationError::Overflow(OverflowLocation::Element));
+                errors.push(CompilationError::Overflow(OverflowLocation::Element {
+                    index,
+                    entries: count_usize,
+                }));
                 0
             });
             elems.insert(
@@ -165,11 +229,14 @@ where {
             );
         }
 
-        for d in module.data.iter() {
+        for (index, d) in module.data.iter().enumerate() {
             let addr = gen.gen("data");
             let cur_size = gen.gen("data_size");
             let initial_size = u32::try_from(d.value.len()).unwrap_or_else(|_| {
-                errors.push(CompilationError::Overflow(OverflowLocation::Data));
+                errors.push(CompilationError::Overflow(OverflowLocation::Data {
+                    index,
+                    bytes: d.value.len(),
+                }));
                 0
             });
             datas.insert(
@@ -182,22 +249,30 @@ where {
             );
         }
 
-        if module.memories.iter().count() > 1 {
+        let mut memories = module.memories.iter();
+        let primary = memories.next();
+        let secondary = memories.next();
+        if memories.next().is_some() || (secondary.is_some() && !options.lower_secondary_memory) {
             errors.push(CompilationError::UnsupportedMultipleMemories);
         }
+        if module.memories.iter().any(|mem| mem.memory64) {
+            errors.push(CompilationError::UnsupportedMemory64);
+        }
 
         let mem = MemLayout {
             addr: gen.gen("memory"),
             cur_size: gen.gen("memory_size"),
-            min_size: if let Some(mem) = module.memories.iter().next() {
+            min_size: if let Some(mem) = primary {
                 u32::try_from(mem.initial.saturating_mul(65536)).unwrap_or_else(|_| {
-                    errors.push(CompilationError::Overflow(OverflowLocation::Memory));
+                    errors.push(CompilationError::Overflow(OverflowLocation::Memory {
+                        pages: mem.initial,
+                    }));
                     0
                 })
             } else {
                 0
             },
-            max_size: if let Some(mem) = module.memories.iter().next() {
+            max_size: if let Some(mem) = primary {
                 if let Some(maximum) = mem.maximum {
                     u32::try_from(maximum.saturating_mul(65536)).unwrap_or(u32::MAX)
                 } else {
@@ -208,11 +283,82 @@ where {
             },
         };
 
+        // A secondary memory can't grow with `setmemsize` the way the primary
+        // one does, since the primary memory has to stay at the end of RAM.
+        // Instead, its full maximum size is reserved up front.
+        let secondary_mem = secondary
+            .filter(|_| options.lower_secondary_memory)
+            .map(|mem| {
+                let max_size = if let Some(maximum) = mem.maximum {
+                    u32::try_from(maximum.saturating_mul(65536)).unwrap_or_else(|_| {
+                        errors.push(CompilationError::Overflow(OverflowLocation::Memory {
+                            pages: maximum,
+                        }));
+                        0
+                    })
+                } else {
+                    errors.push(CompilationError::UnboundedSecondaryMemory);
+                    0
+                };
+                (
+                    mem.id(),
+                    MemLayout {
+                        addr: gen.gen("secondary_memory"),
+                        cur_size: gen.gen("secondary_memory_size"),
+                        min_size: u32::try_from(mem.initial.saturating_mul(65536))
+                            .unwrap_or(u32::MAX)
+                            .min(max_size),
+                        max_size,
+                    },
+                )
+            });
+
         let glk_area = GlkLayout {
             addr: gen.gen("glk_area"),
             size: options.glk_area_size,
         };
 
+        let externref = module
+            .imports
+            .iter()
+            .any(|import| import.module == "glulx" && import.name.starts_with("externref_"))
+            .then(|| ExternrefLayout {
+                addr: gen.gen("externref_handles"),
+                capacity: EXTERNREF_HANDLES,
+                free: gen.gen("externref_free"),
+                used: gen.gen("externref_used"),
+            });
+
+        // Everything else that goes into the story file is small next to
+        // these, so if they already overflow on their own, say so now rather
+        // than leaving the assembler to report it without saying why.
+        let total_bytes: u64 = u64::from(mem.min_size)
+            + secondary_mem
+                .as_ref()
+                .map_or(0, |(_, mem)| u64::from(mem.max_size))
+            + u64::from(glk_area.size)
+            + externref
+                .as_ref()
+                .map_or(0, |handles| u64::from(handles.capacity) * 4)
+            + tables
+                .values()
+                .map(|t| u64::from(t.max_count) * 4)
+                .sum::<u64>()
+            + elems
+                .values()
+                .map(|e| u64::from(e.initial_count) * 4)
+                .sum::<u64>()
+            + datas
+                .values()
+                .map(|d| u64::from(d.initial_size))
+                .sum::<u64>();
+        if total_bytes > u64::from(u32::MAX) {
+            errors.push(CompilationError::Overflow(OverflowLocation::Total {
+                bytes: total_bytes,
+                glk_area_size: glk_area.size,
+            }));
+        }
+
         let hi_return = HiReturnLayout {
             addr: gen.gen("hi_return"),
             size: module
@@ -233,6 +379,16 @@ where {
         let entrypoint = gen.gen("entrypoint");
         let trap = TrapLayout {
             string_table: gen.gen("trap_string_table"),
+            location: gen.gen("trap_location"),
+        };
+
+        let strings = StringsLayout {
+            decoding_table: gen.gen("decoding_table"),
+            id_table: gen.gen("compressed_string_ids"),
+            entries: crate::compress::collect(module).unwrap_or_else(|e| {
+                errors.extend(e);
+                Vec::new()
+            }),
         };
 
         if errors.is_empty() {
@@ -244,10 +400,13 @@ where {
                 elems,
                 datas,
                 mem,
+                secondary_mem,
                 glk_area,
+                externref,
                 hi_return,
                 entrypoint,
                 trap,
+                strings,
             })
         } else {
             Err(errors)
@@ -294,10 +453,39 @@ where {
         &self.mem
     }
 
+    /// Returns the layout of the memory lowered by `--lower-secondary-memory`,
+    /// if the module has one.
+    pub fn secondary_memory(&self) -> Option<&MemLayout> {
+        self.secondary_mem.as_ref().map(|(_, mem)| mem)
+    }
+
+    /// Returns the layout of memory `id`, which is either the primary memory or
+    /// the one lowered by `--lower-secondary-memory`.
+    pub fn memory_by_id(&self, id: MemoryId) -> &MemLayout {
+        match &self.secondary_mem {
+            Some((secondary, mem)) if *secondary == id => mem,
+            _ => &self.mem,
+        }
+    }
+
+    /// Returns true if `id` is the memory lowered by
+    /// `--lower-secondary-memory`, rather than the primary memory.
+    pub fn is_secondary_memory(&self, id: MemoryId) -> bool {
+        self.secondary_mem
+            .as_ref()
+            .is_some_and(|(secondary, _)| *secondary == id)
+    }
+
     pub fn glk_area(&self) -> &GlkLayout {
         &self.glk_area
     }
 
+    /// Returns the layout of the `externref` handle table, which exists only
+    /// if the module imports one of the `externref_*` intrinsics.
+    pub fn externref(&self) -> Option<&ExternrefLayout> {
+        self.externref.as_ref()
+    }
+
     pub fn hi_return(&self) -> &HiReturnLayout {
         &self.hi_return
     }
@@ -309,4 +497,8 @@ where {
     pub fn trap(&self) -> TrapLayout {
         self.trap
     }
+
+    pub fn strings(&self) -> &StringsLayout {
+        &self.strings
+    }
 }
@@ -8,38 +8,164 @@
 //! documentation.
 #![warn(missing_docs)]
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 use bytes::BytesMut;
 use common::Context;
-use glulx_asm::AssemblerError;
+use glulx_asm::{AssemblerError, WriteError};
 
-mod codegen;
+// Defines macros used by the modules below, so it must be declared first.
 mod common;
+
+mod artifacts;
+mod blorb;
+mod codegen;
+mod compress;
+mod const_fold;
 mod data;
+mod dce;
+mod debuginfo;
 mod entrypoint;
 mod error;
+mod export_filter;
+mod features;
 mod glk;
+mod hooks;
 mod intrinsics;
 mod layout;
+mod parallel;
+mod raw;
+mod recursion;
 mod rt;
+mod threads;
+mod validate;
+mod warnings;
 
 #[doc(hidden)]
 #[cfg(feature = "spectest")]
 pub mod spectest;
 
+pub use artifacts::Artifacts;
 use common::LabelGenerator;
 pub use common::{
-    CompilationOptions, DEFAULT_GLK_AREA_SIZE, DEFAULT_STACK_SIZE, DEFAULT_TABLE_GROWTH_LIMIT,
+    CompilationOptions, Conformance, Emit, Label, OptimizeFor, DEFAULT_GLK_AREA_SIZE,
+    DEFAULT_STACK_SIZE, DEFAULT_TABLE_GROWTH_LIMIT, STACK_EXHAUSTED,
 };
+pub use const_fold::fold_constant_exprs;
+pub use dce::eliminate_dead_code;
 pub use error::*;
+pub use export_filter::filter_exports;
+pub use features::{features_json, features_list, FeatureStatus, WasmFeature, WASM_FEATURES};
+pub use hooks::{HookContext, Hooks};
+pub use warnings::check_warnings;
 
 /// Compile a Walrus module into a `BytesMut`.
 ///
-/// This ignores the input and output fields of `options`.
+/// Returns the story file if `options` requests one, or else the assembly
+/// listing. It is an error to request neither; use
+/// [`compile_module_to_artifacts`] to get the other kinds of output. This
+/// ignores the input and output fields of `options`.
 pub fn compile_module_to_bytes(
     options: &CompilationOptions,
     module: &walrus::Module,
 ) -> Result<BytesMut, Vec<CompilationError>> {
+    compile_module_to_bytes_with_hooks(options, module, &mut ())
+}
+
+/// Compile a Walrus module into a `BytesMut`, invoking `hooks` at each
+/// extension point.
+///
+/// Returns the story file if `options` requests one, or else the assembly
+/// listing. It is an error to request neither; use
+/// [`compile_module_to_artifacts`] to get the other kinds of output. This
+/// ignores the input and output fields of `options`.
+pub fn compile_module_to_bytes_with_hooks(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+    hooks: &mut dyn Hooks,
+) -> Result<BytesMut, Vec<CompilationError>> {
+    let wanted = [Emit::Binary, Emit::Asm]
+        .into_iter()
+        .find(|emit| options.emit.contains(emit))
+        .ok_or_else(|| {
+            vec![CompilationError::OtherError(anyhow::anyhow!(
+                "compile_module_to_bytes needs a story file or assembly listing to return; \
+                 use compile_module_to_artifacts for other kinds of output"
+            ))]
+        })?;
+    let artifacts = compile_module_to_artifacts_with_hooks(options, module, hooks)?;
+    Ok(artifacts
+        .into_vec()
+        .into_iter()
+        .find_map(|(emit, bytes)| (emit == wanted).then_some(bytes))
+        .expect("every requested output should be rendered"))
+}
+
+/// Compile a Walrus module into every kind of output that `options` requests.
+///
+/// This ignores the input and output fields of `options`, but reads the Blorb
+/// manifest and the resources it lists if a Blorb file is requested.
+pub fn compile_module_to_artifacts(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+) -> Result<Artifacts, Vec<CompilationError>> {
+    compile_module_to_artifacts_with_hooks(options, module, &mut ())
+}
+
+/// Compile a Walrus module into every kind of output that `options` requests,
+/// invoking `hooks` at each extension point.
+///
+/// This ignores the input and output fields of `options`, but reads the Blorb
+/// manifest and the resources it lists if a Blorb file is requested.
+pub fn compile_module_to_artifacts_with_hooks(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+    hooks: &mut dyn Hooks,
+) -> Result<Artifacts, Vec<CompilationError>> {
+    generate(options, module, hooks, render_artifacts)
+}
+
+/// Compile a Walrus module into a story file, writing it to `w` as it is
+/// assembled. Returns the number of bytes written.
+///
+/// Unlike [`compile_module_to_bytes`], this never holds the whole story file
+/// in memory at once, at the cost of serializing it twice. This ignores the
+/// emit, input, and output fields of `options`.
+pub fn compile_module_to_writer<W: Write>(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+    w: W,
+) -> Result<usize, Vec<CompilationError>> {
+    compile_module_to_writer_with_hooks(options, module, &mut (), w)
+}
+
+/// Compile a Walrus module into a story file, writing it to `w` as it is
+/// assembled and invoking `hooks` at each extension point. Returns the number
+/// of bytes written.
+///
+/// This ignores the emit, input, and output fields of `options`.
+pub fn compile_module_to_writer_with_hooks<W: Write>(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+    hooks: &mut dyn Hooks,
+    w: W,
+) -> Result<usize, Vec<CompilationError>> {
+    generate(options, module, hooks, |_, assembly| {
+        write_story(&assembly, w)
+    })
+}
+
+/// Generates code for `module` and hands the resulting assembly to `finish`
+/// to produce the output.
+fn generate<T, F>(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+    hooks: &mut dyn Hooks,
+    finish: F,
+) -> Result<T, Vec<CompilationError>>
+where
+    F: FnOnce(&Context, glulx_asm::Assembly<'_, Label>) -> Result<T, Vec<CompilationError>>,
+{
     let mut gen = LabelGenerator(0);
     let mut rom_items = Vec::new();
     let mut ram_items = Vec::new();
@@ -49,6 +175,7 @@ pub fn compile_module_to_bytes(
     let rt = rt::RuntimeLabels::new(&mut gen);
 
     let mut errors = Vec::new();
+    let mut sequence_points = Vec::new();
 
     let mut ctx = Context {
         options,
@@ -60,43 +187,33 @@ pub fn compile_module_to_bytes(
         ram_items: &mut ram_items,
         zero_items: &mut zero_items,
         errors: &mut errors,
+        sequence_points: &mut sequence_points,
     };
 
+    hooks.after_layout(&mut ctx.hook_context());
     rt::gen_rt(&mut ctx);
 
-    for function in ctx.module.functions() {
-        let fn_layout = ctx.layout.func(function.id());
-        #[allow(clippy::clone_on_copy)]
-        let label = fn_layout.addr.clone();
-        let typenum = ctx.layout.ty(function.ty()).typenum;
-        ctx.rom_items.push(glulx_asm::concise::blob(
-            typenum.to_be_bytes().as_slice().to_owned(),
-        ));
-        match &function.kind {
-            walrus::FunctionKind::Import(imported_function) => {
-                let import = ctx.module.imports.get(imported_function.import);
-                let module_name = &import.module;
-                if module_name == "glk" {
-                    glk::gen_glk(&mut ctx, imported_function, label);
-                } else if module_name == "glulx" {
-                    intrinsics::gen_intrinsic(&mut ctx, imported_function, label);
-                } else {
-                    ctx.errors
-                        .push(CompilationError::UnrecognizedImport(import.clone()))
-                }
-            }
-            walrus::FunctionKind::Local(local) => {
-                codegen::gen_function(&mut ctx, local, label, function.name.as_deref());
-            }
-            walrus::FunctionKind::Uninitialized(_) => {
-                unreachable!(
-                    "Uninitialized functions shoud not be present in parsed and validated modules."
-                )
-            }
+    let generated = parallel::gen_functions(&ctx);
+    let mut function_ranges = Vec::new();
+    for (function, items) in ctx.module.functions().zip(generated) {
+        hooks.before_function(&mut ctx.hook_context(), function);
+        let start = ctx.rom_items.len();
+        items.append_to(&mut ctx);
+        hooks.after_function(&mut ctx.hook_context(), function, start);
+        if ctx.options.emit.contains(&Emit::Debug) {
+            ctx.rom_items.push(glulx_asm::concise::label(
+                ctx.layout.func(function.id()).end,
+            ));
         }
+        function_ranges.push((function.name.as_deref(), start..ctx.rom_items.len()));
     }
     entrypoint::gen_entrypoint(&mut ctx);
     data::gen_data(&mut ctx);
+    compress::gen_compressed_strings(&mut ctx);
+    if ctx.options.strict {
+        validate::validate_functions(&mut ctx, &function_ranges);
+    }
+    hooks.before_assembly(&mut ctx.hook_context());
 
     if !ctx.errors.is_empty() {
         return Err(errors);
@@ -108,62 +225,235 @@ pub fn compile_module_to_bytes(
         zero_items: std::borrow::Cow::Borrowed(ctx.zero_items),
         stack_size: ctx.options.stack_size,
         start_func: glulx_asm::LabelRef(ctx.layout.entrypoint(), 0),
-        decoding_table: None,
+        decoding_table: compress::initial_decoding_table(&ctx),
     };
 
-    if ctx.options.text {
-        Ok(assembly.to_string().as_str().into())
+    finish(&ctx, assembly)
+}
+
+fn render_artifacts(
+    ctx: &Context,
+    assembly: glulx_asm::Assembly<'_, Label>,
+) -> Result<Artifacts, Vec<CompilationError>> {
+    let emit = &ctx.options.emit;
+    let (mut binary, labels) = if emit.contains(&Emit::Map)
+        || emit.contains(&Emit::Exports)
+        || emit.contains(&Emit::Debug)
+    {
+        let (bytes, labels) = assembly.assemble_with_labels().map_err(assembler_errors)?;
+        (Some(bytes), labels)
+    } else if emit.contains(&Emit::Binary) || emit.contains(&Emit::Blorb) {
+        (
+            Some(assembly.assemble().map_err(assembler_errors)?),
+            Vec::new(),
+        )
+    } else {
+        (None, Vec::new())
+    };
+
+    let mut debug = emit.contains(&Emit::Debug).then(|| {
+        debuginfo::render_debug_info(
+            ctx,
+            &labels,
+            binary
+                .as_deref()
+                .expect("story file should have been assembled"),
+        )
+    });
+
+    let mut blorb = if emit.contains(&Emit::Blorb) {
+        Some(blorb::package(
+            binary
+                .as_deref()
+                .expect("story file should have been assembled"),
+            ctx.options.blorb_manifest.as_deref(),
+        )?)
     } else {
-        match assembly.assemble() {
-            Ok(bytes) => Ok(bytes),
-            Err(AssemblerError::Overflow) => Err(vec![CompilationError::Overflow(
-                OverflowLocation::FinalAssembly,
-            )]),
-            Err(e) => Err(vec![CompilationError::OtherError(e.into())]),
+        None
+    };
+
+    let mut artifacts = Artifacts::default();
+    for e in emit {
+        match e {
+            Emit::Binary => artifacts.push(
+                Emit::Binary,
+                binary
+                    .take()
+                    .expect("story file should have been assembled"),
+            ),
+            Emit::Asm => {
+                let mut listing = BytesMut::new();
+                assembly
+                    .write_listing(&mut listing)
+                    .expect("writing to a BytesMut should not fail");
+                artifacts.push(Emit::Asm, listing);
+            }
+            Emit::Map => artifacts.push(Emit::Map, artifacts::render_map(ctx, &labels)),
+            Emit::Exports => artifacts.push(Emit::Exports, artifacts::render_exports(ctx, &labels)),
+            Emit::Debug => artifacts.push(
+                Emit::Debug,
+                debug
+                    .take()
+                    .expect("debug information should have been rendered"),
+            ),
+            Emit::Blorb => artifacts.push(
+                Emit::Blorb,
+                blorb.take().expect("Blorb file should have been packaged"),
+            ),
         }
     }
+    Ok(artifacts)
+}
+
+fn write_story<W: Write>(
+    assembly: &glulx_asm::Assembly<'_, Label>,
+    w: W,
+) -> Result<usize, Vec<CompilationError>> {
+    assembly.assemble_to_writer(w).map_err(|e| match e {
+        WriteError::Assembler(e) => assembler_errors(e),
+        WriteError::Io(e) => vec![CompilationError::OutputError(e)],
+    })
+}
+
+fn assembler_errors(e: AssemblerError<Label>) -> Vec<CompilationError> {
+    match e {
+        AssemblerError::Overflow => {
+            vec![CompilationError::Overflow(OverflowLocation::FinalAssembly)]
+        }
+        e => vec![CompilationError::OtherError(e.into())],
+    }
+}
+
+/// The result of a successful [`compile`].
+#[derive(Debug)]
+pub struct Compiled {
+    /// The total number of bytes written
+    pub bytes_written: usize,
+    /// Anything [`check_warnings`] found
+    pub warnings: Vec<CompilationWarning>,
 }
 
 /// Compile a WebAssembly module into a Glulx story file.
-pub fn compile(options: &CompilationOptions) -> Result<usize, Vec<CompilationError>> {
+///
+/// If more than one kind of output is requested, the output path is treated
+/// as a stem and each output is written to the stem plus its extension.
+pub fn compile(options: &CompilationOptions) -> Result<Compiled, Vec<CompilationError>> {
+    if options.output.is_none() && options.emit.len() > 1 {
+        return Err(vec![CompilationError::OtherError(anyhow::anyhow!(
+            "An output path is required when emitting more than one kind of output"
+        ))]);
+    }
+
     let mut config = walrus::ModuleConfig::new();
     config.generate_synthetic_names_for_anonymous_items(true);
 
-    let module = if let Some(pathbuf) = &options.input {
-        config
-            .parse_file(pathbuf)
-            .map_err(|e| vec![CompilationError::ValidationError(e)])?
+    let input_vec = if let Some(pathbuf) = &options.input {
+        std::fs::read(pathbuf).map_err(|e| vec![CompilationError::InputError(e)])?
     } else {
         let mut stdin = std::io::stdin();
         let mut input_vec = Vec::new();
         stdin
             .read_to_end(&mut input_vec)
             .map_err(|e| vec![CompilationError::InputError(e)])?;
-        config
-            .parse(&input_vec)
-            .map_err(|e| vec![CompilationError::ValidationError(e)])?
+        input_vec
     };
+    let mut module = config
+        .parse(&fold_constant_exprs(&input_vec))
+        .map_err(|e| vec![CompilationError::ValidationError(e)])?;
 
-    let bytes = compile_module_to_bytes(options, &module)?.freeze();
+    filter_exports(options, &mut module)?;
+    eliminate_dead_code(options, &mut module);
+
+    let warnings = check_warnings(options, &module);
+    if options.deny_warnings && !warnings.is_empty() {
+        return Err(warnings
+            .into_iter()
+            .map(CompilationError::DeniedWarning)
+            .collect());
+    }
+
+    let bytes_written = write_outputs(options, &module)?;
+    Ok(Compiled {
+        bytes_written,
+        warnings,
+    })
+}
+
+/// Returns the path of a hidden file beside `output` to write it through.
+fn temp_path(output: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(output.file_name().unwrap_or_default());
+    name.push(".tmp");
+    output.with_file_name(name)
+}
+
+/// Compiles `module` and writes each requested output, returning the total
+/// number of bytes written.
+fn write_outputs(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+) -> Result<usize, Vec<CompilationError>> {
+    if options.emit == [Emit::Binary] {
+        // Usually the story file is the only output, and by far the largest,
+        // so write it out as it is assembled rather than collecting it first.
+        // Assembly can still fail after writing has begun, so the story goes
+        // to a temporary file beside the output, which replaces the output
+        // only once it is complete. An existing story file is left alone
+        // otherwise, which matters when an interpreter is running it.
+        return generate(options, module, &mut (), |_, assembly| {
+            if let Some(output) = &options.output {
+                let temp = temp_path(output);
+                let file = std::fs::File::create(&temp)
+                    .map_err(|e| vec![CompilationError::OutputError(e)])?;
+                let result =
+                    write_story(&assembly, std::io::BufWriter::new(file)).and_then(|len| {
+                        std::fs::rename(&temp, output)
+                            .map_err(|e| vec![CompilationError::OutputError(e)])?;
+                        Ok(len)
+                    });
+                if result.is_err() {
+                    let _ = std::fs::remove_file(&temp);
+                }
+                result
+            } else {
+                write_story(&assembly, std::io::BufWriter::new(std::io::stdout().lock()))
+            }
+        });
+    }
+
+    let artifacts = compile_module_to_artifacts(options, module)?;
+    let mut total = 0;
 
     if let Some(output) = &options.output {
-        let mut file =
-            std::fs::File::create(output).map_err(|e| vec![CompilationError::OutputError(e)])?;
-        let size = bytes.len();
-        file.write_all(&bytes)
-            .map_err(|e| vec![CompilationError::OutputError(e)])?;
-        file.flush()
-            .map_err(|e| vec![CompilationError::OutputError(e)])?;
-        Ok(size)
+        let multiple = artifacts.len() > 1;
+        for (emit, bytes) in artifacts.iter() {
+            let path = if multiple {
+                let mut path = output.clone().into_os_string();
+                path.push(".");
+                path.push(emit.extension());
+                PathBuf::from(path)
+            } else {
+                output.clone()
+            };
+            let mut file =
+                std::fs::File::create(&path).map_err(|e| vec![CompilationError::OutputError(e)])?;
+            file.write_all(bytes)
+                .map_err(|e| vec![CompilationError::OutputError(e)])?;
+            file.flush()
+                .map_err(|e| vec![CompilationError::OutputError(e)])?;
+            total += bytes.len();
+        }
     } else {
         let mut stdout = std::io::stdout();
-        let size = bytes.len();
-        stdout
-            .write_all(&bytes)
-            .map_err(|e| vec![CompilationError::OutputError(e)])?;
+        for (_, bytes) in artifacts.iter() {
+            stdout
+                .write_all(bytes)
+                .map_err(|e| vec![CompilationError::OutputError(e)])?;
+            total += bytes.len();
+        }
         stdout
             .flush()
             .map_err(|e| vec![CompilationError::OutputError(e)])?;
-        Ok(size)
     }
+    Ok(total)
 }
@@ -3,25 +3,123 @@
 
 use std::{
     ffi::OsString,
-    io::IsTerminal,
+    io::{IsTerminal, Read, Write},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
-    process::ExitCode,
+    process::{Child, Command, ExitCode},
+    thread,
+    time::{Duration, SystemTime},
 };
 
-use clap::{CommandFactory, Parser, ValueHint};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
 use wasm2glulx::{
-    compile, CompilationOptions, DEFAULT_GLK_AREA_SIZE, DEFAULT_STACK_SIZE,
-    DEFAULT_TABLE_GROWTH_LIMIT,
+    compile, features_json, features_list, CompilationOptions, Conformance, Emit, OptimizeFor,
+    DEFAULT_GLK_AREA_SIZE, DEFAULT_STACK_SIZE, DEFAULT_TABLE_GROWTH_LIMIT,
 };
 
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum EmitFormat {
+    Binary,
+    Asm,
+    Map,
+    Exports,
+    Debug,
+    Blorb,
+}
+
+impl From<EmitFormat> for Emit {
+    fn from(format: EmitFormat) -> Emit {
+        match format {
+            EmitFormat::Binary => Emit::Binary,
+            EmitFormat::Asm => Emit::Asm,
+            EmitFormat::Map => Emit::Map,
+            EmitFormat::Exports => Emit::Exports,
+            EmitFormat::Debug => Emit::Debug,
+            EmitFormat::Blorb => Emit::Blorb,
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum ConformanceLevel {
+    Spec,
+    Standard,
+    Fast,
+}
+
+impl From<ConformanceLevel> for Conformance {
+    fn from(level: ConformanceLevel) -> Conformance {
+        match level {
+            ConformanceLevel::Spec => Conformance::Spec,
+            ConformanceLevel::Standard => Conformance::Standard,
+            ConformanceLevel::Fast => Conformance::Fast,
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum OptimizeGoal {
+    Size,
+    Speed,
+}
+
+impl From<OptimizeGoal> for OptimizeFor {
+    fn from(goal: OptimizeGoal) -> OptimizeFor {
+        match goal {
+            OptimizeGoal::Size => OptimizeFor::Size,
+            OptimizeGoal::Speed => OptimizeFor::Speed,
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug)]
+enum FeaturesFormat {
+    List,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Subcommands {
+    /// Assemble a listing, in the syntax that --text emits, into a story file
+    Asm {
+        /// Name of output file, or "-" for stdout
+        ///
+        /// The default is stdout if the input comes from stdin. Otherwise, the
+        /// default is to strip any .glulxasm suffix from the input file name,
+        /// add a .ulx suffix, and output it to the current directory.
+        #[arg(short, long, value_name="FILE", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// Path to an assembly listing, or "-" (default) for stdin
+        #[arg(index = 1, value_name = "LISTING-FILE")]
+        input: Option<PathBuf>,
+    },
+    /// Print an assembly listing of an existing story file
+    Disasm {
+        /// Name of output file, or "-" (default) for stdout
+        #[arg(short, long, value_name="FILE", value_hint = ValueHint::FilePath)]
+        output: Option<PathBuf>,
+
+        /// Path to a Glulx story file or a Blorb containing one, or "-"
+        /// (default) for stdin
+        #[arg(index = 1, value_name = "STORY-FILE")]
+        input: Option<PathBuf>,
+    },
+}
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None, max_term_width = 72)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Subcommands>,
+
     /// Name of output file, or "-" for stdout
     ///
     /// The default is stdout if the input comes from stdin. Otherwise, the
     /// default is to strip any .wasm suffix from the input file name, add a
-    /// .ulx suffix, and output it to the current directory.
+    /// .ulx suffix, and output it to the current directory. With --emit, the
+    /// suffix matches each output.
     #[arg(short, long, value_name="FILE", value_hint = ValueHint::FilePath)]
     output: Option<PathBuf>,
 
@@ -31,9 +129,151 @@ struct Args {
     /// Size (in bytes) of the program stack
     #[arg(long, default_value_t = DEFAULT_STACK_SIZE, value_name="SIZE")]
     stack_size: u32,
+    /// Stop recursive functions before they overflow the stack
+    ///
+    /// Functions which can call themselves, directly or through other
+    /// functions, check on entry that at least BYTES bytes of stack remain.
+    /// A failed check throws 0xffffffff to the innermost active `catch`
+    /// intrinsic, or traps with "call stack exhausted" if there is none.
+    #[arg(long, value_name = "BYTES")]
+    stack_guard: Option<u32>,
     /// Output human-readable assembly rather than a story file
     #[arg(long, default_value_t = false)]
     text: bool,
+    /// Comma-separated list of outputs to produce
+    ///
+    /// Outputs are "binary" (a story file), "asm" (as with --text), "map"
+    /// (function addresses and names), "exports" (exported function
+    /// addresses and names), "debug" (as with --debug-info), and "blorb" (as
+    /// with --blorb). If more than one is given, the output file name is a
+    /// stem to which each output's extension is appended.
+    #[arg(
+        long,
+        value_name = "LIST",
+        value_delimiter = ',',
+        conflicts_with = "text"
+    )]
+    emit: Vec<EmitFormat>,
+    /// Also output an Inform-style debug information file
+    ///
+    /// The file describes each function's address and size. If the input has
+    /// DWARF debug information, it also gives the source file and line where
+    /// each function was declared, and the source line of each address within
+    /// it. Its extension is .dbg.
+    #[arg(long, default_value_t = false)]
+    debug_info: bool,
+    /// Also output a Blorb file packaging resources from MANIFEST
+    ///
+    /// MANIFEST is a TOML file which may name cover art, further pictures
+    /// and sounds, and iFiction metadata. The Blorb file contains the story
+    /// file along with all of these. Its extension is .gblorb.
+    #[arg(long, value_name = "MANIFEST", value_hint = ValueHint::FilePath)]
+    blorb: Option<PathBuf>,
+
+    /// How closely to follow WebAssembly's trapping semantics
+    ///
+    /// "spec" emits every check and ignores --elide-bounds-checks and
+    /// --trap-on-overflow. "standard" emits every check unless other options
+    /// say otherwise. "fast" skips bounds checks on memory accesses through
+    /// locals and constants and signature checks on indirect calls, so a
+    /// program that would trap may misbehave instead.
+    #[arg(long, value_name = "LEVEL", default_value = "standard")]
+    conformance: ConformanceLevel,
+
+    /// Whether to favor smaller or faster code
+    ///
+    /// "size" implements integer comparisons, shifts, and similar operations
+    /// by calling shared runtime routines. "speed" inlines those routines at
+    /// each use, which is faster but makes the story file larger.
+    #[arg(long, value_name = "GOAL", default_value = "size")]
+    optimize_for: OptimizeGoal,
+
+    /// Number of threads to generate code on
+    ///
+    /// Defaults to one per available core. The output doesn't depend on it.
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Call the start function before Glk initialization
+    ///
+    /// By default, a start function which is distinct from glulx_main runs
+    /// after glulx_interrupt_handler is registered.
+    #[arg(long, default_value_t = false)]
+    early_start: bool,
+
+    /// Skip bounds checks already implied by earlier ones
+    ///
+    /// A memory access through a local is not rechecked if an earlier access
+    /// through the same local, in the same basic block, was checked at least
+    /// as far.
+    #[arg(long, default_value_t = false)]
+    elide_bounds_checks: bool,
+
+    /// Leave out functions and data that can never be reached
+    ///
+    /// Anything not reachable from an export, the start function, or an
+    /// active data or element segment is dropped before code generation.
+    #[arg(long, default_value_t = false)]
+    eliminate_dead_code: bool,
+
+    /// Check generated code against Glulx machine limits
+    ///
+    /// Reports an error, rather than producing a story file that fails at run
+    /// time, if any function's call frame or stack operation cannot fit in
+    /// the stack, or if an instruction accesses a local the function doesn't
+    /// have, branches somewhere other than an instruction, or passes a
+    /// function more arguments than it has locals.
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+    /// Treat warnings as errors
+    ///
+    /// Warnings point out things which are probably mistakes, such as an
+    /// exported function that will never be called. With this option, they
+    /// cause compilation to fail before any output is written.
+    #[arg(long, default_value_t = false)]
+    deny_warnings: bool,
+
+    /// Run threads spawned via wasi/thread-spawn inline
+    ///
+    /// Glulx is single-threaded, so by default modules which import
+    /// wasi/thread-spawn are rejected. With this option, each spawned thread
+    /// instead runs to completion before the spawning call returns.
+    #[arg(long, default_value_t = false)]
+    inline_thread_spawn: bool,
+
+    /// Place a second WASM memory in a separate region of RAM
+    ///
+    /// Modules which define more than one memory are rejected by default.
+    /// With this option, a second memory is accepted if it declares a
+    /// maximum size, and is given a region of RAM reserved at that size.
+    #[arg(long, default_value_t = false)]
+    lower_secondary_memory: bool,
+
+    /// Trap on signed overflow in integer arithmetic
+    ///
+    /// Instruments i32 and i64 add, sub, and mul to trap, naming the function
+    /// responsible, if the result overflows as a signed integer. This is a
+    /// debugging aid: code which wraps deliberately will trap too.
+    #[arg(long, default_value_t = false)]
+    trap_on_overflow: bool,
+
+    /// Print a message when a trap occurs
+    ///
+    /// Before quitting, traps print the kind of trap and the function it
+    /// occurred in to the current Glk stream. Without this, traps quit
+    /// silently on most interpreters.
+    #[arg(long, default_value_t = false)]
+    trap_messages: bool,
+
+    /// Keep only function exports matching PATTERN
+    ///
+    /// PATTERN is a regular expression which must match the whole export
+    /// name; give this option more than once to keep several. All other
+    /// function exports, and any code only they reach, are left out of the
+    /// story file. Exports that wasm2glulx itself uses, such as glulx_main,
+    /// are always kept.
+    #[arg(long, value_name = "PATTERN")]
+    export_filter: Vec<String>,
 
     /// Growth limit (in entries) for tables
     ///
@@ -43,6 +283,29 @@ struct Args {
     #[arg(long, default_value_t = DEFAULT_TABLE_GROWTH_LIMIT, value_name="N")]
     table_growth_limit: u32,
 
+    /// Print which WASM feature proposals are supported, then exit
+    ///
+    /// Features are reported as supported, lowered (accepted but translated
+    /// into something weaker than the proposal specifies), or rejected, along
+    /// with the corresponding rustc target feature.
+    #[arg(long, value_name = "FORMAT")]
+    wasm_features: Option<FeaturesFormat>,
+
+    /// Recompile whenever the input file changes
+    ///
+    /// Compiles once, then keeps running and compiles again each time the
+    /// input file is modified, until interrupted. Requires the input to be a
+    /// file and the output not to be stdout.
+    #[arg(long, default_value_t = false)]
+    watch: bool,
+    /// Run COMMAND with the story file after each successful compilation
+    ///
+    /// COMMAND is split on whitespace, and the path of the story file is
+    /// added as its last argument, e.g. --run glulxe. Whatever the previous
+    /// compilation started is killed first.
+    #[arg(long, value_name = "COMMAND", requires = "watch")]
+    run: Option<String>,
+
     /// Path to WASM module, or "-" (default) for stdin
     #[arg(index = 1, value_name = "INPUT-FILE")]
     input: Option<PathBuf>,
@@ -52,7 +315,24 @@ fn main() -> ExitCode {
     let args = Args::parse();
     let stdin = std::io::stdin();
     let stdout = std::io::stdout();
-    let stderr = std::io::stderr();
+
+    match args.command {
+        Some(Subcommands::Asm { output, input }) => return asm(input, output),
+        Some(Subcommands::Disasm { output, input }) => return disasm(input, output),
+        None => {}
+    }
+
+    match args.wasm_features {
+        Some(FeaturesFormat::List) => {
+            print!("{}", features_list());
+            return ExitCode::SUCCESS;
+        }
+        Some(FeaturesFormat::Json) => {
+            print!("{}", features_json());
+            return ExitCode::SUCCESS;
+        }
+        None => {}
+    }
 
     if args.input.is_none() && stdin.is_terminal() {
         eprintln!("\u{1b}[1m\u{1b}[31mwasm2glulx: reading input file from stdin, but stdin is a tty. Add \"-\" to the command line if you want to force this.\u{1b}[39m\u{1b}[22m");
@@ -62,7 +342,27 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
-    if !args.text
+    let mut emit: Vec<Emit> = if !args.emit.is_empty() {
+        let mut emit = Vec::new();
+        for &format in &args.emit {
+            if !emit.contains(&format.into()) {
+                emit.push(format.into());
+            }
+        }
+        emit
+    } else if args.text {
+        vec![Emit::Asm]
+    } else {
+        vec![Emit::Binary]
+    };
+    if args.debug_info && !emit.contains(&Emit::Debug) {
+        emit.push(Emit::Debug);
+    }
+    if args.blorb.is_some() && !emit.contains(&Emit::Blorb) {
+        emit.push(Emit::Blorb);
+    }
+
+    if (emit.contains(&Emit::Binary) || emit.contains(&Emit::Blorb))
         && (args.input.is_none() || args.input.as_deref() == Some(Path::new("-")))
         && args.output.is_none()
         && stdout.is_terminal()
@@ -90,10 +390,9 @@ fn main() -> ExitCode {
         if basename.ends_with(b".wasm") {
             basename.truncate(basename.len() - 5);
         }
-        if args.text {
-            basename.extend_from_slice(b".glulxasm");
-        } else {
-            basename.extend_from_slice(b".ulx");
+        if let [single] = emit.as_slice() {
+            basename.push(b'.');
+            basename.extend_from_slice(single.extension().as_bytes());
         }
         Some(PathBuf::from(unsafe {
             // SAFETY: On all platforms, OsStrings are a self-synchronizing
@@ -104,16 +403,294 @@ fn main() -> ExitCode {
         args.output
     };
 
+    let watch = if args.watch {
+        let (Some(input), Some(output)) = (&input, &output) else {
+            return usage_error("--watch requires an input file and an output other than stdout.");
+        };
+        let run = if let Some(run) = &args.run {
+            let command: Vec<String> = run.split_whitespace().map(str::to_owned).collect();
+            if command.is_empty() {
+                return usage_error("--run was given an empty command.");
+            }
+            let Some(story) = story_path(output, &emit) else {
+                return usage_error("--run requires a story file to be among the outputs.");
+            };
+            Some((command, story))
+        } else {
+            None
+        };
+        Some((input.clone(), run))
+    } else {
+        None
+    };
+
     let mut options = CompilationOptions::new();
     options.set_glk_area_size(args.glk_area_size);
     options.set_stack_size(args.stack_size);
+    options.set_stack_guard(args.stack_guard);
     options.set_table_growth_limit(args.table_growth_limit);
-    options.set_text(args.text);
+    options.set_emit(&emit);
+    options.set_blorb_manifest(args.blorb);
+    options.set_early_start(args.early_start);
+    options.set_elide_bounds_checks(args.elide_bounds_checks);
+    options.set_eliminate_dead_code(args.eliminate_dead_code);
+    options.set_strict(args.strict);
+    options.set_deny_warnings(args.deny_warnings);
+    options.set_inline_thread_spawn(args.inline_thread_spawn);
+    options.set_lower_secondary_memory(args.lower_secondary_memory);
+    options.set_trap_on_overflow(args.trap_on_overflow);
+    options.set_trap_messages(args.trap_messages);
+    options.set_export_filter(args.export_filter);
+    options.set_conformance(args.conformance.into());
+    options.set_optimize_for(args.optimize_for.into());
+    options.set_jobs(args.jobs);
     options.set_input(input);
     options.set_output(output);
 
-    match compile(&options) {
-        Ok(_) => ExitCode::SUCCESS,
+    if let Some((input, run)) = watch {
+        watch_input(&options, &input, run);
+    }
+
+    if compile_and_report(&options) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Runs the `disasm` subcommand.
+fn disasm(input: Option<PathBuf>, output: Option<PathBuf>) -> ExitCode {
+    let input = input.filter(|path| path != Path::new("-"));
+    let output = output.filter(|path| path != Path::new("-"));
+
+    let Some(data) = read_input(input.as_deref()) else {
+        return ExitCode::FAILURE;
+    };
+    let input_name = display_path(input.as_deref(), "<stdin>");
+    let assembly = match glulx_asm::disassemble(blorb_story(&data).unwrap_or(&data)) {
+        Ok(assembly) => assembly,
+        Err(e) => {
+            eprintln!("wasm2glulx: {input_name}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    write_output(output.as_deref(), assembly.to_string().as_bytes())
+}
+
+/// Runs the `asm` subcommand.
+fn asm(input: Option<PathBuf>, output: Option<PathBuf>) -> ExitCode {
+    let input = input.filter(|path| path != Path::new("-"));
+    let output = match output {
+        Some(path) if path == Path::new("-") => None,
+        Some(path) => Some(path),
+        None => input.as_deref().map(|input| {
+            let mut basename = if input.extension() == Some("glulxasm".as_ref()) {
+                input.file_stem()
+            } else {
+                input.file_name()
+            }
+            .unwrap_or_default()
+            .to_owned();
+            basename.push(".ulx");
+            PathBuf::from(basename)
+        }),
+    };
+
+    if output.is_none() && std::io::stdout().is_terminal() {
+        eprintln!("\u{1b}[1m\u{1b}[31mwasm2glulx: writing output to stdout, but stdout is a tty. Add \"-o -\" to the command line if you want to force this.\u{1b}[39m\u{1b}[22m");
+        return ExitCode::FAILURE;
+    }
+
+    let Some(data) = read_input(input.as_deref()) else {
+        return ExitCode::FAILURE;
+    };
+    let input_name = display_path(input.as_deref(), "<stdin>");
+    let Ok(listing) = String::from_utf8(data) else {
+        eprintln!("wasm2glulx: {input_name}: listing is not valid UTF-8");
+        return ExitCode::FAILURE;
+    };
+    let assembly = match glulx_asm::parse_listing(&listing) {
+        Ok(assembly) => assembly,
+        Err(e) => {
+            eprintln!("wasm2glulx: {input_name}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let story = match assembly.assemble() {
+        Ok(story) => story,
+        Err(e) => {
+            eprintln!("wasm2glulx: {input_name}: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    write_output(output.as_deref(), &story)
+}
+
+/// Returns `path` for use in messages, or `default` if there is none.
+fn display_path(path: Option<&Path>, default: &str) -> String {
+    path.map_or(default.to_owned(), |path| path.display().to_string())
+}
+
+/// Reads all of `input`, or of stdin if it's `None`, reporting any error.
+fn read_input(input: Option<&Path>) -> Option<Vec<u8>> {
+    let read = match input {
+        Some(path) => std::fs::read(path),
+        None => {
+            let stdin = std::io::stdin();
+            if stdin.is_terminal() {
+                eprintln!("\u{1b}[1m\u{1b}[31mwasm2glulx: reading input file from stdin, but stdin is a tty. Add \"-\" to the command line if you want to force this.\u{1b}[39m\u{1b}[22m");
+                return None;
+            }
+            let mut data = Vec::new();
+            stdin.lock().read_to_end(&mut data).map(|_| data)
+        }
+    };
+
+    read.map_err(|e| eprintln!("wasm2glulx: {}: {e}", display_path(input, "<stdin>")))
+        .ok()
+}
+
+/// Writes `data` to `output`, or to stdout if it's `None`, reporting any
+/// error.
+fn write_output(output: Option<&Path>, data: &[u8]) -> ExitCode {
+    let written = match output {
+        Some(path) => std::fs::write(path, data),
+        None => std::io::stdout().lock().write_all(data),
+    };
+
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("wasm2glulx: {}: {e}", display_path(output, "<stdout>"));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// If `data` is a Blorb file, returns the story file inside it.
+fn blorb_story(data: &[u8]) -> Option<&[u8]> {
+    let (b"FORM", rest) = data.split_first_chunk::<4>()? else {
+        return None;
+    };
+    let (_, rest) = rest.split_first_chunk::<4>()?;
+    let (b"IFRS", mut chunks) = rest.split_first_chunk::<4>()? else {
+        return None;
+    };
+
+    while let Some((id, rest)) = chunks.split_first_chunk::<4>() {
+        let (len, rest) = rest.split_first_chunk::<4>()?;
+        let len = usize::try_from(u32::from_be_bytes(*len)).ok()?;
+        let body = rest.get(..len)?;
+        if id == b"GLUL" {
+            return Some(body);
+        }
+        chunks = rest.get(len + len % 2..).unwrap_or_default();
+    }
+
+    None
+}
+
+/// Prints `message` as an error about how wasm2glulx was invoked, in bold red
+/// if stderr is a terminal, and returns the exit code to fail with.
+fn usage_error(message: &str) -> ExitCode {
+    if std::io::stderr().is_terminal() {
+        eprintln!("\u{1b}[1m\u{1b}[31mwasm2glulx: {message}\u{1b}[39m\u{1b}[22m");
+    } else {
+        eprintln!("wasm2glulx: {message}");
+    }
+    ExitCode::FAILURE
+}
+
+/// How often `--watch` checks whether the input has changed.
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Returns the path of the story file which will be written, given the output
+/// path and the outputs requested, or `None` if no story file is requested.
+fn story_path(output: &Path, emit: &[Emit]) -> Option<PathBuf> {
+    let story = [Emit::Blorb, Emit::Binary]
+        .into_iter()
+        .find(|e| emit.contains(e))?;
+    if emit.len() > 1 {
+        let mut path = output.to_owned().into_os_string();
+        path.push(".");
+        path.push(story.extension());
+        Some(PathBuf::from(path))
+    } else {
+        Some(output.to_owned())
+    }
+}
+
+/// Returns the modification time and length of `path`, or `None` if it
+/// can't be read.
+fn input_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Compiles whenever `input` changes, forever. After each successful
+/// compilation, if `run` is given, kills whatever the previous one started
+/// and runs its command with the story file's path appended.
+fn watch_input(
+    options: &CompilationOptions,
+    input: &Path,
+    run: Option<(Vec<String>, PathBuf)>,
+) -> ! {
+    let mut last = None;
+    let mut child: Option<Child> = None;
+
+    loop {
+        let stamp = input_stamp(input);
+        if stamp.is_some() && stamp != last {
+            // Whatever is writing the input may not be done yet, so wait until
+            // it has gone one interval without changing.
+            thread::sleep(WATCH_INTERVAL);
+            if input_stamp(input) != stamp {
+                continue;
+            }
+            last = stamp;
+
+            eprintln!("wasm2glulx: compiling {}", input.display());
+            if compile_and_report(options) {
+                if let Some((command, story)) = &run {
+                    if let Some(mut old) = child.take() {
+                        let _ = old.kill();
+                        let _ = old.wait();
+                    }
+                    match Command::new(&command[0])
+                        .args(&command[1..])
+                        .arg(story)
+                        .spawn()
+                    {
+                        Ok(new) => child = Some(new),
+                        Err(e) => eprintln!("wasm2glulx: could not run {}: {e}", command[0]),
+                    }
+                }
+            }
+            eprintln!("wasm2glulx: watching {} for changes", input.display());
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Compiles, printing any warnings or errors to stderr. Returns true if
+/// compilation succeeded.
+fn compile_and_report(options: &CompilationOptions) -> bool {
+    let stderr = std::io::stderr();
+    match compile(options) {
+        Ok(compiled) => {
+            for warning in compiled.warnings {
+                if stderr.is_terminal() {
+                    eprintln!(
+                        "\u{1b}[1m\u{1b}[33mwasm2glulx: warning:\u{1b}[39m\u{1b}[22m {warning}"
+                    );
+                } else {
+                    eprintln!("wasm2glulx: warning: {warning}");
+                }
+            }
+            true
+        }
         Err(errv) => {
             if stderr.is_terminal() {
                 eprintln!(
@@ -134,7 +711,44 @@ fn main() -> ExitCode {
                     eprintln!("* {err}");
                 }
             }
-            ExitCode::FAILURE
+            false
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn story_path_is_the_output_when_it_is_the_only_one() {
+        let output = Path::new("game");
+        assert_eq!(
+            story_path(output, &[Emit::Binary]),
+            Some(PathBuf::from("game"))
+        );
+        assert_eq!(
+            story_path(output, &[Emit::Blorb]),
+            Some(PathBuf::from("game"))
+        );
+    }
+
+    #[test]
+    fn story_path_has_an_extension_beside_other_outputs() {
+        let output = Path::new("game");
+        assert_eq!(
+            story_path(output, &[Emit::Asm, Emit::Binary]),
+            Some(PathBuf::from("game.ulx"))
+        );
+        assert_eq!(
+            story_path(output, &[Emit::Binary, Emit::Debug, Emit::Blorb]),
+            Some(PathBuf::from("game.gblorb"))
+        );
+    }
+
+    #[test]
+    fn story_path_needs_a_story_file() {
+        assert_eq!(story_path(Path::new("game"), &[Emit::Asm]), None);
+        assert_eq!(story_path(Path::new("game"), &[Emit::Asm, Emit::Map]), None);
+    }
+}
@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Parallel generation of functions.
+//!
+//! Each function is generated on a worker thread into item lists of its own,
+//! with a private label generator which starts where the shared one left off.
+//! The results are then appended to the shared lists in module order, and
+//! each function's labels are renumbered to follow whatever was allocated
+//! before it. So the output is identical to generating every function in
+//! turn, regardless of how the work was divided among threads.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use glulx_asm::{Item, ZeroItem};
+use walrus::{ir::InstrLocId, Function, FunctionKind, Module};
+
+use crate::common::{CompilationOptions, Context, Label, LabelGenerator};
+use crate::layout::Layout;
+use crate::rt::RuntimeLabels;
+use crate::{codegen, glk, intrinsics, threads, CompilationError};
+
+/// Everything generated for one function.
+pub struct FunctionItems {
+    rom_items: Vec<Item<Label>>,
+    ram_items: Vec<Item<Label>>,
+    zero_items: Vec<ZeroItem<Label>>,
+    errors: Vec<CompilationError>,
+    sequence_points: Vec<(Label, InstrLocId)>,
+    /// The number the function's first label was generated with.
+    base: usize,
+    /// How many labels the function generated.
+    label_count: usize,
+}
+
+impl FunctionItems {
+    /// Appends these items and errors to `ctx`'s, renumbering labels so that
+    /// they follow those which `ctx` has already generated.
+    pub fn append_to(self, ctx: &mut Context) {
+        let base = self.base;
+        let to = ctx.gen.0;
+        ctx.gen.0 += self.label_count;
+        let rebase = |label: Label| label.rebase(base, to);
+
+        ctx.rom_items
+            .extend(self.rom_items.into_iter().map(|item| item.map(rebase)));
+        ctx.ram_items
+            .extend(self.ram_items.into_iter().map(|item| item.map(rebase)));
+        ctx.zero_items
+            .extend(self.zero_items.into_iter().map(|item| item.map(rebase)));
+        ctx.errors.extend(self.errors);
+        ctx.sequence_points.extend(
+            self.sequence_points
+                .into_iter()
+                .map(|(label, loc)| (rebase(label), loc)),
+        );
+    }
+}
+
+/// Generates every function in the module, spread across as many threads as
+/// `--jobs` allows, or as there are cores, and returns the results in the same order as
+/// `ctx.module.functions()`. Nothing is added to `ctx` itself until each
+/// result is passed to [`FunctionItems::append_to`].
+pub fn gen_functions(ctx: &Context) -> Vec<FunctionItems> {
+    let options = ctx.options;
+    let module = ctx.module;
+    let layout = ctx.layout;
+    let rt = ctx.rt;
+    let base = ctx.gen.0;
+
+    let functions: Vec<&Function> = module.functions().collect();
+    let thread_count = options
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, |n| n.get())
+        .min(functions.len());
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, FunctionItems)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..thread_count)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(function) = functions.get(i) else {
+                            break;
+                        };
+                        done.push((i, gen_one(options, module, layout, rt, base, function)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+
+    results.sort_unstable_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, items)| items).collect()
+}
+
+fn gen_one(
+    options: &CompilationOptions,
+    module: &Module,
+    layout: &Layout,
+    rt: &RuntimeLabels,
+    base: usize,
+    function: &Function,
+) -> FunctionItems {
+    let mut gen = LabelGenerator(base);
+    let mut rom_items = Vec::new();
+    let mut ram_items = Vec::new();
+    let mut zero_items = Vec::new();
+    let mut errors = Vec::new();
+    let mut sequence_points = Vec::new();
+
+    let mut ctx = Context {
+        options,
+        module,
+        layout,
+        rt,
+        gen: &mut gen,
+        rom_items: &mut rom_items,
+        ram_items: &mut ram_items,
+        zero_items: &mut zero_items,
+        errors: &mut errors,
+        sequence_points: &mut sequence_points,
+    };
+
+    let fn_layout = ctx.layout.func(function.id());
+    #[allow(clippy::clone_on_copy)]
+    let label = fn_layout.addr.clone();
+    let stack_guard = fn_layout.stack_guard;
+    let typenum = ctx.layout.ty(function.ty()).typenum;
+    ctx.rom_items.push(glulx_asm::concise::blob(
+        typenum.to_be_bytes().as_slice().to_owned(),
+    ));
+    match &function.kind {
+        FunctionKind::Import(imported_function) => {
+            let import = ctx.module.imports.get(imported_function.import);
+            let module_name = &import.module;
+            if module_name == "glk" {
+                glk::gen_glk(&mut ctx, imported_function, label);
+            } else if module_name == "glulx" {
+                intrinsics::gen_intrinsic(&mut ctx, imported_function, label);
+            } else if threads::is_thread_spawn(import) {
+                threads::gen_thread_spawn(&mut ctx, function.id(), imported_function, label);
+            } else {
+                ctx.errors
+                    .push(CompilationError::UnrecognizedImport(import.clone()))
+            }
+        }
+        FunctionKind::Local(local) => {
+            codegen::gen_function(
+                &mut ctx,
+                local,
+                label,
+                function.name.as_deref(),
+                stack_guard,
+            );
+        }
+        FunctionKind::Uninitialized(_) => {
+            unreachable!(
+                "Uninitialized functions shoud not be present in parsed and validated modules."
+            )
+        }
+    }
+
+    FunctionItems {
+        rom_items,
+        ram_items,
+        zero_items,
+        errors,
+        sequence_points,
+        base,
+        label_count: gen.0 - base,
+    }
+}
@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Intrinsics which emit a single, arbitrary Glulx instruction.
+//!
+//! An import named `raw_<mnemonic>` names one of [`RAW_OPCODES`]. An import
+//! named `raw_0x<hex>` names any instruction by its opcode. Either way, the
+//! instruction's operands are looked up in glulx-asm's opcode table, the
+//! import's type is checked against them, and the instruction is built and
+//! encoded by glulx-asm like any other.
+
+use glulx_asm::concise::*;
+use glulx_asm::{operand_kinds, Instr, Item, LoadOperand, Operand, OperandKind};
+use walrus::{ImportedFunction, ValType};
+
+use crate::common::{Context, Label};
+use crate::CompilationError;
+
+/// Instructions with `raw_<mnemonic>` bindings, by mnemonic and opcode.
+///
+/// Only instructions whose operands are all plain values are listed. Branches,
+/// calls, stack manipulation, and anything taking a Glulx address are left out
+/// since they can't be used safely from a function boundary, but the
+/// `raw_0x<hex>` form can still reach them.
+static RAW_OPCODES: &[(&str, u32)] = &[
+    ("nop", 0x00),
+    ("streamchar", 0x70),
+    ("streamnum", 0x71),
+    ("streamunichar", 0x73),
+    ("gestalt", 0x100),
+    ("debugtrap", 0x101),
+    ("getmemsize", 0x102),
+    ("random", 0x110),
+    ("setrandom", 0x111),
+    ("quit", 0x120),
+    ("verify", 0x121),
+    ("restart", 0x122),
+    ("saveundo", 0x125),
+    ("restoreundo", 0x126),
+    ("hasundo", 0x128),
+    ("discardundo", 0x129),
+    ("numtof", 0x190),
+    ("ftonumz", 0x191),
+    ("ftonumn", 0x192),
+    ("ceil", 0x198),
+    ("floor", 0x199),
+    ("fadd", 0x1A0),
+    ("fsub", 0x1A1),
+    ("fmul", 0x1A2),
+    ("fdiv", 0x1A3),
+    ("sqrt", 0x1A8),
+    ("exp", 0x1A9),
+    ("log", 0x1AA),
+    ("pow", 0x1AB),
+    ("sin", 0x1B0),
+    ("cos", 0x1B1),
+    ("tan", 0x1B2),
+    ("asin", 0x1B3),
+    ("acos", 0x1B4),
+    ("atan", 0x1B5),
+    ("atan2", 0x1B6),
+];
+
+/// Looks up `name`, stripped of its `raw_` prefix, returning its opcode.
+fn lookup(name: &str) -> Option<u32> {
+    if let Some(hex) = name.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        RAW_OPCODES
+            .iter()
+            .find(|(mnemonic, _)| *mnemonic == name)
+            .map(|(_, opcode)| *opcode)
+    }
+}
+
+fn is_word(ty: &ValType) -> bool {
+    matches!(ty, ValType::I32 | ValType::F32)
+}
+
+pub fn gen_raw(ctx: &mut Context, imported_func: &ImportedFunction, my_label: Label) {
+    let import = ctx.module.imports.get(imported_func.import);
+    let ty = ctx.module.types.get(imported_func.ty);
+    let name = import
+        .name
+        .strip_prefix("raw_")
+        .expect("raw intrinsic name should start with raw_");
+
+    // An instruction can be bound only if its operands are some number of
+    // loads followed by at most one store, which become the function's
+    // parameters and result.
+    let Some((loads, stores)) = lookup(name).and_then(operand_kinds).and_then(|kinds| {
+        let loads = kinds
+            .iter()
+            .take_while(|kind| **kind != OperandKind::Store)
+            .count();
+        let stores = kinds.len() - loads;
+        (stores <= 1
+            && kinds[loads..]
+                .iter()
+                .all(|kind| *kind == OperandKind::Store))
+        .then_some((loads, stores))
+    }) else {
+        ctx.errors
+            .push(CompilationError::UnrecognizedImport(import.clone()));
+        return;
+    };
+
+    if ty.params().len() != loads
+        || ty.results().len() != stores
+        || !ty.params().iter().all(is_word)
+        || !ty.results().iter().all(is_word)
+    {
+        ctx.errors.push(CompilationError::IncorrectlyTypedImport {
+            import: import.clone(),
+            expected: (vec![ValType::I32; loads], vec![ValType::I32; stores]),
+            actual: (ty.params().to_owned(), ty.results().to_owned()),
+        });
+        return;
+    }
+
+    // Parameters arrive in locals in reverse order, so the first load operand
+    // reads the highest-numbered local.
+    let load_operands: Vec<LoadOperand<Label>> = (0..loads)
+        .rev()
+        .map(|i| lloc(u32::try_from(i).expect("local index should fit in a u32")))
+        .collect();
+    let store_operand = push();
+    let mut operands: Vec<Operand<'_, Label>> = load_operands.iter().map(Operand::from).collect();
+    if stores == 1 {
+        operands.push(Operand::from(&store_operand));
+    }
+    let opcode = lookup(name).expect("name was already looked up");
+    let instr = Instr::from_operands(opcode, &operands)
+        .expect("operands should match the instruction's operand kinds");
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(u32::try_from(loads).expect("operand count should fit in a u32")),
+        Item::Instr(instr),
+        if stores == 1 { ret(pop()) } else { ret(imm(0)) },
+    );
+}
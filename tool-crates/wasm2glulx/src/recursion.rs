@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Call graph analysis, used to find functions which may recurse for
+//! `--stack-guard` and functions which are never called for warnings.
+
+use std::collections::{HashMap, HashSet};
+
+use walrus::{ir, ConstExpr, ElementItems, FunctionId, FunctionKind, LocalFunction, Module};
+
+/// Collects the direct callees of a function, and whether it makes any
+/// indirect calls or takes any function references.
+#[derive(Default)]
+pub struct Callees {
+    pub direct: Vec<FunctionId>,
+    pub refs: Vec<FunctionId>,
+    pub indirect: bool,
+}
+
+impl Callees {
+    pub fn of(local: &LocalFunction) -> Self {
+        let mut callees = Callees::default();
+        ir::dfs_in_order(&mut callees, local, local.entry_block());
+        callees
+    }
+}
+
+impl ir::Visitor<'_> for Callees {
+    fn visit_call(&mut self, instr: &ir::Call) {
+        self.direct.push(instr.func);
+    }
+
+    fn visit_return_call(&mut self, instr: &ir::ReturnCall) {
+        self.direct.push(instr.func);
+    }
+
+    fn visit_call_indirect(&mut self, _instr: &ir::CallIndirect) {
+        self.indirect = true;
+    }
+
+    fn visit_return_call_indirect(&mut self, _instr: &ir::ReturnCallIndirect) {
+        self.indirect = true;
+    }
+
+    fn visit_ref_func(&mut self, instr: &ir::RefFunc) {
+        self.refs.push(instr.func);
+    }
+}
+
+/// Returns the local functions which can call themselves, either directly or
+/// through other functions. An indirect call is taken to be able to reach any
+/// function which is placed in an element segment or referenced by
+/// `ref.func`, since that is every function which could end up in a table.
+pub fn recursive_functions(module: &Module) -> HashSet<FunctionId> {
+    let ids: Vec<FunctionId> = module.funcs.iter().map(|f| f.id()).collect();
+    let index: HashMap<FunctionId, usize> =
+        ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    // Node `ids.len()` stands for "whatever a table holds": indirect callers
+    // have an edge to it, and it has an edge to every function that might be
+    // in a table.
+    let table_node = ids.len();
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); ids.len() + 1];
+
+    for elem in module.elements.iter() {
+        match &elem.items {
+            ElementItems::Functions(v) => {
+                edges[table_node].extend(v.iter().map(|id| index[id]));
+            }
+            ElementItems::Expressions(_, v) => {
+                for expr in v {
+                    if let ConstExpr::RefFunc(id) = expr {
+                        edges[table_node].push(index[id]);
+                    }
+                }
+            }
+        }
+    }
+
+    for function in module.functions() {
+        if let FunctionKind::Local(local) = &function.kind {
+            let callees = Callees::of(local);
+            let node = index[&function.id()];
+            edges[node].extend(callees.direct.iter().map(|id| index[id]));
+            if callees.indirect {
+                edges[node].push(table_node);
+            }
+            edges[table_node].extend(callees.refs.iter().map(|id| index[id]));
+        }
+    }
+
+    let components = strongly_connected_components(&edges);
+    let mut sizes = vec![0usize; edges.len()];
+    for component in &components {
+        sizes[*component] += 1;
+    }
+
+    ids.iter()
+        .enumerate()
+        .filter(|(node, _)| sizes[components[*node]] > 1 || edges[*node].contains(node))
+        .map(|(_, id)| *id)
+        .collect()
+}
+
+/// Tarjan's algorithm, without recursion so that deep call graphs can't
+/// overflow the compiler's own stack. Returns the component number of each
+/// node.
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<usize> {
+    const UNVISITED: usize = usize::MAX;
+
+    let mut order = vec![UNVISITED; edges.len()];
+    let mut low = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut component = vec![UNVISITED; edges.len()];
+    let mut stack = Vec::new();
+    let mut next_order = 0;
+    let mut next_component = 0;
+
+    for root in 0..edges.len() {
+        if order[root] != UNVISITED {
+            continue;
+        }
+
+        // Each entry is a node being visited and how many of its edges have
+        // been followed so far.
+        let mut work = vec![(root, 0)];
+        order[root] = next_order;
+        low[root] = next_order;
+        next_order += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(frame) = work.last_mut() {
+            let node = frame.0;
+            if let Some(&next) = edges[node].get(frame.1) {
+                frame.1 += 1;
+                if order[next] == UNVISITED {
+                    order[next] = next_order;
+                    low[next] = next_order;
+                    next_order += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    work.push((next, 0));
+                } else if on_stack[next] {
+                    low[node] = low[node].min(order[next]);
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[node]);
+            }
+            if low[node] == order[node] {
+                loop {
+                    let member = stack.pop().expect("node should still be on the stack");
+                    on_stack[member] = false;
+                    component[member] = next_component;
+                    if member == node {
+                        break;
+                    }
+                }
+                next_component += 1;
+            }
+        }
+    }
+
+    component
+}
@@ -4,9 +4,15 @@
 use core::{f32, f64};
 
 use crate::common::*;
+use crate::layout::MemLayout;
 use glulx_asm::concise::*;
 
 use bytes::{BufMut, BytesMut};
+use glulx_asm::{
+    CallingConvention, FunctionBuilder, Item, LiteralPool, LoadOperand, Local, MysteryString,
+    StoreOperand,
+};
+use std::num::NonZeroU32;
 pub struct RuntimeLabels {
     pub swap: Label,
     pub swaps: Label,
@@ -14,10 +20,12 @@ pub struct RuntimeLabels {
     pub checkglkaddr: Label,
     pub checkstr: Label,
     pub checkunistr: Label,
+    pub memload128: Label,
     pub memload64: Label,
     pub memload32: Label,
     pub memload16: Label,
     pub memload8: Label,
+    pub memstore128: Label,
     pub memstore64: Label,
     pub memstore32: Label,
     pub memstore16: Label,
@@ -136,6 +144,62 @@ pub struct RuntimeLabels {
     pub trap_undefined_element: Label,
     pub trap_uninitialized_element: Label,
     pub trap_call_stack_exhausted: Label,
+    pub stack_remaining: Label,
+    pub stack_guard_failed: Label,
+    pub catch_token: Label,
+    pub trap_glk_area_size_mismatch: Label,
+    pub trap_integer_overflow_in: Label,
+    pub i32_add_checked: Label,
+    pub i32_sub_checked: Label,
+    pub i32_mul_checked: Label,
+    pub i64_add_checked: Label,
+    pub i64_sub_checked: Label,
+    pub i64_mul_checked: Label,
+    pub v128_and: Label,
+    pub v128_or: Label,
+    pub v128_xor: Label,
+    pub v128_andnot: Label,
+    pub v128_not: Label,
+    pub v128_bitselect: Label,
+    pub i8x16_add: Label,
+    pub i8x16_sub: Label,
+    pub i8x16_neg: Label,
+    pub i16x8_add: Label,
+    pub i16x8_sub: Label,
+    pub i16x8_neg: Label,
+    pub i16x8_mul: Label,
+    pub i32x4_add: Label,
+    pub i32x4_sub: Label,
+    pub i32x4_neg: Label,
+    pub i32x4_mul: Label,
+    pub v128_splat: Label,
+    pub v128_lane_word: Label,
+    pub v128_replace_lane: Label,
+    pub i8x16_swizzle: Label,
+    pub i8x16_shuffle: Label,
+    pub secondary_memory: MemoryLabels,
+}
+
+/// Labels of the helpers which access a particular memory. The primary
+/// memory's helpers are the like-named fields of [`RuntimeLabels`]; the memory
+/// lowered by `--lower-secondary-memory` gets a set of its own.
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryLabels {
+    pub checkaddr: Label,
+    pub memload128: Label,
+    pub memload64: Label,
+    pub memload32: Label,
+    pub memload16: Label,
+    pub memload8: Label,
+    pub memstore128: Label,
+    pub memstore64: Label,
+    pub memstore32: Label,
+    pub memstore16: Label,
+    pub memstore8: Label,
+    pub memory_init: Label,
+    pub memory_copy: Label,
+    pub memory_fill: Label,
+    pub memory_grow: Label,
 }
 
 impl RuntimeLabels {
@@ -147,10 +211,12 @@ impl RuntimeLabels {
             checkglkaddr: gen.gen("rt_checkglkaddr"),
             checkstr: gen.gen("rt_checkstr"),
             checkunistr: gen.gen("rt_checkunistr"),
+            memload128: gen.gen("rt_memload128"),
             memload64: gen.gen("rt_memload64"),
             memload32: gen.gen("rt_memload32"),
             memload16: gen.gen("rt_memload16"),
             memload8: gen.gen("rt_memload8"),
+            memstore128: gen.gen("rt_memstore128"),
             memstore64: gen.gen("rt_memstore64"),
             memstore32: gen.gen("rt_memstore32"),
             memstore16: gen.gen("rt_memstore16"),
@@ -269,6 +335,77 @@ impl RuntimeLabels {
             trap_undefined_element: gen.gen("trap_undefined_element"),
             trap_uninitialized_element: gen.gen("trap_uninitialized_element"),
             trap_call_stack_exhausted: gen.gen("trap_call_stack_exhausted"),
+            stack_remaining: gen.gen("rt_stack_remaining"),
+            stack_guard_failed: gen.gen("rt_stack_guard_failed"),
+            catch_token: gen.gen("rt_catch_token"),
+            trap_glk_area_size_mismatch: gen.gen("trap_glk_area_size_mismatch"),
+            trap_integer_overflow_in: gen.gen("trap_integer_overflow_in"),
+            i32_add_checked: gen.gen("rt_i32_add_checked"),
+            i32_sub_checked: gen.gen("rt_i32_sub_checked"),
+            i32_mul_checked: gen.gen("rt_i32_mul_checked"),
+            i64_add_checked: gen.gen("rt_i64_add_checked"),
+            i64_sub_checked: gen.gen("rt_i64_sub_checked"),
+            i64_mul_checked: gen.gen("rt_i64_mul_checked"),
+            v128_and: gen.gen("rt_v128_and"),
+            v128_or: gen.gen("rt_v128_or"),
+            v128_xor: gen.gen("rt_v128_xor"),
+            v128_andnot: gen.gen("rt_v128_andnot"),
+            v128_not: gen.gen("rt_v128_not"),
+            v128_bitselect: gen.gen("rt_v128_bitselect"),
+            i8x16_add: gen.gen("rt_i8x16_add"),
+            i8x16_sub: gen.gen("rt_i8x16_sub"),
+            i8x16_neg: gen.gen("rt_i8x16_neg"),
+            i16x8_add: gen.gen("rt_i16x8_add"),
+            i16x8_sub: gen.gen("rt_i16x8_sub"),
+            i16x8_neg: gen.gen("rt_i16x8_neg"),
+            i16x8_mul: gen.gen("rt_i16x8_mul"),
+            i32x4_add: gen.gen("rt_i32x4_add"),
+            i32x4_sub: gen.gen("rt_i32x4_sub"),
+            i32x4_neg: gen.gen("rt_i32x4_neg"),
+            i32x4_mul: gen.gen("rt_i32x4_mul"),
+            v128_splat: gen.gen("rt_v128_splat"),
+            v128_lane_word: gen.gen("rt_v128_lane_word"),
+            v128_replace_lane: gen.gen("rt_v128_replace_lane"),
+            i8x16_swizzle: gen.gen("rt_i8x16_swizzle"),
+            i8x16_shuffle: gen.gen("rt_i8x16_shuffle"),
+            secondary_memory: MemoryLabels {
+                checkaddr: gen.gen("rt_secondary_checkaddr"),
+                memload128: gen.gen("rt_secondary_memload128"),
+                memload64: gen.gen("rt_secondary_memload64"),
+                memload32: gen.gen("rt_secondary_memload32"),
+                memload16: gen.gen("rt_secondary_memload16"),
+                memload8: gen.gen("rt_secondary_memload8"),
+                memstore128: gen.gen("rt_secondary_memstore128"),
+                memstore64: gen.gen("rt_secondary_memstore64"),
+                memstore32: gen.gen("rt_secondary_memstore32"),
+                memstore16: gen.gen("rt_secondary_memstore16"),
+                memstore8: gen.gen("rt_secondary_memstore8"),
+                memory_init: gen.gen("rt_secondary_memory_init"),
+                memory_copy: gen.gen("rt_secondary_memory_copy"),
+                memory_fill: gen.gen("rt_secondary_memory_fill"),
+                memory_grow: gen.gen("rt_secondary_memory_grow"),
+            },
+        }
+    }
+
+    /// Returns the labels of the primary memory's helpers.
+    pub fn primary_memory(&self) -> MemoryLabels {
+        MemoryLabels {
+            checkaddr: self.checkaddr,
+            memload128: self.memload128,
+            memload64: self.memload64,
+            memload32: self.memload32,
+            memload16: self.memload16,
+            memload8: self.memload8,
+            memstore128: self.memstore128,
+            memstore64: self.memstore64,
+            memstore32: self.memstore32,
+            memstore16: self.memstore16,
+            memstore8: self.memstore8,
+            memory_init: self.memory_init,
+            memory_copy: self.memory_copy,
+            memory_fill: self.memory_fill,
+            memory_grow: self.memory_grow,
         }
     }
 }
@@ -304,7 +441,7 @@ fn gen_swaps(ctx: &mut Context) {
     );
 }
 
-fn gen_checkaddr(ctx: &mut Context) {
+fn gen_checkaddr(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 0;
     let offset = 1;
     let size = 2;
@@ -314,18 +451,14 @@ fn gen_checkaddr(ctx: &mut Context) {
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.checkaddr),
+        label(labels.checkaddr),
         fnhead_local(5),
         jgtu(
             lloc(size),
-            derefl(ctx.layout.memory().cur_size),
+            derefl(mem.cur_size),
             ctx.rt.trap_out_of_bounds_memory_access
         ),
-        sub(
-            derefl(ctx.layout.memory().cur_size),
-            lloc(size),
-            sloc(end_minus_size)
-        ),
+        sub(derefl(mem.cur_size), lloc(size), sloc(end_minus_size)),
         add(lloc(addr), lloc(offset), sloc(addr_plus_offset)),
         jltu(
             lloc(addr_plus_offset),
@@ -443,7 +576,7 @@ fn gen_checkunistr(ctx: &mut Context) {
     );
 }
 
-fn gen_memload64(ctx: &mut Context) {
+fn gen_memload64(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 1;
     let offset = 0;
 
@@ -451,10 +584,10 @@ fn gen_memload64(ctx: &mut Context) {
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memload64),
+        label(labels.memload64),
         fnhead_local(3),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(8),
@@ -462,7 +595,7 @@ fn gen_memload64(ctx: &mut Context) {
         ),
         aload(
             lloc(addr_plus_offset),
-            imml_off_shift(ctx.layout.memory().addr, 4, 2),
+            imml_off_shift(mem.addr, 4, 2),
             push()
         ),
         callfi(
@@ -472,82 +605,74 @@ fn gen_memload64(ctx: &mut Context) {
         ),
         aload(
             lloc(addr_plus_offset),
-            imml_off_shift(ctx.layout.memory().addr, 0, 2),
+            imml_off_shift(mem.addr, 0, 2),
             push()
         ),
         tailcall(imml(ctx.rt.swap), imm(1)),
     )
 }
 
-fn gen_memload32(ctx: &mut Context) {
+fn gen_memload32(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 1;
     let offset = 0;
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memload32),
+        label(labels.memload32),
         fnhead_local(2),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(4),
             push()
         ),
-        aload(
-            pop(),
-            imml_off_shift(ctx.layout.memory().addr, 0, 2),
-            push()
-        ),
+        aload(pop(), imml_off_shift(mem.addr, 0, 2), push()),
         tailcall(imml(ctx.rt.swap), imm(1)),
     );
 }
 
-fn gen_memload16(ctx: &mut Context) {
+fn gen_memload16(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 1;
     let offset = 0;
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memload16),
+        label(labels.memload16),
         fnhead_local(2),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(2),
             push()
         ),
-        aloads(
-            pop(),
-            imml_off_shift(ctx.layout.memory().addr, 0, 1),
-            push()
-        ),
+        aloads(pop(), imml_off_shift(mem.addr, 0, 1), push()),
         tailcall(imml(ctx.rt.swaps), imm(1)),
     );
 }
 
-fn gen_memload8(ctx: &mut Context) {
+fn gen_memload8(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 1;
     let offset = 0;
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memload8),
+        label(labels.memload8),
         fnhead_local(2),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(1),
             push()
         ),
-        aloadb(pop(), imml(ctx.layout.memory().addr), push()),
+        aloadb(pop(), imml(mem.addr), push()),
         ret(pop()),
     );
 }
 
-fn gen_memstore64(ctx: &mut Context) {
+fn gen_memstore64(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 3;
     let val_lo = 2;
     let val_hi = 1;
@@ -557,10 +682,10 @@ fn gen_memstore64(ctx: &mut Context) {
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memstore64),
+        label(labels.memstore64),
         fnhead_local(5),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(8),
@@ -569,84 +694,168 @@ fn gen_memstore64(ctx: &mut Context) {
         callfi(imml(ctx.rt.swap), lloc(val_lo), push()),
         astore(
             lloc(addr_plus_offset),
-            imml_off_shift(ctx.layout.memory().addr, 0, 2),
+            imml_off_shift(mem.addr, 0, 2),
             pop()
         ),
         callfi(imml(ctx.rt.swap), lloc(val_hi), push()),
         astore(
             lloc(addr_plus_offset),
-            imml_off_shift(ctx.layout.memory().addr, 4, 2),
+            imml_off_shift(mem.addr, 4, 2),
             pop()
         ),
         ret(imm(0)),
     );
 }
 
-fn gen_memstore32(ctx: &mut Context) {
+fn gen_memstore32(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 2;
     let val = 1;
     let offset = 0;
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memstore32),
+        label(labels.memstore32),
         fnhead_local(3),
         callfi(imml(ctx.rt.swap), lloc(val), push()),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(4),
             push(),
         ),
-        astore(pop(), imml_off_shift(ctx.layout.memory().addr, 0, 2), pop()),
+        astore(pop(), imml_off_shift(mem.addr, 0, 2), pop()),
         ret(imm(0)),
     );
 }
 
-fn gen_memstore16(ctx: &mut Context) {
+fn gen_memstore16(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 2;
     let val = 1;
     let offset = 0;
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memstore16),
+        label(labels.memstore16),
         fnhead_local(3),
         callfi(imml(ctx.rt.swaps), lloc(val), push()),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(2),
             push(),
         ),
-        astores(pop(), imml_off_shift(ctx.layout.memory().addr, 0, 1), pop()),
+        astores(pop(), imml_off_shift(mem.addr, 0, 1), pop()),
         ret(imm(0)),
     );
 }
 
-fn gen_memstore8(ctx: &mut Context) {
+fn gen_memstore8(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let addr = 2;
     let val = 1;
     let offset = 0;
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memstore8),
+        label(labels.memstore8),
         fnhead_local(3),
         callfiii(
-            imml(ctx.rt.checkaddr),
+            imml(labels.checkaddr),
             lloc(addr),
             lloc(offset),
             imm(1),
             push(),
         ),
-        astoreb(pop(), imml(ctx.layout.memory().addr), lloc(val)),
+        astoreb(pop(), imml(mem.addr), lloc(val)),
         ret(imm(0)),
     );
 }
 
+/// Loads a v128 from memory. Like `memload64`, but returns word 0 and leaves
+/// words 3, 2, and 1 of the result at consecutive words of the hi-return
+/// area.
+fn gen_memload128(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
+    let addr = 1;
+    let offset = 0;
+
+    let addr_plus_offset = 2;
+
+    push_all!(
+        ctx.rom_items,
+        label(labels.memload128),
+        fnhead_local(3),
+        callfiii(
+            imml(labels.checkaddr),
+            lloc(addr),
+            lloc(offset),
+            imm(16),
+            sloc(addr_plus_offset)
+        ),
+    );
+
+    for word in (1..4).rev() {
+        push_all!(
+            ctx.rom_items,
+            aload(
+                lloc(addr_plus_offset),
+                imml_off_shift(mem.addr, 4 * word, 2),
+                push()
+            ),
+            callfi(
+                imml(ctx.rt.swap),
+                pop(),
+                storel_off(ctx.layout.hi_return().addr, 4 * (3 - word))
+            ),
+        );
+    }
+
+    push_all!(
+        ctx.rom_items,
+        aload(
+            lloc(addr_plus_offset),
+            imml_off_shift(mem.addr, 0, 2),
+            push()
+        ),
+        tailcall(imml(ctx.rt.swap), imm(1)),
+    );
+}
+
+fn gen_memstore128(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
+    let addr = 5;
+    let offset = 0;
+
+    let addr_plus_offset = 6;
+
+    push_all!(
+        ctx.rom_items,
+        label(labels.memstore128),
+        fnhead_local(7),
+        callfiii(
+            imml(labels.checkaddr),
+            lloc(addr),
+            lloc(offset),
+            imm(16),
+            sloc(addr_plus_offset)
+        ),
+    );
+
+    // Word `k` of the value is in local `4 - k`.
+    for word in 0..4 {
+        push_all!(
+            ctx.rom_items,
+            callfi(imml(ctx.rt.swap), lloc(4 - word), push()),
+            astore(
+                lloc(addr_plus_offset),
+                imml_off_shift(mem.addr, 4 * word as i32, 2),
+                pop()
+            ),
+        );
+    }
+
+    ctx.rom_items.push(ret(imm(0)));
+}
+
 fn gen_swaparray(ctx: &mut Context) {
     let arraybase = 0;
     let arraylen = 1;
@@ -889,12 +1098,11 @@ fn gen_i32_rotr(ctx: &mut Context) {
     )
 }
 
-fn gen_i32_clz(ctx: &mut Context) {
+fn gen_i32_clz(ctx: &mut Context, pool: &mut LiteralPool<Label>) {
     let lead8 = ctx.gen.gen("clz_lead8");
     let lead16 = ctx.gen.gen("clz_lead16");
     let lead24 = ctx.gen.gen("clz_lead24");
 
-    let clz_table = ctx.gen.gen("clz_table");
     let mut table_bytes = BytesMut::with_capacity(256);
 
     for x in 0u8..=255 {
@@ -905,6 +1113,10 @@ fn gen_i32_clz(ctx: &mut Context) {
         );
     }
 
+    let clz_table = pool.intern(table_bytes.freeze(), NonZeroU32::MIN, || {
+        ctx.gen.gen("clz_table")
+    });
+
     let arg = 0;
     let tmp = 1;
 
@@ -932,17 +1144,14 @@ fn gen_i32_clz(ctx: &mut Context) {
         aloadb(imml(clz_table), lloc(arg), push()),
         add(pop(), imm(24), push()),
         ret(pop()),
-        label(clz_table),
-        blob(table_bytes.freeze()),
     )
 }
 
-fn gen_i32_ctz(ctx: &mut Context) {
+fn gen_i32_ctz(ctx: &mut Context, pool: &mut LiteralPool<Label>) {
     let trail8 = ctx.gen.gen("ctz_trail8");
     let trail16 = ctx.gen.gen("ctz_trail16");
     let trail24 = ctx.gen.gen("ctz_trail24");
 
-    let ctz_table = ctx.gen.gen("ctz_table");
     let mut table_bytes = BytesMut::with_capacity(256);
 
     for x in 0u8..=255 {
@@ -953,6 +1162,10 @@ fn gen_i32_ctz(ctx: &mut Context) {
         );
     }
 
+    let ctz_table = pool.intern(table_bytes.freeze(), NonZeroU32::MIN, || {
+        ctx.gen.gen("ctz_table")
+    });
+
     let arg = 0;
     let tmp = 1;
 
@@ -983,13 +1196,10 @@ fn gen_i32_ctz(ctx: &mut Context) {
         aloadb(imml(ctz_table), pop(), push()),
         add(pop(), imm(24), push()),
         ret(pop()),
-        label(ctz_table),
-        blob(table_bytes.freeze()),
     );
 }
 
-fn gen_i32_popcnt(ctx: &mut Context) {
-    let popcnt_table = ctx.gen.gen("popcnt_table");
+fn gen_i32_popcnt(ctx: &mut Context, pool: &mut LiteralPool<Label>) {
     let mut table_bytes = BytesMut::with_capacity(256);
 
     for x in 0u8..=255 {
@@ -1000,6 +1210,10 @@ fn gen_i32_popcnt(ctx: &mut Context) {
         );
     }
 
+    let popcnt_table = pool.intern(table_bytes.freeze(), NonZeroU32::MIN, || {
+        ctx.gen.gen("popcnt_table")
+    });
+
     let arg = 0;
 
     push_all!(
@@ -1020,8 +1234,6 @@ fn gen_i32_popcnt(ctx: &mut Context) {
         aloadb(imml(popcnt_table), pop(), push()),
         add(pop(), pop(), push()),
         ret(pop()),
-        label(popcnt_table),
-        blob(table_bytes.freeze()),
     );
 }
 
@@ -1311,7 +1523,7 @@ fn gen_i64_div_u(ctx: &mut Context) {
         jnz(lloc(n_lo), kk_xz),
         // K0/X0
         copy(imm(0), storel(ctx.layout.hi_return().addr)),
-        callfii(imml(ctx.rt.i32_div_u), lloc(d_lo), lloc(n_lo), push()),
+        callfii(imml(ctx.rt.i32_div_u), lloc(d_hi), lloc(n_hi), push()),
         ret(pop()),
         label(kk_xz),
         // KK/X0
@@ -3543,40 +3755,660 @@ fn gen_f64_convert_i64_s(ctx: &mut Context) {
     );
 }
 
-fn gen_trap(ctx: &mut Context) {
+/// Returns where a routine returning a v128 should put word `k` of its result.
+/// Words 3, 2, and 1 go to consecutive words of the hi-return area, and word 0
+/// is pushed so that it can be returned.
+fn v128_result_word(ctx: &Context, k: u32) -> StoreOperand<Label> {
+    if k == 0 {
+        push()
+    } else {
+        storel_off(ctx.layout.hi_return().addr, (4 * (3 - k)) as i32)
+    }
+}
+
+/// Generates a routine taking one v128 argument which computes each word of
+/// its result from the corresponding word of the argument. `op` is given the
+/// argument word and the destination for the result word, and must leave the
+/// stack as it found it apart from anything it pushes to the destination.
+fn gen_v128_wordwise1<F>(ctx: &mut Context, routine: Label, op: F)
+where
+    F: Fn(LoadOperand<Label>, StoreOperand<Label>) -> Vec<Item<Label>>,
+{
+    // Word `k` of the argument is in local `3 - k`.
+    push_all!(ctx.rom_items, label(routine), fnhead_local(4));
+    for k in (0..4).rev() {
+        let out = v128_result_word(ctx, k);
+        ctx.rom_items.extend(op(lloc(3 - k), out));
+    }
+    ctx.rom_items.push(ret(pop()));
+}
+
+/// Like `gen_v128_wordwise1`, but for routines taking two v128 arguments.
+fn gen_v128_wordwise2<F>(ctx: &mut Context, routine: Label, op: F)
+where
+    F: Fn(LoadOperand<Label>, LoadOperand<Label>, StoreOperand<Label>) -> Vec<Item<Label>>,
+{
+    // Word `k` of the first argument is in local `7 - k`, and word `k` of the
+    // second is in local `3 - k`.
+    push_all!(ctx.rom_items, label(routine), fnhead_local(8));
+    for k in (0..4).rev() {
+        let out = v128_result_word(ctx, k);
+        ctx.rom_items.extend(op(lloc(7 - k), lloc(3 - k), out));
+    }
+    ctx.rom_items.push(ret(pop()));
+}
+
+fn gen_v128_and(ctx: &mut Context) {
+    let routine = ctx.rt.v128_and;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| vec![bitand(x, y, out)]);
+}
+
+fn gen_v128_or(ctx: &mut Context) {
+    let routine = ctx.rt.v128_or;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| vec![bitor(x, y, out)]);
+}
+
+fn gen_v128_xor(ctx: &mut Context) {
+    let routine = ctx.rt.v128_xor;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| vec![bitxor(x, y, out)]);
+}
+
+fn gen_v128_andnot(ctx: &mut Context) {
+    let routine = ctx.rt.v128_andnot;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| {
+        vec![bitnot(y, push()), bitand(x, pop(), out)]
+    });
+}
+
+fn gen_v128_not(ctx: &mut Context) {
+    let routine = ctx.rt.v128_not;
+    gen_v128_wordwise1(ctx, routine, |x, out| vec![bitnot(x, out)]);
+}
+
+fn gen_v128_bitselect(ctx: &mut Context) {
+    // Word `k` of the first operand is in local `11 - k`, of the second in
+    // local `7 - k`, and of the mask in local `3 - k`.
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.trap_unreachable),
-        debugtrap(uimm(TrapCode::Unreachable.into())),
-        quit(),
-        label(ctx.rt.trap_integer_overflow),
-        debugtrap(uimm(TrapCode::IntegerOverflow.into())),
-        quit(),
-        label(ctx.rt.trap_integer_divide_by_zero),
-        debugtrap(uimm(TrapCode::IntegerDivideByZero.into())),
-        quit(),
-        label(ctx.rt.trap_invalid_conversion_to_integer),
-        debugtrap(uimm(TrapCode::InvalidConversionToInteger.into())),
-        quit(),
-        label(ctx.rt.trap_out_of_bounds_memory_access),
-        debugtrap(uimm(TrapCode::OutOfBoundsMemoryAccess.into())),
-        quit(),
-        label(ctx.rt.trap_indirect_call_type_mismatch),
-        debugtrap(uimm(TrapCode::IndirectCallTypeMismatch.into())),
-        quit(),
-        label(ctx.rt.trap_out_of_bounds_table_access),
-        debugtrap(uimm(TrapCode::OutOfBoundsTableAccess.into())),
-        quit(),
-        label(ctx.rt.trap_undefined_element),
-        debugtrap(uimm(TrapCode::UndefinedElement.into())),
-        quit(),
-        label(ctx.rt.trap_uninitialized_element),
-        debugtrap(uimm(TrapCode::UninitializedElement.into())),
-        quit(),
-        label(ctx.rt.trap_call_stack_exhausted),
-        debugtrap(uimm(TrapCode::CallStackExhausted.into())),
+        label(ctx.rt.v128_bitselect),
+        fnhead_local(12)
+    );
+    for k in (0..4).rev() {
+        let out = v128_result_word(ctx, k);
+        push_all!(
+            ctx.rom_items,
+            bitxor(lloc(11 - k), lloc(7 - k), push()),
+            bitand(pop(), lloc(3 - k), push()),
+            bitxor(pop(), lloc(7 - k), out),
+        );
+    }
+    ctx.rom_items.push(ret(pop()));
+}
+
+/// Generates a routine which adds two v128s lanewise. `high` has the high bit
+/// of each lane set. Masking off the high bits keeps carries from crossing
+/// lanes, and the high bits are then filled in from the operands and the
+/// carry into them.
+fn gen_swar_add(ctx: &mut Context, routine: Label, high: u32) {
+    gen_v128_wordwise2(ctx, routine, |x, y, out| {
+        vec![
+            bitand(x, uimm(!high), push()),
+            bitand(y, uimm(!high), push()),
+            add(pop(), pop(), push()),
+            bitxor(x, y, push()),
+            bitand(pop(), uimm(high), push()),
+            bitxor(pop(), pop(), out),
+        ]
+    });
+}
+
+/// Generates a routine which subtracts two v128s lanewise, by the same method
+/// as `gen_swar_add`. Setting the high bit of each lane of the minuend keeps
+/// borrows from crossing lanes.
+fn gen_swar_sub(ctx: &mut Context, routine: Label, high: u32) {
+    gen_v128_wordwise2(ctx, routine, |x, y, out| {
+        vec![
+            bitand(y, uimm(!high), push()),
+            bitor(x, uimm(high), push()),
+            sub(pop(), pop(), push()),
+            bitnot(y, push()),
+            bitxor(x, pop(), push()),
+            bitand(pop(), uimm(high), push()),
+            bitxor(pop(), pop(), out),
+        ]
+    });
+}
+
+/// Generates a routine which negates a v128 lanewise, by subtracting it from
+/// zero as in `gen_swar_sub`.
+fn gen_swar_neg(ctx: &mut Context, routine: Label, high: u32) {
+    gen_v128_wordwise1(ctx, routine, |x, out| {
+        vec![
+            bitand(x, uimm(!high), push()),
+            sub(uimm(high), pop(), push()),
+            bitnot(x, push()),
+            bitand(pop(), uimm(high), push()),
+            bitxor(pop(), pop(), out),
+        ]
+    });
+}
+
+fn gen_i16x8_mul(ctx: &mut Context) {
+    let routine = ctx.rt.i16x8_mul;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| {
+        vec![
+            bitand(x, imm(0xffff), push()),
+            bitand(y, imm(0xffff), push()),
+            mul(pop(), pop(), push()),
+            bitand(pop(), imm(0xffff), push()),
+            ushiftr(x, imm(16), push()),
+            ushiftr(y, imm(16), push()),
+            mul(pop(), pop(), push()),
+            shiftl(pop(), imm(16), push()),
+            bitor(pop(), pop(), out),
+        ]
+    });
+}
+
+fn gen_i32x4_add(ctx: &mut Context) {
+    let routine = ctx.rt.i32x4_add;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| vec![add(x, y, out)]);
+}
+
+fn gen_i32x4_sub(ctx: &mut Context) {
+    let routine = ctx.rt.i32x4_sub;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| vec![sub(x, y, out)]);
+}
+
+fn gen_i32x4_neg(ctx: &mut Context) {
+    let routine = ctx.rt.i32x4_neg;
+    gen_v128_wordwise1(ctx, routine, |x, out| vec![sub(imm(0), x, out)]);
+}
+
+fn gen_i32x4_mul(ctx: &mut Context) {
+    let routine = ctx.rt.i32x4_mul;
+    gen_v128_wordwise2(ctx, routine, |x, y, out| vec![mul(x, y, out)]);
+}
+
+/// Generates a routine which returns a v128 with every word equal to its
+/// argument. Callers replicate narrower lanes across the word first.
+fn gen_v128_splat(ctx: &mut Context) {
+    let x = 0;
+
+    push_all!(
+        ctx.rom_items,
+        label(ctx.rt.v128_splat),
+        fnhead_local(1),
+        copy(lloc(x), storel(ctx.layout.hi_return().addr)),
+        copy(lloc(x), storel_off(ctx.layout.hi_return().addr, 4)),
+        copy(lloc(x), storel_off(ctx.layout.hi_return().addr, 8)),
+        ret(lloc(x)),
+    );
+}
+
+/// Generates a routine which takes a word index `k` followed by a v128, and
+/// returns word `k` of the v128.
+fn gen_v128_lane_word(ctx: &mut Context) {
+    let k = 0;
+
+    // Word `j` of the v128 is in local `4 - j`. Pushing them in order leaves
+    // word `j` at depth `3 - j`.
+    push_all!(
+        ctx.rom_items,
+        label(ctx.rt.v128_lane_word),
+        fnhead_local(5),
+        copy(lloc(4), push()),
+        copy(lloc(3), push()),
+        copy(lloc(2), push()),
+        copy(lloc(1), push()),
+        sub(imm(3), lloc(k), push()),
+        stkpeek(pop(), push()),
+        ret(pop()),
+    );
+}
+
+/// Generates a routine which takes a word index `k`, a shift, a mask, a lane
+/// value, and a v128, and returns the v128 with the bits of word `k` selected
+/// by the mask replaced by the lane value shifted left by the shift.
+fn gen_v128_replace_lane(ctx: &mut Context) {
+    let k = 0;
+    let shift = 1;
+    let mask = 2;
+    let val = 3;
+
+    let bits = 8;
+
+    push_all!(
+        ctx.rom_items,
+        label(ctx.rt.v128_replace_lane),
+        fnhead_local(9),
+        shiftl(lloc(val), lloc(shift), push()),
+        bitand(pop(), lloc(mask), sloc(bits)),
+    );
+
+    // Word `j` of the v128 is in local `7 - j`.
+    for j in 0..4 {
+        let skip = ctx.gen.gen("v128_replace_lane_skip");
+        push_all!(
+            ctx.rom_items,
+            jne(lloc(k), uimm(j), skip),
+            bitnot(lloc(mask), push()),
+            bitand(lloc(7 - j), pop(), push()),
+            bitor(pop(), lloc(bits), sloc(7 - j)),
+            label(skip),
+        );
+    }
+
+    push_all!(
+        ctx.rom_items,
+        copy(lloc(4), storel(ctx.layout.hi_return().addr)),
+        copy(lloc(5), storel_off(ctx.layout.hi_return().addr, 4)),
+        copy(lloc(6), storel_off(ctx.layout.hi_return().addr, 8)),
+        ret(lloc(7)),
+    );
+}
+
+/// Generates a routine which takes a table of `table_words` words, given as
+/// v128s, followed by a v128 of byte indices. Byte `i` of the result is the
+/// byte of the table selected by byte `i` of the indices, or 0 if that index
+/// is past the end of the table.
+fn gen_v128_select_bytes(ctx: &mut Context, routine: Label, table_words: u32) {
+    // Word `k` of the indices is in local `3 - k`, and word `t` of the table
+    // is in local `3 + table_words - t`.
+    let i = 4 + table_words;
+    let j = i + 1;
+    let acc = i + 2;
+    let tmp = i + 3;
+
+    let top = ctx.gen.gen("v128_select_bytes_top");
+    let skip = ctx.gen.gen("v128_select_bytes_skip");
+    let done = ctx.gen.gen("v128_select_bytes_done");
+
+    push_all!(ctx.rom_items, label(routine), fnhead_local(i + 4));
+
+    // Push the arguments back onto the stack so that `stkpeek` can index them.
+    // This leaves word `k` of the indices at depth `3 - k` and word `t` of the
+    // table at depth `3 + table_words - t`.
+    for local in (0..i).rev() {
+        ctx.rom_items.push(copy(lloc(local), push()));
+    }
+
+    // Build the result a byte at a time from the most significant end,
+    // storing each word as it's completed.
+    push_all!(
+        ctx.rom_items,
+        copy(imm(16), sloc(i)),
+        label(top),
+        sub(lloc(i), imm(1), sloc(i)),
+        ushiftr(lloc(i), imm(2), push()),
+        sub(imm(3), pop(), push()),
+        stkpeek(pop(), sloc(tmp)),
+        bitand(lloc(i), imm(3), push()),
+        shiftl(pop(), imm(3), push()),
+        ushiftr(lloc(tmp), pop(), push()),
+        bitand(pop(), imm(0xff), sloc(j)),
+        shiftl(lloc(acc), imm(8), sloc(acc)),
+        jgeu(lloc(j), uimm(4 * table_words), skip),
+        ushiftr(lloc(j), imm(2), push()),
+        sub(uimm(3 + table_words), pop(), push()),
+        stkpeek(pop(), sloc(tmp)),
+        bitand(lloc(j), imm(3), push()),
+        shiftl(pop(), imm(3), push()),
+        ushiftr(lloc(tmp), pop(), push()),
+        bitand(pop(), imm(0xff), push()),
+        bitor(lloc(acc), pop(), sloc(acc)),
+        label(skip),
+        bitand(lloc(i), imm(3), push()),
+        jnz(pop(), top),
+        jz(lloc(i), done),
+        ushiftr(lloc(i), imm(2), push()),
+        sub(imm(3), pop(), push()),
+        astore(imml(ctx.layout.hi_return().addr), pop(), lloc(acc)),
+        jump(top),
+        label(done),
+        ret(lloc(acc)),
+    );
+}
+
+/// Returns how many bytes of stack remain free. Glulx has no instruction
+/// which reads the stack pointer, but the token which `catch` generates is
+/// the stack pointer in Glulxe and other interpreters that implement `throw`
+/// by restoring it. The call stub which `catch` pushes is discarded when this
+/// routine returns.
+fn gen_stack_remaining(ctx: &mut Context) {
+    let caught = ctx.gen.gen("stack_remaining_caught");
+
+    push_all!(
+        ctx.rom_items,
+        label(ctx.rt.stack_remaining),
+        fnhead_local(0),
+        catch(push(), caught),
+        label(caught),
+        sub(uimm(ctx.options.stack_size), pop(), push()),
+        ret(pop())
+    );
+}
+
+/// Code which a function's `--stack-guard` check branches to when too little
+/// stack remains. If a `catch` intrinsic is active, this throws
+/// [`STACK_EXHAUSTED`] to the innermost one, whose token is kept in
+/// `catch_token`; otherwise it traps.
+fn gen_stack_guard_failed(ctx: &mut Context) {
+    ctx.zero_items.push(zalign(4));
+    ctx.zero_items.push(zlabel(ctx.rt.catch_token));
+    ctx.zero_items.push(zspace(4));
+
+    push_all!(
+        ctx.rom_items,
+        label(ctx.rt.stack_guard_failed),
+        jz(derefl(ctx.rt.catch_token), ctx.rt.trap_call_stack_exhausted),
+        throw(uimm(STACK_EXHAUSTED), derefl(ctx.rt.catch_token)),
+    );
+}
+
+fn gen_trap(ctx: &mut Context, pool: &mut LiteralPool<Label>) {
+    let traps = [
+        (ctx.rt.trap_unreachable, TrapCode::Unreachable),
+        (ctx.rt.trap_integer_overflow, TrapCode::IntegerOverflow),
+        (
+            ctx.rt.trap_integer_divide_by_zero,
+            TrapCode::IntegerDivideByZero,
+        ),
+        (
+            ctx.rt.trap_invalid_conversion_to_integer,
+            TrapCode::InvalidConversionToInteger,
+        ),
+        (
+            ctx.rt.trap_out_of_bounds_memory_access,
+            TrapCode::OutOfBoundsMemoryAccess,
+        ),
+        (
+            ctx.rt.trap_indirect_call_type_mismatch,
+            TrapCode::IndirectCallTypeMismatch,
+        ),
+        (
+            ctx.rt.trap_out_of_bounds_table_access,
+            TrapCode::OutOfBoundsTableAccess,
+        ),
+        (ctx.rt.trap_undefined_element, TrapCode::UndefinedElement),
+        (
+            ctx.rt.trap_uninitialized_element,
+            TrapCode::UninitializedElement,
+        ),
+        (
+            ctx.rt.trap_call_stack_exhausted,
+            TrapCode::CallStackExhausted,
+        ),
+        (
+            ctx.rt.trap_glk_area_size_mismatch,
+            TrapCode::GlkAreaSizeMismatch,
+        ),
+    ];
+
+    if !ctx.options.trap_messages {
+        for (trap_label, code) in traps {
+            push_all!(
+                ctx.rom_items,
+                label(trap_label),
+                debugtrap(uimm(code.into())),
+                quit(),
+            );
+        }
+        return;
+    }
+
+    // Each trap pushes its code and joins a common path which prints
+    // "[wasm trap: <kind> in <location>]" to the current stream, if there is
+    // one, before quitting.
+    let report = ctx.gen.gen("trap_report");
+    let no_location = ctx.gen.gen("trap_no_location");
+    let no_stream = ctx.gen.gen("trap_no_stream");
+    let prefix = pool.intern_string(
+        MysteryString::from_chars_lossy("\n[wasm trap: ".chars()),
+        || ctx.gen.gen("trap_prefix"),
+    );
+    let infix = pool.intern_string(MysteryString::from_chars_lossy(" in ".chars()), || {
+        ctx.gen.gen("trap_infix")
+    });
+
+    for (trap_label, code) in traps {
+        push_all!(
+            ctx.rom_items,
+            label(trap_label),
+            copy(uimm(code.into()), push()),
+            jump(report),
+        );
+    }
+
+    push_all!(
+        ctx.rom_items,
+        label(report),
+        glk(imm(0x0048) /*glk_stream_get_current*/, imm(0), push()),
+        jz(pop(), no_stream),
+        streamstr(imml(prefix)),
+        stkpeek(imm(0), push()),
+        aload(imml(ctx.layout.trap().string_table), pop(), push()),
+        streamstr(pop()),
+        jz(derefl(ctx.layout.trap().location), no_location),
+        streamstr(imml(infix)),
+        streamstr(derefl(ctx.layout.trap().location)),
+        label(no_location),
+        streamchar(imm(b']'.into())),
+        streamchar(imm(b'\n'.into())),
+        label(no_stream),
+        debugtrap(pop()),
         quit(),
-    )
+    );
+}
+
+fn gen_trap_integer_overflow_in(ctx: &mut Context, pool: &mut LiteralPool<Label>) {
+    let mut f = FunctionBuilder::new(
+        ctx.rt.trap_integer_overflow_in,
+        CallingConvention::ArgsInLocals,
+    );
+    let name = f.arg("name");
+
+    // The general trap message already names the function.
+    if ctx.options.trap_messages {
+        f.push(jump(ctx.rt.trap_integer_overflow));
+        ctx.rom_items.extend(f.finish());
+        return;
+    }
+
+    let no_stream = ctx.gen.gen("overflow_no_stream");
+    let prefix = pool.intern_string(
+        MysteryString::from_chars_lossy("\n[integer overflow in ".chars()),
+        || ctx.gen.gen("overflow_prefix"),
+    );
+
+    f.extend([
+        glk(imm(0x0048) /*glk_stream_get_current*/, imm(0), push()),
+        jz(pop(), no_stream),
+        streamstr(imml(prefix)),
+        streamstr(name.load()),
+        streamchar(imm(b']'.into())),
+        streamchar(imm(b'\n'.into())),
+        label(no_stream),
+        jump(ctx.rt.trap_integer_overflow),
+    ]);
+    ctx.rom_items.extend(f.finish());
+}
+
+/// Starts building a 32-bit checked arithmetic helper, which is called as
+/// `callfiii helper y x name`. Returns the builder and its `y`, `x`, and `name`
+/// arguments.
+fn checked32_builder(label: Label) -> (FunctionBuilder<Label>, Local, Local, Local) {
+    let mut f = FunctionBuilder::new(label, CallingConvention::ArgsInLocals);
+    let y = f.arg("y");
+    let x = f.arg("x");
+    let name = f.arg("name");
+    (f, y, x, name)
+}
+
+/// Starts building a 64-bit checked arithmetic helper, which takes `x` and `y`
+/// low word first and then `name` on the stack. Returns the builder and its
+/// `name`, `y_hi`, `y_lo`, `x_hi`, and `x_lo` arguments, in which the stack
+/// arguments arrive in reverse order.
+fn checked64_builder(label: Label) -> (FunctionBuilder<Label>, [Local; 5]) {
+    let mut f = FunctionBuilder::new(label, CallingConvention::ArgsInLocals);
+    let args = ["name", "y_hi", "y_lo", "x_hi", "x_lo"].map(|arg| f.arg(arg));
+    (f, args)
+}
+
+/// Finishes a checked arithmetic helper whose result is in `result` unless
+/// the code before jumps to `overflow`.
+fn finish_checked(
+    ctx: &mut Context,
+    mut f: FunctionBuilder<Label>,
+    name: Local,
+    result: Local,
+    overflow: Label,
+) {
+    f.extend([
+        ret(result.load()),
+        label(overflow),
+        copy(name.load(), push()),
+        tailcall(imml(ctx.rt.trap_integer_overflow_in), imm(1)),
+    ]);
+    ctx.rom_items.extend(f.finish());
+}
+
+fn gen_i32_add_checked(ctx: &mut Context) {
+    let (mut f, y, x, name) = checked32_builder(ctx.rt.i32_add_checked);
+    let sum = f.local("sum");
+    let overflow = ctx.gen.gen("add32_overflow");
+
+    f.extend([
+        add(x.load(), y.load(), sum.store()),
+        // Overflow iff the sum's sign differs from both operands' signs.
+        bitxor(x.load(), sum.load(), push()),
+        bitxor(y.load(), sum.load(), push()),
+        bitand(pop(), pop(), push()),
+        jlt(pop(), imm(0), overflow),
+    ]);
+    finish_checked(ctx, f, name, sum, overflow);
+}
+
+fn gen_i32_sub_checked(ctx: &mut Context) {
+    let (mut f, y, x, name) = checked32_builder(ctx.rt.i32_sub_checked);
+    let diff = f.local("diff");
+    let overflow = ctx.gen.gen("sub32_overflow");
+
+    f.extend([
+        sub(x.load(), y.load(), diff.store()),
+        // Overflow iff the operands' signs differ and the difference's sign
+        // differs from the minuend's.
+        bitxor(x.load(), y.load(), push()),
+        bitxor(x.load(), diff.load(), push()),
+        bitand(pop(), pop(), push()),
+        jlt(pop(), imm(0), overflow),
+    ]);
+    finish_checked(ctx, f, name, diff, overflow);
+}
+
+fn gen_i32_mul_checked(ctx: &mut Context) {
+    let (mut f, y, x, name) = checked32_builder(ctx.rt.i32_mul_checked);
+    let prod = f.local("prod");
+    let ok = ctx.gen.gen("mul32_ok");
+    let divcheck = ctx.gen.gen("mul32_divcheck");
+    let overflow = ctx.gen.gen("mul32_overflow");
+
+    f.extend([
+        mul(x.load(), y.load(), prod.store()),
+        jz(x.load(), ok),
+        // Dividing by -1 could itself overflow, so handle it separately: -1 * y
+        // overflows only when y is the minimum integer.
+        jne(x.load(), imm(-1), divcheck),
+        jeq(y.load(), uimm(0x80000000), overflow),
+        jump(ok),
+        label(divcheck),
+        div(prod.load(), x.load(), push()),
+        jne(pop(), y.load(), overflow),
+        label(ok),
+    ]);
+    finish_checked(ctx, f, name, prod, overflow);
+}
+
+fn gen_i64_add_checked(ctx: &mut Context) {
+    let (mut f, [name, y_hi, y_lo, x_hi, x_lo]) = checked64_builder(ctx.rt.i64_add_checked);
+    let sum_lo = f.local("sum_lo");
+    let sum_hi = f.local("sum_hi");
+    let overflow = ctx.gen.gen("add64_overflow");
+
+    f.extend([
+        copy(x_lo.load(), push()),
+        copy(x_hi.load(), push()),
+        copy(y_lo.load(), push()),
+        copy(y_hi.load(), push()),
+        call(imml(ctx.rt.i64_add), imm(4), sum_lo.store()),
+        copy(derefl(ctx.layout.hi_return().addr), sum_hi.store()),
+        bitxor(x_hi.load(), sum_hi.load(), push()),
+        bitxor(y_hi.load(), sum_hi.load(), push()),
+        bitand(pop(), pop(), push()),
+        jlt(pop(), imm(0), overflow),
+    ]);
+    finish_checked(ctx, f, name, sum_lo, overflow);
+}
+
+fn gen_i64_sub_checked(ctx: &mut Context) {
+    let (mut f, [name, y_hi, y_lo, x_hi, x_lo]) = checked64_builder(ctx.rt.i64_sub_checked);
+    let diff_lo = f.local("diff_lo");
+    let diff_hi = f.local("diff_hi");
+    let overflow = ctx.gen.gen("sub64_overflow");
+
+    f.extend([
+        copy(x_lo.load(), push()),
+        copy(x_hi.load(), push()),
+        copy(y_lo.load(), push()),
+        copy(y_hi.load(), push()),
+        call(imml(ctx.rt.i64_sub), imm(4), diff_lo.store()),
+        copy(derefl(ctx.layout.hi_return().addr), diff_hi.store()),
+        bitxor(x_hi.load(), y_hi.load(), push()),
+        bitxor(x_hi.load(), diff_hi.load(), push()),
+        bitand(pop(), pop(), push()),
+        jlt(pop(), imm(0), overflow),
+    ]);
+    finish_checked(ctx, f, name, diff_lo, overflow);
+}
+
+fn gen_i64_mul_checked(ctx: &mut Context) {
+    let (mut f, [name, y_hi, y_lo, x_hi, x_lo]) = checked64_builder(ctx.rt.i64_mul_checked);
+    let prod_lo = f.local("prod_lo");
+    let prod_hi = f.local("prod_hi");
+    let nonzero = ctx.gen.gen("mul64_nonzero");
+    let divcheck = ctx.gen.gen("mul64_divcheck");
+    let ok = ctx.gen.gen("mul64_ok");
+    let overflow = ctx.gen.gen("mul64_overflow");
+
+    f.extend([
+        copy(x_lo.load(), push()),
+        copy(x_hi.load(), push()),
+        copy(y_lo.load(), push()),
+        copy(y_hi.load(), push()),
+        call(imml(ctx.rt.i64_mul), imm(4), prod_lo.store()),
+        copy(derefl(ctx.layout.hi_return().addr), prod_hi.store()),
+        jnz(x_hi.load(), nonzero),
+        jz(x_lo.load(), ok),
+        label(nonzero),
+        // As in the 32-bit case, -1 * y overflows only when y is the minimum
+        // integer, and dividing by -1 must be avoided.
+        jne(x_hi.load(), imm(-1), divcheck),
+        jne(x_lo.load(), imm(-1), divcheck),
+        jne(y_hi.load(), uimm(0x80000000), ok),
+        jz(y_lo.load(), overflow),
+        jump(ok),
+        label(divcheck),
+        copy(prod_lo.load(), push()),
+        copy(prod_hi.load(), push()),
+        copy(x_lo.load(), push()),
+        copy(x_hi.load(), push()),
+        call(imml(ctx.rt.i64_div_s), imm(4), push()),
+        jne(pop(), y_lo.load(), overflow),
+        jne(derefl(ctx.layout.hi_return().addr), y_hi.load(), overflow),
+        label(ok),
+        copy(prod_hi.load(), storel(ctx.layout.hi_return().addr)),
+    ]);
+    finish_checked(ctx, f, name, prod_lo, overflow);
 }
 
 fn gen_table_init_or_copy(ctx: &mut Context) {
@@ -3672,7 +4504,7 @@ fn gen_table_fill(ctx: &mut Context) {
     );
 }
 
-fn gen_memory_init(ctx: &mut Context) {
+fn gen_memory_init(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let mem_offset = 4;
     let data_offset = 3;
     let n = 2;
@@ -3681,7 +4513,7 @@ fn gen_memory_init(ctx: &mut Context) {
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memory_init),
+        label(labels.memory_init),
         fnhead_local(5),
         jgtu(
             lloc(data_offset),
@@ -3692,23 +4524,19 @@ fn gen_memory_init(ctx: &mut Context) {
         jgtu(lloc(n), pop(), ctx.rt.trap_out_of_bounds_memory_access),
         jgtu(
             lloc(mem_offset),
-            derefl(ctx.layout.memory().cur_size),
+            derefl(mem.cur_size),
             ctx.rt.trap_out_of_bounds_memory_access
         ),
-        sub(
-            derefl(ctx.layout.memory().cur_size),
-            lloc(mem_offset),
-            push()
-        ),
+        sub(derefl(mem.cur_size), lloc(mem_offset), push()),
         jgtu(lloc(n), pop(), ctx.rt.trap_out_of_bounds_memory_access),
-        add(lloc(mem_offset), imml(ctx.layout.memory().addr), push()),
+        add(lloc(mem_offset), imml(mem.addr), push()),
         add(lloc(data_offset), lloc(data_addr), push()),
         mcopy(lloc(n), pop(), pop()),
         ret(imm(0)),
     )
 }
 
-fn gen_memory_copy(ctx: &mut Context) {
+fn gen_memory_copy(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let d = 2;
     let s = 1;
     let n = 0;
@@ -3718,7 +4546,7 @@ fn gen_memory_copy(ctx: &mut Context) {
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memory_copy),
+        label(labels.memory_copy),
         fnhead_local(5),
         add(lloc(s), lloc(n), sloc(s_plus_n)),
         add(lloc(d), lloc(n), sloc(d_plus_n)),
@@ -3734,22 +4562,22 @@ fn gen_memory_copy(ctx: &mut Context) {
         ),
         jgtu(
             lloc(s_plus_n),
-            derefl(ctx.layout.memory().cur_size),
+            derefl(mem.cur_size),
             ctx.rt.trap_out_of_bounds_memory_access
         ),
         jgtu(
             lloc(d_plus_n),
-            derefl(ctx.layout.memory().cur_size),
+            derefl(mem.cur_size),
             ctx.rt.trap_out_of_bounds_memory_access
         ),
-        add(imml(ctx.layout.memory().addr), lloc(d), push()),
-        add(imml(ctx.layout.memory().addr), lloc(s), push()),
+        add(imml(mem.addr), lloc(d), push()),
+        add(imml(mem.addr), lloc(s), push()),
         mcopy(lloc(n), pop(), pop()),
         ret(imm(0))
     )
 }
 
-fn gen_memory_fill(ctx: &mut Context) {
+fn gen_memory_fill(ctx: &mut Context, labels: &MemoryLabels, mem: &MemLayout) {
     let d = 2;
     let val = 1;
     let n = 0;
@@ -3763,7 +4591,7 @@ fn gen_memory_fill(ctx: &mut Context) {
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memory_fill),
+        label(labels.memory_fill),
         fnhead_local(4),
         add(lloc(d), lloc(n), sloc(d_plus_n)),
         jltu(
@@ -3773,7 +4601,7 @@ fn gen_memory_fill(ctx: &mut Context) {
         ),
         jgtu(
             lloc(d_plus_n),
-            derefl(ctx.layout.memory().cur_size),
+            derefl(mem.cur_size),
             ctx.rt.trap_out_of_bounds_memory_access
         ),
         jz(lloc(1), memzero),
@@ -3784,57 +4612,64 @@ fn gen_memory_fill(ctx: &mut Context) {
         bitor(lloc(val), pop(), sloc(val)),
         label(loop_start),
         jltu(lloc(n), uimm(4), loop_done),
-        astore(
-            lloc(d),
-            imml_off_shift(ctx.layout.memory().addr, 0, 2),
-            lloc(val)
-        ),
+        astore(lloc(d), imml_off_shift(mem.addr, 0, 2), lloc(val)),
         sub(lloc(n), uimm(4), sloc(n)),
         add(lloc(d), uimm(4), sloc(d)),
         jump(loop_start),
         label(loop_done),
         jltu(lloc(n), uimm(2), halfword_done),
-        astores(
-            lloc(d),
-            imml_off_shift(ctx.layout.memory().addr, 0, 1),
-            lloc(val)
-        ),
+        astores(lloc(d), imml_off_shift(mem.addr, 0, 1), lloc(val)),
         sub(lloc(n), uimm(2), sloc(n)),
         add(lloc(d), uimm(2), sloc(d)),
         label(halfword_done),
         jz_ret(lloc(n), false),
-        astoreb(lloc(d), imml_off(ctx.layout.memory().addr, 0), lloc(val)),
+        astoreb(lloc(d), imml_off(mem.addr, 0), lloc(val)),
         ret(imm(0)),
         label(memzero),
-        add(imml(ctx.layout.memory().addr), lloc(d), push()),
+        add(imml(mem.addr), lloc(d), push()),
         mzero(lloc(n), pop()),
         ret(imm(0)),
     )
 }
 
-pub fn gen_memory_grow(ctx: &mut Context) {
+/// Generates `memory.grow` for `mem`. The primary memory is at the end of
+/// RAM, and grows RAM along with it when `resizes_ram` is true; a secondary
+/// memory has its maximum size reserved already and only needs its size
+/// updated.
+pub fn gen_memory_grow(
+    ctx: &mut Context,
+    labels: &MemoryLabels,
+    mem: &MemLayout,
+    resizes_ram: bool,
+) {
     let growth = 0;
     let fail = ctx.gen.gen("rt_memory_grow_fail");
 
     push_all!(
         ctx.rom_items,
-        label(ctx.rt.memory_grow),
+        label(labels.memory_grow),
         fnhead_local(1),
         jgtu(lloc(growth), uimm(65535), fail),
         shiftl(lloc(growth), imm(16), sloc(growth)),
-        jgtu(lloc(growth), uimm(ctx.layout.memory().max_size), fail),
-        sub(uimm(ctx.layout.memory().max_size), lloc(growth), push()),
-        jltu(pop(), derefl(ctx.layout.memory().cur_size), fail),
-        getmemsize(push()),
-        add(lloc(growth), pop(), push()),
-        setmemsize(pop(), push()),
-        jnz(pop(), fail),
-        copy(derefl(ctx.layout.memory().cur_size), push()),
-        add(
-            derefl(ctx.layout.memory().cur_size),
-            lloc(growth),
-            storel(ctx.layout.memory().cur_size)
-        ),
+        jgtu(lloc(growth), uimm(mem.max_size), fail),
+        sub(uimm(mem.max_size), lloc(growth), push()),
+        jltu(pop(), derefl(mem.cur_size), fail),
+    );
+
+    if resizes_ram {
+        push_all!(
+            ctx.rom_items,
+            getmemsize(push()),
+            add(lloc(growth), pop(), push()),
+            setmemsize(pop(), push()),
+            jnz(pop(), fail),
+        );
+    }
+
+    push_all!(
+        ctx.rom_items,
+        copy(derefl(mem.cur_size), push()),
+        add(derefl(mem.cur_size), lloc(growth), storel(mem.cur_size)),
         ushiftr(pop(), imm(16), push()),
         ret(pop()),
         label(fail),
@@ -3842,21 +4677,49 @@ pub fn gen_memory_grow(ctx: &mut Context) {
     );
 }
 
+/// Generates a second copy of every helper which accesses memory, for the
+/// memory lowered by `--lower-secondary-memory`.
+fn gen_secondary_memory(ctx: &mut Context, mem: &MemLayout) {
+    let labels = ctx.rt.secondary_memory;
+
+    gen_checkaddr(ctx, &labels, mem);
+    gen_memload128(ctx, &labels, mem);
+    gen_memload64(ctx, &labels, mem);
+    gen_memload32(ctx, &labels, mem);
+    gen_memload16(ctx, &labels, mem);
+    gen_memload8(ctx, &labels, mem);
+    gen_memstore128(ctx, &labels, mem);
+    gen_memstore64(ctx, &labels, mem);
+    gen_memstore32(ctx, &labels, mem);
+    gen_memstore16(ctx, &labels, mem);
+    gen_memstore8(ctx, &labels, mem);
+    gen_memory_init(ctx, &labels, mem);
+    gen_memory_copy(ctx, &labels, mem);
+    gen_memory_fill(ctx, &labels, mem);
+    gen_memory_grow(ctx, &labels, mem, false);
+}
+
 pub fn gen_rt(ctx: &mut Context) {
+    let mut pool = LiteralPool::new();
+    let layout = ctx.layout;
+    let primary = ctx.rt.primary_memory();
+
     gen_swap(ctx);
     gen_swaps(ctx);
-    gen_checkaddr(ctx);
+    gen_checkaddr(ctx, &primary, layout.memory());
     gen_checkglkaddr(ctx);
     gen_checkstr(ctx);
     gen_checkunistr(ctx);
-    gen_memload64(ctx);
-    gen_memload32(ctx);
-    gen_memload16(ctx);
-    gen_memload8(ctx);
-    gen_memstore64(ctx);
-    gen_memstore32(ctx);
-    gen_memstore16(ctx);
-    gen_memstore8(ctx);
+    gen_memload128(ctx, &primary, layout.memory());
+    gen_memload64(ctx, &primary, layout.memory());
+    gen_memload32(ctx, &primary, layout.memory());
+    gen_memload16(ctx, &primary, layout.memory());
+    gen_memload8(ctx, &primary, layout.memory());
+    gen_memstore128(ctx, &primary, layout.memory());
+    gen_memstore64(ctx, &primary, layout.memory());
+    gen_memstore32(ctx, &primary, layout.memory());
+    gen_memstore16(ctx, &primary, layout.memory());
+    gen_memstore8(ctx, &primary, layout.memory());
     gen_swaparray(ctx);
     gen_swapglkarray(ctx);
     gen_swapunistr(ctx);
@@ -3867,9 +4730,9 @@ pub fn gen_rt(ctx: &mut Context) {
     gen_i32_shr_u(ctx);
     gen_i32_rotl(ctx);
     gen_i32_rotr(ctx);
-    gen_i32_clz(ctx);
-    gen_i32_ctz(ctx);
-    gen_i32_popcnt(ctx);
+    gen_i32_clz(ctx, &mut pool);
+    gen_i32_ctz(ctx, &mut pool);
+    gen_i32_popcnt(ctx, &mut pool);
     gen_i32_eqz(ctx);
     gen_i32_eq(ctx);
     gen_i32_ne(ctx);
@@ -3946,12 +4809,52 @@ pub fn gen_rt(ctx: &mut Context) {
     gen_f64_convert_i32_u(ctx);
     gen_f64_convert_i64_u(ctx);
     gen_f64_convert_i64_s(ctx);
-    gen_trap(ctx);
+    gen_trap(ctx, &mut pool);
+    gen_stack_remaining(ctx);
+    if ctx.options.stack_guard.is_some() {
+        gen_stack_guard_failed(ctx);
+    }
+    if ctx.options.traps_on_overflow() {
+        gen_trap_integer_overflow_in(ctx, &mut pool);
+        gen_i32_add_checked(ctx);
+        gen_i32_sub_checked(ctx);
+        gen_i32_mul_checked(ctx);
+        gen_i64_add_checked(ctx);
+        gen_i64_sub_checked(ctx);
+        gen_i64_mul_checked(ctx);
+    }
     gen_table_init_or_copy(ctx);
     gen_table_grow(ctx);
     gen_table_fill(ctx);
-    gen_memory_init(ctx);
-    gen_memory_copy(ctx);
-    gen_memory_fill(ctx);
-    gen_memory_grow(ctx);
+    gen_memory_init(ctx, &primary, layout.memory());
+    gen_memory_copy(ctx, &primary, layout.memory());
+    gen_memory_fill(ctx, &primary, layout.memory());
+    gen_memory_grow(ctx, &primary, layout.memory(), true);
+    gen_v128_and(ctx);
+    gen_v128_or(ctx);
+    gen_v128_xor(ctx);
+    gen_v128_andnot(ctx);
+    gen_v128_not(ctx);
+    gen_v128_bitselect(ctx);
+    gen_swar_add(ctx, ctx.rt.i8x16_add, 0x80808080);
+    gen_swar_sub(ctx, ctx.rt.i8x16_sub, 0x80808080);
+    gen_swar_neg(ctx, ctx.rt.i8x16_neg, 0x80808080);
+    gen_swar_add(ctx, ctx.rt.i16x8_add, 0x80008000);
+    gen_swar_sub(ctx, ctx.rt.i16x8_sub, 0x80008000);
+    gen_swar_neg(ctx, ctx.rt.i16x8_neg, 0x80008000);
+    gen_i16x8_mul(ctx);
+    gen_i32x4_add(ctx);
+    gen_i32x4_sub(ctx);
+    gen_i32x4_neg(ctx);
+    gen_i32x4_mul(ctx);
+    gen_v128_splat(ctx);
+    gen_v128_lane_word(ctx);
+    gen_v128_replace_lane(ctx);
+    gen_v128_select_bytes(ctx, ctx.rt.i8x16_swizzle, 4);
+    gen_v128_select_bytes(ctx, ctx.rt.i8x16_shuffle, 8);
+    if let Some(mem) = layout.secondary_memory() {
+        gen_secondary_memory(ctx, mem);
+    }
+
+    ctx.rom_items.extend(pool.into_items());
 }
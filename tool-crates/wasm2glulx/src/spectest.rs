@@ -14,7 +14,7 @@ use wast::{
 
 use crate::{compile_module_to_bytes, CompilationError};
 
-use super::CompilationOptions;
+use super::{CompilationOptions, Conformance};
 
 #[derive(Debug)]
 pub struct WastTest {
@@ -456,8 +456,8 @@ fn build_module(
     invokes: &[(String, Vec<ConstExpr>)],
     execute: WastExecute,
 ) -> Result<Module> {
-    let mut module =
-        Module::from_buffer(encoded_module).context("failed to build walrus module")?;
+    let mut module = Module::from_buffer(&crate::fold_constant_exprs(encoded_module))
+        .context("failed to build walrus module")?;
 
     let result_type = find_result_type(&module, &execute)?;
     let ty_id = module.types.add(&result_type, &[]);
@@ -679,9 +679,11 @@ impl WastTest {
 
         std::fs::write(&wasm_path, &self.module).unwrap();
 
-        let module = walrus::Module::from_buffer(&self.module)
+        let module = walrus::Module::from_buffer(&crate::fold_constant_exprs(&self.module))
             .expect("WASM module bytecode produced by WAST should be valid");
-        let compiled = match super::compile_module_to_bytes(&CompilationOptions::new(), &module) {
+        let mut options = CompilationOptions::new();
+        options.set_conformance(Conformance::Spec);
+        let compiled = match super::compile_module_to_bytes(&options, &module) {
             Ok(compiled) => compiled,
             Err(ev) => {
                 // Uncomment if needed to debug missing/duplicate labels, etc.
@@ -736,7 +738,6 @@ impl WastTest {
         if interpreted != self.expected_result {
             std::fs::write(&actual_path, format!("{:?}", interpreted)).unwrap();
             std::fs::write(&expected_path, format!("{:?}", self.expected_result)).unwrap();
-            let mut options = CompilationOptions::new();
             options.set_text(true);
             let asm_out = compile_module_to_bytes(&options, &module)
                 .expect("If binary compilation succeeded, text compilation should too");
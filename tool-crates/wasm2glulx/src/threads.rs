@@ -0,0 +1,232 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Handling for imports which spawn threads.
+//!
+//! Glulx is single-threaded, so these can't be supported faithfully. By
+//! default they're rejected with an error naming every function that calls
+//! them. With `--inline-thread-spawn`, a spawned thread instead runs to
+//! completion before the spawn call returns, which works for code that spawns
+//! threads but doesn't need them to run concurrently with their parent.
+//!
+//! Under wasi-threads, each thread is a separate instance sharing only memory,
+//! so its globals are its own. `wasi_thread_start` points `__stack_pointer`
+//! and `__tls_base` at the new thread's stack and TLS block and never puts them
+//! back, so an inlined thread is bracketed by saving and restoring every
+//! mutable global. Thread ids count up from 1, and once the 2^29 - 1 that
+//! wasi-threads allows are used up, spawning fails with `-EAGAIN`.
+
+use glulx_asm::concise::*;
+use walrus::{
+    ir, ConstExpr, ElementItems, FunctionId, FunctionKind, GlobalKind, Import, ImportedFunction,
+    TypeId, ValType,
+};
+
+use crate::common::{Context, Label};
+use crate::CompilationError;
+
+/// Returns true if `import` is a thread-spawning function.
+pub fn is_thread_spawn(import: &Import) -> bool {
+    import.module == "wasi" && import.name == "thread-spawn"
+}
+
+/// The largest thread id wasi-threads allows.
+const MAX_THREAD_ID: u32 = 0x1fff_ffff;
+
+/// The WASI errno for "resource unavailable, try again".
+const EAGAIN: i32 = 6;
+
+struct CallFinder {
+    target: FunctionId,
+    /// The target's type, if the target is in a table and so might be called
+    /// indirectly.
+    indirect_ty: Option<TypeId>,
+    found: bool,
+}
+
+impl ir::Visitor<'_> for CallFinder {
+    fn visit_call(&mut self, instr: &ir::Call) {
+        if instr.func == self.target {
+            self.found = true;
+        }
+    }
+
+    fn visit_return_call(&mut self, instr: &ir::ReturnCall) {
+        if instr.func == self.target {
+            self.found = true;
+        }
+    }
+
+    fn visit_call_indirect(&mut self, instr: &ir::CallIndirect) {
+        if Some(instr.ty) == self.indirect_ty {
+            self.found = true;
+        }
+    }
+
+    fn visit_return_call_indirect(&mut self, instr: &ir::ReturnCallIndirect) {
+        if Some(instr.ty) == self.indirect_ty {
+            self.found = true;
+        }
+    }
+
+    fn visit_ref_func(&mut self, instr: &ir::RefFunc) {
+        if instr.func == self.target {
+            self.found = true;
+        }
+    }
+}
+
+/// Returns true if some element segment contains `target`.
+fn in_table(ctx: &Context, target: FunctionId) -> bool {
+    ctx.module.elements.iter().any(|elem| match &elem.items {
+        ElementItems::Functions(funcs) => funcs.contains(&target),
+        ElementItems::Expressions(_, exprs) => exprs
+            .iter()
+            .any(|expr| matches!(expr, ConstExpr::RefFunc(f) if *f == target)),
+    })
+}
+
+/// Returns the names of all functions which might call `target`: those which
+/// call it directly or take a reference to it and, if it's in a table, those
+/// which make an indirect call of its type.
+fn callers(ctx: &Context, target: FunctionId) -> Vec<String> {
+    let indirect_ty = in_table(ctx, target).then(|| ctx.module.funcs.get(target).ty());
+    let mut callers = Vec::new();
+    for function in ctx.module.functions() {
+        if let FunctionKind::Local(local) = &function.kind {
+            let mut finder = CallFinder {
+                target,
+                indirect_ty,
+                found: false,
+            };
+            ir::dfs_in_order(&mut finder, local, local.entry_block());
+            if finder.found {
+                callers.push(
+                    function
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| format!("<function {}>", function.id().index())),
+                );
+            }
+        }
+    }
+    callers
+}
+
+pub fn gen_thread_spawn(
+    ctx: &mut Context,
+    id: FunctionId,
+    imported_func: &ImportedFunction,
+    my_label: Label,
+) {
+    let import = ctx.module.imports.get(imported_func.import);
+    let ty = ctx.module.types.get(imported_func.ty);
+
+    if ty.params() != [ValType::I32] || ty.results() != [ValType::I32] {
+        ctx.errors.push(CompilationError::IncorrectlyTypedImport {
+            import: import.clone(),
+            expected: (vec![ValType::I32], vec![ValType::I32]),
+            actual: (ty.params().to_owned(), ty.results().to_owned()),
+        });
+        return;
+    }
+
+    if !ctx.options.inline_thread_spawn {
+        ctx.errors.push(CompilationError::ThreadSpawn {
+            import: import.clone(),
+            callers: callers(ctx, id),
+        });
+        return;
+    }
+
+    let Ok(thread_start) = ctx.module.exports.get_func("wasi_thread_start") else {
+        ctx.errors
+            .push(CompilationError::ValidationError(anyhow::anyhow!(
+                "Module imports wasi/thread-spawn but does not export wasi_thread_start"
+            )));
+        return;
+    };
+
+    let thread_start_ty = ctx
+        .module
+        .types
+        .get(ctx.module.funcs.get(thread_start).ty());
+    if thread_start_ty.params() != [ValType::I32, ValType::I32]
+        || !thread_start_ty.results().is_empty()
+    {
+        ctx.errors.push(CompilationError::IncorrectlyTypedExport {
+            export: ctx
+                .module
+                .exports
+                .get_exported_func(thread_start)
+                .unwrap()
+                .clone(),
+            expected: (vec![ValType::I32, ValType::I32], Vec::new()),
+            actual: (
+                thread_start_ty.params().to_owned(),
+                thread_start_ty.results().to_owned(),
+            ),
+        });
+        return;
+    }
+
+    let start_arg = 0;
+    let thread_id = 1;
+
+    let next_thread_id = ctx.gen.gen("next_thread_id");
+    let exhausted = ctx.gen.gen("thread_ids_exhausted");
+    push_all!(
+        ctx.ram_items,
+        label(next_thread_id),
+        blob(1u32.to_be_bytes().to_vec())
+    );
+
+    push_all!(
+        ctx.rom_items,
+        label(my_label),
+        fnhead_local(2),
+        copy(derefl(next_thread_id), sloc(thread_id)),
+        jgtu(lloc(thread_id), uimm(MAX_THREAD_ID), exhausted),
+        add(lloc(thread_id), imm(1), storel(next_thread_id)),
+    );
+
+    let saved: Vec<(Label, u32)> = ctx
+        .module
+        .globals
+        .iter()
+        .filter(|global| global.mutable && matches!(global.kind, GlobalKind::Local(_)))
+        .map(|global| {
+            let layout = ctx.layout.global(global.id());
+            (layout.addr, layout.words)
+        })
+        .collect();
+
+    for &(addr, words) in &saved {
+        for word in 0..words {
+            ctx.rom_items
+                .push(copy(derefl_off(addr, (4 * word) as i32), push()));
+        }
+    }
+
+    // Parameters are passed in reverse order, so `start_arg` comes first.
+    ctx.rom_items.push(callfii(
+        imml(ctx.layout.func(thread_start).addr),
+        lloc(start_arg),
+        lloc(thread_id),
+        discard(),
+    ));
+
+    for &(addr, words) in saved.iter().rev() {
+        for word in (0..words).rev() {
+            ctx.rom_items
+                .push(copy(pop(), storel_off(addr, (4 * word) as i32)));
+        }
+    }
+
+    push_all!(
+        ctx.rom_items,
+        ret(lloc(thread_id)),
+        label(exhausted),
+        ret(imm(-EAGAIN)),
+    );
+}
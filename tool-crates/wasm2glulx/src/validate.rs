@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Strict-mode checks that generated code stays within limits which a Glulx
+//! interpreter would otherwise only enforce at run time, if at all.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use glulx_asm::{CallingConvention, Instr, Item, LabelRef, LoadOperand, Operand, StoreOperand};
+
+use crate::{
+    common::{call_frame_len, Context, Label},
+    CompilationError,
+};
+
+/// What the checks need to know about labels defined in ROM.
+#[derive(Debug, Default)]
+struct RomLabels {
+    /// Labels of function headers, with each function's calling convention and
+    /// number of locals.
+    functions: HashMap<Label, (CallingConvention, u32)>,
+    /// Labels of instructions.
+    code: HashSet<Label>,
+}
+
+impl RomLabels {
+    fn collect(items: &[Item<Label>]) -> RomLabels {
+        let mut labels = RomLabels::default();
+        let mut pending = Vec::new();
+        for item in items {
+            match item {
+                Item::Label(label) => pending.push(*label),
+                Item::FnHeader(cc, locals) => labels
+                    .functions
+                    .extend(pending.drain(..).map(|label| (label, (*cc, *locals)))),
+                Item::Instr(_) => labels.code.extend(pending.drain(..)),
+                _ => pending.clear(),
+            }
+        }
+        labels
+    }
+}
+
+/// Checks the items generated for each function. Each entry of `functions`
+/// gives a function's name and the range of `ctx.rom_items` which its items
+/// occupy. This must run after all ROM items have been generated, so that
+/// calls and branches can be checked against their targets.
+pub fn validate_functions(ctx: &mut Context, functions: &[(Option<&str>, Range<usize>)]) {
+    let stack_size = u64::from(ctx.options.stack_size);
+    let labels = RomLabels::collect(ctx.rom_items);
+
+    for (function_name, range) in functions {
+        let mut violations = Vec::new();
+        let mut locals = 0;
+
+        for item in &ctx.rom_items[range.clone()] {
+            match item {
+                Item::FnHeader(_, n) => {
+                    locals = *n;
+                    let frame_len = call_frame_len(locals);
+                    if frame_len > stack_size {
+                        violations.push(format!(
+                            "a call frame with {locals} locals needs {frame_len} bytes of stack, but the stack is only {stack_size} bytes"
+                        ));
+                    }
+                }
+                Item::Instr(instr) => {
+                    check_stack_operations(&mut violations, instr, stack_size);
+                    check_operands(&mut violations, instr, locals, &labels);
+                    check_call(&mut violations, instr, &labels);
+                }
+                _ => {}
+            }
+        }
+
+        for reason in violations {
+            ctx.errors.push(CompilationError::GlulxLimitExceeded {
+                function: function_name.map(|s| s.to_owned()),
+                reason,
+            });
+        }
+    }
+}
+
+/// Checks that instructions which operate on a counted number of stack values
+/// can do so without overflowing the stack.
+fn check_stack_operations(violations: &mut Vec<String>, instr: &Instr<Label>, stack_size: u64) {
+    match instr {
+        Instr::Call(_, LoadOperand::Imm(args), _) | Instr::Tailcall(_, LoadOperand::Imm(args)) => {
+            check_stack_words(violations, "call", *args, stack_size);
+        }
+        Instr::Stkcopy(LoadOperand::Imm(words)) => {
+            check_stack_words(violations, "stkcopy", *words, stack_size);
+        }
+        Instr::Stkroll(LoadOperand::Imm(words), _) => {
+            check_stack_words(violations, "stkroll", *words, stack_size);
+        }
+        _ => {}
+    }
+}
+
+/// Checks that an instruction operating on `words` stack values can do so
+/// without overflowing a stack of `stack_size` bytes.
+fn check_stack_words(violations: &mut Vec<String>, mnemonic: &str, words: i32, stack_size: u64) {
+    match u64::try_from(words) {
+        Ok(words) if 4 * words <= stack_size => {}
+        Ok(words) => violations.push(format!(
+            "`{mnemonic}` operates on {words} stack values, which exceeds the {stack_size}-byte stack"
+        )),
+        Err(_) => violations.push(format!(
+            "`{mnemonic}` has a negative operand count of {words}"
+        )),
+    }
+}
+
+/// Returns whether the instruction's last operand is a branch offset.
+fn is_branch(instr: &Instr<Label>) -> bool {
+    matches!(
+        instr,
+        Instr::Jump(_)
+            | Instr::Jz(_, _)
+            | Instr::Jnz(_, _)
+            | Instr::Jeq(_, _, _)
+            | Instr::Jne(_, _, _)
+            | Instr::Jlt(_, _, _)
+            | Instr::Jle(_, _, _)
+            | Instr::Jgt(_, _, _)
+            | Instr::Jge(_, _, _)
+            | Instr::Jltu(_, _, _)
+            | Instr::Jleu(_, _, _)
+            | Instr::Jgtu(_, _, _)
+            | Instr::Jgeu(_, _, _)
+            | Instr::Catch(_, _)
+            | Instr::Jisnan(_, _)
+            | Instr::Jisinf(_, _)
+            | Instr::Jfeq(_, _, _, _)
+            | Instr::Jfne(_, _, _, _)
+            | Instr::Jflt(_, _, _)
+            | Instr::Jfle(_, _, _)
+            | Instr::Jfgt(_, _, _)
+            | Instr::Jfge(_, _, _)
+            | Instr::Jdisnan(_, _, _)
+            | Instr::Jdisinf(_, _, _)
+            | Instr::Jdeq(_, _, _, _, _, _, _)
+            | Instr::Jdne(_, _, _, _, _, _, _)
+            | Instr::Jdlt(_, _, _, _, _)
+            | Instr::Jdle(_, _, _, _, _)
+            | Instr::Jdgt(_, _, _, _, _)
+            | Instr::Jdge(_, _, _, _, _)
+    )
+}
+
+/// Checks that every local an instruction accesses exists in a function with
+/// `locals` locals, and that its branch offset, if it has one, lands on an
+/// instruction.
+fn check_operands(
+    violations: &mut Vec<String>,
+    instr: &Instr<Label>,
+    locals: u32,
+    labels: &RomLabels,
+) {
+    let operands = instr.operands();
+    let branch = is_branch(instr).then(|| operands.len() - 1);
+
+    for (i, operand) in operands.into_iter().enumerate() {
+        match operand {
+            Operand::Load(LoadOperand::FrameAddr(addr))
+            | Operand::Store(StoreOperand::FrameAddr(addr)) => {
+                if addr % 4 != 0 {
+                    violations.push(format!(
+                        "`{instr}` accesses frame offset {addr}, which is not the start of a local"
+                    ));
+                } else if addr / 4 >= locals {
+                    violations.push(format!(
+                        "`{instr}` accesses local {}, but the function has only {locals} locals",
+                        addr / 4
+                    ));
+                }
+            }
+            Operand::Load(LoadOperand::Branch(target))
+                if Some(i) == branch && !labels.code.contains(target) =>
+            {
+                violations.push(format!(
+                    "`{instr}` branches to {target}, which does not label an instruction"
+                ));
+            }
+            Operand::Load(LoadOperand::Branch(_)) if Some(i) == branch => {}
+            Operand::Load(LoadOperand::Branch(_)) => {
+                violations.push(format!(
+                    "`{instr}` has a branch target in an operand which is not a branch offset"
+                ));
+            }
+            Operand::Load(LoadOperand::Imm(offset))
+                if Some(i) == branch && !(0..=1).contains(offset) =>
+            {
+                violations.push(format!(
+                    "`{instr}` has a raw branch offset of {offset}, rather than a label"
+                ));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Checks that a direct call targets a function, and passes it no more
+/// arguments than it has locals to receive them.
+fn check_call(violations: &mut Vec<String>, instr: &Instr<Label>, labels: &RomLabels) {
+    let (callee, args) = match instr {
+        Instr::Call(LoadOperand::ImmLabel(LabelRef(callee, 0), 0), LoadOperand::Imm(args), _)
+        | Instr::Tailcall(LoadOperand::ImmLabel(LabelRef(callee, 0), 0), LoadOperand::Imm(args)) => {
+            let Ok(args) = u32::try_from(*args) else {
+                // Already reported by check_stack_words.
+                return;
+            };
+            (callee, args)
+        }
+        Instr::Callf(LoadOperand::ImmLabel(LabelRef(callee, 0), 0), _) => (callee, 0),
+        Instr::Callfi(LoadOperand::ImmLabel(LabelRef(callee, 0), 0), _, _) => (callee, 1),
+        Instr::Callfii(LoadOperand::ImmLabel(LabelRef(callee, 0), 0), _, _, _) => (callee, 2),
+        Instr::Callfiii(LoadOperand::ImmLabel(LabelRef(callee, 0), 0), _, _, _, _) => (callee, 3),
+        _ => return,
+    };
+
+    match labels.functions.get(callee) {
+        Some((CallingConvention::ArgsInLocals, locals)) if args > *locals => {
+            violations.push(format!(
+                "`{instr}` passes {args} arguments to a function with only {locals} locals"
+            ));
+        }
+        Some(_) => {}
+        None if labels.code.contains(callee) => {
+            violations.push(format!(
+                "`{instr}` calls {callee}, which labels an instruction rather than a function"
+            ));
+        }
+        None => {}
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks for things which are probably mistakes but don't prevent
+//! compilation.
+
+use std::collections::{BTreeMap, HashSet};
+
+use walrus::{
+    ConstExpr, ElementItems, ExportItem, FunctionId, FunctionKind, GlobalKind, ImportKind, Module,
+};
+
+use crate::common::Emit;
+use crate::export_filter::RESERVED_EXPORTS;
+use crate::recursion::Callees;
+use crate::{CompilationOptions, CompilationWarning};
+
+/// Tables which can grow by at least this many entries get a warning.
+const LARGE_TABLE_GROWTH: u32 = 1 << 20;
+
+/// Story file extensions which assembly text shouldn't be written to.
+const STORY_EXTENSIONS: &[&str] = &["ulx", "gblorb", "blb"];
+
+/// Returns warnings about `module` and `options`.
+///
+/// [`compile`](crate::compile) calls this after filtering exports and
+/// eliminating dead code, and fails if any are found and
+/// [`CompilationOptions::set_deny_warnings`] is enabled.
+pub fn check_warnings(options: &CompilationOptions, module: &Module) -> Vec<CompilationWarning> {
+    let mut warnings = Vec::new();
+    check_exports(options, module, &mut warnings);
+    check_table_growth(options, module, &mut warnings);
+    check_imports(module, &mut warnings);
+    check_output_path(options, &mut warnings);
+    warnings
+}
+
+/// Warns about exported functions which nothing that wasm2glulx runs can
+/// reach. They're expected when the exports file is being emitted, since then
+/// their addresses are presumably wanted by something outside the game.
+fn check_exports(
+    options: &CompilationOptions,
+    module: &Module,
+    warnings: &mut Vec<CompilationWarning>,
+) {
+    if options.emit.contains(&Emit::Exports) {
+        return;
+    }
+
+    let mut roots: Vec<FunctionId> = module.start.into_iter().collect();
+    roots.extend(
+        RESERVED_EXPORTS
+            .iter()
+            .filter_map(|name| module.exports.get_func(name).ok()),
+    );
+    if roots.is_empty() {
+        // There's no entrypoint, which is an error of its own.
+        return;
+    }
+
+    // Anything which might be in a table is taken to be reachable once
+    // anything reachable makes an indirect call.
+    let mut tabled = referenced_outside_code(module);
+    let mut reachable: HashSet<FunctionId> = HashSet::new();
+    let mut any_indirect = false;
+    let mut work = roots;
+    loop {
+        if work.is_empty() && any_indirect {
+            work.append(&mut tabled);
+        }
+        let Some(id) = work.pop() else {
+            break;
+        };
+        if !reachable.insert(id) {
+            continue;
+        }
+        if let FunctionKind::Local(local) = &module.funcs.get(id).kind {
+            let callees = Callees::of(local);
+            work.extend(callees.direct);
+            tabled.extend(callees.refs);
+            any_indirect |= callees.indirect;
+        }
+    }
+
+    for export in module.exports.iter() {
+        if let ExportItem::Function(id) = export.item {
+            if !reachable.contains(&id) {
+                warnings.push(CompilationWarning::UnreachableExport {
+                    name: export.name.clone(),
+                });
+            }
+        }
+    }
+}
+
+/// Warns about tables which reserve a lot of RAM to grow into.
+fn check_table_growth(
+    options: &CompilationOptions,
+    module: &Module,
+    warnings: &mut Vec<CompilationWarning>,
+) {
+    for (index, table) in module.tables.iter().enumerate() {
+        let initial = u32::try_from(table.initial).unwrap_or(u32::MAX);
+        let maximum = u32::try_from(table.maximum.unwrap_or(u64::MAX)).unwrap_or(u32::MAX);
+        let entries = maximum
+            .saturating_sub(initial)
+            .min(options.table_growth_limit);
+        if entries >= LARGE_TABLE_GROWTH {
+            warnings.push(CompilationWarning::TableGrowth { index, entries });
+        }
+    }
+}
+
+/// Warns about import namespaces from which no imported function is used.
+fn check_imports(module: &Module, warnings: &mut Vec<CompilationWarning>) {
+    let mut used: HashSet<FunctionId> = referenced_outside_code(module).into_iter().collect();
+    for function in module.functions() {
+        if let FunctionKind::Local(local) = &function.kind {
+            let callees = Callees::of(local);
+            used.extend(callees.direct);
+            used.extend(callees.refs);
+        }
+    }
+    for export in module.exports.iter() {
+        if let ExportItem::Function(id) = export.item {
+            used.insert(id);
+        }
+    }
+
+    let mut namespaces: BTreeMap<&str, (bool, Vec<String>)> = BTreeMap::new();
+    for import in module.imports.iter() {
+        if let ImportKind::Function(id) = import.kind {
+            let (any_used, names) = namespaces.entry(&import.module).or_default();
+            *any_used |= used.contains(&id);
+            names.push(import.name.clone());
+        }
+    }
+
+    for (module, (any_used, names)) in namespaces {
+        if !any_used {
+            warnings.push(CompilationWarning::UnusedImportNamespace {
+                module: module.to_owned(),
+                names,
+            });
+        }
+    }
+}
+
+/// Returns the functions referred to by element segments and global
+/// initializers.
+fn referenced_outside_code(module: &Module) -> Vec<FunctionId> {
+    let mut ids = Vec::new();
+    for elem in module.elements.iter() {
+        match &elem.items {
+            ElementItems::Functions(v) => ids.extend(v),
+            ElementItems::Expressions(_, v) => {
+                for expr in v {
+                    if let ConstExpr::RefFunc(id) = expr {
+                        ids.push(*id);
+                    }
+                }
+            }
+        }
+    }
+    for global in module.globals.iter() {
+        if let GlobalKind::Local(ConstExpr::RefFunc(id)) = global.kind {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// Warns if assembly text is the only output and is going to a file whose
+/// extension says it's a story file.
+fn check_output_path(options: &CompilationOptions, warnings: &mut Vec<CompilationWarning>) {
+    if options.emit != [Emit::Asm] {
+        return;
+    }
+    if let Some(output) = &options.output {
+        let is_story = output
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| STORY_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if is_story {
+            warnings.push(CompilationWarning::TextToBinaryPath(output.clone()));
+        }
+    }
+}
@@ -0,0 +1,199 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks the chunk layout of Blorb files against the Blorb specification.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use wasm2glulx::{CompilationError, CompilationOptions, Emit};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nnot really a picture";
+const JPEG: &[u8] = b"\xff\xd8\xffnor this";
+const OGG: &[u8] = b"OggS and an odd length";
+
+fn module() -> walrus::Module {
+    common::wat(r#"(module (func (export "glulx_main")))"#)
+}
+
+/// Writes `files` into a fresh directory named after the test, and returns
+/// the directory.
+fn resource_dir(test: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("blorb")
+        .join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, data) in files {
+        std::fs::write(dir.join(name), data).unwrap();
+    }
+    dir
+}
+
+fn compile(manifest: Option<PathBuf>) -> Result<(Vec<u8>, Vec<u8>), Vec<CompilationError>> {
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Binary, Emit::Blorb]);
+    options.set_blorb_manifest(manifest);
+    let artifacts = wasm2glulx::compile_module_to_artifacts(&options, &module())?;
+    Ok((
+        artifacts.get(Emit::Binary).unwrap().to_vec(),
+        artifacts.get(Emit::Blorb).unwrap().to_vec(),
+    ))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// A chunk as found by walking the `FORM`: its offset, type, and contents.
+type Chunk<'a> = (usize, &'a [u8], &'a [u8]);
+
+/// Walks the chunks of a Blorb file, checking the `FORM` header and that
+/// every chunk is padded to an even length.
+fn chunks(blorb: &[u8]) -> Vec<Chunk<'_>> {
+    assert_eq!(&blorb[0..4], b"FORM");
+    assert_eq!(u32_at(blorb, 4) as usize, blorb.len() - 8);
+    assert_eq!(&blorb[8..12], b"IFRS");
+
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset < blorb.len() {
+        assert_eq!(offset % 2, 0, "chunk at {offset:#x} is misaligned");
+        let ty = &blorb[offset..offset + 4];
+        let len = u32_at(blorb, offset + 4) as usize;
+        chunks.push((offset, ty, &blorb[offset + 8..offset + 8 + len]));
+        offset += 8 + len + len % 2;
+    }
+    assert_eq!(offset, blorb.len());
+    chunks
+}
+
+/// Parses the resource index, which must be the first chunk.
+fn index<'a>(chunks: &[Chunk<'a>]) -> Vec<(&'a [u8], u32, usize)> {
+    let (_, ty, data) = chunks[0];
+    assert_eq!(ty, b"RIdx");
+    let count = u32_at(data, 0) as usize;
+    assert_eq!(data.len(), 4 + 12 * count);
+    (0..count)
+        .map(|i| {
+            let entry = &data[4 + 12 * i..];
+            (&entry[0..4], u32_at(entry, 4), u32_at(entry, 8) as usize)
+        })
+        .collect()
+}
+
+#[test]
+fn story_alone() {
+    let (story, blorb) = compile(None).unwrap();
+    let chunks = chunks(&blorb);
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(index(&chunks), [(&b"Exec"[..], 0, chunks[1].0)]);
+    assert_eq!(chunks[1].1, b"GLUL");
+    assert_eq!(chunks[1].2, story.as_slice());
+}
+
+#[test]
+fn resources_from_manifest() {
+    let dir = resource_dir(
+        "resources_from_manifest",
+        &[
+            ("cover.png", PNG),
+            ("map.jpg", JPEG),
+            ("theme.ogg", OGG),
+            ("game.iFiction", b"<ifindex/>"),
+            (
+                "blorb.toml",
+                br#"
+cover = "cover.png"
+ifiction = "game.iFiction"
+
+[[picture]]
+number = 5
+file = "map.jpg"
+
+[[sound]]
+number = 3
+file = "theme.ogg"
+"#,
+            ),
+        ],
+    );
+    let (story, blorb) = compile(Some(dir.join("blorb.toml"))).unwrap();
+    let chunks = chunks(&blorb);
+
+    // Indexed chunks follow the index in index order, then the frontispiece
+    // and metadata.
+    let types: Vec<&[u8]> = chunks.iter().map(|(_, ty, _)| *ty).collect();
+    assert_eq!(
+        types,
+        [
+            &b"RIdx"[..],
+            b"GLUL",
+            b"PNG ",
+            b"JPEG",
+            b"OGGV",
+            b"Fspc",
+            b"IFmd"
+        ]
+    );
+    assert_eq!(
+        index(&chunks),
+        [
+            (&b"Exec"[..], 0, chunks[1].0),
+            (b"Pict", 1, chunks[2].0),
+            (b"Pict", 5, chunks[3].0),
+            (b"Snd ", 3, chunks[4].0),
+        ]
+    );
+
+    assert_eq!(chunks[1].2, story.as_slice());
+    assert_eq!(chunks[2].2, PNG);
+    assert_eq!(chunks[3].2, JPEG);
+    assert_eq!(chunks[4].2, OGG);
+    assert_eq!(chunks[5].2, 1u32.to_be_bytes());
+    assert_eq!(chunks[6].2, b"<ifindex/>");
+}
+
+#[test]
+fn aiff_is_stored_without_a_second_header() {
+    let mut aiff = b"FORM".to_vec();
+    aiff.extend(1192u32.to_be_bytes());
+    aiff.extend(b"AIFF");
+    aiff.resize(1200, 0);
+    let dir = resource_dir(
+        "aiff_is_stored_without_a_second_header",
+        &[
+            ("beep.aiff", &aiff),
+            (
+                "blorb.toml",
+                b"[[sound]]\nnumber = 1\nfile = \"beep.aiff\"\n",
+            ),
+        ],
+    );
+    let (_, blorb) = compile(Some(dir.join("blorb.toml"))).unwrap();
+    let chunks = chunks(&blorb);
+    assert_eq!(index(&chunks)[1], (&b"Snd "[..], 1, chunks[2].0));
+    assert_eq!(chunks[2].1, b"FORM");
+    assert_eq!(&blorb[chunks[2].0..chunks[2].0 + 12], &aiff[..12]);
+}
+
+#[test]
+fn duplicate_picture_numbers_are_rejected() {
+    let dir = resource_dir(
+        "duplicate_picture_numbers_are_rejected",
+        &[
+            ("cover.png", PNG),
+            (
+                "blorb.toml",
+                b"cover = \"cover.png\"\n[[picture]]\nnumber = 1\nfile = \"cover.png\"\n",
+            ),
+        ],
+    );
+    let errors = compile(Some(dir.join("blorb.toml"))).unwrap_err();
+    assert!(
+        matches!(&errors[..], [CompilationError::BlorbError(e)]
+            if e.to_string().contains("Picture 1 is given more than once")),
+        "{errors:?}"
+    );
+}
@@ -0,0 +1,133 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Helpers for integration tests which compile hand-written WAT with
+//! particular options and run the result under bogoglulx.
+//!
+//! Programs report results through the `glulx/spectest_result` intrinsic,
+//! which bogoglulx prints as eight hex digits per word.
+
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::Command;
+
+use wasm2glulx::{CompilationError, CompilationOptions};
+
+/// Parses `src` as WAT and returns the module.
+pub fn wat(src: &str) -> walrus::Module {
+    let buf = wast::parser::ParseBuffer::new(src).unwrap();
+    let mut wat: wast::Wat = wast::parser::parse(&buf).unwrap();
+    walrus::Module::from_buffer(&wat.encode().unwrap()).unwrap()
+}
+
+/// Compiles `module` to a story file, panicking if compilation fails.
+pub fn compile(options: &CompilationOptions, module: &walrus::Module) -> Vec<u8> {
+    match wasm2glulx::compile_module_to_bytes(options, module) {
+        Ok(story) => story.to_vec(),
+        Err(errors) => panic!("Compilation failed. First error: {}", errors[0]),
+    }
+}
+
+/// Compiles `module`, expecting failure, and returns the errors.
+pub fn compile_errors(
+    options: &CompilationOptions,
+    module: &walrus::Module,
+) -> Vec<CompilationError> {
+    match wasm2glulx::compile_module_to_bytes(options, module) {
+        Ok(_) => panic!("Compilation should have failed"),
+        Err(errors) => errors,
+    }
+}
+
+/// Runs `story` under bogoglulx, saving it as `<name>.ulx` in the test
+/// scratch directory. Returns the words the program printed, or the message
+/// of the trap or interpreter error that stopped it, including its leading
+/// `!` or `?`.
+pub fn run(name: &str, story: &[u8]) -> Result<Vec<u32>, String> {
+    match run_until_stopped(name, story) {
+        (_, Some(message)) => Err(message),
+        (words, None) => Ok(words),
+    }
+}
+
+/// Like [`run`], but also returns the words printed before a trap or
+/// interpreter error stopped the program.
+pub fn run_until_stopped(name: &str, story: &[u8]) -> (Vec<u32>, Option<String>) {
+    run_story(name, story, false)
+}
+
+/// Like [`run_until_stopped`], but bogoglulx reports that a Glk stream is
+/// open, so code which prints only when there is somewhere to print runs.
+pub fn run_with_stream(name: &str, story: &[u8]) -> (Vec<u32>, Option<String>) {
+    run_story(name, story, true)
+}
+
+fn run_story(name: &str, story: &[u8], stream: bool) -> (Vec<u32>, Option<String>) {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.ulx"));
+    std::fs::write(&path, story).unwrap();
+    let mut command = Command::new(env!("BOGOGLULX_BIN"));
+    if stream {
+        command.env("BOGOGLULX_STREAM", "1");
+    }
+    let output = command
+        .arg(&path)
+        .output()
+        .unwrap_or_else(|e| panic!("bogoglulx execution failed: {e}"));
+    let stdout = String::from_utf8(output.stdout).expect("Bogoglulx output should be valid UTF-8");
+
+    let (words, stopped) = match stdout.find(['!', '?']) {
+        Some(index) => (&stdout[..index], Some(stdout[index..].to_owned())),
+        None => (stdout.as_str(), None),
+    };
+    assert_eq!(words.len() % 8, 0, "Partial word in output {stdout:?}");
+    let words = (0..words.len())
+        .step_by(8)
+        .map(|i| u32::from_str_radix(&words[i..i + 8], 16).unwrap())
+        .collect();
+    (words, stopped)
+}
+
+/// Compiles and runs `module`; see [`run`].
+pub fn compile_and_run(
+    name: &str,
+    options: &CompilationOptions,
+    module: &walrus::Module,
+) -> Result<Vec<u32>, String> {
+    run(name, &compile(options, module))
+}
+
+/// Compiles the Rust program at `src` to WebAssembly at `wasm_path`, passing
+/// `rustc` any `extra_args` as well. Panics with instructions if `rustc` can't
+/// target `wasm32-unknown-unknown`.
+pub fn build_wasm(src: &Path, wasm_path: &Path, extra_args: &[&str]) {
+    let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc)
+        .args([
+            "--edition=2021",
+            "--target=wasm32-unknown-unknown",
+            "--crate-type=cdylib",
+            "-Copt-level=s",
+            "-Cpanic=abort",
+        ])
+        .args(extra_args)
+        .arg("-o")
+        .arg(wasm_path)
+        .arg(src)
+        .output()
+        .expect("rustc should be executable");
+
+    if output.status.success() {
+        return;
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("E0463") || stderr.contains("may not be installed") {
+        panic!(
+            "The wasm32-unknown-unknown target is not installed. Install it with \
+             `rustup target add wasm32-unknown-unknown`, or pass `--skip golden \
+             --skip dwarf` to leave out the tests which need it."
+        );
+    }
+    panic!("Failed to compile {} to wasm:\n{stderr}", src.display());
+}
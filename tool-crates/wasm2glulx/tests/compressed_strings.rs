@@ -0,0 +1 @@
+wasm2glulx_spectest_macro::spectest!("spec-tests/compressed_strings.wast");
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--conformance`.
+
+mod common;
+
+use wasm2glulx::{CompilationOptions, Conformance};
+
+fn with_conformance(conformance: Conformance) -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_conformance(conformance);
+    options
+}
+
+/// Calls a `() -> i32` function through a `(i32) -> i32` signature. Glulx
+/// discards the extra argument, so without the type check the call returns
+/// normally.
+const MISTYPED_CALL: &str = r#"
+(module
+  (import "glulx" "spectest_result" (func $result (param i32)))
+  (type $unary (func (param i32) (result i32)))
+  (table 1 funcref)
+  (elem (i32.const 0) $seven)
+  (func $seven (result i32) (i32.const 7))
+  (func (export "glulx_main")
+    (call $result
+      (call_indirect (type $unary) (i32.const 1) (i32.const 0)))))
+"#;
+
+#[test]
+fn fast_skips_indirect_call_type_checks() {
+    let output = common::compile_and_run(
+        "conformance_fast",
+        &with_conformance(Conformance::Fast),
+        &common::wat(MISTYPED_CALL),
+    );
+    assert_eq!(output, Ok(vec![7]));
+}
+
+#[test]
+fn other_levels_check_indirect_call_types() {
+    for (name, conformance) in [
+        ("conformance_standard", Conformance::Standard),
+        ("conformance_spec", Conformance::Spec),
+    ] {
+        let output = common::compile_and_run(
+            name,
+            &with_conformance(conformance),
+            &common::wat(MISTYPED_CALL),
+        );
+        assert_eq!(output, Err("!indirect call type mismatch".to_owned()));
+    }
+}
+
+#[test]
+fn spec_overrides_trap_on_overflow() {
+    let module = common::wat(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $add (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func (export "glulx_main")
+            (call $result (call $add (i32.const 0x7fffffff) (i32.const 1)))))
+        "#,
+    );
+    let mut options = with_conformance(Conformance::Spec);
+    options.set_trap_on_overflow(true);
+    assert_eq!(
+        common::compile_and_run("conformance_spec_overflow", &options, &module),
+        Ok(vec![0x8000_0000])
+    );
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--eliminate-dead-code`.
+//!
+//! The symbol map names every function which was compiled, so it shows which
+//! ones were dropped.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions, Emit};
+
+/// An import which wasm2glulx doesn't recognize, and which nothing calls.
+const UNUSED_IMPORT: &str = r#"(import "glulx" "unused_result" (func $unused_import (param i32)))"#;
+
+const MODULE: &str = r#"
+(module
+  (import "glulx" "spectest_result" (func $result (param i32)))
+  (import "glulx" "unused_result" (func $unused_import (param i32)))
+  (type $nullary (func (result i32)))
+  (table $used 2 funcref)
+  (table $unused 1 funcref)
+  (elem (table $used) (i32.const 0) func $in_used_table $also_in_used_table)
+  (elem (table $unused) (i32.const 0) func $in_unused_table)
+  (elem declare func $declared $referenced)
+  (func $called (result i32) (i32.const 1))
+  (func $called_only_by_dead (result i32) (i32.const 2))
+  (func $dead (result i32) (call $called_only_by_dead))
+  (func $in_used_table (result i32) (i32.const 3))
+  (func $also_in_used_table (result i32) (i32.const 4))
+  (func $in_unused_table (result i32) (i32.const 5))
+  (func $declared (result i32) (i32.const 6))
+  (func $referenced (result i32) (i32.const 7))
+  (func $started (call $result (i32.const 8)))
+  (func $exported (export "other") (result i32) (i32.const 9))
+  (func (export "glulx_main")
+    (call $result (call $called))
+    (call $result (call_indirect (type $nullary) (i32.const 1)))
+    (drop (ref.func $referenced)))
+  (start $started))
+"#;
+
+fn options(eliminate: bool) -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_eliminate_dead_code(eliminate);
+    options
+}
+
+/// Returns the names of the functions compiled from `MODULE`, or the
+/// compilation errors.
+fn compiled_functions(eliminate: bool) -> Result<Vec<String>, Vec<CompilationError>> {
+    let mut module = common::wat(MODULE);
+    let mut options = options(eliminate);
+    wasm2glulx::eliminate_dead_code(&options, &mut module);
+    options.set_emit(&[Emit::Map]);
+    let artifacts = wasm2glulx::compile_module_to_artifacts(&options, &module)?;
+    let (_, map) = artifacts.iter().next().unwrap();
+    Ok(std::str::from_utf8(map)
+        .unwrap()
+        .lines()
+        .map(|line| line.split_once(' ').unwrap().1.to_owned())
+        .collect())
+}
+
+#[test]
+fn unreachable_functions_are_dropped() {
+    let functions = compiled_functions(true).unwrap();
+    for kept in [
+        "called",
+        "in_used_table",
+        "also_in_used_table",
+        "referenced",
+        "started",
+        "exported",
+    ] {
+        assert!(
+            functions.iter().any(|f| f == kept),
+            "{kept} should be kept: {functions:?}"
+        );
+    }
+    for dropped in ["dead", "called_only_by_dead", "in_unused_table", "declared"] {
+        assert!(
+            !functions.iter().any(|f| f == dropped),
+            "{dropped} should be dropped: {functions:?}"
+        );
+    }
+}
+
+#[test]
+fn unused_unrecognized_imports_are_dropped() {
+    let errors = compiled_functions(false).unwrap_err();
+    assert!(
+        matches!(errors[..], [CompilationError::UnrecognizedImport(_)]),
+        "{errors:?}"
+    );
+    assert!(compiled_functions(true).is_ok());
+}
+
+#[test]
+fn nothing_is_dropped_by_default() {
+    let mut module = common::wat(MODULE);
+    let before = module.funcs.iter().count();
+    wasm2glulx::eliminate_dead_code(&options(false), &mut module);
+    assert_eq!(module.funcs.iter().count(), before);
+}
+
+#[test]
+fn program_behaves_the_same() {
+    assert!(MODULE.contains(UNUSED_IMPORT));
+    let mut module = common::wat(&MODULE.replace(UNUSED_IMPORT, ""));
+    // The start function's result, then glulx_main's two.
+    let expected = Ok(vec![8, 1, 4]);
+    assert_eq!(
+        common::compile_and_run("dce_off", &options(false), &module),
+        expected
+    );
+    wasm2glulx::eliminate_dead_code(&options(true), &mut module);
+    assert_eq!(
+        common::compile_and_run("dce_on", &options(true), &module),
+        expected
+    );
+}
@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--debug-info`.
+//!
+//! The `dwarf` tests compile `tests/debuginfo/lines.rs` with `rustc -g`, and so
+//! need `rustc` to be able to target `wasm32-unknown-unknown`, like the golden
+//! tests.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use wasm2glulx::{CompilationOptions, Emit};
+
+/// First and last lines of `triangle` in `tests/debuginfo/lines.rs`.
+const TRIANGLE_LINES: (u64, u64) = (14, 20);
+
+struct Routine {
+    name: String,
+    address: u32,
+    byte_count: u32,
+    /// File index and line of the declaration.
+    declared: Option<(usize, u64)>,
+    /// Address, file index, and line of each sequence point.
+    sequence_points: Vec<(u32, usize, u64)>,
+}
+
+struct DebugInfo {
+    sources: Vec<String>,
+    routines: Vec<Routine>,
+}
+
+/// Returns the contents of each `<tag>` element in `xml`, in order.
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let body = &rest[start + open.len()..];
+        let end = body.find(&close).expect("elements should be closed");
+        found.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    found
+}
+
+fn element<'a>(xml: &'a str, tag: &str) -> &'a str {
+    elements(xml, tag)[0]
+}
+
+fn location(xml: &str) -> (usize, u64) {
+    (
+        element(xml, "file-index").parse().unwrap(),
+        element(xml, "line").parse().unwrap(),
+    )
+}
+
+impl DebugInfo {
+    fn parse(xml: &str) -> DebugInfo {
+        let sources = xml
+            .split("<given-path>")
+            .skip(1)
+            .map(|s| s.split_once("</given-path>").unwrap().0.to_owned())
+            .collect();
+        let routines = elements(xml, "routine")
+            .into_iter()
+            .map(|routine| {
+                let (header, points) = routine
+                    .split_once("<sequence-point>")
+                    .map_or((routine, ""), |(header, _)| {
+                        (header, &routine[header.len()..])
+                    });
+                Routine {
+                    name: element(header, "identifier").to_owned(),
+                    address: element(header, "address").parse().unwrap(),
+                    byte_count: element(header, "byte-count").parse().unwrap(),
+                    declared: header
+                        .contains("<source-code-location>")
+                        .then(|| location(header)),
+                    sequence_points: elements(points, "sequence-point")
+                        .into_iter()
+                        .map(|point| {
+                            let (file, line) = location(point);
+                            (element(point, "address").parse().unwrap(), file, line)
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        DebugInfo { sources, routines }
+    }
+
+    fn routine(&self, name: &str) -> &Routine {
+        self.routines
+            .iter()
+            .find(|routine| routine.name == name)
+            .unwrap_or_else(|| panic!("no routine named {name}"))
+    }
+}
+
+/// Compiles `module`, returning the story file and its debug information.
+fn compile_with_debug_info(module: &walrus::Module) -> (Vec<u8>, DebugInfo) {
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Binary, Emit::Debug]);
+    let artifacts = match wasm2glulx::compile_module_to_artifacts(&options, module) {
+        Ok(artifacts) => artifacts,
+        Err(errors) => panic!("Compilation failed. First error: {}", errors[0]),
+    };
+    let story = artifacts.get(Emit::Binary).unwrap().to_vec();
+    let debug = artifacts.get(Emit::Debug).unwrap();
+    let debug = DebugInfo::parse(std::str::from_utf8(debug).unwrap());
+    (story, debug)
+}
+
+fn ramstart(story: &[u8]) -> u32 {
+    u32::from_be_bytes(story[8..12].try_into().unwrap())
+}
+
+#[test]
+fn routines_cover_only_their_own_code() {
+    let module = common::wat(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $small (result i32) (i32.const 1))
+          (func $large (param $n i32) (result i32)
+            (local $acc i32)
+            (block $done
+              (loop $top
+                (br_if $done (i32.eqz (local.get $n)))
+                (local.set $acc (i32.add (local.get $acc) (local.get $n)))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br $top)))
+            (local.get $acc))
+          (func $main (export "glulx_main")
+            (call $result (i32.add (call $small) (call $large (i32.const 3))))))
+        "#,
+    );
+    let (story, debug) = compile_with_debug_info(&module);
+
+    let mut routines: Vec<&Routine> = debug.routines.iter().collect();
+    routines.sort_by_key(|routine| routine.address);
+    for pair in routines.windows(2) {
+        assert!(
+            pair[0].address + pair[0].byte_count <= pair[1].address,
+            "{} overlaps {}",
+            pair[0].name,
+            pair[1].name
+        );
+    }
+
+    // The runtime and the entrypoint follow the last function, so it must
+    // stop well short of RAMSTART.
+    let last = routines.last().unwrap();
+    assert!(last.byte_count > 0);
+    assert!(last.address + last.byte_count < ramstart(&story));
+
+    assert!(debug.routine("small").byte_count < debug.routine("large").byte_count);
+    assert!(debug.routine("large").byte_count < 64);
+}
+
+#[test]
+fn sequence_points_do_not_change_the_story_file() {
+    let module = common::wat(
+        r#"
+        (module
+          (func $main (export "glulx_main")
+            (local $i i32)
+            (loop $top
+              (local.set $i (i32.add (local.get $i) (i32.const 1)))
+              (br_if $top (i32.lt_u (local.get $i) (i32.const 10))))))
+        "#,
+    );
+    let (story, debug) = compile_with_debug_info(&module);
+    assert!(story == common::compile(&CompilationOptions::new(), &module));
+    assert!(debug.routine("main").byte_count > 0);
+}
+
+#[test]
+fn modules_without_dwarf_have_no_source_locations() {
+    let module = common::wat(r#"(module (func $main (export "glulx_main")))"#);
+    let (_, debug) = compile_with_debug_info(&module);
+    assert!(debug.sources.is_empty());
+    let main = debug.routine("main");
+    assert!(main.declared.is_none());
+    assert!(main.sequence_points.is_empty());
+}
+
+/// Compiles `tests/debuginfo/lines.rs` with DWARF, by way of `<name>.wasm`
+/// in the test scratch directory.
+fn dwarf_debug_info(name: &str) -> DebugInfo {
+    let src = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/debuginfo/lines.rs");
+    let wasm = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.wasm"));
+    common::build_wasm(&src, &wasm, &["-g"]);
+    let module = walrus::Module::from_file(&wasm).expect("rustc should produce valid wasm");
+    compile_with_debug_info(&module).1
+}
+
+#[test]
+fn dwarf_gives_declaration_lines() {
+    let debug = dwarf_debug_info("debuginfo_declarations");
+    let (file, line) = debug
+        .routine("triangle")
+        .declared
+        .expect("triangle should have a declaration line");
+    assert!(debug.sources[file].ends_with("lines.rs"));
+    assert_eq!(line, TRIANGLE_LINES.0);
+}
+
+#[test]
+fn dwarf_gives_sequence_points_within_each_routine() {
+    let debug = dwarf_debug_info("debuginfo_sequence_points");
+
+    for routine in &debug.routines {
+        for (address, file, _) in &routine.sequence_points {
+            assert!(
+                (routine.address..routine.address + routine.byte_count).contains(address),
+                "sequence point {address} lies outside {}",
+                routine.name
+            );
+            assert!(*file < debug.sources.len());
+        }
+    }
+
+    let triangle = debug.routine("triangle");
+    let lines: Vec<u64> = triangle
+        .sequence_points
+        .iter()
+        .filter(|(_, file, _)| debug.sources[*file].ends_with("lines.rs"))
+        .map(|(_, _, line)| *line)
+        .collect();
+    assert!(
+        lines.len() > 1,
+        "triangle should have sequence points on several lines"
+    );
+    for line in lines {
+        assert!(
+            (TRIANGLE_LINES.0..=TRIANGLE_LINES.1).contains(&line),
+            "line {line} is outside triangle"
+        );
+    }
+}
@@ -0,0 +1,25 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+// The debuginfo tests check line numbers in this file; keep `triangle` on
+// lines 14 to 20, or update the constants there to match.
+
+#[link(wasm_import_module = "glulx")]
+extern "C" {
+    fn spectest_result(word: u32);
+}
+
+#[inline(never)]
+#[no_mangle]
+pub extern "C" fn triangle(n: u32) -> u32 {
+    let mut total = 0;
+    for i in 0..=n {
+        total += i * i;
+    }
+    total
+}
+
+#[no_mangle]
+pub extern "C" fn glulx_main() {
+    unsafe { spectest_result(triangle(10)) }
+}
@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--elide-bounds-checks`.
+//!
+//! Each bounds check is a call to one of the runtime's `memload` or
+//! `memstore` helpers, so the listing shows how many were kept.
+
+mod common;
+
+use wasm2glulx::{CompilationOptions, Conformance, Emit};
+
+const TRAP: &str = "!out of bounds memory access";
+
+/// A module whose function `$f` runs `body` with the address in `$p`.
+fn module(body: &str) -> walrus::Module {
+    common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (memory 1)
+          (func $f (param $p i32) (local $q i32)
+            {body})
+          (func (export "glulx_main") (call $f (i32.const 0xfff0))))
+        "#
+    ))
+}
+
+fn options(elide: bool) -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_elide_bounds_checks(elide);
+    options
+}
+
+/// Counts the bounds checks in the listing of `body` compiled with `options`.
+fn checks_with(options: &CompilationOptions, body: &str) -> usize {
+    let mut options = options.clone();
+    options.set_emit(&[Emit::Asm]);
+    let listing = String::from_utf8(common::compile(&options, &module(body))).unwrap();
+    listing
+        .lines()
+        .filter(|line| line.contains("(rt_memload") || line.contains("(rt_memstore"))
+        .filter(|line| line.starts_with('\t'))
+        .count()
+}
+
+/// Returns the bounds checks in `body` without and with the option.
+fn checks(body: &str) -> (usize, usize) {
+    (
+        checks_with(&options(false), body),
+        checks_with(&options(true), body),
+    )
+}
+
+#[test]
+fn accesses_within_a_checked_extent_skip_the_check() {
+    // The second access is within the first one's extent.
+    assert_eq!(
+        checks(
+            "(drop (i32.load offset=4 (local.get $p)))
+             (drop (i32.load (local.get $p)))"
+        ),
+        (2, 1)
+    );
+    assert_eq!(
+        checks(
+            "(drop (i64.load (local.get $p)))
+             (drop (i32.load8_u offset=7 (local.get $p)))
+             (i32.store16 offset=2 (local.get $p) (i32.const 0))"
+        ),
+        (3, 1)
+    );
+    // Stores are checked too, and count for later loads.
+    assert_eq!(
+        checks(
+            "(i32.store (local.get $p) (i32.const 0))
+             (drop (i32.load16_u offset=2 (local.get $p)))"
+        ),
+        (2, 1)
+    );
+}
+
+#[test]
+fn accesses_past_the_checked_extent_are_checked() {
+    assert_eq!(
+        checks(
+            "(drop (i32.load (local.get $p)))
+             (drop (i32.load offset=1 (local.get $p)))"
+        ),
+        (2, 2)
+    );
+    // A check through one local says nothing about another.
+    assert_eq!(
+        checks(
+            "(local.set $q (local.get $p))
+             (drop (i32.load (local.get $p)))
+             (drop (i32.load (local.get $q)))"
+        ),
+        (2, 2)
+    );
+}
+
+#[test]
+fn writing_the_local_forgets_its_checks() {
+    for write in [
+        "(local.set $p (i32.const 0x10000))",
+        "(drop (local.tee $p (i32.const 0x10000)))",
+    ] {
+        assert_eq!(
+            checks(&format!(
+                "(drop (i32.load (local.get $p)))
+                 {write}
+                 (drop (i32.load (local.get $p)))"
+            )),
+            (2, 2),
+            "{write}"
+        );
+    }
+}
+
+#[test]
+fn labels_forget_checks() {
+    // A loop head can be reached from its back edge, and an if arm's end from
+    // the other arm, so neither may rely on earlier checks.
+    for body in [
+        "(drop (i32.load (local.get $p)))
+         (loop (drop (i32.load (local.get $p))))",
+        "(drop (i32.load (local.get $p)))
+         (block (br_if 0 (local.get $q)) (local.set $p (i32.const 0)))
+         (drop (i32.load (local.get $p)))",
+        "(drop (i32.load (local.get $p)))
+         (if (local.get $q) (then (local.set $p (i32.const 0))))
+         (drop (i32.load (local.get $p)))",
+    ] {
+        assert_eq!(checks(body), (2, 2), "{body}");
+    }
+}
+
+#[test]
+fn spec_conformance_keeps_every_check() {
+    let mut spec = options(true);
+    spec.set_conformance(Conformance::Spec);
+    assert_eq!(
+        checks_with(
+            &spec,
+            "(drop (i32.load offset=4 (local.get $p)))
+             (drop (i32.load (local.get $p)))"
+        ),
+        2
+    );
+}
+
+#[test]
+fn elided_accesses_read_and_write_memory() {
+    // $p is 0xfff0, 16 bytes before the end of memory.
+    let module = module(
+        "(i32.store offset=12 (local.get $p) (i32.const 0x11223344))
+         (i32.store (local.get $p) (i32.const 0x55667788))
+         (call $result (i32.load offset=12 (local.get $p)))
+         (call $result (i32.load (local.get $p)))
+         (call $result (i32.load8_u offset=13 (local.get $p)))
+         (call $result (i32.load16_u offset=2 (local.get $p)))",
+    );
+    assert_eq!(
+        common::compile_and_run("elide_bounds_checks_access", &options(true), &module),
+        Ok(vec![0x11223344, 0x55667788, 0x33, 0x5566])
+    );
+}
+
+#[test]
+fn out_of_bounds_accesses_still_trap() {
+    // The first access reaches the last word of memory, so the second must
+    // still be checked, and traps.
+    let past_extent = module(
+        "(call $result (i32.load offset=12 (local.get $p)))
+         (call $result (i32.load offset=13 (local.get $p)))",
+    );
+    // After $p moves out of bounds, the same access traps.
+    let after_write = module(
+        "(call $result (i32.load (local.get $p)))
+         (local.set $p (i32.const 0x10000))
+         (call $result (i32.load (local.get $p)))",
+    );
+    for (name, module) in [("past_extent", past_extent), ("after_write", after_write)] {
+        let story = common::compile(&options(true), &module);
+        let (output, stopped) =
+            common::run_until_stopped(&format!("elide_bounds_checks_{name}"), &story);
+        assert_eq!(output.len(), 1, "{name}");
+        assert_eq!(stopped.as_deref(), Some(TRAP), "{name}");
+    }
+}
@@ -0,0 +1,176 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--emit` with more than one kind of output.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use wasm2glulx::{CompilationError, CompilationOptions, Emit, OverflowLocation};
+
+const SRC: &str = r#"
+    (module
+      (import "glulx" "spectest_result" (func $result (param i32)))
+      (func $answer (export "answer") (result i32) (i32.const 42))
+      (func (export "glulx_main") (call $result (call $answer))))
+"#;
+
+fn options(emit: &[Emit]) -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_emit(emit);
+    options
+}
+
+#[test]
+fn artifacts_come_in_the_order_requested() {
+    let module = common::wat(SRC);
+    let emit = [Emit::Map, Emit::Binary, Emit::Asm];
+    let artifacts = wasm2glulx::compile_module_to_artifacts(&options(&emit), &module).unwrap();
+
+    let kinds: Vec<Emit> = artifacts.iter().map(|(emit, _)| emit).collect();
+    assert_eq!(kinds, emit);
+
+    let story = artifacts.get(Emit::Binary).unwrap();
+    assert!(story[..] == common::compile(&CompilationOptions::new(), &module)[..]);
+    assert_eq!(
+        common::run("emit_artifacts", story),
+        Ok(vec![42]),
+        "the story file should be unaffected by the other outputs"
+    );
+    let listing = std::str::from_utf8(artifacts.get(Emit::Asm).unwrap()).unwrap();
+    assert!(listing.contains(".fnlocal"));
+    assert!(!artifacts.get(Emit::Map).unwrap().is_empty());
+}
+
+#[test]
+fn bytes_prefers_the_story_file_then_the_listing() {
+    let module = common::wat(SRC);
+    let story = common::compile(&CompilationOptions::new(), &module);
+    let listing = common::compile(&options(&[Emit::Asm]), &module);
+
+    assert!(common::compile(&options(&[Emit::Map, Emit::Binary, Emit::Asm]), &module) == story);
+    assert!(common::compile(&options(&[Emit::Exports, Emit::Asm]), &module) == listing);
+}
+
+#[test]
+fn bytes_without_story_file_or_listing_is_an_error() {
+    let module = common::wat(SRC);
+    for emit in [&[][..], &[Emit::Map], &[Emit::Map, Emit::Exports]] {
+        let errors = common::compile_errors(&options(emit), &module);
+        assert!(
+            errors[0]
+                .to_string()
+                .contains("compile_module_to_artifacts"),
+            "unexpected error for {emit:?}: {}",
+            errors[0]
+        );
+    }
+}
+
+/// Writes [`SRC`] as `<name>.wasm` in the test scratch directory and returns
+/// options which compile it to the stem `<name>`, with any outputs left over
+/// from earlier runs removed.
+fn file_options(name: &str, emit: &[Emit]) -> (CompilationOptions, PathBuf) {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let input = dir.join(format!("{name}.wasm"));
+    let mut module = common::wat(SRC);
+    std::fs::write(&input, module.emit_wasm()).unwrap();
+    let stem = dir.join(name);
+    let _ = std::fs::remove_file(&stem);
+    for emit in [Emit::Binary, Emit::Asm, Emit::Map, Emit::Exports] {
+        let _ = std::fs::remove_file(with_extension(&stem, emit));
+    }
+
+    let mut options = options(emit);
+    options.set_input(Some(input));
+    options.set_output(Some(stem.clone()));
+    (options, stem)
+}
+
+fn with_extension(stem: &Path, emit: Emit) -> PathBuf {
+    let mut path = stem.to_owned().into_os_string();
+    path.push(".");
+    path.push(emit.extension());
+    PathBuf::from(path)
+}
+
+#[test]
+fn multiple_outputs_are_written_beside_the_stem() {
+    let emit = [Emit::Binary, Emit::Asm, Emit::Exports];
+    let (options, stem) = file_options("emit_stem", &emit);
+    let compiled = wasm2glulx::compile(&options).unwrap();
+
+    assert!(
+        !stem.exists(),
+        "nothing should be written to the stem itself"
+    );
+    assert!(!with_extension(&stem, Emit::Map).exists());
+    let mut total = 0;
+    for emit in emit {
+        let path = with_extension(&stem, emit);
+        total += std::fs::metadata(&path)
+            .unwrap_or_else(|e| panic!("{} should exist: {e}", path.display()))
+            .len();
+    }
+    assert_eq!(u64::try_from(compiled.bytes_written).unwrap(), total);
+
+    let story = std::fs::read(with_extension(&stem, Emit::Binary)).unwrap();
+    assert_eq!(common::run("emit_stem_story", &story), Ok(vec![42]));
+    let exports = std::fs::read_to_string(with_extension(&stem, Emit::Exports)).unwrap();
+    assert!(exports.contains("answer"));
+}
+
+#[test]
+fn single_output_is_written_to_the_path_itself() {
+    for emit in [Emit::Binary, Emit::Asm] {
+        let (options, stem) = file_options("emit_single", &[emit]);
+        wasm2glulx::compile(&options).unwrap();
+        assert!(stem.exists());
+        assert!(!with_extension(&stem, emit).exists());
+    }
+}
+
+#[test]
+fn multiple_outputs_need_an_output_path() {
+    let mut options = options(&[Emit::Binary, Emit::Asm]);
+    options.set_input(Some(PathBuf::from("does-not-matter.wasm")));
+    let errors = wasm2glulx::compile(&options).unwrap_err();
+    assert!(errors[0].to_string().contains("output path is required"));
+}
+
+#[test]
+fn failed_compilation_leaves_the_story_file_alone() {
+    // This fits the up-front size check, but not once the code is added, so
+    // it fails only in final assembly.
+    let mut module = common::wat(
+        r#"
+        (module
+          (memory 65535)
+          (table 15000 15000 funcref)
+          (func (export "glulx_main")))
+        "#,
+    );
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let input = dir.join("emit_failed.wasm");
+    std::fs::write(&input, module.emit_wasm()).unwrap();
+    let output = dir.join("emit_failed");
+    std::fs::write(&output, "the previous story").unwrap();
+
+    let mut options = options(&[Emit::Binary]);
+    options.set_input(Some(input));
+    options.set_output(Some(output.clone()));
+    let errors = wasm2glulx::compile(&options).unwrap_err();
+    assert!(
+        matches!(
+            errors[..],
+            [CompilationError::Overflow(OverflowLocation::FinalAssembly)]
+        ),
+        "{errors:?}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "the previous story"
+    );
+    assert!(!dir.join(".emit_failed.tmp").exists());
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks which exports `filter_exports` keeps.
+
+mod common;
+
+use wasm2glulx::{filter_exports, CompilationError, CompilationOptions};
+
+fn module() -> walrus::Module {
+    common::wat(
+        r#"
+        (module
+          (memory (export "memory") 1)
+          (global (export "counter") (mut i32) (i32.const 0))
+          (func (export "glulx_main") (call $used))
+          (func (export "glulx_interrupt_handler"))
+          (func $used (export "game_turn"))
+          (func (export "game_save"))
+          (func (export "debug_dump") (call $helper))
+          (func $helper))
+        "#,
+    )
+}
+
+fn filtered(patterns: &[&str]) -> Result<(Vec<String>, usize), Vec<CompilationError>> {
+    let mut options = CompilationOptions::new();
+    options.set_export_filter(patterns.iter().map(|p| p.to_string()).collect());
+    let mut module = module();
+    filter_exports(&options, &mut module)?;
+    let mut exports: Vec<String> = module.exports.iter().map(|e| e.name.clone()).collect();
+    exports.sort();
+    Ok((exports, module.funcs.iter().count()))
+}
+
+#[test]
+fn no_patterns_keeps_everything() {
+    let (exports, funcs) = filtered(&[]).unwrap();
+    assert_eq!(exports.len(), 7);
+    assert_eq!(funcs, 6);
+}
+
+#[test]
+fn patterns_must_match_the_whole_name() {
+    let (exports, _) = filtered(&["game", "turn"]).unwrap();
+    assert_eq!(
+        exports,
+        ["counter", "glulx_interrupt_handler", "glulx_main", "memory"]
+    );
+}
+
+#[test]
+fn regex_patterns_select_exports() {
+    let (exports, funcs) = filtered(&["game_.*"]).unwrap();
+    assert_eq!(
+        exports,
+        [
+            "counter",
+            "game_save",
+            "game_turn",
+            "glulx_interrupt_handler",
+            "glulx_main",
+            "memory"
+        ]
+    );
+    // debug_dump and the helper only it calls are gone.
+    assert_eq!(funcs, 4);
+}
+
+#[test]
+fn reserved_exports_and_non_functions_are_kept() {
+    let (exports, funcs) = filtered(&["nothing_matches_this"]).unwrap();
+    assert_eq!(
+        exports,
+        ["counter", "glulx_interrupt_handler", "glulx_main", "memory"]
+    );
+    // glulx_main still calls game_turn's function, so it survives unexported.
+    assert_eq!(funcs, 3);
+}
+
+#[test]
+fn invalid_patterns_are_rejected() {
+    let errors = filtered(&["game_("]).unwrap_err();
+    assert!(
+        matches!(&errors[..], [CompilationError::OtherError(e)]
+            if e.to_string().starts_with("Invalid export filter")),
+        "{errors:?}"
+    );
+}
@@ -0,0 +1,182 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Keeps [`WASM_FEATURES`] in sync with what the compiler actually does.
+//!
+//! Every feature in the table has sample modules here: some which use the
+//! feature and should compile, and some which should be rejected. Which
+//! samples are allowed depends on the feature's status, so changing what the
+//! compiler accepts without updating the table (or vice versa) fails this
+//! test.
+
+use wasm2glulx::{CompilationOptions, FeatureStatus, WASM_FEATURES};
+
+struct Samples {
+    accepted: &'static [&'static str],
+    rejected: &'static [&'static str],
+}
+
+fn samples(feature: &str) -> Samples {
+    match feature {
+        "mutable-globals" => Samples {
+            accepted: &[r#"(global $g (mut i32) (i32.const 0))
+                (func (export "glulx_main") (global.set $g (i32.const 1)))"#],
+            rejected: &[],
+        },
+        "sign-extension-ops" => Samples {
+            accepted: &[r#"(func (export "glulx_main")
+                (drop (i32.extend8_s (i32.const 0x80))))"#],
+            rejected: &[],
+        },
+        "nontrapping-float-to-int" => Samples {
+            accepted: &[r#"(func (export "glulx_main")
+                (drop (i32.trunc_sat_f32_s (f32.const 1e10))))"#],
+            rejected: &[],
+        },
+        "multi-value" => Samples {
+            accepted: &[r#"(func $pair (result i32 i32) (i32.const 1) (i32.const 2))
+                (func (export "glulx_main") (call $pair) (drop) (drop))"#],
+            rejected: &[],
+        },
+        "bulk-memory" => Samples {
+            accepted: &[r#"(memory 1)
+                (func (export "glulx_main")
+                  (memory.fill (i32.const 0) (i32.const 1) (i32.const 16)))"#],
+            rejected: &[],
+        },
+        "reference-types" => Samples {
+            accepted: &[r#"(table 1 externref)
+                (func (export "glulx_main")
+                  (table.set (i32.const 0) (ref.null extern))
+                  (drop (ref.is_null (table.get (i32.const 0)))))"#],
+            rejected: &[],
+        },
+        "branch-hinting" => Samples {
+            accepted: &[r#"(@custom "metadata.code.branch_hint" "")
+                (func (export "glulx_main"))"#],
+            rejected: &[],
+        },
+        "threads" => Samples {
+            accepted: &[r#"(memory 1 1 shared)
+                (func (export "glulx_main")
+                  (i32.atomic.store (i32.const 0) (i32.atomic.load (i32.const 4))))"#],
+            rejected: &[r#"(memory 1 1 shared)
+                (func (export "glulx_main")
+                  (drop (i32.atomic.rmw.add (i32.const 0) (i32.const 1))))"#],
+        },
+        "simd" => Samples {
+            accepted: &[r#"(func (export "glulx_main")
+                (drop (i32x4.add (v128.const i32x4 1 2 3 4) (v128.const i32x4 5 6 7 8))))"#],
+            rejected: &[r#"(func (export "glulx_main")
+                (drop (f32x4.sqrt (v128.const f32x4 1 2 3 4))))"#],
+        },
+        "relaxed-simd" => Samples {
+            accepted: &[],
+            rejected: &[r#"(func (export "glulx_main")
+                (drop (i32x4.relaxed_trunc_f32x4_s (v128.const f32x4 1 2 3 4))))"#],
+        },
+        "tail-call" => Samples {
+            accepted: &[r#"(func $f (result i32) (i32.const 1))
+                (func $g (result i32) (return_call $f))
+                (func (export "glulx_main") (drop (call $g)))"#],
+            rejected: &[],
+        },
+        "extended-const" => Samples {
+            accepted: &[r#"(global $g i32 (i32.add (i32.const 1) (i32.const 2)))
+                (func (export "glulx_main") (drop (global.get $g)))"#],
+            rejected: &[],
+        },
+        "exception-handling" => Samples {
+            accepted: &[],
+            rejected: &[r#"(tag $e)
+                (func (export "glulx_main") (throw $e))"#],
+        },
+        "multi-memory" => Samples {
+            accepted: &[],
+            rejected: &[r#"(memory $a 1) (memory $b 1 1)
+                (func (export "glulx_main")
+                  (i32.store $b (i32.const 0) (i32.load $a (i32.const 0))))"#],
+        },
+        "memory64" => Samples {
+            accepted: &[],
+            rejected: &[r#"(memory i64 1)
+                (func (export "glulx_main") (drop (i32.load (i64.const 0))))"#],
+        },
+        "gc" => Samples {
+            accepted: &[],
+            rejected: &[r#"(type $s (struct (field i32)))
+                (func (export "glulx_main")
+                  (drop (struct.new $s (i32.const 1))))"#],
+        },
+        other => panic!("No samples for feature `{other}`; add some to tests/features.rs"),
+    }
+}
+
+/// Returns whether the module compiles, following the same path as
+/// [`wasm2glulx::compile`].
+fn compiles(body: &str) -> bool {
+    let src = format!("(module {body})");
+    let buf = wast::parser::ParseBuffer::new(&src).unwrap();
+    let mut wat: wast::Wat =
+        wast::parser::parse(&buf).unwrap_or_else(|e| panic!("Sample doesn't parse: {e}\n{src}"));
+    let wasm = wat.encode().unwrap();
+
+    let mut config = walrus::ModuleConfig::new();
+    config.generate_synthetic_names_for_anonymous_items(true);
+    let Ok(module) = config.parse(&wasm2glulx::fold_constant_exprs(&wasm)) else {
+        return false;
+    };
+    wasm2glulx::compile_module_to_bytes(&CompilationOptions::new(), &module).is_ok()
+}
+
+#[test]
+fn feature_table_matches_compiler() {
+    for feature in WASM_FEATURES {
+        let samples = samples(feature.name);
+        let (want_accepted, want_rejected) = match feature.status {
+            FeatureStatus::Supported | FeatureStatus::Lowered => (true, false),
+            FeatureStatus::Partial => (true, true),
+            FeatureStatus::Rejected => (false, true),
+        };
+        assert_eq!(
+            !samples.accepted.is_empty(),
+            want_accepted,
+            "`{}` is {}, so it should{} have accepted samples",
+            feature.name,
+            feature.status,
+            if want_accepted { "" } else { " not" }
+        );
+        assert_eq!(
+            !samples.rejected.is_empty(),
+            want_rejected,
+            "`{}` is {}, so it should{} have rejected samples",
+            feature.name,
+            feature.status,
+            if want_rejected { "" } else { " not" }
+        );
+        for sample in samples.accepted {
+            assert!(
+                compiles(sample),
+                "`{}` is {}, but this doesn't compile:\n{sample}",
+                feature.name,
+                feature.status
+            );
+        }
+        for sample in samples.rejected {
+            assert!(
+                !compiles(sample),
+                "This use of `{}` should be rejected, but compiles:\n{sample}",
+                feature.name
+            );
+        }
+    }
+}
+
+#[test]
+fn features_json_lists_every_feature() {
+    let json = wasm2glulx::features_json();
+    for feature in WASM_FEATURES {
+        assert!(json.contains(&format!("\"name\": \"{}\"", feature.name)));
+        assert!(json.contains(&format!("\"status\": \"{}\"", feature.status)));
+    }
+}
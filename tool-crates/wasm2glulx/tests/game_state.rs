@@ -0,0 +1,75 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for the game state intrinsics: `save`, `restore`, and the undo
+//! functions.
+//!
+//! bogoglulx implements none of the instructions behind these, so the tests
+//! check the listing rather than running anything.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions, Emit};
+
+/// Each intrinsic, the signature documented for it in the manual, and the
+/// instruction it should emit.
+const INTRINSICS: &[(&str, &str, &str)] = &[
+    ("save", "(param i32) (result i32)", "\tsave "),
+    ("restore", "(param i32) (result i32)", "\trestore "),
+    ("saveundo", "(result i32)", "\tsaveundo "),
+    ("restoreundo", "(result i32)", "\trestoreundo "),
+    ("hasundo", "(result i32)", "\thasundo "),
+    ("discardundo", "", "\tdiscardundo\n"),
+];
+
+/// Compiles a module which imports `name` with signature `ty` and calls it
+/// with zero arguments as needed, returning the listing or the errors.
+fn listing_calling(name: &str, ty: &str) -> Result<String, Vec<CompilationError>> {
+    let args = if ty.contains("param") {
+        "(i32.const 0)"
+    } else {
+        ""
+    };
+    let call = if ty.contains("result") {
+        format!("(drop (call $f {args}))")
+    } else {
+        format!("(call $f {args})")
+    };
+    let module = common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "{name}" (func $f {ty}))
+          (func (export "glulx_main") {call}))
+        "#
+    ));
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Asm]);
+    let listing = wasm2glulx::compile_module_to_bytes(&options, &module)?;
+    Ok(String::from_utf8(listing.to_vec()).unwrap())
+}
+
+#[test]
+fn each_intrinsic_emits_its_own_instruction() {
+    for (name, ty, instr) in INTRINSICS {
+        let listing = listing_calling(name, ty)
+            .unwrap_or_else(|errors| panic!("{name} {ty} was rejected: {}", errors[0]));
+        assert!(listing.contains(instr), "{name} should emit {instr:?}");
+        for (_, _, other) in INTRINSICS {
+            if other != instr {
+                assert!(!listing.contains(other), "{name} should not emit {other:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn undo_intrinsics_take_no_arguments() {
+    for name in ["saveundo", "restoreundo", "hasundo"] {
+        let errors = listing_calling(name, "(param i32)").unwrap_err();
+        assert!(
+            matches!(errors[0], CompilationError::IncorrectlyTypedImport { .. }),
+            "{name} with a parameter should be rejected, not {}",
+            errors[0]
+        );
+    }
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for the startup check against an exported
+//! `glulx_expected_glkarea_size`.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions};
+
+/// A module which expects a Glk area of `size` bytes, stored at address 16,
+/// and reports 1 from `glulx_main`.
+fn expecting(size: u32) -> walrus::Module {
+    common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (memory 1)
+          (data (i32.const 16) "{}")
+          (global (export "glulx_expected_glkarea_size") i32 (i32.const 16))
+          (func (export "glulx_main")
+            (call $result (i32.const 1))))
+        "#,
+        size.to_le_bytes()
+            .iter()
+            .map(|b| format!("\\{b:02x}"))
+            .collect::<String>()
+    ))
+}
+
+#[test]
+fn matching_size_starts_normally() {
+    let mut options = CompilationOptions::new();
+    options.set_glk_area_size(8192);
+    let output = common::compile_and_run("glk_area_check_match", &options, &expecting(8192));
+    assert_eq!(output, Ok(vec![1]));
+}
+
+#[test]
+fn mismatched_size_traps_before_main() {
+    let output = common::compile_and_run(
+        "glk_area_check_mismatch",
+        &CompilationOptions::new(),
+        &expecting(8192),
+    );
+    assert_eq!(output, Err("!glk area size mismatch".to_owned()));
+}
+
+#[test]
+fn expected_size_global_must_be_i32() {
+    let module = common::wat(
+        r#"
+        (module
+          (global (export "glulx_expected_glkarea_size") i64 (i64.const 4096))
+          (func (export "glulx_main")))
+        "#,
+    );
+    let errors = common::compile_errors(&CompilationOptions::new(), &module);
+    assert!(
+        matches!(
+            errors.as_slice(),
+            [CompilationError::ValidationError(e)]
+                if e.to_string() == "Exported global glulx_expected_glkarea_size must have type i32"
+        ),
+        "{errors:?}"
+    );
+}
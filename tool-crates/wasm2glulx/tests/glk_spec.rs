@@ -0,0 +1,86 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that the Glk bindings in wasm2glulx-ffi match `glk.spec`, and that
+//! `glk.spec` gives the right selectors for extension functions.
+//!
+//! The build script renders the bindings that `glk.spec` describes, and this
+//! test compares them against `wasm2glulx-ffi/src/glk_functions.rs`. Run with
+//! `WASM2GLULX_BLESS=1` to rewrite that file after changing the spec. If
+//! wasm2glulx-ffi isn't alongside this crate, as when building from a
+//! published package, the test is skipped with a message.
+
+use std::path::PathBuf;
+
+const GENERATED: &str = include_str!(concat!(env!("OUT_DIR"), "/glk_ffi.rs"));
+
+fn bless() -> bool {
+    std::env::var_os("WASM2GLULX_BLESS").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+#[test]
+fn ffi_bindings_match_spec() {
+    let mut ffi_src = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    ffi_src.extend(["..", "..", "game-crates", "wasm2glulx-ffi", "src"]);
+    if !ffi_src.is_dir() {
+        eprintln!(
+            "Skipping Glk binding check: {} not found",
+            ffi_src.display()
+        );
+        return;
+    }
+
+    let path = ffi_src.join("glk_functions.rs");
+    if bless() {
+        std::fs::write(&path, GENERATED).expect("FFI bindings should be writable");
+        return;
+    }
+
+    let actual = std::fs::read_to_string(&path).expect("FFI bindings should be readable");
+    assert!(
+        actual == GENERATED,
+        "{} is out of date with glk.spec; rerun with WASM2GLULX_BLESS=1 to update it",
+        path.display()
+    );
+}
+
+/// Compiles a module which calls the Glk function `name`, which takes
+/// `params` arguments, and returns the assembly listing.
+fn listing_calling(name: &str, params: usize) -> String {
+    let src = format!(
+        r#"
+        (module
+          (import "glk" "{name}" (func $f (param {})))
+          (func (export "glulx_main") (call $f {})))
+        "#,
+        "i32 ".repeat(params),
+        "(i32.const 0) ".repeat(params),
+    );
+    let buf = wast::parser::ParseBuffer::new(&src).unwrap();
+    let mut wat: wast::Wat = wast::parser::parse(&buf).unwrap();
+    let module = walrus::Module::from_buffer(&wat.encode().unwrap()).unwrap();
+    let mut options = wasm2glulx::CompilationOptions::new();
+    options.set_text(true);
+    let listing = wasm2glulx::compile_module_to_bytes(&options, &module).unwrap();
+    String::from_utf8(listing.to_vec()).unwrap()
+}
+
+/// Gargoyle's extension functions have no published specification, so their
+/// selectors are pinned here against the dispatch table in Gargoyle's
+/// `gi_dispa.c`.
+#[test]
+fn garglk_selectors_match_gargoyle() {
+    for (name, params, selector) in [
+        ("garglk_set_zcolors", 2, 0x1100),
+        ("garglk_set_zcolors_stream", 3, 0x1101),
+        ("garglk_set_reversevideo", 1, 0x1102),
+        ("garglk_set_reversevideo_stream", 2, 0x1103),
+    ] {
+        let listing = listing_calling(name, params);
+        let expected = format!("\tglk {selector:#x} {params:#x} discard\n");
+        assert!(
+            listing.contains(&expected),
+            "{name} should be called with selector {selector:#x}"
+        );
+    }
+}
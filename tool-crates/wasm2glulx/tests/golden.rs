@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Golden-file regression tests for whole Rust programs.
+//!
+//! Each program in `tests/golden/` is compiled to WebAssembly with `rustc`,
+//! translated to Glulx, and run under bogoglulx. Programs report results by
+//! calling the `spectest_result` intrinsic, which bogoglulx prints as eight hex
+//! digits per word. The printed words are compared against
+//! `tests/golden/<name>.expected`, one word per line, and the story file's
+//! size metrics against `tests/golden/<name>.metrics`.
+//!
+//! Size metrics may drift by up to [`SIZE_TOLERANCE_PERCENT`] before a test
+//! fails, so that routine rustc upgrades don't break the suite. Run with
+//! `WASM2GLULX_BLESS=1` to rewrite the expected and metrics files after an
+//! intentional change; that is the only time these tests write into the source
+//! tree. A missing expected or metrics file is a failure otherwise.
+//!
+//! The tests need `rustc` to be able to target `wasm32-unknown-unknown`, and
+//! fail if it can't. Install it with `rustup target add
+//! wasm32-unknown-unknown`, or pass `--skip golden` to leave these tests out.
+
+mod common;
+
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use wasm2glulx::CompilationOptions;
+
+const SIZE_TOLERANCE_PERCENT: u64 = 2;
+
+fn golden_dir() -> PathBuf {
+    let mut dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    dir.push("tests");
+    dir.push("golden");
+    dir
+}
+
+fn bless() -> bool {
+    std::env::var_os("WASM2GLULX_BLESS").is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Size metrics read from a story file's header.
+struct Metrics {
+    rom_bytes: u64,
+    file_bytes: u64,
+    memory_bytes: u64,
+}
+
+impl Metrics {
+    fn from_story(story: &[u8]) -> Metrics {
+        let word = |offset: usize| {
+            u64::from(u32::from_be_bytes(
+                story[offset..offset + 4].try_into().unwrap(),
+            ))
+        };
+        Metrics {
+            rom_bytes: word(8),
+            file_bytes: word(12),
+            memory_bytes: word(16),
+        }
+    }
+
+    fn fields(&self) -> [(&'static str, u64); 3] {
+        [
+            ("rom_bytes", self.rom_bytes),
+            ("file_bytes", self.file_bytes),
+            ("memory_bytes", self.memory_bytes),
+        ]
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, value) in self.fields() {
+            writeln!(out, "{name} {value}").unwrap();
+        }
+        out
+    }
+
+    fn parse(text: &str) -> Vec<(String, u64)> {
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (name, value) = line
+                    .split_once(' ')
+                    .expect("metrics lines should have the form `name value`");
+                (
+                    name.to_owned(),
+                    value
+                        .trim()
+                        .parse()
+                        .expect("metrics values should be integers"),
+                )
+            })
+            .collect()
+    }
+}
+
+fn within_tolerance(expected: u64, actual: u64) -> bool {
+    expected.abs_diff(actual) * 100 <= expected * SIZE_TOLERANCE_PERCENT
+}
+
+fn run_golden(name: &str) {
+    let golden = golden_dir();
+    let src_path = golden.join(format!("{name}.rs"));
+    let expected_path = golden.join(format!("{name}.expected"));
+    let metrics_path = golden.join(format!("{name}.metrics"));
+
+    let workdir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&workdir).unwrap();
+    let wasm_path = workdir.join(format!("{name}.wasm"));
+    let story_path = workdir.join(format!("{name}.ulx"));
+
+    common::build_wasm(&src_path, &wasm_path, &[]);
+
+    let module = walrus::Module::from_file(&wasm_path).expect("rustc should produce valid wasm");
+    let story = match wasm2glulx::compile_module_to_bytes(&CompilationOptions::new(), &module) {
+        Ok(story) => story,
+        Err(errors) => panic!("Compilation failed. First error: {}", errors[0]),
+    };
+    std::fs::write(&story_path, &story).unwrap();
+
+    let output = Command::new(env!("BOGOGLULX_BIN"))
+        .arg(&story_path)
+        .output()
+        .unwrap_or_else(|e| panic!("bogoglulx execution failed: {e}"));
+    let stdout =
+        std::str::from_utf8(&output.stdout).expect("Bogoglulx output should be valid UTF-8");
+
+    let mut actual = String::new();
+    let mut rest = stdout;
+    while !rest.is_empty() {
+        if rest.starts_with(['!', '?']) || rest.len() < 8 {
+            writeln!(actual, "{rest}").unwrap();
+            break;
+        }
+        let (word, tail) = rest.split_at(8);
+        writeln!(actual, "{word}").unwrap();
+        rest = tail;
+    }
+
+    let metrics = Metrics::from_story(&story);
+
+    if bless() {
+        std::fs::write(&expected_path, &actual).unwrap();
+        std::fs::write(&metrics_path, metrics.render()).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read_to_string(&expected_path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read {}: {e}\nRun with WASM2GLULX_BLESS=1 to record it.",
+            expected_path.display()
+        )
+    });
+    assert!(
+        actual == expected,
+        "Output of {name} differed from {}.\nActual:\n{actual}",
+        expected_path.display()
+    );
+
+    let recorded = std::fs::read_to_string(&metrics_path).unwrap_or_else(|e| {
+        panic!(
+            "Failed to read {}: {e}\nRun with WASM2GLULX_BLESS=1 to record it.",
+            metrics_path.display()
+        )
+    });
+
+    let mut regressions = Vec::new();
+    for (field, expected) in Metrics::parse(&recorded) {
+        let Some((_, actual)) = metrics.fields().into_iter().find(|(f, _)| *f == field) else {
+            panic!("Unknown metric `{field}` in {}", metrics_path.display());
+        };
+        if !within_tolerance(expected, actual) {
+            regressions.push(format!("{field}: expected {expected}, got {actual}"));
+        }
+    }
+    assert!(
+        regressions.is_empty(),
+        "Size metrics of {name} changed by more than {SIZE_TOLERANCE_PERCENT}%:\n{}\nRun with WASM2GLULX_BLESS=1 to accept.",
+        regressions.join("\n")
+    );
+
+    let _ = std::fs::remove_file(&story_path);
+    let _ = std::fs::remove_file(&wasm_path);
+}
+
+macro_rules! golden {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                run_golden(stringify!($name));
+            }
+        )*
+    };
+}
+
+golden!(hashmap, fmt, recursion, float);
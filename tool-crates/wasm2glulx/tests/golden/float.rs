@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+#[link(wasm_import_module = "glulx")]
+extern "C" {
+    fn spectest_result(word: u32);
+}
+
+fn emit(word: u32) {
+    unsafe { spectest_result(word) }
+}
+
+fn emit_f32(x: f32) {
+    emit(x.to_bits());
+}
+
+fn emit_f64(x: f64) {
+    let bits = x.to_bits();
+    emit((bits >> 32) as u32);
+    emit(bits as u32);
+}
+
+#[inline(never)]
+fn newton_sqrt(x: f64) -> f64 {
+    let mut guess = x / 2.0;
+    for _ in 0..20 {
+        guess = (guess + x / guess) / 2.0;
+    }
+    guess
+}
+
+#[inline(never)]
+fn leibniz_pi(terms: u32) -> f64 {
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for k in 0..terms {
+        sum += sign / f64::from(2 * k + 1);
+        sign = -sign;
+    }
+    4.0 * sum
+}
+
+#[inline(never)]
+fn mandelbrot_escape(cr: f32, ci: f32) -> u32 {
+    let (mut zr, mut zi) = (0.0f32, 0.0f32);
+    for i in 0..100 {
+        if zr * zr + zi * zi > 4.0 {
+            return i;
+        }
+        let t = zr * zr - zi * zi + cr;
+        zi = 2.0 * zr * zi + ci;
+        zr = t;
+    }
+    100
+}
+
+#[no_mangle]
+pub extern "C" fn glulx_main() {
+    emit_f64(newton_sqrt(2.0));
+    emit_f64(2.0f64.sqrt());
+    emit_f32(10.0f32.sqrt());
+    emit_f64(leibniz_pi(1000));
+
+    let mut escapes = 0u32;
+    for y in -10..=10 {
+        for x in -20..=10 {
+            escapes = escapes
+                .wrapping_mul(31)
+                .wrapping_add(mandelbrot_escape(x as f32 / 10.0, y as f32 / 10.0));
+        }
+    }
+    emit(escapes);
+
+    emit_f64((-7.5f64).floor());
+    emit_f64(7.5f64.ceil());
+    emit_f64((2.5f64).trunc());
+    emit_f32(f32::from_bits(0x3fc0_0000).mul_add(2.0, 0.25));
+    emit((1.0e10f64) as i32 as u32);
+    emit((-1.0f32) as u32);
+    emit(f64::NAN as u32);
+    emit_f64(u64::MAX as f64);
+    emit_f32(i64::MIN as f32);
+    emit_f64(f64::from(f32::MIN_POSITIVE) * 0.5);
+    emit_f32((1.0e-40f64) as f32);
+    emit(f64::NAN.min(1.0).to_bits() as u32);
+    emit((0.1f64 + 0.2 == 0.3).into());
+}
@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+use std::fmt::Write;
+
+#[link(wasm_import_module = "glulx")]
+extern "C" {
+    fn spectest_result(word: u32);
+}
+
+fn emit(word: u32) {
+    unsafe { spectest_result(word) }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct Room {
+    name: &'static str,
+    exits: [Option<u8>; 4],
+    lit: bool,
+}
+
+#[no_mangle]
+pub extern "C" fn glulx_main() {
+    let room = Room {
+        name: "West of House",
+        exits: [Some(1), None, Some(3), None],
+        lit: true,
+    };
+
+    let mut out = String::new();
+    writeln!(out, "{}|{:>6}|{:<6}|{:^7}|", 42, -17, "ab", "mid").unwrap();
+    writeln!(
+        out,
+        "{:x} {:#X} {:o} {:b} {:08.3}",
+        48879, 3054, 8, 5u8, 3.14159
+    )
+    .unwrap();
+    writeln!(out, "{} {} {:e} {}", 1.5f32, -0.1f64, 6.02e23, f64::MAX).unwrap();
+    writeln!(out, "{:?}", room).unwrap();
+    writeln!(out, "{:?} {:?}", "tab\there", 'λ').unwrap();
+    writeln!(out, "{}", i128::MIN).unwrap();
+
+    for byte in out.bytes() {
+        emit(byte.into());
+    }
+}
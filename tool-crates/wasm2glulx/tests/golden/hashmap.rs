@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+use std::collections::HashMap;
+
+#[link(wasm_import_module = "glulx")]
+extern "C" {
+    fn spectest_result(word: u32);
+}
+
+fn emit(word: u32) {
+    unsafe { spectest_result(word) }
+}
+
+const TEXT: &str = "it was the best of times it was the worst of times it was the age of \
+                    wisdom it was the age of foolishness";
+
+#[no_mangle]
+pub extern "C" fn glulx_main() {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for word in TEXT.split_whitespace() {
+        *counts.entry(word.to_owned()).or_insert(0) += 1;
+    }
+
+    let mut squares: HashMap<u32, u32> = HashMap::with_capacity(4);
+    for i in 0..500 {
+        squares.insert(i, i.wrapping_mul(i));
+    }
+    for i in (0..500).step_by(3) {
+        squares.remove(&i);
+    }
+
+    let mut words: Vec<(&String, &u32)> = counts.iter().collect();
+    words.sort();
+
+    emit(words.len() as u32);
+    for (word, count) in words {
+        for byte in word.bytes() {
+            emit(byte.into());
+        }
+        emit(*count);
+    }
+
+    emit(squares.len() as u32);
+    emit(squares.values().fold(0u32, |acc, v| acc.wrapping_add(*v)));
+    emit(squares.get(&499).copied().unwrap_or(u32::MAX));
+    emit(squares.get(&498).copied().unwrap_or(u32::MAX));
+}
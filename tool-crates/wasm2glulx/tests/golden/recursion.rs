@@ -0,0 +1,98 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+#[link(wasm_import_module = "glulx")]
+extern "C" {
+    fn spectest_result(word: u32);
+}
+
+fn emit(word: u32) {
+    unsafe { spectest_result(word) }
+}
+
+#[inline(never)]
+fn fib(n: u32) -> u32 {
+    if n < 2 {
+        n
+    } else {
+        fib(n - 1) + fib(n - 2)
+    }
+}
+
+#[inline(never)]
+fn ackermann(m: u64, n: u64) -> u64 {
+    match (m, n) {
+        (0, n) => n + 1,
+        (m, 0) => ackermann(m - 1, 1),
+        (m, n) => ackermann(m - 1, ackermann(m, n - 1)),
+    }
+}
+
+#[inline(never)]
+fn hanoi(n: u32, from: u32, to: u32, via: u32, moves: &mut Vec<(u32, u32)>) {
+    if n > 0 {
+        hanoi(n - 1, from, via, to, moves);
+        moves.push((from, to));
+        hanoi(n - 1, via, to, from, moves);
+    }
+}
+
+#[inline(never)]
+fn is_even(n: u32) -> bool {
+    n == 0 || is_odd(n - 1)
+}
+
+#[inline(never)]
+fn is_odd(n: u32) -> bool {
+    n != 0 && is_even(n - 1)
+}
+
+#[derive(Debug)]
+enum Expr {
+    Num(i32),
+    Add(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Neg(Box<Expr>),
+}
+
+fn eval(expr: &Expr) -> i32 {
+    match expr {
+        Expr::Num(n) => *n,
+        Expr::Add(a, b) => eval(a).wrapping_add(eval(b)),
+        Expr::Mul(a, b) => eval(a).wrapping_mul(eval(b)),
+        Expr::Neg(a) => eval(a).wrapping_neg(),
+    }
+}
+
+fn build(depth: u32) -> Expr {
+    match depth % 3 {
+        _ if depth == 0 => Expr::Num(7),
+        0 => Expr::Add(
+            Box::new(build(depth - 1)),
+            Box::new(Expr::Num(depth as i32)),
+        ),
+        1 => Expr::Mul(Box::new(build(depth - 1)), Box::new(Expr::Num(3))),
+        _ => Expr::Neg(Box::new(build(depth - 1))),
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn glulx_main() {
+    emit(fib(20));
+
+    let ack = ackermann(2, 3);
+    emit((ack >> 32) as u32);
+    emit(ack as u32);
+
+    let mut moves = Vec::new();
+    hanoi(8, 1, 3, 2, &mut moves);
+    emit(moves.len() as u32);
+    emit(moves.iter().fold(0u32, |acc, &(from, to)| {
+        acc.rotate_left(3) ^ (from * 4 + to)
+    }));
+
+    emit(is_even(1000).into());
+    emit(is_odd(777).into());
+
+    emit(eval(&build(40)) as u32);
+}
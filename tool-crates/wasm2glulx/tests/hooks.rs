@@ -0,0 +1,134 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests that [`Hooks`] callbacks are called at the documented points, and
+//! that what they add is assembled.
+
+mod common;
+
+use glulx_asm::concise::*;
+use glulx_asm::{Item, MysteryString};
+use walrus::Function;
+use wasm2glulx::{CompilationOptions, Emit, HookContext, Hooks, Label};
+
+const MODULE: &str = r#"
+(module
+  (func $first (result i32) (i32.const 1))
+  (func $second (result i32) (i32.const 2))
+  (func $main (export "glulx_main")
+    (drop (i32.add (call $first) (call $second)))))
+"#;
+
+/// Records each callback it receives, in order.
+#[derive(Default)]
+struct Recorder {
+    events: Vec<String>,
+    /// `ctx.rom_items.len()` as of the last `before_function` call.
+    len_before: usize,
+}
+
+impl Hooks for Recorder {
+    fn after_layout(&mut self, ctx: &mut HookContext<'_>) {
+        assert!(ctx.rom_items.is_empty(), "no items should exist yet");
+        self.events.push("after_layout".to_owned());
+    }
+
+    fn before_function(&mut self, ctx: &mut HookContext<'_>, function: &Function) {
+        self.len_before = ctx.rom_items.len();
+        self.events.push(format!("before {}", name(function)));
+    }
+
+    fn after_function(&mut self, ctx: &mut HookContext<'_>, function: &Function, start: usize) {
+        assert_eq!(start, self.len_before, "{}", name(function));
+        assert!(start < ctx.rom_items.len(), "{}", name(function));
+        self.events.push(format!("after {}", name(function)));
+    }
+
+    fn before_assembly(&mut self, _ctx: &mut HookContext<'_>) {
+        self.events.push("before_assembly".to_owned());
+    }
+}
+
+fn name(function: &Function) -> &str {
+    function.name.as_deref().unwrap_or("?")
+}
+
+#[test]
+fn callbacks_run_in_order() {
+    let module = common::wat(MODULE);
+    let mut recorder = Recorder::default();
+    wasm2glulx::compile_module_to_bytes_with_hooks(
+        &CompilationOptions::new(),
+        &module,
+        &mut recorder,
+    )
+    .unwrap();
+    assert_eq!(
+        recorder.events,
+        [
+            "after_layout",
+            "before first",
+            "after first",
+            "before second",
+            "after second",
+            "before main",
+            "after main",
+            "before_assembly",
+        ]
+    );
+}
+
+/// Appends a labelled string to ROM just before assembly.
+struct AppendString(&'static str);
+
+impl Hooks for AppendString {
+    fn before_assembly(&mut self, ctx: &mut HookContext<'_>) {
+        let string_label: Label = ctx.gen_label("hook_string");
+        ctx.rom_items.extend([
+            label(string_label),
+            Item::MysteryString(MysteryString::try_from(self.0).unwrap()),
+        ]);
+    }
+}
+
+#[test]
+fn added_items_are_assembled() {
+    const MESSAGE: &str = "added by a hook";
+    let module = common::wat(MODULE);
+
+    let story = wasm2glulx::compile_module_to_bytes_with_hooks(
+        &CompilationOptions::new(),
+        &module,
+        &mut AppendString(MESSAGE),
+    )
+    .unwrap();
+    assert!(story
+        .windows(MESSAGE.len())
+        .any(|window| window == MESSAGE.as_bytes()));
+    assert_eq!(
+        common::run("hooks_added_items", &story),
+        Ok(vec![]),
+        "the program should still run"
+    );
+
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Asm]);
+    let listing = wasm2glulx::compile_module_to_bytes_with_hooks(
+        &options,
+        &module,
+        &mut AppendString(MESSAGE),
+    )
+    .unwrap();
+    let listing = String::from_utf8(listing.to_vec()).unwrap();
+    assert!(listing.contains(&format!(".string \"{MESSAGE}\"")));
+}
+
+#[test]
+fn unit_hooks_change_nothing() {
+    let module = common::wat(MODULE);
+    let options = CompilationOptions::new();
+    let with_unit =
+        wasm2glulx::compile_module_to_bytes_with_hooks(&options, &module, &mut ()).unwrap();
+    let without = wasm2glulx::compile_module_to_bytes(&options, &module).unwrap();
+    assert_eq!(with_unit, without);
+}
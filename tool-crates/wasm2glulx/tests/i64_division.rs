@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for 64-bit division where both operands' low words are zero, which
+//! the spec tests don't cover.
+
+mod common;
+
+use wasm2glulx::CompilationOptions;
+
+/// Runs `x <op> y`, returning the result's words, low first, or the trap
+/// message.
+fn run(op: &str, x: u64, y: u64) -> Result<Vec<u32>, String> {
+    let module = common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $op (param $x i64) (param $y i64) (local $r i64)
+            (local.set $r (i64.{op} (local.get $x) (local.get $y)))
+            (call $result (i32.wrap_i64 (local.get $r)))
+            (call $result (i32.wrap_i64 (i64.shr_u (local.get $r) (i64.const 32)))))
+          (func (export "glulx_main")
+            (call $op (i64.const {x}) (i64.const {y}))))
+        "#,
+        x = x as i64,
+        y = y as i64,
+    ));
+    common::compile_and_run(
+        &format!("i64_{op}_{x:x}_{y:x}"),
+        &CompilationOptions::new(),
+        &module,
+    )
+}
+
+fn words(value: u64) -> Vec<u32> {
+    vec![value as u32, (value >> 32) as u32]
+}
+
+#[test]
+fn zero_low_words_divide_correctly() {
+    for (x, y) in [
+        (0x8000_0000_0000_0000u64, 0x4000_0000_0000_0000u64),
+        (0x0000_0007_0000_0000, 0x0000_0002_0000_0000),
+        (0xffff_ffff_0000_0000, 0x0000_0001_0000_0000),
+        (0x0000_0001_0000_0000, 0x0000_0002_0000_0000),
+    ] {
+        assert_eq!(run("div_u", x, y), Ok(words(x / y)), "{x:#x} / {y:#x}");
+        assert_eq!(run("rem_u", x, y), Ok(words(x % y)), "{x:#x} % {y:#x}");
+        let (sx, sy) = (x as i64, y as i64);
+        assert_eq!(
+            run("div_s", x, y),
+            Ok(words(sx.wrapping_div(sy) as u64)),
+            "{sx} / {sy}"
+        );
+        assert_eq!(
+            run("rem_s", x, y),
+            Ok(words(sx.wrapping_rem(sy) as u64)),
+            "{sx} % {sy}"
+        );
+    }
+}
+
+#[test]
+fn dividing_by_zero_still_traps() {
+    for op in ["div_u", "div_s", "rem_u", "rem_s"] {
+        assert_eq!(
+            run(op, 0x0000_0001_0000_0000, 0),
+            Err("!integer divide by zero".to_owned()),
+            "{op}"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for the paths which access memory with array instructions directly
+//! rather than through the runtime's bounds-checking helpers: constant
+//! addresses within the minimum memory size, and, when the bounds check can be
+//! skipped, offsets which fold into the array instruction's index.
+
+mod common;
+
+use wasm2glulx::{CompilationOptions, Conformance, Emit};
+
+/// Where the test data lives in memory.
+const BASE: u32 = 16;
+
+/// The test data: the bytes 0x11, 0x22, ..., 0xff, 0x10.
+fn data() -> Vec<u8> {
+    (1..=16u32)
+        .map(|i| u8::try_from((i * 0x11) & 0xff).unwrap())
+        .collect()
+}
+
+fn le(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, byte| (acc << 8) | u32::from(*byte))
+}
+
+/// The loads the program performs, as (instruction, size, offset), in order.
+const LOADS: &[(&str, usize, u32)] = &[
+    ("i32.load", 4, 0),
+    ("i32.load", 4, 1),
+    ("i32.load", 4, 2),
+    ("i32.load", 4, 4),
+    ("i32.load", 4, 6),
+    ("i32.load16_u", 2, 0),
+    ("i32.load16_u", 2, 1),
+    ("i32.load16_u", 2, 4),
+    ("i32.load8_u", 1, 0),
+    ("i32.load8_u", 1, 3),
+];
+
+/// A program which reads from the test data through `addr`, which must
+/// evaluate to [`BASE`], and reports each result. It then overwrites some of
+/// the data through `addr`, and reports all of it byte by byte.
+fn program(addr: &str) -> String {
+    let data: String = data().iter().map(|b| format!("\\{b:02x}")).collect();
+    let mut body = String::new();
+    for (instr, _, offset) in LOADS {
+        body.push_str(&format!(
+            "(call $result ({instr} offset={offset} {addr}))\n"
+        ));
+    }
+    for offset in [0, 4] {
+        body.push_str(&format!(
+            "(call $i64_result (i64.load offset={offset} {addr}))\n"
+        ));
+    }
+    body.push_str(&format!(
+        "(i32.store offset=8 {addr} (i32.const 0x01020304))
+         (i32.store offset=1 {addr} (i32.const 0x05060708))
+         (i32.store16 offset=12 {addr} (i32.const 0x090a))
+         (i32.store16 offset=5 {addr} (i32.const 0x0b0c))
+         (i32.store8 offset=14 {addr} (i32.const 0x0d))
+         (i64.store offset=0 {addr} (i64.const 0x0e0f_1011_1213_1415))
+         (i64.store offset=10 {addr} (i64.const 0x1617_1819_1a1b_1c1d))\n"
+    ));
+    for i in 0..20 {
+        body.push_str(&format!(
+            "(call $result (i32.load8_u (i32.const {})))\n",
+            BASE + i
+        ));
+    }
+
+    format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (memory 1)
+          (data (i32.const {BASE}) "{data}")
+          (func $i64_result (param $x i64)
+            (call $result (i32.wrap_i64 (local.get $x)))
+            (call $result (i32.wrap_i64 (i64.shr_u (local.get $x) (i64.const 32)))))
+          (func $run (param $base i32)
+            {body})
+          (func (export "glulx_main") (call $run (i32.const {BASE}))))
+        "#
+    )
+}
+
+fn expected() -> Vec<u32> {
+    let mut mem = [0u8; 64];
+    let base = usize::try_from(BASE).unwrap();
+    mem[base..base + 16].copy_from_slice(&data());
+    let at = |offset: u32| base + usize::try_from(offset).unwrap();
+
+    let mut out = Vec::new();
+    for (_, size, offset) in LOADS {
+        out.push(le(&mem[at(*offset)..at(*offset) + size]));
+    }
+    for offset in [0, 4] {
+        out.push(le(&mem[at(offset)..at(offset) + 4]));
+        out.push(le(&mem[at(offset) + 4..at(offset) + 8]));
+    }
+
+    let stores: &[(u32, &[u8])] = &[
+        (8, &0x01020304u32.to_le_bytes()),
+        (1, &0x05060708u32.to_le_bytes()),
+        (12, &0x090au16.to_le_bytes()),
+        (5, &0x0b0cu16.to_le_bytes()),
+        (14, &[0x0d]),
+        (0, &0x0e0f_1011_1213_1415u64.to_le_bytes()),
+        (10, &0x1617_1819_1a1b_1c1du64.to_le_bytes()),
+    ];
+    for (offset, bytes) in stores {
+        mem[at(*offset)..at(*offset) + bytes.len()].copy_from_slice(bytes);
+    }
+    out.extend(mem[base..base + 20].iter().map(|b| u32::from(*b)));
+    out
+}
+
+fn options(conformance: Conformance, elide: bool) -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_conformance(conformance);
+    options.set_elide_bounds_checks(elide);
+    options
+}
+
+/// Runs the program through each combination of options which selects a
+/// different path for dynamic addresses.
+fn assert_program_correct(name: &str, addr: &str) {
+    let module = common::wat(&program(addr));
+    for (suffix, options) in [
+        ("helpers", options(Conformance::Standard, false)),
+        ("elided", options(Conformance::Standard, true)),
+        ("fast", options(Conformance::Fast, false)),
+    ] {
+        let output = common::compile_and_run(&format!("{name}_{suffix}"), &options, &module);
+        assert_eq!(output, Ok(expected()), "with {suffix} options");
+    }
+}
+
+#[test]
+fn dynamic_addresses_read_and_write_correctly() {
+    assert_program_correct("fast_paths_dynamic", "(local.get $base)");
+}
+
+#[test]
+fn constant_addresses_read_and_write_correctly() {
+    assert_program_correct("fast_paths_constant", &format!("(i32.const {BASE})"));
+}
+
+/// Counts the `add` instructions in the listing of a program which loads
+/// `instr` at `offset` from a parameter, with bounds checks skipped.
+fn adds_in_listing(instr: &str, offset: u32) -> usize {
+    let module = common::wat(&format!(
+        r#"
+        (module
+          (memory 1)
+          (func $f (param $p i32) (drop ({instr} offset={offset} (local.get $p))))
+          (func (export "glulx_main") (call $f (i32.const 0))))
+        "#
+    ));
+    let mut options = options(Conformance::Fast, false);
+    options.set_emit(&[Emit::Asm]);
+    let listing = String::from_utf8(common::compile(&options, &module)).unwrap();
+    listing.matches("\tadd ").count()
+}
+
+#[test]
+fn aligned_offsets_fold_into_the_index() {
+    for (instr, aligned, unaligned) in [
+        ("i32.load", 8, 6),
+        ("i32.load16_u", 6, 5),
+        ("i64.load", 8, 6),
+    ] {
+        let folded = adds_in_listing(instr, aligned);
+        assert_eq!(
+            folded,
+            adds_in_listing(instr, 0),
+            "{instr} offset={aligned}"
+        );
+        assert!(
+            adds_in_listing(instr, unaligned) > folded,
+            "{instr} offset={unaligned} should need an add"
+        );
+    }
+    // Any offset is aligned for a byte access.
+    assert_eq!(
+        adds_in_listing("i32.load8_u", 7),
+        adds_in_listing("i32.load8_u", 0)
+    );
+}
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Checks that the routines `--optimize-for=speed` inlines give the same
+//! results as the runtime calls they replace.
+
+mod common;
+
+use wasm2glulx::{CompilationOptions, OptimizeFor};
+
+const VALUES: [u32; 11] = [
+    0, 1, 2, 31, 32, 33, 0x12345678, 0x7fffffff, 0x80000000, 0xfffffffe, 0xffffffff,
+];
+
+type Semantics = fn(u32, u32) -> u32;
+
+/// The comparisons and shifts, which have inline forms, and the rotates,
+/// which don't, along with what WASM says each computes.
+const BINOPS: [(&str, Semantics); 15] = [
+    ("i32.eq", |x, y| (x == y).into()),
+    ("i32.ne", |x, y| (x != y).into()),
+    ("i32.lt_s", |x, y| ((x as i32) < (y as i32)).into()),
+    ("i32.lt_u", |x, y| (x < y).into()),
+    ("i32.gt_s", |x, y| ((x as i32) > (y as i32)).into()),
+    ("i32.gt_u", |x, y| (x > y).into()),
+    ("i32.le_s", |x, y| ((x as i32) <= (y as i32)).into()),
+    ("i32.le_u", |x, y| (x <= y).into()),
+    ("i32.ge_s", |x, y| ((x as i32) >= (y as i32)).into()),
+    ("i32.ge_u", |x, y| (x >= y).into()),
+    ("i32.shl", |x, y| x.wrapping_shl(y)),
+    ("i32.shr_s", |x, y| (x as i32).wrapping_shr(y) as u32),
+    ("i32.shr_u", |x, y| x.wrapping_shr(y)),
+    ("i32.rotl", |x, y| x.rotate_left(y)),
+    ("i32.rotr", |x, y| x.rotate_right(y)),
+];
+
+/// Ways of supplying an operand: as a constant, which the generated code
+/// takes as an immediate, or as a call's result, which it pops off the stack.
+fn operand(value: u32, popped: bool) -> String {
+    if popped {
+        format!("(call $id (i32.const {value}))")
+    } else {
+        format!("(i32.const {value})")
+    }
+}
+
+/// A module which reports the result of every operation on every pair of
+/// [`VALUES`], for each way of supplying the operands, along with the
+/// results it ought to report.
+fn module() -> (walrus::Module, Vec<u32>) {
+    let mut src = String::from(
+        r#"(module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $id (param i32) (result i32) (local.get 0))
+        "#,
+    );
+    let mut expected = Vec::new();
+    let mut main = String::from(r#"(func (export "glulx_main")"#);
+
+    src.push_str("(func $eqz");
+    for x in VALUES {
+        for popped in [false, true] {
+            src.push_str(&format!("(call $result (i32.eqz {}))", operand(x, popped)));
+            expected.push((x == 0).into());
+        }
+    }
+    src.push(')');
+    main.push_str("(call $eqz)");
+
+    for (i, (op, f)) in BINOPS.iter().enumerate() {
+        src.push_str(&format!("(func $op{i}"));
+        for x in VALUES {
+            for y in VALUES {
+                for (x_popped, y_popped) in
+                    [(false, false), (true, false), (false, true), (true, true)]
+                {
+                    src.push_str(&format!(
+                        "(call $result ({op} {} {}))",
+                        operand(x, x_popped),
+                        operand(y, y_popped)
+                    ));
+                    expected.push(f(x, y));
+                }
+            }
+        }
+        src.push(')');
+        main.push_str(&format!("(call $op{i})"));
+    }
+
+    src.push_str(&main);
+    src.push_str("))");
+    (common::wat(&src), expected)
+}
+
+fn optimizing_for(optimize_for: OptimizeFor) -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_optimize_for(optimize_for);
+    options
+}
+
+#[test]
+fn speed_matches_size() {
+    let (module, expected) = module();
+    let size = common::compile(&optimizing_for(OptimizeFor::Size), &module);
+    let speed = common::compile(&optimizing_for(OptimizeFor::Speed), &module);
+    assert!(size != speed, "--optimize-for should change the story file");
+
+    let size = common::run("optimize_for_size", &size).unwrap();
+    let speed = common::run("optimize_for_speed", &speed).unwrap();
+    assert_eq!(size.len(), expected.len());
+    assert_eq!(speed.len(), expected.len());
+    for (i, want) in expected.iter().enumerate() {
+        assert_eq!(
+            size[i], *want,
+            "result {i} is wrong under --optimize-for=size"
+        );
+        assert_eq!(
+            speed[i], *want,
+            "result {i} is wrong under --optimize-for=speed"
+        );
+    }
+}
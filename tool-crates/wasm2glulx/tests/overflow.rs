@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests that layout reports modules which can't fit in Glulx's address space,
+//! or whose call frames can't fit on the stack, by the item responsible.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions, OverflowLocation};
+
+/// Compiles `src`, expecting exactly one error, and returns it.
+fn overflow(options: &CompilationOptions, src: &str) -> (OverflowLocation, String) {
+    let mut errors = common::compile_errors(options, &common::wat(src));
+    assert_eq!(errors.len(), 1, "Unexpected errors: {errors:?}");
+    let error = errors.remove(0);
+    let message = error.to_string();
+    match error {
+        CompilationError::Overflow(loc) => (loc, message),
+        other => panic!("Expected an overflow, got {other:?}"),
+    }
+}
+
+#[test]
+fn table_too_large_to_address() {
+    // The table has no declared maximum, so it reserves room to grow by the
+    // whole growth limit.
+    let mut options = CompilationOptions::new();
+    options.set_table_growth_limit(1 << 30);
+    let (loc, message) = overflow(&options, "(module (table 0 funcref))");
+    assert!(matches!(
+        loc,
+        OverflowLocation::Table {
+            index: 0,
+            entries: 1073741824
+        }
+    ));
+    assert_eq!(
+        message,
+        "Table 0 (1073741824 entries) overflows Glulx's 4GiB address space"
+    );
+}
+
+#[test]
+fn memory_too_large_to_address() {
+    let (loc, message) = overflow(&CompilationOptions::new(), "(module (memory 65536))");
+    assert!(matches!(loc, OverflowLocation::Memory { pages: 65536 }));
+    assert_eq!(
+        message,
+        "The program memory (65536 pages) overflows Glulx's 4GiB address space"
+    );
+}
+
+#[test]
+fn total_overflow_is_reported_up_front() {
+    // Each of these fits on its own, but not together.
+    let (loc, message) = overflow(
+        &CompilationOptions::new(),
+        "(module (memory 65535) (table 1048576 1048576 funcref))",
+    );
+    let bytes = 65535 * 65536 + 4 * 1048576 + 4096;
+    assert!(matches!(
+        loc,
+        OverflowLocation::Total {
+            bytes: b,
+            glk_area_size: 4096
+        } if b == bytes
+    ));
+    assert_eq!(
+        message,
+        format!(
+            "The total size of the module's memory, tables, and segments, plus the Glk area ({bytes} bytes), overflows Glulx's 4GiB address space"
+        )
+    );
+}
+
+#[test]
+fn total_overflow_suggests_smaller_glk_area() {
+    let mut options = CompilationOptions::new();
+    options.set_glk_area_size(1 << 20);
+    let (loc, message) = overflow(&options, "(module (memory 65535))");
+    assert!(matches!(
+        loc,
+        OverflowLocation::Total {
+            glk_area_size: 1048576,
+            ..
+        }
+    ));
+    assert!(
+        message.ends_with("Try a --glk-area-size of at most 65535 bytes."),
+        "{message}"
+    );
+}
+
+#[test]
+fn total_overflow_without_glk_area_to_blame() {
+    // The Glk area is larger than the default, but shrinking it back to the
+    // default still wouldn't leave enough room.
+    let mut options = CompilationOptions::new();
+    options.set_glk_area_size(1 << 20);
+    let (_, message) = overflow(
+        &options,
+        "(module (memory 65535) (table 1048576 1048576 funcref))",
+    );
+    assert!(!message.contains("--glk-area-size"), "{message}");
+}
+
+#[test]
+fn call_frame_too_large_for_stack() {
+    let mut options = CompilationOptions::new();
+    options.set_stack_size(64);
+    let (loc, message) = overflow(
+        &options,
+        r#"
+        (module
+          (func (export "glulx_main"))
+          (func $many_locals (export "many_locals")
+            (param i32 i32 i32 i32 i32 i32 i32 i32 i32 i32
+                   i32 i32 i32 i32 i32 i32 i32 i32 i32 i32)))
+        "#,
+    );
+    // The call stub, the frame header, the locals format, and 20 locals.
+    let bytes = 16 + 8 + 4 + 4 * 20;
+    assert!(matches!(
+        &loc,
+        OverflowLocation::Frame {
+            function: Some(name),
+            bytes: b,
+            stack_size: 64,
+        } if name == "many_locals" && *b == bytes
+    ));
+    assert_eq!(
+        message,
+        format!(
+            "The call frame of the function `many_locals` ({bytes} bytes) overflows the 64-byte stack. Try a --stack-size of at least {bytes} bytes."
+        )
+    );
+}
@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for the `raw_<mnemonic>` and `raw_0x<hex>` intrinsics.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions, Emit};
+
+/// A module which imports `name` as `$f` with type `ty` and runs `body`.
+fn module(name: &str, ty: &str, body: &str) -> walrus::Module {
+    common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (import "glulx" "{name}" (func $f {ty}))
+          (func (export "glulx_main") {body}))
+        "#
+    ))
+}
+
+#[test]
+fn named_instructions_run() {
+    let options = CompilationOptions::new();
+    // bogoglulx prints with streamnum too, so this prints like a result.
+    let streamnum = module(
+        "raw_streamnum",
+        "(param i32)",
+        "(call $f (i32.const 0x1234))",
+    );
+    assert_eq!(
+        common::compile_and_run("raw_streamnum", &options, &streamnum),
+        Ok(vec![0x1234])
+    );
+
+    let fadd = module(
+        "raw_fadd",
+        "(param f32 f32) (result f32)",
+        "(call $result (i32.reinterpret_f32 (call $f (f32.const 1.5) (f32.const 2.25))))",
+    );
+    assert_eq!(
+        common::compile_and_run("raw_fadd", &options, &fadd),
+        Ok(vec![3.75f32.to_bits()])
+    );
+}
+
+#[test]
+fn hex_opcodes_run() {
+    // 0x71 is streamnum.
+    let module = module("raw_0x71", "(param i32)", "(call $f (i32.const 42))");
+    assert_eq!(
+        common::compile_and_run("raw_0x71", &CompilationOptions::new(), &module),
+        Ok(vec![42])
+    );
+}
+
+#[test]
+fn operands_come_from_parameters_in_order() {
+    // 0x11 is sub, so the operand order is observable.
+    let module = module(
+        "raw_0x11",
+        "(param i32 i32) (result i32)",
+        "(call $result (call $f (i32.const 10) (i32.const 3)))",
+    );
+    assert_eq!(
+        common::compile_and_run("raw_sub", &CompilationOptions::new(), &module),
+        Ok(vec![7])
+    );
+}
+
+#[test]
+fn listing_shows_the_instruction() {
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Asm]);
+    for name in ["raw_streamnum", "raw_0x71"] {
+        let module = module(name, "(param i32)", "(call $f (i32.const 0))");
+        let listing = String::from_utf8(common::compile(&options, &module)).unwrap();
+        assert!(
+            listing.contains("\tstreamnum "),
+            "{name} should be listed as streamnum"
+        );
+    }
+}
+
+#[test]
+fn mistyped_imports_are_rejected() {
+    for (name, ty) in [
+        ("raw_streamnum", "(param i32 i32)"),
+        ("raw_streamnum", "(param i32) (result i32)"),
+        ("raw_fadd", "(param f64 f64) (result f64)"),
+        ("raw_0x10", "(param i32) (result i32)"),
+    ] {
+        let errors = common::compile_errors(&CompilationOptions::new(), &module(name, ty, ""));
+        assert!(
+            matches!(
+                errors[..],
+                [CompilationError::IncorrectlyTypedImport { .. }]
+            ),
+            "{name} {ty} should be rejected as mistyped, not {errors:?}"
+        );
+    }
+}
+
+#[test]
+fn unknown_and_unbindable_instructions_are_rejected() {
+    for name in [
+        // No such mnemonic.
+        "raw_frobnicate",
+        // These take function addresses, so they have no named binding.
+        "raw_setiosys",
+        "raw_accelfunc",
+        "raw_accelparam",
+        // No such opcode.
+        "raw_0x1234",
+        // Not hexadecimal.
+        "raw_0xzz",
+        // catch's store operand comes before its branch operand.
+        "raw_0x32",
+    ] {
+        let errors = common::compile_errors(&CompilationOptions::new(), &module(name, "", ""));
+        assert!(
+            matches!(errors[..], [CompilationError::UnrecognizedImport(_)]),
+            "{name} should be unrecognized, not {errors:?}"
+        );
+    }
+}
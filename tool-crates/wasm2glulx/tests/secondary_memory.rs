@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--lower-secondary-memory`.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions};
+
+fn lowering() -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_lower_secondary_memory(true);
+    options
+}
+
+/// A module whose second memory, `$scratch`, is one page which can grow to
+/// two. `glulx_main` runs `body` with `$result` available for reporting.
+fn module(body: &str) -> walrus::Module {
+    common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (memory $main 1)
+          (memory $scratch 1 2)
+          (data (memory $main) (i32.const 8) "\aa\aa\aa\aa")
+          (data (memory $scratch) (i32.const 8) "\01\02\03\04")
+          (func (export "glulx_main")
+            {body}))
+        "#
+    ))
+}
+
+fn run(name: &str, body: &str) -> Result<Vec<u32>, String> {
+    common::compile_and_run(name, &lowering(), &module(body))
+}
+
+#[test]
+fn active_data_initializes_each_memory() {
+    assert_eq!(
+        run(
+            "secondary_memory_data",
+            r#"
+            (call $result (i32.load $scratch (i32.const 8)))
+            (call $result (i32.load $main (i32.const 8)))
+            "#,
+        ),
+        Ok(vec![0x04030201, 0xaaaaaaaa])
+    );
+}
+
+#[test]
+fn loads_and_stores_stay_in_their_memory() {
+    assert_eq!(
+        run(
+            "secondary_memory_i32",
+            r#"
+            (i32.store $scratch (i32.const 100) (i32.const 0x12345678))
+            (i32.store $main (i32.const 100) (i32.const 0x0badf00d))
+            (call $result (i32.load $scratch (i32.const 100)))
+            (call $result (i32.load $main (i32.const 100)))
+            (call $result (i32.load8_u $scratch (i32.const 100)))
+            "#,
+        ),
+        Ok(vec![0x12345678, 0x0badf00d, 0x78])
+    );
+}
+
+#[test]
+fn i64_loads_and_stores() {
+    assert_eq!(
+        run(
+            "secondary_memory_i64",
+            r#"
+            (i64.store $scratch (i32.const 200) (i64.const 0x0123456789abcdef))
+            (call $result (i32.load $scratch (i32.const 200)))
+            (call $result (i32.load $scratch (i32.const 204)))
+            (call $result
+              (i32.wrap_i64
+                (i64.shr_u (i64.load $scratch (i32.const 200)) (i64.const 32))))
+            (call $result (i32.load $main (i32.const 200)))
+            "#,
+        ),
+        Ok(vec![0x89abcdef, 0x01234567, 0x01234567, 0])
+    );
+}
+
+#[test]
+fn size_and_growth_up_to_the_maximum() {
+    assert_eq!(
+        run(
+            "secondary_memory_grow",
+            r#"
+            (call $result (memory.size $scratch))
+            (call $result (memory.grow $scratch (i32.const 1)))
+            (call $result (memory.size $scratch))
+            (call $result (memory.grow $scratch (i32.const 1)))
+            (call $result (memory.size $scratch))
+            (i32.store $scratch (i32.const 65536) (i32.const 7))
+            (call $result (i32.load $scratch (i32.const 65536)))
+            (call $result (memory.size $main))
+            "#,
+        ),
+        Ok(vec![1, 1, 2, 0xffffffff, 2, 7, 1])
+    );
+}
+
+#[test]
+fn out_of_bounds_access_traps() {
+    assert_eq!(
+        run(
+            "secondary_memory_oob",
+            r#"
+            (call $result (i32.const 1))
+            (drop (i32.load $scratch (i32.const 65534)))
+            (call $result (i32.const 2))
+            "#,
+        ),
+        Err("!out of bounds memory access".to_owned())
+    );
+}
+
+#[test]
+fn second_memory_is_rejected_without_the_option() {
+    let errors = common::compile_errors(&CompilationOptions::new(), &module(""));
+    assert!(
+        matches!(errors[..], [CompilationError::UnsupportedMultipleMemories]),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn second_memory_must_declare_a_maximum() {
+    let module = common::wat(
+        r#"
+        (module
+          (memory $main 1)
+          (memory $scratch 1)
+          (func (export "glulx_main")))
+        "#,
+    );
+    let errors = common::compile_errors(&lowering(), &module);
+    assert!(
+        matches!(errors[..], [CompilationError::UnboundedSecondaryMemory]),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn copying_between_memories_is_rejected() {
+    let errors = common::compile_errors(
+        &lowering(),
+        &module("(memory.copy $main $scratch (i32.const 0) (i32.const 0) (i32.const 4))"),
+    );
+    assert!(
+        matches!(
+            &errors[..],
+            [CompilationError::UnsupportedInstruction { instr, .. }]
+                if *instr == "memory.copy between different memories"
+        ),
+        "{errors:?}"
+    );
+
+    // Copying within the second memory is fine.
+    assert_eq!(
+        run(
+            "secondary_memory_copy",
+            r#"
+            (memory.copy $scratch $scratch (i32.const 16) (i32.const 8) (i32.const 4))
+            (call $result (i32.load $scratch (i32.const 16)))
+            "#,
+        ),
+        Ok(vec![0x04030201])
+    );
+}
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--stack-guard`.
+
+mod common;
+
+use wasm2glulx::{CompilationOptions, STACK_EXHAUSTED};
+
+fn guarded() -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_stack_size(4096);
+    options.set_stack_guard(Some(256));
+    options
+}
+
+/// `$depth` recurses `n` times. `glulx_main` reports what `catch` returns
+/// from recursing to the depth given to `$body`, and then 2 to show that the
+/// program carried on.
+fn catching(depth: u32) -> String {
+    format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (import "glulx" "catch" (func $catch (param i32 i32) (result i32)))
+          (table 1 funcref)
+          (elem (i32.const 0) $body)
+          (func $depth (param $n i32) (result i32)
+            (if (result i32) (i32.eqz (local.get $n))
+              (then (i32.const 1))
+              (else (call $depth (i32.sub (local.get $n) (i32.const 1))))))
+          (func $body (param $token i32) (param $n i32) (result i32)
+            (call $depth (local.get $n)))
+          (func (export "glulx_main")
+            (call $result (call $catch (i32.const 0) (i32.const {depth})))
+            (call $result (i32.const 2))))
+        "#
+    )
+}
+
+#[test]
+fn exhausted_stack_is_thrown_to_catch() {
+    let output = common::compile_and_run(
+        "stack_guard_caught",
+        &guarded(),
+        &common::wat(&catching(100_000)),
+    );
+    assert_eq!(output, Ok(vec![STACK_EXHAUSTED, 2]));
+}
+
+#[test]
+fn shallow_recursion_returns_normally() {
+    let output = common::compile_and_run(
+        "stack_guard_shallow",
+        &guarded(),
+        &common::wat(&catching(10)),
+    );
+    assert_eq!(output, Ok(vec![1, 2]));
+}
+
+#[test]
+fn exhausted_stack_traps_without_catch() {
+    let module = common::wat(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $forever (param $n i32) (result i32)
+            (call $forever (i32.add (local.get $n) (i32.const 1))))
+          (func (export "glulx_main")
+            (call $result (call $forever (i32.const 0)))))
+        "#,
+    );
+    let output = common::compile_and_run("stack_guard_uncaught", &guarded(), &module);
+    assert_eq!(output, Err("!call stack exhausted".to_owned()));
+}
+
+#[test]
+fn catch_outside_the_recursion_is_not_used_after_it_returns() {
+    // The first catch returns before the recursion starts, so the guard must
+    // not throw to its stale token.
+    let module = common::wat(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (import "glulx" "catch" (func $catch (param i32 i32) (result i32)))
+          (table 1 funcref)
+          (elem (i32.const 0) $body)
+          (func $body (param $token i32) (param $arg i32) (result i32)
+            (local.get $arg))
+          (func $forever (param $n i32) (result i32)
+            (call $forever (i32.add (local.get $n) (i32.const 1))))
+          (func (export "glulx_main")
+            (call $result (call $catch (i32.const 0) (i32.const 5)))
+            (call $result (call $forever (i32.const 0)))))
+        "#,
+    );
+    let (output, stopped) =
+        common::run_until_stopped("stack_guard_stale", &common::compile(&guarded(), &module));
+    assert_eq!(output, [5]);
+    assert_eq!(stopped.as_deref(), Some("!call stack exhausted"));
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for the order in which the generated entrypoint runs the start
+//! function, Glk initialization, and `glulx_main`.
+//!
+//! Bogoglulx has no Glk, so registering the interrupt handler stops it with
+//! an error. What the program printed before that shows which of the start
+//! function and Glk initialization ran first.
+
+mod common;
+
+use wasm2glulx::CompilationOptions;
+
+const MODULE: &str = r#"
+(module
+  (import "glulx" "spectest_result" (func $result (param i32)))
+  (func $start (call $result (i32.const 1)))
+  (func (export "glulx_main") (call $result (i32.const 2)))
+  (func (export "glulx_interrupt_handler"))
+  (start $start))
+"#;
+
+fn assert_stopped_at_glk(stopped: Option<String>) {
+    let message = stopped.expect("the program should stop at the glk opcode");
+    assert!(
+        message.starts_with("?Called unsupported Glk function.: 2"),
+        "unexpected stop: {message}"
+    );
+}
+
+#[test]
+fn start_function_runs_after_glk_initialization_by_default() {
+    let story = common::compile(&CompilationOptions::new(), &common::wat(MODULE));
+    let (output, stopped) = common::run_until_stopped("startup_default", &story);
+    assert!(output.is_empty());
+    assert_stopped_at_glk(stopped);
+}
+
+#[test]
+fn early_start_runs_start_function_before_glk_initialization() {
+    let mut options = CompilationOptions::new();
+    options.set_early_start(true);
+    let story = common::compile(&options, &common::wat(MODULE));
+    let (output, stopped) = common::run_until_stopped("startup_early", &story);
+    assert_eq!(output, [1]);
+    assert_stopped_at_glk(stopped);
+}
+
+#[test]
+fn start_function_runs_before_glulx_main() {
+    let module = common::wat(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $start (call $result (i32.const 1)))
+          (func (export "glulx_main") (call $result (i32.const 2)))
+          (start $start))
+        "#,
+    );
+    for early_start in [false, true] {
+        let mut options = CompilationOptions::new();
+        options.set_early_start(early_start);
+        let output = common::compile_and_run("startup_order", &options, &module).unwrap();
+        assert_eq!(output, [1, 2]);
+    }
+}
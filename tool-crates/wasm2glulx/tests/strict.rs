@@ -0,0 +1,213 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--strict`.
+//!
+//! The compiler shouldn't generate code which strict mode rejects, so most of
+//! these tests use an `after_function` hook to append a broken instruction to
+//! an otherwise valid function.
+
+mod common;
+
+use glulx_asm::concise::*;
+use glulx_asm::{Instr, Item, LoadOperand};
+use walrus::Function;
+use wasm2glulx::{CompilationError, CompilationOptions, HookContext, Hooks, Label};
+
+const MODULE: &str = r#"
+(module
+  (func $main (export "glulx_main") (local i32)
+    (local.set 0 (i32.const 1))))
+"#;
+
+fn strict() -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_strict(true);
+    options
+}
+
+/// Appends the items returned by its closure to `$main`.
+struct Append<F>(F);
+
+impl<F> Hooks for Append<F>
+where
+    F: FnMut(&mut HookContext<'_>) -> Vec<Item<Label>>,
+{
+    fn after_function(&mut self, ctx: &mut HookContext<'_>, function: &Function, _start: usize) {
+        if function.name.as_deref() == Some("main") {
+            let items = (self.0)(ctx);
+            ctx.rom_items.extend(items);
+        }
+    }
+}
+
+/// Compiles `MODULE` in strict mode with `hooks`, and returns the reasons
+/// given by every error, which must all be strict-mode violations.
+fn violations(hooks: &mut dyn Hooks) -> Vec<String> {
+    let module = common::wat(MODULE);
+    let errors = match wasm2glulx::compile_module_to_bytes_with_hooks(&strict(), &module, hooks) {
+        Ok(_) => panic!("Compilation should have failed"),
+        Err(errors) => errors,
+    };
+    errors
+        .into_iter()
+        .map(|e| match e {
+            CompilationError::GlulxLimitExceeded { function, reason } => {
+                assert_eq!(function.as_deref(), Some("main"));
+                reason
+            }
+            e => panic!("Unexpected error: {e}"),
+        })
+        .collect()
+}
+
+fn assert_one_violation(hooks: &mut dyn Hooks, expected: &str) {
+    let violations = violations(hooks);
+    assert_eq!(violations.len(), 1, "{violations:?}");
+    assert!(
+        violations[0].contains(expected),
+        "{:?} should mention {expected:?}",
+        violations[0]
+    );
+}
+
+#[test]
+fn accepts_what_the_compiler_generates() {
+    let module = common::wat(MODULE);
+    common::compile(&strict(), &module);
+    common::compile_and_run(
+        "strict_accepts",
+        &strict(),
+        &common::wat(
+            r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (table 2 funcref)
+          (elem (i32.const 0) $add $fac)
+          (func $add (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1)))
+          (func $fac (param i64) (result i64)
+            (if (result i64) (i64.eqz (local.get 0))
+              (then (i64.const 1))
+              (else (i64.mul (local.get 0)
+                (call $fac (i64.sub (local.get 0) (i64.const 1)))))))
+          (func (export "glulx_main")
+            (call $result
+              (call_indirect (param i32 i32) (result i32)
+                (i32.const 2) (i32.const 3) (i32.const 0)))
+            (call $result (i32.wrap_i64 (call $fac (i64.const 5))))
+            (block $out
+              (loop $again
+                (br_if $out (f64.lt (f64.const 2) (f64.const 1)))
+                (br_table $out $again (i32.const 0))))))
+        "#,
+        ),
+    )
+    .map(|output| assert_eq!(output, [5, 120]))
+    .unwrap();
+}
+
+#[test]
+fn rejects_oversized_call_frames() {
+    // Code generation already rejects WASM functions whose frames can't fit on
+    // the stack, so the oversized frame has to come from a hook.
+    assert_one_violation(
+        &mut Append(|ctx: &mut HookContext<'_>| {
+            let callee = ctx.gen_label("callee");
+            vec![label(callee), fnhead_local(300_000), ret(imm(0))]
+        }),
+        "a call frame with 300000 locals",
+    );
+}
+
+#[test]
+fn rejects_out_of_range_locals() {
+    assert_one_violation(
+        &mut Append(|_: &mut HookContext<'_>| vec![copy(lloc(1), push())]),
+        "accesses local 1, but the function has only 1 locals",
+    );
+    assert_one_violation(
+        &mut Append(|_: &mut HookContext<'_>| vec![copy(imm(0), sloc(7))]),
+        "accesses local 7",
+    );
+}
+
+#[test]
+fn rejects_misaligned_locals() {
+    assert_one_violation(
+        &mut Append(|_: &mut HookContext<'_>| {
+            vec![Item::Instr(Instr::Copy(LoadOperand::FrameAddr(2), push()))]
+        }),
+        "frame offset 2",
+    );
+}
+
+#[test]
+fn rejects_branches_which_do_not_land_on_instructions() {
+    assert_one_violation(
+        &mut Append(|ctx: &mut HookContext<'_>| {
+            let data = ctx.gen_label("data");
+            vec![jump(data), label(data), blob(vec![0u8; 4])]
+        }),
+        "which does not label an instruction",
+    );
+}
+
+#[test]
+fn rejects_raw_branch_offsets() {
+    assert_one_violation(
+        &mut Append(|_: &mut HookContext<'_>| {
+            vec![Item::Instr(Instr::Jz(pop(), LoadOperand::Imm(12)))]
+        }),
+        "raw branch offset of 12",
+    );
+    // 0 and 1 mean "return false" and "return true", and are fine.
+    let module = common::wat(MODULE);
+    wasm2glulx::compile_module_to_bytes_with_hooks(
+        &strict(),
+        &module,
+        &mut Append(|_: &mut HookContext<'_>| vec![jump_ret(true)]),
+    )
+    .unwrap();
+}
+
+#[test]
+fn rejects_branch_targets_outside_the_branch_offset() {
+    assert_one_violation(
+        &mut Append(|ctx: &mut HookContext<'_>| {
+            let here = ctx.gen_label("here");
+            vec![
+                label(here),
+                Item::Instr(Instr::Copy(LoadOperand::Branch(here), push())),
+            ]
+        }),
+        "not a branch offset",
+    );
+}
+
+#[test]
+fn rejects_calls_with_more_arguments_than_locals() {
+    assert_one_violation(
+        &mut Append(|ctx: &mut HookContext<'_>| {
+            let callee = ctx.gen_label("callee");
+            vec![
+                callfii(imml(callee), imm(1), imm(2), discard()),
+                label(callee),
+                fnhead_local(1),
+                ret(imm(0)),
+            ]
+        }),
+        "passes 2 arguments to a function with only 1 locals",
+    );
+}
+
+#[test]
+fn rejects_calls_to_instructions() {
+    assert_one_violation(
+        &mut Append(|ctx: &mut HookContext<'_>| {
+            let here = ctx.gen_label("here");
+            vec![label(here), callf(imml(here), discard())]
+        }),
+        "labels an instruction rather than a function",
+    );
+}
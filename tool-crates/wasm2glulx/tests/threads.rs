@@ -0,0 +1,127 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `wasi/thread-spawn`, with and without `--inline-thread-spawn`.
+
+mod common;
+
+use wasm2glulx::{CompilationError, CompilationOptions};
+
+/// A module which spawns two threads the way wasi-libc does: each
+/// `wasi_thread_start` switches to a stack and TLS block described by its
+/// start argument, and records what it saw there.
+const SPAWNER: &str = r#"
+(module
+  (import "wasi" "thread-spawn" (func $spawn (param i32) (result i32)))
+  (import "glulx" "spectest_result" (func $result (param i32)))
+  (memory 1)
+  (global $sp (mut i32) (i32.const 1024))
+  (global $tls (mut i32) (i32.const 0))
+  (global $wide (mut i64) (i64.const 0x100000002))
+  (global $answer i32 (i32.const 42))
+
+  (func (export "wasi_thread_start") (param $tid i32) (param $arg i32)
+    (global.set $sp (i32.load (local.get $arg)))
+    (global.set $tls (i32.load offset=4 (local.get $arg)))
+    (global.set $wide (i64.const -1))
+    (i32.store offset=8 (local.get $arg) (local.get $tid))
+    (i32.store offset=12 (local.get $arg) (global.get $sp)))
+
+  (func (export "glulx_main")
+    (i32.store (i32.const 16) (i32.const 5000))
+    (i32.store (i32.const 20) (i32.const 6000))
+    (call $result (call $spawn (i32.const 16)))
+    (call $result (call $spawn (i32.const 16)))
+    (call $result (i32.load (i32.const 24)))
+    (call $result (i32.load (i32.const 28)))
+    (call $result (global.get $sp))
+    (call $result (global.get $tls))
+    (call $result (i32.wrap_i64 (global.get $wide)))
+    (call $result (i32.wrap_i64 (i64.shr_u (global.get $wide) (i64.const 32))))
+    (call $result (global.get $answer))))
+"#;
+
+#[test]
+fn inlined_threads_get_ids_and_their_own_globals() {
+    let mut options = CompilationOptions::new();
+    options.set_inline_thread_spawn(true);
+    let output =
+        common::compile_and_run("threads_inline", &options, &common::wat(SPAWNER)).unwrap();
+    assert_eq!(
+        output,
+        [
+            1,    // first thread id
+            2,    // second thread id
+            2,    // the id the second thread was passed
+            5000, // the stack pointer the second thread switched to
+            1024, // the parent's stack pointer, restored
+            0,    // the parent's TLS base, restored
+            2,    // both halves of the parent's i64 global, restored
+            1, 42,
+        ]
+    );
+}
+
+#[test]
+fn spawning_is_rejected_by_default() {
+    let errors = common::compile_errors(&CompilationOptions::new(), &common::wat(SPAWNER));
+    assert!(
+        errors
+            .iter()
+            .any(|e| matches!(e, CompilationError::ThreadSpawn { .. })),
+        "{errors:?}"
+    );
+}
+
+#[test]
+fn rejection_names_direct_and_indirect_callers() {
+    let module = common::wat(
+        r#"
+        (module
+          (import "wasi" "thread-spawn" (func $spawn (param i32) (result i32)))
+          (type $spawn_ty (func (param i32) (result i32)))
+          (table funcref (elem $spawn))
+          (func $direct (param i32) (result i32)
+            (call $spawn (local.get 0)))
+          (func $indirect (param i32) (result i32)
+            (call_indirect (type $spawn_ty) (local.get 0) (i32.const 0)))
+          (func $other_type (param i32)
+            (call_indirect (param i32) (local.get 0) (i32.const 0)))
+          (func $unrelated (result i32) (i32.const 0))
+          (func (export "glulx_main")
+            (drop (call $direct (i32.const 0)))
+            (drop (call $indirect (i32.const 0)))
+            (call $other_type (call $unrelated))))
+        "#,
+    );
+    let errors = common::compile_errors(&CompilationOptions::new(), &module);
+    let callers = errors
+        .iter()
+        .find_map(|e| match e {
+            CompilationError::ThreadSpawn { callers, .. } => Some(callers.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("{errors:?}"));
+    assert_eq!(callers, ["direct", "indirect"]);
+}
+
+#[test]
+fn inlining_requires_wasi_thread_start() {
+    let module = common::wat(
+        r#"
+        (module
+          (import "wasi" "thread-spawn" (func $spawn (param i32) (result i32)))
+          (func (export "glulx_main") (drop (call $spawn (i32.const 0)))))
+        "#,
+    );
+    let mut options = CompilationOptions::new();
+    options.set_inline_thread_spawn(true);
+    let errors = common::compile_errors(&options, &module);
+    assert!(
+        errors
+            .iter()
+            .any(|e| matches!(e, CompilationError::ValidationError(e)
+            if e.to_string().contains("wasi_thread_start"))),
+        "{errors:?}"
+    );
+}
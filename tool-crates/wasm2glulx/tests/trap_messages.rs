@@ -0,0 +1,143 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--trap-messages`.
+//!
+//! bogoglulx prints each character as a word, so the message is read back
+//! from the words printed before the trap.
+
+mod common;
+
+use wasm2glulx::CompilationOptions;
+
+fn with_messages() -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_trap_messages(true);
+    options
+}
+
+/// Compiles and runs `module` with a Glk stream open, and returns what it
+/// printed as text along with the trap that stopped it.
+fn run(name: &str, options: &CompilationOptions, module: &walrus::Module) -> (String, String) {
+    let story = common::compile(options, module);
+    let (words, stopped) = common::run_with_stream(name, &story);
+    let text = words
+        .into_iter()
+        .map(|w| char::from_u32(w).unwrap())
+        .collect();
+    (text, stopped.expect("The program should have trapped"))
+}
+
+/// The offset of the named function's body within the module, as the trap
+/// message reports it.
+fn offset(module: &walrus::Module, name: &str) -> usize {
+    let id = module.funcs.by_name(name).unwrap();
+    module
+        .funcs
+        .get(id)
+        .kind
+        .unwrap_local()
+        .original_range
+        .clone()
+        .unwrap()
+        .start
+}
+
+#[test]
+fn message_names_the_trap_and_the_function() {
+    let module = common::wat(
+        r#"
+        (module
+          (func $fails (export "glulx_main")
+            unreachable))
+        "#,
+    );
+    let (text, trap) = run("trap_messages_unreachable", &with_messages(), &module);
+    assert_eq!(
+        text,
+        format!(
+            "\n[wasm trap: unreachable in fails (wasm offset {:#x})]\n",
+            offset(&module, "fails")
+        )
+    );
+    assert_eq!(trap, "!unreachable");
+}
+
+#[test]
+fn location_is_restored_after_a_call_returns() {
+    // The load traps in a runtime helper after $past_the_end has returned,
+    // so the message should name $load, not $past_the_end or the helper.
+    let module = common::wat(
+        r#"
+        (module
+          (memory 1)
+          (func $past_the_end (result i32)
+            (i32.const 65536))
+          (func $load (export "glulx_main")
+            (drop (i32.load (call $past_the_end)))))
+        "#,
+    );
+    let (text, trap) = run("trap_messages_after_call", &with_messages(), &module);
+    assert_eq!(
+        text,
+        format!(
+            "\n[wasm trap: out of bounds memory access in load (wasm offset {:#x})]\n",
+            offset(&module, "load")
+        )
+    );
+    assert_eq!(trap, "!out of bounds memory access");
+}
+
+#[test]
+fn trap_in_a_callee_names_the_callee() {
+    let module = common::wat(
+        r#"
+        (module
+          (func $inner
+            unreachable)
+          (func $outer (export "glulx_main")
+            (call $inner)))
+        "#,
+    );
+    let (text, _) = run("trap_messages_callee", &with_messages(), &module);
+    assert!(
+        text.contains("unreachable in inner (wasm offset"),
+        "{text:?}"
+    );
+}
+
+#[test]
+fn traps_before_any_function_have_no_location() {
+    // The Glk area check runs during startup, before any function has set
+    // the location.
+    let module = common::wat(
+        r#"
+        (module
+          (memory 1)
+          (data (i32.const 16) "\00\20\00\00")
+          (global (export "glulx_expected_glkarea_size") i32 (i32.const 16))
+          (func (export "glulx_main")))
+        "#,
+    );
+    let (text, trap) = run("trap_messages_startup", &with_messages(), &module);
+    assert_eq!(text, "\n[wasm trap: glk area size mismatch]\n");
+    assert_eq!(trap, "!glk area size mismatch");
+}
+
+#[test]
+fn nothing_is_printed_without_the_option() {
+    let module = common::wat(
+        r#"
+        (module
+          (func (export "glulx_main")
+            unreachable))
+        "#,
+    );
+    let (text, trap) = run(
+        "trap_messages_disabled",
+        &CompilationOptions::new(),
+        &module,
+    );
+    assert_eq!(text, "");
+    assert_eq!(trap, "!unreachable");
+}
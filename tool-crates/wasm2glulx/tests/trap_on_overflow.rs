@@ -0,0 +1,151 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for `--trap-on-overflow`.
+//!
+//! The spec tests always compile with [`Conformance::Spec`], which ignores
+//! this option, so these build their own modules. Operands are passed as
+//! parameters so that nothing can be folded at compile time.
+
+mod common;
+
+use wasm2glulx::{CompilationOptions, Conformance};
+
+const TRAP: &str = "!integer overflow";
+
+/// Runs `x <op> y` at type `ty`, returning the result's words, low first, or
+/// the trap message.
+fn run(
+    ty: &str,
+    op: &str,
+    x: i64,
+    y: i64,
+    options: &CompilationOptions,
+) -> Result<Vec<u32>, String> {
+    let report = if ty == "i32" {
+        "(call $result (local.get $r))".to_owned()
+    } else {
+        "(call $result (i32.wrap_i64 (local.get $r)))
+         (call $result (i32.wrap_i64 (i64.shr_u (local.get $r) (i64.const 32))))"
+            .to_owned()
+    };
+    let module = common::wat(&format!(
+        r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (func $op (param $x {ty}) (param $y {ty}) (local $r {ty})
+            (local.set $r ({ty}.{op} (local.get $x) (local.get $y)))
+            {report})
+          (func (export "glulx_main")
+            (call $op ({ty}.const {x}) ({ty}.const {y}))))
+        "#
+    ));
+    common::compile_and_run(&format!("overflow_{ty}_{op}"), options, &module)
+}
+
+fn trapping() -> CompilationOptions {
+    let mut options = CompilationOptions::new();
+    options.set_trap_on_overflow(true);
+    options
+}
+
+fn words(ty: &str, value: i64) -> Vec<u32> {
+    let value = value as u64;
+    if ty == "i32" {
+        vec![value as u32]
+    } else {
+        vec![value as u32, (value >> 32) as u32]
+    }
+}
+
+/// For each type, operands of each operation which stay in range.
+const IN_RANGE: &[(&str, &str, i64, i64)] = &[
+    ("i32", "add", 0x7fff_fffe, 1),
+    ("i32", "add", -0x8000_0000, 0x7fff_ffff),
+    ("i32", "sub", -0x7fff_ffff, 1),
+    ("i32", "sub", -1, 0x7fff_ffff),
+    ("i32", "mul", 0x4000_0000, -2),
+    ("i32", "mul", -0x8000_0000, 1),
+    ("i32", "mul", 46340, 46340),
+    ("i64", "add", i64::MAX - 1, 1),
+    ("i64", "add", i64::MIN, i64::MAX),
+    ("i64", "sub", i64::MIN + 1, 1),
+    ("i64", "sub", -1, i64::MAX),
+    ("i64", "mul", 0x4000_0000_0000_0000, -2),
+    ("i64", "mul", i64::MIN, 1),
+    ("i64", "mul", 0xffff_ffff, 0x7fff_ffff),
+];
+
+/// Operands which overflow, with the wrapped result WebAssembly defines.
+const OVERFLOWING: &[(&str, &str, i64, i64, i64)] = &[
+    ("i32", "add", 0x7fff_ffff, 1, -0x8000_0000),
+    ("i32", "add", -0x8000_0000, -1, 0x7fff_ffff),
+    ("i32", "sub", -0x8000_0000, 1, 0x7fff_ffff),
+    ("i32", "sub", 0, -0x8000_0000, -0x8000_0000),
+    ("i32", "mul", 0x4000_0000, 2, -0x8000_0000),
+    ("i32", "mul", -0x8000_0000, -1, -0x8000_0000),
+    ("i32", "mul", 46341, 46341, -2_147_479_015),
+    ("i64", "add", i64::MAX, 1, i64::MIN),
+    ("i64", "add", i64::MIN, -1, i64::MAX),
+    ("i64", "sub", i64::MIN, 1, i64::MAX),
+    ("i64", "sub", 0, i64::MIN, i64::MIN),
+    ("i64", "mul", 0x4000_0000_0000_0000, 2, i64::MIN),
+    ("i64", "mul", i64::MIN, -1, i64::MIN),
+    ("i64", "mul", 0x1_0000_0000, 0x8000_0000, i64::MIN),
+];
+
+fn checked(x: i64, y: i64, ty: &str, op: &str) -> i64 {
+    let result = match op {
+        "add" => x.checked_add(y),
+        "sub" => x.checked_sub(y),
+        "mul" => x.checked_mul(y),
+        _ => unreachable!(),
+    }
+    .unwrap();
+    if ty == "i32" {
+        i64::from(i32::try_from(result).unwrap())
+    } else {
+        result
+    }
+}
+
+#[test]
+fn in_range_results_are_unchanged() {
+    for &(ty, op, x, y) in IN_RANGE {
+        assert_eq!(
+            run(ty, op, x, y, &trapping()),
+            Ok(words(ty, checked(x, y, ty, op))),
+            "{ty}.{op} {x} {y}"
+        );
+    }
+}
+
+#[test]
+fn signed_overflow_traps() {
+    let mut with_messages = trapping();
+    with_messages.set_trap_messages(true);
+    for options in [trapping(), with_messages] {
+        for &(ty, op, x, y, _) in OVERFLOWING {
+            assert_eq!(
+                run(ty, op, x, y, &options),
+                Err(TRAP.to_owned()),
+                "{ty}.{op} {x} {y}"
+            );
+        }
+    }
+}
+
+#[test]
+fn overflow_wraps_without_the_option() {
+    let mut spec = trapping();
+    spec.set_conformance(Conformance::Spec);
+    for options in [CompilationOptions::new(), spec] {
+        for &(ty, op, x, y, wrapped) in OVERFLOWING {
+            assert_eq!(
+                run(ty, op, x, y, &options),
+                Ok(words(ty, wrapped)),
+                "{ty}.{op} {x} {y}"
+            );
+        }
+    }
+}
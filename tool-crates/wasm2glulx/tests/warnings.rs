@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: Apache-2.0 WITH LLVM-exception
+// Copyright 2024 Daniel Fox Franke.
+
+//! Tests for the warnings which `compile` returns alongside its output, and
+//! for `--deny-warnings`.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use wasm2glulx::{CompilationError, CompilationOptions, CompilationWarning, Emit};
+
+/// A module with an exported function which is never called.
+const ORPHAN: &str = r#"
+(module
+  (func (export "glulx_main"))
+  (func (export "orphan")))
+"#;
+
+fn warnings(options: &CompilationOptions, src: &str) -> Vec<CompilationWarning> {
+    wasm2glulx::check_warnings(options, &common::wat(src))
+}
+
+/// Writes `src` as `<name>.wasm` in the test scratch directory and returns
+/// options which compile it to `<name>.ulx`, with any output left over from
+/// an earlier run removed.
+fn file_options(name: &str, src: &str) -> (CompilationOptions, PathBuf) {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR"));
+    let input = dir.join(format!("{name}.wasm"));
+    std::fs::write(&input, common::wat(src).emit_wasm()).unwrap();
+    let output = dir.join(format!("{name}.ulx"));
+    let _ = std::fs::remove_file(&output);
+
+    let mut options = CompilationOptions::new();
+    options.set_input(Some(input));
+    options.set_output(Some(output.clone()));
+    (options, output)
+}
+
+#[test]
+fn unreachable_export() {
+    let found = warnings(&CompilationOptions::new(), ORPHAN);
+    assert!(
+        matches!(
+            found.as_slice(),
+            [CompilationWarning::UnreachableExport { name }] if name == "orphan"
+        ),
+        "{found:?}"
+    );
+    assert_eq!(
+        found[0].to_string(),
+        "The exported function `orphan` is never called from the entrypoint, so it will never run"
+    );
+}
+
+#[test]
+fn reachable_exports_are_not_warned_about() {
+    let direct = r#"
+        (module
+          (func $callee (export "callee"))
+          (func (export "glulx_main") (call $callee)))
+    "#;
+    assert!(warnings(&CompilationOptions::new(), direct).is_empty());
+
+    // Once anything reachable makes an indirect call, everything in a table
+    // might be called.
+    let indirect = r#"
+        (module
+          (type $t (func))
+          (table funcref (elem $callee))
+          (func $callee (export "callee"))
+          (func (export "glulx_main") (call_indirect (type $t) (i32.const 0))))
+    "#;
+    assert!(warnings(&CompilationOptions::new(), indirect).is_empty());
+}
+
+#[test]
+fn unreachable_exports_are_expected_when_emitting_exports() {
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Binary, Emit::Exports]);
+    assert!(warnings(&options, ORPHAN).is_empty());
+}
+
+#[test]
+fn large_table_growth() {
+    let src = r#"
+        (module
+          (table 0 funcref)
+          (func (export "glulx_main")))
+    "#;
+    assert!(warnings(&CompilationOptions::new(), src).is_empty());
+
+    let mut options = CompilationOptions::new();
+    options.set_table_growth_limit(1 << 20);
+    let found = warnings(&options, src);
+    assert!(
+        matches!(
+            found.as_slice(),
+            [CompilationWarning::TableGrowth {
+                index: 0,
+                entries: 1048576
+            }]
+        ),
+        "{found:?}"
+    );
+}
+
+#[test]
+fn declared_table_maximum_bounds_growth() {
+    let mut options = CompilationOptions::new();
+    options.set_table_growth_limit(1 << 20);
+    let src = r#"
+        (module
+          (table 0 16 funcref)
+          (func (export "glulx_main")))
+    "#;
+    assert!(warnings(&options, src).is_empty());
+}
+
+#[test]
+fn unused_import_namespace() {
+    let src = r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (import "glulx" "stack_remaining" (func (result i32)))
+          (func (export "glulx_main")))
+    "#;
+    let found = warnings(&CompilationOptions::new(), src);
+    assert!(
+        matches!(
+            found.as_slice(),
+            [CompilationWarning::UnusedImportNamespace { module, names }]
+                if module == "glulx" && names == &["spectest_result", "stack_remaining"]
+        ),
+        "{found:?}"
+    );
+}
+
+#[test]
+fn namespace_with_any_used_import_is_not_warned_about() {
+    let src = r#"
+        (module
+          (import "glulx" "spectest_result" (func $result (param i32)))
+          (import "glulx" "stack_remaining" (func (result i32)))
+          (func (export "glulx_main") (call $result (i32.const 0))))
+    "#;
+    assert!(warnings(&CompilationOptions::new(), src).is_empty());
+}
+
+#[test]
+fn assembly_text_to_story_path() {
+    let src = r#"(module (func (export "glulx_main")))"#;
+    for (path, warned) in [("game.ulx", true), ("game.GBLORB", true), ("game.s", false)] {
+        let mut options = CompilationOptions::new();
+        options.set_emit(&[Emit::Asm]);
+        options.set_output(Some(PathBuf::from(path)));
+        let found = warnings(&options, src);
+        if warned {
+            assert!(
+                matches!(
+                    found.as_slice(),
+                    [CompilationWarning::TextToBinaryPath(p)] if p == Path::new(path)
+                ),
+                "{path}: {found:?}"
+            );
+        } else {
+            assert!(found.is_empty(), "{path}: {found:?}");
+        }
+    }
+
+    // Writing the story file too means the listing goes beside it instead.
+    let mut options = CompilationOptions::new();
+    options.set_emit(&[Emit::Binary, Emit::Asm]);
+    options.set_output(Some(PathBuf::from("game.ulx")));
+    assert!(warnings(&options, src).is_empty());
+}
+
+#[test]
+fn compile_returns_warnings_with_output() {
+    let (options, output) = file_options("warnings_returned", ORPHAN);
+    let compiled = wasm2glulx::compile(&options).unwrap();
+    assert!(matches!(
+        compiled.warnings.as_slice(),
+        [CompilationWarning::UnreachableExport { name }] if name == "orphan"
+    ));
+    assert_eq!(
+        compiled.bytes_written,
+        std::fs::metadata(&output).unwrap().len() as usize
+    );
+}
+
+#[test]
+fn deny_warnings_fails_without_writing_output() {
+    let (mut options, output) = file_options("warnings_denied", ORPHAN);
+    options.set_deny_warnings(true);
+    let errors = wasm2glulx::compile(&options).unwrap_err();
+    assert!(
+        matches!(
+            errors.as_slice(),
+            [CompilationError::DeniedWarning(CompilationWarning::UnreachableExport { name })]
+                if name == "orphan"
+        ),
+        "{errors:?}"
+    );
+    assert!(errors[0]
+        .to_string()
+        .ends_with("(denied by --deny-warnings)"));
+    assert!(!output.exists());
+}
+
+#[test]
+fn deny_warnings_allows_clean_modules() {
+    let (mut options, output) =
+        file_options("warnings_clean", r#"(module (func (export "glulx_main")))"#);
+    options.set_deny_warnings(true);
+    let compiled = wasm2glulx::compile(&options).unwrap();
+    assert!(compiled.warnings.is_empty());
+    assert!(output.exists());
+}